@@ -0,0 +1,578 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{Sender, UnboundedReceiver, UnboundedSender};
+use tokio::{task, time};
+
+mod party;
+
+use crate::config::{self, MediaDirectory, MpdConfig, PodcastsConfig, SplayConfig};
+use crate::input::keybinds::Keybinds;
+use crate::library::db::{self, LibraryDb};
+use crate::library::{self, scanner};
+use crate::mpd::MpdClient;
+use crate::player::{self, Player};
+use crate::podcasts;
+use crate::queue;
+use crate::state::session::Session;
+use crate::state::AppState;
+use crate::ui::song_format::SongFormats;
+use crate::ui::theme::Theme;
+use crate::utils::constants::requests::*;
+use crate::utils::constants::{AudioBackends, PlayerModes, PlayerStates};
+
+// how long a macro waits for the interface to handle a step before going on without it
+const MACRO_STEP_TIMEOUT: Duration = Duration::from_millis(1000);
+// how many requests wait for the player before more are turned away, so one that's stuck doesn't
+// pile them up to all play out once it's back
+const PLAYER_REQUESTS_LEN: usize = 32;
+
+// makes the player again when its thread dies, what can't be made is said on the status line
+pub type NewPlayer = Box<dyn Fn() -> Result<Box<dyn Player + Send>, String>>;
+
+// every thread sends its requests to the one channel the dispatcher reads, which hands each to
+// whatever handles it. something new only needs a variant of AppRequests and an arm in dispatch
+pub struct Dispatcher {
+    pub state: Arc<Mutex<AppState>>,
+    pub main_tx: UnboundedSender<AppRequests>,
+    pub ui_tx: UnboundedSender<UIRequests>,
+    pub player_tx: Sender<PlayerRequests>,
+    pub player_handle: Option<task::JoinHandle<()>>, // replaced when the player is started again
+    pub new_player: NewPlayer,
+    pub scrobbler_tx: UnboundedSender<ScrobblerRequests>,
+    pub notification_tx: UnboundedSender<NotificationRequests>,
+    pub metadata_tx: UnboundedSender<MetadataRequests>,
+    pub stats_db: Arc<Mutex<LibraryDb>>, // saves what is changed from the UI
+    pub keybinds: Arc<Mutex<Keybinds>>,
+    pub join_handlers: Vec<JoinHandle<()>>, // joined on quit
+    pub scan: Option<task::JoinHandle<()>>, // or what reads the library from mpd, none when attached
+    pub mode: PlayerModes,
+    pub backend: AudioBackends,
+    pub media_dirs: Vec<MediaDirectory>,
+    pub library_override: Vec<MediaDirectory>, // given on the command line
+    pub ipc_socket: Option<PathBuf>,            // removed on quit
+    pub no_ui: bool,
+    pub clear_asked: Option<Instant>, // the queue was asked to be cleared during a party
+}
+
+impl Dispatcher {
+    // the dispatcher keeps a sender itself, so this only ends on quit
+    pub async fn run(mut self, mut main_rx: UnboundedReceiver<AppRequests>) {
+        while let Some(request) = main_rx.recv().await {
+            match request {
+                AppRequests::Quit => self.quit().await,
+                request => self.dispatch(request).await,
+            }
+        }
+    }
+
+    // what reads or writes files and the database is done on the runtime's blocking threads, it
+    // still finishes before the next request is dispatched
+    async fn dispatch(&mut self, request: AppRequests) {
+        // guests can only play and add to the queue during a party
+        if self.state.lock().unwrap().ui.party {
+            let mut guard = self.state.lock().unwrap();
+            if party::forbidden(&request) {
+                guard.ui.notify_error("Only adding to the queue works during a party");
+                return;
+            }
+            if matches!(request, AppRequests::QueueRequests(QueueRequests::Clear))
+                && !party::confirm_clear(&mut self.clear_asked, &mut guard)
+            {
+                return;
+            }
+        }
+        match request {
+            // the queue ran out, which is when playing without the interface is done
+            AppRequests::UIRequests(UIRequests::PlayerEvent(PlayerEvents::TrackEnded))
+                if self.no_ui =>
+            {
+                if self.state.lock().unwrap().player.curr_state == PlayerStates::STOPPED {
+                    let _ = self.main_tx.send(AppRequests::Quit);
+                }
+            }
+            // attached to a daemon, which lets the services and the desktop know itself
+            AppRequests::UIRequests(UIRequests::PlayerEvent(PlayerEvents::TrackStarted(song)))
+                if self.mode != PlayerModes::REMOTE =>
+            {
+                let _ = self
+                    .notification_tx
+                    .send(NotificationRequests::SongChanged(song.to_owned()));
+                // streams aren't songs the services know
+                if !song.is_stream() {
+                    let _ = self
+                        .scrobbler_tx
+                        .send(ScrobblerRequests::NowPlaying(song.to_owned()));
+                }
+                let _ = self
+                    .ui_tx
+                    .send(UIRequests::PlayerEvent(PlayerEvents::TrackStarted(song)));
+            }
+            AppRequests::UIRequests(UIRequests::PlayerEvent(PlayerEvents::Error(reason))) => {
+                let _ = self
+                    .notification_tx
+                    .send(NotificationRequests::Error(reason.to_owned()));
+                let _ = self
+                    .ui_tx
+                    .send(UIRequests::PlayerEvent(PlayerEvents::Error(reason)));
+            }
+            AppRequests::UIRequests(request) => {
+                let _ = self.ui_tx.send(request);
+            }
+            AppRequests::PlayerRequests(request) => self.send_to_player(request),
+            AppRequests::ScrobblerRequests(request) => {
+                let _ = self.scrobbler_tx.send(request);
+            }
+            AppRequests::MetadataRequests(request) => {
+                let _ = self.metadata_tx.send(request);
+            }
+            AppRequests::QueueRequests(request) => {
+                let state = self.state.clone();
+                let _ = task::spawn_blocking(move || queue::handle(&state, request)).await;
+            }
+            AppRequests::LibraryRequests(request) => {
+                let state = self.state.clone();
+                let stats_db = self.stats_db.clone();
+                let main_tx = self.main_tx.clone();
+                let _ = task::spawn_blocking(move || {
+                    library::handle(&state, &mut stats_db.lock().unwrap(), &main_tx, request)
+                })
+                .await;
+            }
+            AppRequests::ReloadConfig => self.reload_config().await,
+            AppRequests::Macro(requests) => {
+                for request in requests {
+                    // what's on screen, like the selected song, has to be there for what's next
+                    let wait = matches!(request, AppRequests::UIRequests(_)) && !self.no_ui;
+                    Box::pin(self.dispatch(request)).await;
+                    if wait {
+                        let (reply, handled) = Reply::new();
+                        let _ = self.ui_tx.send(UIRequests::Handled(reply));
+                        let _ = time::timeout(MACRO_STEP_TIMEOUT, handled).await;
+                    }
+                }
+            }
+            // quitting waits for the player, which is done from `run`
+            AppRequests::Quit => {
+                let _ = self.main_tx.send(AppRequests::Quit);
+            }
+        }
+    }
+
+    async fn quit(&mut self) {
+        // attached to a daemon, which keeps playing and saves all of it once it quits
+        if self.mode == PlayerModes::REMOTE {
+            let _ = self.ui_tx.send(UIRequests::Quit);
+            self.quit_player(&[PlayerRequests::Quit]).await;
+            for handler in self.join_handlers.drain(..) {
+                let _ = handler.join();
+            }
+            info!("Detached from the daemon");
+            std::process::exit(0);
+        }
+        // before the player stops and forgets where it was
+        let session = Session::capture(&self.state.lock().unwrap());
+        // what the player sends as it stops isn't handled anymore
+        let left = {
+            let mut guard = self.state.lock().unwrap();
+            let progress = guard.player.progress;
+            match guard.player.curr_song.to_owned() {
+                Some(song) => guard.podcasts.leave(&song, Some(progress)),
+                None => None,
+            }
+        };
+        let stats_db = self.stats_db.clone();
+        let _ = task::spawn_blocking(move || {
+            if let Err(err) = session.save() {
+                error!("Could not save the session. Reason: {}", err);
+            }
+            if let Some((url, position)) = left {
+                if let Err(err) = stats_db.lock().unwrap().save_position(&url, position) {
+                    error!("Could not save where the episode was left. Reason: {}", err);
+                }
+            }
+        })
+        .await;
+        let _ = self.ui_tx.send(UIRequests::Quit);
+        self.quit_player(&[PlayerRequests::Stop, PlayerRequests::Quit]).await;
+        for handler in self.join_handlers.drain(..) {
+            let _ = handler.join();
+        }
+        if let Some(socket) = &self.ipc_socket {
+            let _ = fs::remove_file(socket);
+        }
+        info!("Gracefully shutting down");
+        std::process::exit(0);
+    }
+
+    // waits for the player to finish what it was told last, unless it's too stuck to be told
+    async fn quit_player(&mut self, requests: &[PlayerRequests]) {
+        for request in requests {
+            if let Err(err) = self.player_tx.try_send(request.to_owned()) {
+                warn!("Not waiting for the player, it couldn't be told to quit. Reason: {}", err);
+                return;
+            }
+        }
+        if let Some(handle) = self.player_handle.take() {
+            let _ = handle.await;
+        }
+    }
+
+    // a player that's stuck has the request turned away and one whose thread died is started
+    // again to be given it, either way it's said on the status line instead of going nowhere
+    fn send_to_player(&mut self, request: PlayerRequests) {
+        let request = match self.player_tx.try_send(request) {
+            Ok(()) => return,
+            Err(TrySendError::Full(request)) => {
+                warn!("The player is busy, dropped {:?}", request);
+                self.state
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .ui
+                    .notify_error("The player isn't responding, try again in a moment");
+                return;
+            }
+            Err(TrySendError::Closed(request)) => request,
+        };
+        error!("The player stopped, starting it again");
+        // a player that panicked while it had the state locked left it poisoned, and what it
+        // was in the middle of changing is put right by stopping
+        self.state.clear_poison();
+        let player = match (self.new_player)() {
+            Ok(player) => player,
+            Err(err) => {
+                error!("Could not start the player again. Reason: {}", err);
+                self.state
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .ui
+                    .notify_error(format!("The player stopped and could not start again. {}", err));
+                return;
+            }
+        };
+        // whatever it was playing went with it
+        {
+            let mut guard = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+            player::set_stopped(&mut guard);
+            guard.ui.notify_error("The player stopped and was started again");
+        }
+        let (player_tx, handle) = spawn_player(&self.state, player, &self.main_tx);
+        self.player_tx = player_tx;
+        // the task that died is done, there's nothing to wait for on quit
+        self.player_handle = Some(handle);
+        let _ = self.player_tx.try_send(request);
+    }
+
+    async fn reload_config(&mut self) {
+        let read = task::spawn_blocking(|| config::read().map_err(|err| err.to_string())).await;
+        let config = match read.unwrap_or_else(|err| Err(err.to_string())) {
+            Ok(config) => config,
+            Err(err) => {
+                error!("Could not reload the config. Reason: {}", err);
+                self.state.lock().unwrap().ui.error =
+                    Some(format!("Could not reload the config. {}", err));
+                return;
+            }
+        };
+        apply_config(&self.state, &config);
+        let theme = Theme::from_config(&config.theme).unwrap_or_default();
+        let _ = self.ui_tx.send(UIRequests::SetTheme(theme));
+        let _ = self
+            .scrobbler_tx
+            .send(ScrobblerRequests::SetConfig(config.scrobbling.clone()));
+        let _ = self
+            .notification_tx
+            .send(NotificationRequests::SetConfig(config.notifications.clone()));
+        let _ = self
+            .metadata_tx
+            .send(MetadataRequests::SetConfig(config.metadata()));
+        *self.keybinds.lock().unwrap() =
+            Keybinds::from_config(&config.keybindings, &config.macros).unwrap_or_default();
+        spawn_podcasts(&self.state, config.podcasts.clone(), &self.main_tx);
+        if self.mode != PlayerModes::REMOTE && config.player.mode != self.mode {
+            info!("Playing through mpd or not changes once splay is restarted");
+            self.state
+                .lock()
+                .unwrap()
+                .ui
+                .notify("Playing through mpd or not changes once splay is restarted");
+        }
+        if config.player.backend != self.backend {
+            info!("The audio backend changes once splay is restarted");
+            self.state
+                .lock()
+                .unwrap()
+                .ui
+                .notify("The audio backend changes once splay is restarted");
+        }
+        // the library directories are the server's when playing through mpd
+        if self.mode == PlayerModes::LOCAL
+            && library_dirs(&config, &self.library_override) != self.media_dirs
+        {
+            if self.scan.as_ref().is_none_or(task::JoinHandle::is_finished) {
+                self.media_dirs = library_dirs(&config, &self.library_override);
+                let opened = task::spawn_blocking(|| {
+                    LibraryDb::open(&db::path()).map_err(|err| err.to_string())
+                })
+                .await;
+                match opened.unwrap_or_else(|err| Err(err.to_string())) {
+                    Ok(library_db) => {
+                        self.scan = Some(spawn_scan(
+                            &self.state,
+                            self.media_dirs.clone(),
+                            library_db,
+                            &self.main_tx,
+                        ))
+                    }
+                    Err(err) => error!(
+                        "Could not open the library database to scan. Reason: {}",
+                        err
+                    ),
+                }
+            } else {
+                self.state.lock().unwrap().ui.notify_error(
+                    "The library directories can't change during a scan, reload again once \
+                    it's done",
+                );
+            }
+        }
+        info!("Reloaded the config");
+        self.state.lock().unwrap().ui.notify("Reloaded the config");
+    }
+}
+
+// the settings that can change while running, used at startup and when the config is reloaded
+pub fn apply_config(state: &Arc<Mutex<AppState>>, config: &SplayConfig) {
+    player::set_crossfade(state, config.player.crossfade_secs);
+    player::set_fade(state, config.player.fade_ms);
+    player::set_play_count_threshold(state, config.player.play_count_threshold);
+    player::set_balance(state, config.player.balance);
+    let mut guard = state.lock().unwrap();
+    guard.player.replay_gain = config.player.replay_gain;
+    guard.player.preamp_db = config.player.preamp_db;
+    guard.player.mono = config.player.mono;
+    guard.player.eq_preset = config.equalizer.preset;
+    guard.player.eq_bands = config.equalizer.gains();
+    guard.library.smart_playlists = config.playlists.clone();
+    guard.library.playlist_dir = config.media.playlist_directory();
+    guard.ui.columns = config.song_list.columns.clone();
+    guard.ui.group_by_album = config.song_list.group_by_album;
+    guard.ui.formats = SongFormats::from_config(&config.formats).unwrap_or_default();
+    guard.ui.layout = config.layout.clone();
+    guard.ui.tick_rate = config.ui.tick_rate;
+    guard.ui.party = config.ui.party;
+    guard.ui.marquee_speed = config.ui.marquee_speed;
+    guard.ui.marquee_pause = Duration::from_millis(config.ui.marquee_pause_ms);
+    guard.podcasts.download_dir = config.podcasts.download_directory();
+}
+
+// the directories given on the command line replace the ones in the config
+pub fn library_dirs(
+    config: &SplayConfig,
+    library_override: &[MediaDirectory],
+) -> Vec<MediaDirectory> {
+    if library_override.is_empty() {
+        config.media.directories.clone()
+    } else {
+        library_override.to_vec()
+    }
+}
+
+// the task the player listens for its requests in. one that panics lets go of the state's
+// poison straight away, so the other threads can go on until it's started again
+pub fn spawn_player(
+    state: &Arc<Mutex<AppState>>,
+    player: Box<dyn Player + Send>,
+    main_tx: &UnboundedSender<AppRequests>,
+) -> (Sender<PlayerRequests>, task::JoinHandle<()>) {
+    let (player_tx, player_rx) = tokio::sync::mpsc::channel(PLAYER_REQUESTS_LEN);
+    let listening = tokio::spawn(player::listen(
+        player,
+        state.clone(),
+        player_rx,
+        main_tx.clone(),
+    ));
+    let cloned_state = state.clone();
+    let handle = tokio::spawn(async move {
+        if listening.await.is_err() {
+            cloned_state.clear_poison();
+        }
+    });
+    (player_tx, handle)
+}
+
+// look for changes in the library directories in the background. not joined on quit, there's no
+// need to wait for a scan to finish
+pub fn spawn_scan(
+    state: &Arc<Mutex<AppState>>,
+    media_dirs: Vec<MediaDirectory>,
+    mut library_db: LibraryDb,
+    main_tx: &UnboundedSender<AppRequests>,
+) -> task::JoinHandle<()> {
+    state.lock().unwrap().library.roots = media_dirs.iter().map(MediaDirectory::path).collect();
+    let cloned_state = state.clone();
+    let cloned_main_tx = main_tx.clone();
+    task::spawn_blocking(move || {
+        scanner::scan(&media_dirs, &mut library_db, |scanned, total| {
+            let _ = cloned_main_tx.send(AppRequests::UIRequests(UIRequests::ScanProgress(
+                scanned, total,
+            )));
+        });
+        match library_db.load_songs() {
+            Ok(mut songs) => {
+                library::check_missing(&mut songs);
+                cloned_state.lock().unwrap().library.set_songs(songs);
+            }
+            Err(err) => {
+                error!("Could not load the library. Reason: {}", err);
+                return;
+            }
+        }
+        let _ = cloned_main_tx.send(AppRequests::UIRequests(UIRequests::LibraryUpdated));
+    })
+}
+
+// ask the mpd server for its library in the background, it's what's played when playing
+// through mpd
+pub fn spawn_mpd_library(
+    state: &Arc<Mutex<AppState>>,
+    config: MpdConfig,
+    main_tx: &UnboundedSender<AppRequests>,
+) -> task::JoinHandle<()> {
+    let cloned_state = state.clone();
+    let cloned_main_tx = main_tx.clone();
+    task::spawn_blocking(move || {
+        let songs = match MpdClient::connect(&config).and_then(|mut client| client.songs()) {
+            Ok(songs) => songs,
+            Err(err) => {
+                error!("Could not read the library from mpd. Reason: {}", err);
+                cloned_state.lock().unwrap().ui.error =
+                    Some(format!("Could not read the library from mpd. {}", err));
+                return;
+            }
+        };
+        info!("Read {} songs from mpd", songs.len());
+        cloned_state.lock().unwrap().library.set_songs(songs);
+        let _ = cloned_main_tx.send(AppRequests::UIRequests(UIRequests::LibraryUpdated));
+    })
+}
+
+// read the feeds subscribed to in the background, which happens again each time the config is
+// reloaded. not joined on quit either
+pub fn spawn_podcasts(
+    state: &Arc<Mutex<AppState>>,
+    config: PodcastsConfig,
+    main_tx: &UnboundedSender<AppRequests>,
+) {
+    let cloned_state = state.clone();
+    let cloned_main_tx = main_tx.clone();
+    task::spawn_blocking(move || {
+        let download_dir = config.download_directory();
+        let mut fetched = Vec::new();
+        for feed in &config.feeds {
+            match podcasts::fetch(feed, download_dir.as_deref()) {
+                Ok(podcast) => fetched.push(podcast),
+                Err(err) => {
+                    error!("Could not read the feed {}. Reason: {}", feed, err);
+                    cloned_state
+                        .lock()
+                        .unwrap()
+                        .ui
+                        .notify_error(format!("Could not read the feed {}. {}", feed, err));
+                }
+            }
+        }
+        info!("Read {} podcasts", fetched.len());
+        cloned_state.lock().unwrap().podcasts.podcasts = fetched;
+        let _ = cloned_main_tx.send(AppRequests::UIRequests(UIRequests::PodcastsUpdated));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::errors::PlayerError;
+    use std::path::Path;
+    use std::sync::mpsc;
+
+    // dies halfway through changing the state, which leaves it poisoned
+    struct PanickingPlayer;
+
+    impl Player for PanickingPlayer {
+        fn handle(
+            &mut self,
+            app_state: &Arc<Mutex<AppState>>,
+            _main_tx: &UnboundedSender<AppRequests>,
+            _request: PlayerRequests,
+        ) -> Result<(), PlayerError> {
+            let _guard = app_state.lock().unwrap();
+            panic!("the player broke");
+        }
+    }
+
+    // passes on whatever it's asked to do
+    struct RecordingPlayer(mpsc::Sender<PlayerRequests>);
+
+    impl Player for RecordingPlayer {
+        fn handle(
+            &mut self,
+            _app_state: &Arc<Mutex<AppState>>,
+            _main_tx: &UnboundedSender<AppRequests>,
+            request: PlayerRequests,
+        ) -> Result<(), PlayerError> {
+            let _ = self.0.send(request);
+            Ok(())
+        }
+    }
+
+    fn dispatcher(player: Box<dyn Player + Send>, new_player: NewPlayer) -> Dispatcher {
+        let state = Arc::new(Mutex::new(AppState::default()));
+        let (main_tx, _) = tokio::sync::mpsc::unbounded_channel();
+        let (player_tx, player_handle) = spawn_player(&state, player, &main_tx);
+        Dispatcher {
+            state,
+            main_tx,
+            ui_tx: tokio::sync::mpsc::unbounded_channel().0,
+            player_tx,
+            player_handle: Some(player_handle),
+            new_player,
+            scrobbler_tx: tokio::sync::mpsc::unbounded_channel().0,
+            notification_tx: tokio::sync::mpsc::unbounded_channel().0,
+            metadata_tx: tokio::sync::mpsc::unbounded_channel().0,
+            stats_db: Arc::new(Mutex::new(LibraryDb::open(Path::new(":memory:")).unwrap())),
+            keybinds: Arc::new(Mutex::new(Keybinds::default())),
+            join_handlers: vec![],
+            scan: None,
+            mode: PlayerModes::LOCAL,
+            backend: AudioBackends::default(),
+            media_dirs: vec![],
+            library_override: vec![],
+            ipc_socket: None,
+            no_ui: true,
+            clear_asked: None,
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn a_player_that_panicked_is_started_again() {
+        let (played_tx, played_rx) = mpsc::channel();
+        let mut dispatcher = dispatcher(
+            Box::new(PanickingPlayer),
+            Box::new(move || Ok(Box::new(RecordingPlayer(played_tx.clone())))),
+        );
+
+        dispatcher.send_to_player(PlayerRequests::Pause);
+        let _ = dispatcher.player_handle.take().unwrap().await;
+        assert!(dispatcher.state.lock().is_ok());
+
+        dispatcher.send_to_player(PlayerRequests::Resume);
+        let played = played_rx.recv_timeout(Duration::from_secs(1));
+        assert!(matches!(played, Ok(PlayerRequests::Resume)));
+        let state = dispatcher.state.lock().unwrap();
+        assert_eq!(state.player.curr_state, PlayerStates::STOPPED);
+        assert!(state.ui.notifications.iter().any(|notification| notification.error));
+    }
+}
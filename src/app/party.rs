@@ -0,0 +1,54 @@
+use std::time::{Duration, Instant};
+
+use crate::state::AppState;
+use crate::utils::constants::requests::*;
+
+// how long after asking to clear the queue asking again clears it
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(5);
+
+// what guests can't do while it's a party: change the library or take songs out of the queue.
+// playing and adding to the queue still work
+pub fn forbidden(request: &AppRequests) -> bool {
+    matches!(
+        request,
+        AppRequests::UIRequests(
+            UIRequests::EditTags
+                | UIRequests::Rate(_)
+                | UIRequests::ChangeRating(_)
+                | UIRequests::ToggleFavorite
+                | UIRequests::RemoveFromQueue
+                | UIRequests::MoveInQueue(_)
+                | UIRequests::DownloadEpisode
+                | UIRequests::ConfirmFiles(_)
+        ) | AppRequests::LibraryRequests(
+            LibraryRequests::PurgeMissing
+                | LibraryRequests::Remove(_)
+                | LibraryRequests::SaveSearch(_)
+                | LibraryRequests::ForgetSearch(_)
+                | LibraryRequests::AddBookmark(_)
+                | LibraryRequests::DeleteBookmark(_)
+                | LibraryRequests::MeasureGain(_)
+                | LibraryRequests::ChangeFiles(..)
+        ) | AppRequests::QueueRequests(
+            QueueRequests::SaveAsPlaylist(_)
+                | QueueRequests::AddToPlaylist(_)
+                | QueueRequests::Undo
+                | QueueRequests::Redo
+        )
+    )
+}
+
+// the queue is only cleared when that's asked for twice in a row
+pub fn confirm_clear(asked: &mut Option<Instant>, state: &mut AppState) -> bool {
+    if asked
+        .take()
+        .is_some_and(|at| at.elapsed() < CONFIRM_TIMEOUT)
+    {
+        return true;
+    }
+    *asked = Some(Instant::now());
+    state
+        .ui
+        .notify("It's a party, clear the queue again to really clear it");
+    false
+}
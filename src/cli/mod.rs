@@ -0,0 +1,43 @@
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+// what can be changed about a single run from the command line
+#[derive(Parser, Debug)]
+#[command(version, about = "A terminal music player")]
+pub struct Args {
+    /// Audio file, directory or playlist to start playing right away
+    pub path: Option<PathBuf>,
+
+    /// Read this config file instead of looking for one
+    #[arg(short, long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Use this library directory instead of the ones in the config, can be given more than once
+    #[arg(short, long = "library", value_name = "DIR")]
+    pub libraries: Vec<String>,
+
+    /// Play without the interface and quit once the queue runs out
+    #[arg(long, requires = "path")]
+    pub no_ui: bool,
+
+    /// Keep playing in the background without the interface, for `splay attach` to control
+    #[arg(long, conflicts_with = "no_ui")]
+    pub daemon: bool,
+
+    // set on the daemon once it's left the terminal
+    #[arg(long, hide = true, requires = "daemon")]
+    pub detached: bool,
+
+    /// Read every file in the library again instead of only the ones that changed
+    #[arg(long)]
+    pub rescan: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    /// Show the interface of a splay started with --daemon. quitting it leaves the music playing
+    Attach,
+}
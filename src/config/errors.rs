@@ -0,0 +1,11 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Could not read {path}: {reason}")]
+    Unreadable { path: String, reason: String },
+    #[error("{path} is not valid: {reason}")]
+    Parsing { path: String, reason: String },
+    #[error("{field}: {reason}")]
+    Invalid { field: String, reason: String },
+}
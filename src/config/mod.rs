@@ -0,0 +1,764 @@
+pub mod errors;
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use toml_edit::{value, Array, Document};
+
+use crate::input::keybinds::Keybinds;
+use crate::library::smart_playlist::SmartPlaylist;
+use crate::player::{
+    channels::MAX_BALANCE, equalizer::MAX_GAIN_DB, MAX_CROSSFADE_SECS, MAX_FADE_MS,
+};
+use crate::ui::song_format::SongFormats;
+use crate::ui::theme::Theme;
+use crate::ui::{MAX_TICK_RATE, MIN_TICK_RATE};
+use crate::utils::constants::{
+    AudioBackends, EqPresets, LayoutDirections, LayoutWidgets, PlayerModes, ReplayGainModes,
+    SongColumns, ThemePresets, EQ_NUM_BANDS,
+};
+use errors::ConfigError;
+
+// read from the working directory if there's one there, which is handy while developing
+const LOCAL_CONFIG_PATH: &str = "config.toml";
+
+// given with --config, replaces looking for the file
+static PATH_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+#[derive(Deserialize)]
+pub struct SplayConfig {
+    pub media: Media,
+    #[serde(default)]
+    pub player: PlayerConfig,
+    #[serde(default)]
+    pub equalizer: EqualizerConfig,
+    #[serde(default)]
+    pub playlists: Vec<SmartPlaylist>,
+    #[serde(default)]
+    pub theme: ThemeConfig,
+    #[serde(default)]
+    pub song_list: SongListConfig,
+    #[serde(default)]
+    pub formats: FormatsConfig,
+    #[serde(default)]
+    pub ui: UiConfig,
+    #[serde(default)]
+    pub layout: LayoutConfig,
+    #[serde(default)]
+    pub scrobbling: ScrobblingConfig,
+    #[serde(default)]
+    pub mpd: MpdConfig,
+    #[serde(default)]
+    pub podcasts: PodcastsConfig,
+    #[serde(default)]
+    pub ipc: IpcConfig,
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    #[serde(default)]
+    pub mpris: MprisConfig,
+    #[serde(default)]
+    pub metadata: MetadataConfig,
+    // action name to the keys that trigger it
+    #[serde(default)]
+    pub keybindings: HashMap<String, KeyList>,
+    // keys to the actions they run one after the other, eg. "ctrl+p" = ["clear_queue", "enqueue"]
+    #[serde(default)]
+    pub macros: HashMap<String, Vec<String>>,
+}
+
+#[derive(Deserialize)]
+pub struct Media {
+    pub directories: Vec<MediaDirectory>,
+    playlist_directory: Option<String>,
+}
+
+impl Media {
+    pub fn playlist_directory(&self) -> Option<String> {
+        self.playlist_directory.as_deref().map(expand_home)
+    }
+}
+
+// a library root, either just its path or a table with options for it
+#[derive(Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum MediaDirectory {
+    Path(String),
+    WithOptions {
+        path: String,
+        // keep the songs found last time instead of removing them when the directory is missing
+        // or empty, eg. a network share that isn't mounted
+        #[serde(default)]
+        skip_if_unmounted: bool,
+    },
+}
+
+impl MediaDirectory {
+    pub fn path(&self) -> String {
+        let path = match self {
+            MediaDirectory::Path(path) => path,
+            MediaDirectory::WithOptions { path, .. } => path,
+        };
+        expand_home(path)
+    }
+
+    pub fn skip_if_unmounted(&self) -> bool {
+        match self {
+            MediaDirectory::Path(_) => false,
+            MediaDirectory::WithOptions {
+                skip_if_unmounted, ..
+            } => *skip_if_unmounted,
+        }
+    }
+}
+
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix('~'), env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{}{}", home, rest),
+        _ => path.to_string(),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct PlayerConfig {
+    pub mode: PlayerModes,
+    pub backend: AudioBackends,
+    pub crossfade_secs: u64,
+    pub fade_ms: u64,
+    pub replay_gain: ReplayGainModes,
+    pub preamp_db: f32,
+    pub mono: bool,
+    pub balance: f32,
+    pub play_count_threshold: u8, // percent of a song that has to be played for it to count
+    pub resume: bool, // play the song from the last session again from where it was
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        Self {
+            mode: PlayerModes::default(),
+            backend: AudioBackends::default(),
+            crossfade_secs: 0,
+            fade_ms: 100,
+            replay_gain: ReplayGainModes::default(),
+            preamp_db: 0.,
+            mono: false,
+            balance: 0.,
+            play_count_threshold: 50,
+            resume: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct EqualizerConfig {
+    pub preset: EqPresets,
+    // only used by the custom preset
+    pub bands: Option<[f32; EQ_NUM_BANDS]>,
+}
+
+impl EqualizerConfig {
+    pub fn gains(&self) -> [f32; EQ_NUM_BANDS] {
+        match (self.preset, self.bands) {
+            (EqPresets::CUSTOM, Some(bands)) => bands,
+            (preset, _) => preset.gains(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct SongListConfig {
+    // in the order they're shown, columns left out are hidden
+    pub columns: Vec<ColumnConfig>,
+    // a header with the album and its year over each run of songs from the same album
+    pub group_by_album: bool,
+}
+
+impl Default for SongListConfig {
+    fn default() -> Self {
+        Self {
+            columns: vec![
+                ColumnConfig::new(SongColumns::TITLE, Some(40)),
+                ColumnConfig::new(SongColumns::ARTIST, Some(25)),
+                ColumnConfig::new(SongColumns::ALBUM, None),
+                ColumnConfig::new(SongColumns::DURATION, None),
+            ],
+            group_by_album: false,
+        }
+    }
+}
+
+// how songs are written out, with fields like %title% and groups in braces that are left out
+// when a field in them is empty
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct FormatsConfig {
+    pub song: String, // in the song lists and the queue
+    pub now_playing: String, // a line for each line of it
+}
+
+impl Default for FormatsConfig {
+    fn default() -> Self {
+        Self {
+            song: "%title%".to_string(),
+            now_playing: "%title%\n%artist%".to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct UiConfig {
+    // redraws a second while something moves, eg. the progress bar or the visualizer
+    pub tick_rate: u64,
+    // only adding to the queue, for guests to pick songs
+    pub party: bool,
+    // cells a second that titles too long for the now playing bar scroll by, at least 1
+    pub marquee_speed: u16,
+    pub marquee_pause_ms: u64, // at either end before scrolling back
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            tick_rate: 10,
+            party: false,
+            marquee_speed: 4,
+            marquee_pause_ms: 2000,
+        }
+    }
+}
+
+// how the screen is split into panes, from the outside in. a pane shows a widget or is split
+// again, and the top of the layout is always split
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(untagged)]
+pub enum LayoutConfig {
+    Split {
+        direction: LayoutDirections,
+        #[serde(default)]
+        size: Option<PaneSize>,
+        panes: Vec<LayoutConfig>,
+    },
+    Widget {
+        widget: LayoutWidgets,
+        #[serde(default)]
+        size: Option<PaneSize>,
+    },
+}
+
+// rows or columns as a number, or a share of the split like "20%". panes without one share
+// what's left
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(untagged)]
+pub enum PaneSize {
+    Cells(u16),
+    Share(String),
+}
+
+impl PaneSize {
+    pub fn percent(&self) -> Option<u16> {
+        match self {
+            PaneSize::Cells(_) => None,
+            PaneSize::Share(share) => share
+                .trim()
+                .strip_suffix('%')
+                .and_then(|percent| percent.trim().parse().ok())
+                .filter(|percent| (1..=100).contains(percent)),
+        }
+    }
+}
+
+// the tabs, then the song that's playing, the open tab and the status line
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        let widget = |widget, size| LayoutConfig::Widget { widget, size };
+        LayoutConfig::Split {
+            direction: LayoutDirections::VERTICAL,
+            size: None,
+            panes: vec![
+                widget(LayoutWidgets::TABS, Some(PaneSize::Cells(1))),
+                widget(
+                    LayoutWidgets::NOW_PLAYING,
+                    Some(PaneSize::Share("20%".to_string())),
+                ),
+                widget(LayoutWidgets::MAIN, None),
+                widget(LayoutWidgets::STATUS, Some(PaneSize::Cells(1))),
+            ],
+        }
+    }
+}
+
+impl LayoutConfig {
+    pub fn size(&self) -> Option<&PaneSize> {
+        match self {
+            LayoutConfig::Split { size, .. } | LayoutConfig::Widget { size, .. } => size.as_ref(),
+        }
+    }
+
+    // every widget in the layout, in the order they're drawn
+    pub fn widgets(&self) -> Vec<LayoutWidgets> {
+        match self {
+            LayoutConfig::Split { panes, .. } => panes.iter().flat_map(Self::widgets).collect(),
+            LayoutConfig::Widget { widget, .. } => vec![*widget],
+        }
+    }
+
+    pub fn contains(&self, widget: LayoutWidgets) -> bool {
+        self.widgets().contains(&widget)
+    }
+
+    // what's wrong with the layout, if anything
+    fn problem(&self) -> Option<String> {
+        if let LayoutConfig::Widget { .. } = self {
+            return Some("needs a direction and panes to split the screen into".to_string());
+        }
+        let widgets = self.widgets();
+        if !widgets.contains(&LayoutWidgets::MAIN) {
+            return Some("needs a main pane for the open tab".to_string());
+        }
+        if let Some((_, twice)) = widgets
+            .iter()
+            .enumerate()
+            .find(|(idx, widget)| widgets[..*idx].contains(widget))
+        {
+            return Some(format!("has {} more than once", twice.name()));
+        }
+        self.bad_size()
+    }
+
+    fn bad_size(&self) -> Option<String> {
+        if let Some(size) = self.size() {
+            if let (PaneSize::Share(share), None) = (size, size.percent()) {
+                return Some(format!(
+                    "has a size of \"{}\", sizes are a number or a share like \"20%\"",
+                    share
+                ));
+            }
+        }
+        match self {
+            LayoutConfig::Split { panes, .. } if panes.is_empty() => {
+                Some("has a split with no panes".to_string())
+            }
+            LayoutConfig::Split { panes, .. } => panes.iter().find_map(Self::bad_size),
+            LayoutConfig::Widget { .. } => None,
+        }
+    }
+}
+
+// the server played through when the player's mode is mpd
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct MpdConfig {
+    pub host: String,
+    pub port: u16,
+    pub password: Option<String>,
+}
+
+impl Default for MpdConfig {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_string(),
+            port: 6600,
+            password: None,
+        }
+    }
+}
+
+// feeds subscribed to, with episodes streamed unless they've been downloaded
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct PodcastsConfig {
+    pub feeds: Vec<String>,
+    download_directory: Option<String>,
+}
+
+impl PodcastsConfig {
+    pub fn download_directory(&self) -> Option<String> {
+        self.download_directory.as_deref().map(expand_home)
+    }
+}
+
+// where scripts send commands to the running player
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct IpcConfig {
+    pub enabled: bool,
+    socket: Option<String>,
+    pub tcp_port: Option<u16>, // on localhost, besides the socket
+}
+
+impl Default for IpcConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            socket: None,
+            tcp_port: None,
+        }
+    }
+}
+
+impl IpcConfig {
+    // $XDG_RUNTIME_DIR/splay.sock unless another one is set, falling back to the temp directory
+    pub fn socket(&self) -> PathBuf {
+        if let Some(socket) = &self.socket {
+            return PathBuf::from(expand_home(socket));
+        }
+        match env::var("XDG_RUNTIME_DIR").ok().filter(|dir| !dir.is_empty()) {
+            Some(dir) => PathBuf::from(dir).join("splay.sock"),
+            None => {
+                let user = env::var("USER").unwrap_or_default();
+                env::temp_dir().join(format!("splay-{}.sock", user))
+            }
+        }
+    }
+}
+
+// the media keys and the desktop's player widgets, on linux
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct MprisConfig {
+    pub enabled: bool,
+}
+
+impl Default for MprisConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+// desktop notifications of the song that starts playing and of what went wrong
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct NotificationsConfig {
+    pub enabled: bool,
+    pub timeout_ms: u32, // how long each stays up, 0 until it's closed
+    pub when_focused: bool, // also while the terminal splay is in has the focus
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout_ms: 5000,
+            when_focused: false,
+        }
+    }
+}
+
+// tags, cover art and artist bios looked up on MusicBrainz and Last.fm. off unless it's enabled,
+// for those who'd rather nothing was sent anywhere
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct MetadataConfig {
+    pub online: bool,
+    pub lastfm_api_key: String, // for bios and more art, the one scrobbling uses if it's not set
+}
+
+// services what's played is sent to, each off until it's enabled
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct ScrobblingConfig {
+    pub lastfm: LastfmConfig,
+    pub listenbrainz: ListenBrainzConfig,
+}
+
+// an api account from last.fm/api/account/create, and the login it scrobbles to
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct LastfmConfig {
+    pub enabled: bool,
+    pub api_key: String,
+    pub api_secret: String,
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct ListenBrainzConfig {
+    pub enabled: bool,
+    pub token: String, // from listenbrainz.org/settings
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+pub struct ColumnConfig {
+    pub name: SongColumns,
+    // percent of the list's width. without one the duration is as wide as it needs and the
+    // other columns share what's left
+    pub width: Option<u16>,
+}
+
+impl ColumnConfig {
+    pub fn new(name: SongColumns, width: Option<u16>) -> Self {
+        Self { name, width }
+    }
+}
+
+// a preset with any of its styles replaced
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub preset: ThemePresets,
+    pub text: Option<StyleConfig>,
+    pub highlight: Option<StyleConfig>, // the selected row of a list
+    pub accent: Option<StyleConfig>,
+    pub dimmed: Option<StyleConfig>,
+    pub error: Option<StyleConfig>,
+    pub gauge: Option<StyleConfig>,
+    pub highlight_symbol: Option<String>,
+    pub chart_colors: Option<Vec<String>>,
+}
+
+// colors are names like "cyan" or hex like "#88c0d0", modifiers are eg. "bold" or "italic"
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct StyleConfig {
+    pub fg: Option<String>,
+    pub bg: Option<String>,
+    pub modifiers: Option<Vec<String>>,
+}
+
+// one key or several for the same action
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyList {
+    pub fn keys(&self) -> Vec<String> {
+        match self {
+            KeyList::One(key) => vec![key.to_owned()],
+            KeyList::Many(keys) => keys.to_owned(),
+        }
+    }
+}
+
+// read and written instead of the usual places for the rest of the run
+pub fn set_path(path: PathBuf) {
+    let _ = PATH_OVERRIDE.set(path);
+}
+
+// ./config.toml, then $XDG_CONFIG_HOME/splay/config.toml, falling back to ~/.config. when none
+// of them exist this is where a new one is written
+pub fn path() -> PathBuf {
+    if let Some(path) = PATH_OVERRIDE.get() {
+        return path.to_owned();
+    }
+    let local = PathBuf::from(LOCAL_CONFIG_PATH);
+    if local.exists() {
+        return local;
+    }
+    let config_home = env::var("XDG_CONFIG_HOME")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var("HOME")
+                .ok()
+                .map(|home| PathBuf::from(home).join(".config"))
+        });
+    match config_home {
+        Some(config_home) => config_home.join("splay").join("config.toml"),
+        None => local,
+    }
+}
+
+pub fn read() -> Result<SplayConfig, Box<dyn Error>> {
+    let path = path();
+    let contents = fs::read_to_string(&path).map_err(|err| ConfigError::Unreadable {
+        path: path.display().to_string(),
+        reason: err.to_string(),
+    })?;
+    let config: SplayConfig =
+        toml::from_str(contents.as_str()).map_err(|err| ConfigError::Parsing {
+            path: path.display().to_string(),
+            reason: err.to_string(),
+        })?;
+    config.validate()?;
+    Ok(config)
+}
+
+impl SplayConfig {
+    // with the api key scrobbling uses if it doesn't have its own
+    pub fn metadata(&self) -> MetadataConfig {
+        let mut metadata = self.metadata.clone();
+        if metadata.lastfm_api_key.is_empty() {
+            metadata.lastfm_api_key = self.scrobbling.lastfm.api_key.to_owned();
+        }
+        metadata
+    }
+
+    // catch values that would otherwise be clamped or ignored without a word
+    fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |field: &str, reason: String| {
+            Err(ConfigError::Invalid {
+                field: field.to_string(),
+                reason,
+            })
+        };
+        if self.media.directories.is_empty() {
+            return invalid(
+                "media.directories",
+                "needs at least one directory".to_string(),
+            );
+        }
+        let player = &self.player;
+        if player.crossfade_secs > MAX_CROSSFADE_SECS {
+            return invalid(
+                "player.crossfade_secs",
+                format!(
+                    "is {}, it can be at most {}",
+                    player.crossfade_secs, MAX_CROSSFADE_SECS
+                ),
+            );
+        }
+        if player.fade_ms > MAX_FADE_MS {
+            return invalid(
+                "player.fade_ms",
+                format!("is {}, it can be at most {}", player.fade_ms, MAX_FADE_MS),
+            );
+        }
+        if player.balance.abs() > MAX_BALANCE {
+            return invalid(
+                "player.balance",
+                format!("is {}, it has to be between -1.0 and 1.0", player.balance),
+            );
+        }
+        if player.play_count_threshold > 100 {
+            return invalid(
+                "player.play_count_threshold",
+                format!(
+                    "is {}, it's a percentage from 0 to 100",
+                    player.play_count_threshold
+                ),
+            );
+        }
+        if !(MIN_TICK_RATE..=MAX_TICK_RATE).contains(&self.ui.tick_rate) {
+            return invalid(
+                "ui.tick_rate",
+                format!(
+                    "is {}, it has to be between {} and {}",
+                    self.ui.tick_rate, MIN_TICK_RATE, MAX_TICK_RATE
+                ),
+            );
+        }
+        if self.ui.marquee_speed == 0 {
+            return invalid("ui.marquee_speed", "is 0, it has to be at least 1".to_string());
+        }
+        if let Some(problem) = self.layout.problem() {
+            return invalid("layout", problem);
+        }
+        let lastfm = &self.scrobbling.lastfm;
+        if lastfm.enabled {
+            let fields = [
+                ("api_key", &lastfm.api_key),
+                ("api_secret", &lastfm.api_secret),
+                ("username", &lastfm.username),
+                ("password", &lastfm.password),
+            ];
+            if let Some((field, _)) = fields.iter().find(|(_, value)| value.is_empty()) {
+                return invalid(
+                    &format!("scrobbling.lastfm.{}", field),
+                    "is needed to scrobble to last.fm".to_string(),
+                );
+            }
+        }
+        if self.scrobbling.listenbrainz.enabled && self.scrobbling.listenbrainz.token.is_empty() {
+            return invalid(
+                "scrobbling.listenbrainz.token",
+                "is needed to scrobble to ListenBrainz".to_string(),
+            );
+        }
+        if self.player.mode == PlayerModes::MPD && self.mpd.host.is_empty() {
+            return invalid("mpd.host", "is needed to play through mpd".to_string());
+        }
+        if let Some(bands) = &self.equalizer.bands {
+            if let Some(gain) = bands.iter().find(|gain| gain.abs() > MAX_GAIN_DB) {
+                return invalid(
+                    "equalizer.bands",
+                    format!(
+                        "has a gain of {} dB, they have to be within ±{} dB",
+                        gain, MAX_GAIN_DB
+                    ),
+                );
+            }
+        }
+        for playlist in &self.playlists {
+            if playlist.rules.is_empty() {
+                return invalid(
+                    "playlists",
+                    format!("\"{}\" needs at least one rule", playlist.name),
+                );
+            }
+        }
+        let columns = &self.song_list.columns;
+        if columns.is_empty() {
+            return invalid(
+                "song_list.columns",
+                "needs at least one column".to_string(),
+            );
+        }
+        for (idx, column) in columns.iter().enumerate() {
+            if columns[..idx].iter().any(|other| other.name == column.name) {
+                return invalid(
+                    "song_list.columns",
+                    format!("has the {} column more than once", column.name.name()),
+                );
+            }
+        }
+        let total_width: u32 = columns
+            .iter()
+            .filter_map(|column| column.width)
+            .map(u32::from)
+            .sum();
+        if total_width > 100 {
+            return invalid(
+                "song_list.columns",
+                format!(
+                    "has widths adding up to {}%, they can take at most 100%",
+                    total_width
+                ),
+            );
+        }
+        // these already say which field is wrong
+        for error in [
+            Theme::from_config(&self.theme).err(),
+            SongFormats::from_config(&self.formats).err(),
+            Keybinds::from_config(&self.keybindings, &self.macros).err(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            let (field, reason) = error.split_once(": ").unwrap_or(("", &error));
+            return invalid(field, reason.to_string());
+        }
+        Ok(())
+    }
+}
+
+// write the equalizer settings back into the config file, keeping the rest of it as it was
+pub fn save_equalizer(preset: EqPresets, bands: &[f32; EQ_NUM_BANDS]) -> Result<(), Box<dyn Error>> {
+    let path = path();
+    let contents = fs::read_to_string(&path).unwrap_or_default();
+    let mut document = contents.parse::<Document>()?;
+    document["equalizer"]["preset"] = value(preset.name());
+    let mut array = Array::new();
+    for gain in bands {
+        array.push(*gain as f64);
+    }
+    document["equalizer"]["bands"] = value(array);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, document.to_string())?;
+    Ok(())
+}
@@ -0,0 +1,274 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::library::song;
+use crate::utils::constants::requests::{
+    AppRequests, FileActions, LibraryRequests, PlayerRequests, QueueRequests, UIRequests,
+};
+use crate::utils::constants::LibraryViews;
+
+// every command that can be typed after ":", to complete them
+const COMMANDS: [&str; 32] = [
+    "add",
+    "add-to-playlist",
+    "bookmark",
+    "clear",
+    "crossfade",
+    "delete-bookmark",
+    "delete-file",
+    "download",
+    "fetch-info",
+    "forget-search",
+    "move-to",
+    "next",
+    "open",
+    "party",
+    "pause",
+    "play",
+    "q",
+    "quit",
+    "redo",
+    "reload",
+    "repeat",
+    "restart",
+    "replaygain",
+    "save-playlist",
+    "save-search",
+    "seek",
+    "shuffle",
+    "speed",
+    "stop",
+    "trash",
+    "undo",
+    "view",
+];
+
+const VIEWS: [(&str, LibraryViews); 10] = [
+    ("songs", LibraryViews::SONGS),
+    ("artists", LibraryViews::ARTISTS),
+    ("albums", LibraryViews::ALBUMS),
+    ("playlists", LibraryViews::PLAYLISTS),
+    ("duplicates", LibraryViews::DUPLICATES),
+    ("most-played", LibraryViews::MOST_PLAYED),
+    ("recently-played", LibraryViews::RECENTLY_PLAYED),
+    ("recently-added", LibraryViews::RECENTLY_ADDED),
+    ("podcasts", LibraryViews::PODCASTS),
+    ("files", LibraryViews::FILES),
+];
+
+// the request a command line stands for, eg. "seek 1:30" or "add ~/music/album"
+pub fn parse(line: &str) -> Result<AppRequests, String> {
+    let line = line.trim();
+    let (command, arg) = match line.split_once(char::is_whitespace) {
+        Some((command, arg)) => (command, Some(arg.trim())),
+        None => (line, None),
+    };
+    let request = match (command, arg) {
+        ("q" | "quit", None) => AppRequests::Quit,
+        ("add", Some(path)) => {
+            AppRequests::QueueRequests(QueueRequests::AddPath(expand_home(path)))
+        }
+        ("add-to-playlist", Some(name)) => {
+            AppRequests::QueueRequests(QueueRequests::AddToPlaylist(name.to_string()))
+        }
+        ("save-playlist", name) => {
+            AppRequests::QueueRequests(QueueRequests::SaveAsPlaylist(name.map(str::to_string)))
+        }
+        ("save-search", Some(name)) => {
+            AppRequests::LibraryRequests(LibraryRequests::SaveSearch(name.to_string()))
+        }
+        ("forget-search", Some(name)) => {
+            AppRequests::LibraryRequests(LibraryRequests::ForgetSearch(name.to_string()))
+        }
+        ("bookmark", name) => {
+            AppRequests::LibraryRequests(LibraryRequests::AddBookmark(name.map(str::to_string)))
+        }
+        ("delete-bookmark", Some(name)) => {
+            AppRequests::LibraryRequests(LibraryRequests::DeleteBookmark(name.to_string()))
+        }
+        ("clear", None) => AppRequests::QueueRequests(QueueRequests::Clear),
+        ("download", None) => AppRequests::UIRequests(UIRequests::DownloadEpisode),
+        ("fetch-info", None) => AppRequests::UIRequests(UIRequests::FetchMetadata),
+        ("party", None) => AppRequests::UIRequests(UIRequests::ToggleParty),
+        ("open", Some(url)) if song::is_stream_url(url) => {
+            AppRequests::UIRequests(UIRequests::OpenStream(url.to_string()))
+        }
+        ("open", Some(url)) => return Err(format!("\"{}\" is not an http or https url", url)),
+        ("seek", Some(time)) => AppRequests::PlayerRequests(parse_seek(time)?),
+        ("play", None) => AppRequests::PlayerRequests(PlayerRequests::Resume),
+        ("pause", None) => AppRequests::PlayerRequests(PlayerRequests::Pause),
+        ("stop", None) => AppRequests::PlayerRequests(PlayerRequests::Stop),
+        ("next", None) => AppRequests::PlayerRequests(PlayerRequests::Next),
+        ("restart", None) => AppRequests::PlayerRequests(PlayerRequests::Restart),
+        ("shuffle", None) => AppRequests::PlayerRequests(PlayerRequests::ToggleShuffle),
+        ("repeat", None) => AppRequests::PlayerRequests(PlayerRequests::CycleRepeat),
+        ("speed", Some(speed)) => match speed.parse::<f32>() {
+            Ok(speed) => AppRequests::PlayerRequests(PlayerRequests::SetSpeed(speed)),
+            Err(_) => return Err(format!("\"{}\" is not a speed, eg. 1.25", speed)),
+        },
+        ("crossfade", Some(secs)) => match secs.parse::<u64>() {
+            Ok(secs) => AppRequests::PlayerRequests(PlayerRequests::SetCrossfade(secs)),
+            Err(_) => return Err(format!("\"{}\" is not a number of seconds", secs)),
+        },
+        ("reload", None) => AppRequests::ReloadConfig,
+        ("undo", None) => AppRequests::QueueRequests(QueueRequests::Undo),
+        ("redo", None) => AppRequests::QueueRequests(QueueRequests::Redo),
+        ("replaygain", None) => AppRequests::LibraryRequests(LibraryRequests::MeasureGain(false)),
+        ("replaygain", Some("write")) => {
+            AppRequests::LibraryRequests(LibraryRequests::MeasureGain(true))
+        }
+        ("replaygain", Some(arg)) => {
+            return Err(format!("replaygain only takes \"write\", not \"{}\"", arg))
+        }
+        ("move-to", Some(dir)) => {
+            AppRequests::UIRequests(UIRequests::ConfirmFiles(FileActions::MoveTo(expand_home(dir))))
+        }
+        ("trash", None) => AppRequests::UIRequests(UIRequests::ConfirmFiles(FileActions::Trash)),
+        ("delete-file", None) => {
+            AppRequests::UIRequests(UIRequests::ConfirmFiles(FileActions::Delete))
+        }
+        ("view", Some(name)) => match VIEWS.iter().find(|(view, _)| *view == name) {
+            Some((_, view)) => AppRequests::UIRequests(UIRequests::ShowView(*view)),
+            None => return Err(format!("There's no \"{}\" view", name)),
+        },
+        ("", None) => return Err("Type a command".to_string()),
+        (command, _) if !COMMANDS.contains(&command) => {
+            return Err(format!("Unknown command \"{}\"", command))
+        }
+        (command, Some(_)) => return Err(format!("{} doesn't take anything after it", command)),
+        (command, None) => return Err(format!("{} needs {}", command, usage(command))),
+    };
+    Ok(request)
+}
+
+fn usage(command: &str) -> &'static str {
+    match command {
+        "add" => "a file, directory or playlist",
+        "add-to-playlist" => "the name of a playlist",
+        "move-to" => "the directory to move the selected songs' files to",
+        "save-search" | "forget-search" => "the name of a saved search",
+        "delete-bookmark" => "the name of a bookmark of the song playing",
+        "open" => "the url of a stream, eg. an internet radio station",
+        "seek" => "a time like 1:30, or +10 and -10 to seek from where it is",
+        "speed" => "a speed like 1.25",
+        "crossfade" => "a number of seconds",
+        "view" => {
+            "one of songs, artists, albums, playlists, duplicates, most-played, recently-played, \
+            recently-added, podcasts and files"
+        }
+        _ => "something after it",
+    }
+}
+
+// "1:30" and "90" are positions in the song, "+10" and "-10" are relative to where it is
+fn parse_seek(time: &str) -> Result<PlayerRequests, String> {
+    let invalid = || format!("\"{}\" is not a time, eg. 1:30 or +10", time);
+    if let Some(secs) = time.strip_prefix('+') {
+        let secs = parse_time(secs).ok_or_else(invalid)?;
+        return Ok(PlayerRequests::SeekRelative(secs.as_secs() as i64));
+    }
+    if let Some(secs) = time.strip_prefix('-') {
+        let secs = parse_time(secs).ok_or_else(invalid)?;
+        return Ok(PlayerRequests::SeekRelative(-(secs.as_secs() as i64)));
+    }
+    Ok(PlayerRequests::SeekTo(
+        parse_time(time).ok_or_else(invalid)?,
+    ))
+}
+
+// seconds, minutes:seconds or hours:minutes:seconds
+fn parse_time(time: &str) -> Option<Duration> {
+    let mut secs = 0;
+    for (idx, part) in time.split(':').enumerate() {
+        if idx > 2 {
+            return None;
+        }
+        secs = secs * 60 + part.parse::<u64>().ok()?;
+    }
+    Some(Duration::from_secs(secs))
+}
+
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => PathBuf::from(format!("{}{}", home, rest)),
+        _ => PathBuf::from(path),
+    }
+}
+
+// the lines tab could complete the command line to. command names are completed, then the
+// paths of ":add" and the views of ":view"
+pub fn complete(line: &str) -> Vec<String> {
+    let (command, arg) = match line.split_once(' ') {
+        Some(split) => split,
+        None => {
+            return COMMANDS
+                .iter()
+                .filter(|command| command.starts_with(line))
+                .map(|command| command.to_string())
+                .collect()
+        }
+    };
+    let args = match command {
+        "add" | "move-to" => complete_path(arg),
+        "view" => VIEWS
+            .iter()
+            .map(|(view, _)| view.to_string())
+            .filter(|view| view.starts_with(arg))
+            .collect(),
+        _ => Vec::new(),
+    };
+    args.into_iter()
+        .map(|arg| format!("{} {}", command, arg))
+        .collect()
+}
+
+// the entries of the directory being typed that start with what's typed of the last part.
+// directories end with a slash so that tab can go on into them
+fn complete_path(typed: &str) -> Vec<String> {
+    let (dir, prefix) = match typed.rfind('/') {
+        Some(idx) => typed.split_at(idx + 1),
+        None => ("", typed),
+    };
+    let read_dir = match dir {
+        "" => PathBuf::from("."),
+        dir => expand_home(dir),
+    };
+    let entries = match fs::read_dir(read_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut paths: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // hidden files only when asked for
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.path().is_dir();
+            Some(format!("{}{}{}", dir, name, if is_dir { "/" } else { "" }))
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+// the longest start that every completion has in common
+pub fn common_prefix(completions: &[String]) -> String {
+    let first = match completions.first() {
+        Some(first) => first,
+        None => return String::new(),
+    };
+    let mut len = first.len();
+    for completion in completions {
+        len = first
+            .char_indices()
+            .zip(completion.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((idx, ch), _)| idx + ch.len_utf8())
+            .min(len);
+    }
+    first[..len].to_string()
+}
@@ -2,117 +2,506 @@ use std::collections::HashMap;
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
-use crate::utils::constants::requests::{AppRequests, PlayerRequests, UIRequests};
+use crate::config::KeyList;
+use crate::utils::constants::requests::{
+    AppRequests, LibraryRequests, PlayerRequests, QueueRequests, UIRequests,
+};
+use crate::utils::constants::{AppViews, LibraryViews};
+
+// the keys of every action unless the config binds it to something else. keys are written like
+// "j", "J", "ctrl+c", "alt+1", "space" or "left", and keys separated by spaces have to be
+// pressed one after the other, eg. "g g"
+const DEFAULT_BINDINGS: [(&str, &[&str]); 93] = [
+    ("quit", &["q", "ctrl+c"]),
+    ("down", &["j", "down"]),
+    ("up", &["k", "up"]),
+    ("top", &["g g", "home"]),
+    ("bottom", &["G", "end"]),
+    ("page_up", &["pageup"]),
+    ("page_down", &["pagedown"]),
+    ("jump_to_letter", &["'"]),
+    ("enter", &["enter"]),
+    ("back", &["esc"]),
+    ("search", &["ctrl+f"]),
+    ("next_tab", &["tab"]),
+    ("library_tab", &["1"]),
+    ("queue_tab", &["2"]),
+    ("playlists_tab", &["3"]),
+    ("search_tab", &["4"]),
+    ("settings_tab", &["5"]),
+    ("next_view", &["backtab"]),
+    ("songs_view", &["f1"]),
+    ("artists_view", &["f2"]),
+    ("albums_view", &["f3"]),
+    ("playlists_view", &[]), // the same as the playlists tab
+    ("duplicates_view", &["f4"]),
+    ("most_played_view", &["f5"]),
+    ("recently_played_view", &["f6"]),
+    ("recently_added_view", &["f9"]),
+    ("podcasts_view", &["f7"]),
+    ("files_view", &["f8"]),
+    ("jump_to_song", &["g c"]),
+    ("jump_to_playing", &["z"]),
+    ("toggle_favorite", &["f"]),
+    ("cycle_sort", &["O"]),
+    ("toggle_lyrics", &["L"]),
+    ("toggle_visualizer", &["V"]),
+    ("toggle_stats", &["i"]),
+    ("toggle_log", &["~"]),
+    ("clear_rating", &["alt+0"]),
+    ("rate_1", &["alt+1"]),
+    ("rate_2", &["alt+2"]),
+    ("rate_3", &["alt+3"]),
+    ("rate_4", &["alt+4"]),
+    ("rate_5", &["alt+5"]),
+    ("rate_up", &["+"]),
+    ("rate_down", &["-"]),
+    ("edit_tags", &["e"]),
+    ("song_menu", &["M"]),
+    ("file_info", &["I"]),
+    ("bookmarks", &["B"]),
+    ("pause", &["p"]),
+    ("resume", &["P"]),
+    ("play_pause", &["space"]),
+    ("stop", &["x"]),
+    ("stop_after_current", &["S"]),
+    ("next", &["n"]),
+    ("seek_backward", &["h", "left"]),
+    ("seek_forward", &["l", "right"]),
+    ("toggle_shuffle", &["s"]),
+    ("cycle_repeat", &["r"]),
+    ("mark_loop", &["b"]),
+    ("speed_up", &["]"]),
+    ("slow_down", &["["]),
+    ("reset_speed", &["="]),
+    ("toggle_mono", &["m"]),
+    ("balance_left", &[","]),
+    ("balance_right", &["."]),
+    ("equalizer", &["E"]),
+    ("enqueue", &["a"]),
+    ("insert_next", &["A"]),
+    ("clear_queue", &["C"]),
+    ("save_queue", &["W"]),
+    ("load_playlist", &["o"]),
+    ("purge_missing", &["X"]),
+    ("undo", &["u"]),
+    ("redo", &["ctrl+r"]),
+    ("reload_config", &["R"]),
+    ("command_mode", &[":"]),
+    ("visual_mode", &["v"]),
+    ("toggle_group", &["c"]),
+    // only while the equalizer is open
+    ("eq_lower", &["h", "left"]),
+    ("eq_raise", &["l", "right"]),
+    ("eq_next_preset", &["tab"]),
+    ("eq_close", &["E"]),
+    // only while the last search is highlighted
+    ("search_next", &["n"]),
+    ("search_previous", &["N"]),
+    // only while the artists view shows its columns
+    ("columns_left", &["left"]),
+    ("columns_right", &["right"]),
+    // only while a queue is shown beside the open tab
+    ("panes_cycle", &["tab", "backtab"]),
+    // only while the queue tab is shown, or the queue beside it has the focus
+    ("queue_move_up", &["K", "ctrl+up"]),
+    ("queue_move_down", &["J", "ctrl+down"]),
+    ("queue_remove", &["d"]),
+    ("queue_clear", &["c"]),
+    // only while a list of the library, playlists or search tab has the focus
+    ("library_half_page_down", &["d", "ctrl+d"]),
+    ("library_half_page_up", &["ctrl+u"]),
+];
+
+pub type KeySequence = Vec<KeyEvent>;
 
 pub struct Keybinds {
-    pub lookup: HashMap<KeyEvent, AppRequests>,
+    pub lookup: HashMap<KeySequence, AppRequests>,
+    // the actions of each context by its name, checked before the rest while it's active
+    pub context_lookups: HashMap<&'static str, HashMap<KeySequence, AppRequests>>,
+}
+
+// whether the keys pressed so far are bound to something, or could still become a binding
+#[derive(Debug, Clone)]
+pub enum Lookup {
+    Found(AppRequests),
+    // `Some` if the keys are a binding of their own that's used if nothing else is pressed
+    Pending(Option<AppRequests>),
+    NotFound,
 }
 
 impl Keybinds {
-    pub fn from_config(path: String) -> Self {
-        Keybinds::default()
-        //TODO: need to verify that a key isn't bound to two actions
+    // the defaults with the actions in the config's [keybindings] bound to the keys given there
+    // instead, and the keys in [macros] bound to the actions listed for them
+    pub fn from_config(
+        bindings: &HashMap<String, KeyList>,
+        macros: &HashMap<String, Vec<String>>,
+    ) -> Result<Self, String> {
+        let mut keys: Vec<(String, Vec<String>)> = DEFAULT_BINDINGS
+            .iter()
+            .map(|(action, keys)| {
+                (
+                    action.to_string(),
+                    keys.iter().map(|key| key.to_string()).collect(),
+                )
+            })
+            .collect();
+        for (action, key_list) in bindings {
+            if action_request(action).is_none() {
+                return Err(format!("keybindings: unknown action \"{}\"", action));
+            }
+            let user_keys = key_list.keys();
+            for sequence in user_keys.iter() {
+                parse_sequence(sequence)
+                    .map_err(|err| format!("keybindings.{}: {}", action, err))?;
+            }
+            // a key taken by the config is no longer bound to the default action, unless it's
+            // in another lookup
+            let context = context(action);
+            for (other, other_keys) in keys.iter_mut() {
+                if self::context(other) == context {
+                    other_keys.retain(|key| !user_keys.contains(key));
+                }
+            }
+            match keys.iter_mut().find(|(other, _)| other == action) {
+                Some((_, action_keys)) => *action_keys = user_keys,
+                None => keys.push((action.to_owned(), user_keys)),
+            }
+        }
+
+        let mut lookup = HashMap::new();
+        let mut context_lookups: HashMap<&'static str, HashMap<KeySequence, AppRequests>> =
+            HashMap::new();
+        for (action, action_keys) in keys {
+            let request = match action_request(&action) {
+                Some(request) => request,
+                None => continue,
+            };
+            let lookup = match context(&action) {
+                "" => &mut lookup,
+                context => context_lookups.entry(context).or_default(),
+            };
+            for sequence in action_keys {
+                lookup.insert(parse_sequence(&sequence)?, request.to_owned());
+            }
+        }
+        // a macro takes its keys from every action, even the ones only bound while something is
+        // open
+        for (sequence, actions) in macros {
+            let keys =
+                parse_sequence(sequence).map_err(|err| format!("macros.{}: {}", sequence, err))?;
+            let mut requests = Vec::new();
+            for action in actions {
+                match action_request(action) {
+                    Some(request) => requests.push(request),
+                    None => {
+                        return Err(format!("macros.{}: unknown action \"{}\"", sequence, action))
+                    }
+                }
+            }
+            if requests.is_empty() {
+                return Err(format!("macros.{}: needs at least one action", sequence));
+            }
+            for context_lookup in context_lookups.values_mut() {
+                context_lookup.remove(&keys);
+            }
+            lookup.insert(keys, AppRequests::Macro(requests));
+        }
+        Ok(Keybinds {
+            lookup,
+            context_lookups,
+        })
+    }
+
+    // the contexts that are active are checked in the order they're given
+    pub fn lookup(&self, keys: &[KeyEvent], active: &[&str]) -> Lookup {
+        for context in active {
+            if let Some(lookup) = self.context_lookups.get(context) {
+                match find(lookup, keys) {
+                    Lookup::NotFound => (),
+                    found => return found,
+                }
+            }
+        }
+        find(&self.lookup, keys)
     }
 }
 
 impl Default for Keybinds {
     fn default() -> Self {
-        let mut lookup: HashMap<KeyEvent, AppRequests> = HashMap::new();
-        lookup.insert(
-            KeyEvent {
-                code: KeyCode::Char('q'),
-                modifiers: KeyModifiers::NONE,
-            },
-            AppRequests::Quit,
-        );
-        lookup.insert(
-            KeyEvent {
-                code: KeyCode::Char('c'),
-                modifiers: KeyModifiers::CONTROL,
-            },
-            AppRequests::Quit,
-        );
-        lookup.insert(
-            KeyEvent {
-                code: KeyCode::Char('j'),
-                modifiers: KeyModifiers::NONE,
-            },
-            AppRequests::UIRequests(UIRequests::Down),
-        );
-        lookup.insert(
-            KeyEvent {
-                code: KeyCode::Char('k'),
-                modifiers: KeyModifiers::NONE,
-            },
-            AppRequests::UIRequests(UIRequests::Up),
-        );
-        // lookup.insert(
-        //     KeyEvent {
-        //         code: KeyCode::Enter,
-        //         modifiers: KeyModifiers::NONE,
-        //     },
-        //     AppRequests::UIRequests(UIRequests::Enter),
-        // );
-        lookup.insert(
-            KeyEvent {
-                code: KeyCode::Down,
-                modifiers: KeyModifiers::NONE,
-            },
-            AppRequests::UIRequests(UIRequests::Down),
-        );
-        lookup.insert(
-            KeyEvent {
-                code: KeyCode::Up,
-                modifiers: KeyModifiers::NONE,
-            },
-            AppRequests::UIRequests(UIRequests::Up),
-        );
-        lookup.insert(
-            KeyEvent {
-                code: KeyCode::Enter,
-                modifiers: KeyModifiers::NONE,
-            },
-            AppRequests::PlayerRequests(PlayerRequests::Start),
-        );
-        lookup.insert(
-            KeyEvent {
-                code: KeyCode::Char('f'),
-                modifiers: KeyModifiers::CONTROL,
-            },
-            AppRequests::UIRequests(UIRequests::ShowSearch),
-        );
+        Keybinds::from_config(&HashMap::new(), &HashMap::new()).unwrap()
+    }
+}
 
-        lookup.insert(
-            KeyEvent {
-                code: KeyCode::Esc,
-                modifiers: KeyModifiers::NONE,
-            },
-            AppRequests::UIRequests(UIRequests::GoBack),
-        );
+// the actions that are only bound while something is open, by the name of what has to be open
+const CONTEXTS: [(&str, &[&str]); 6] = [
+    ("equalizer", &["eq_lower", "eq_raise", "eq_next_preset", "eq_close"]),
+    ("search", &["search_next", "search_previous"]),
+    ("columns", &["columns_left", "columns_right"]),
+    ("queue", &["queue_move_up", "queue_move_down", "queue_remove", "queue_clear"]),
+    ("library", &["library_half_page_down", "library_half_page_up"]),
+    ("panes", &["panes_cycle"]),
+];
 
-        lookup.insert(
-            KeyEvent {
-                code: KeyCode::Char('p'),
-                modifiers: KeyModifiers::NONE,
-            },
-            AppRequests::PlayerRequests(PlayerRequests::Pause),
-        );
+// the context an action is only bound in, empty for the rest
+fn context(action: &str) -> &'static str {
+    CONTEXTS
+        .into_iter()
+        .find(|(_, actions)| actions.contains(&action))
+        .map(|(context, _)| context)
+        .unwrap_or_default()
+}
 
-        lookup.insert(
-            KeyEvent {
-                code: KeyCode::Char(' '),
-                modifiers: KeyModifiers::NONE,
-            },
-            AppRequests::PlayerRequests(PlayerRequests::Resume),
-        );
-        lookup.insert(
-            KeyEvent {
-                code: KeyCode::Char('x'),
-                modifiers: KeyModifiers::NONE,
+fn find(lookup: &HashMap<KeySequence, AppRequests>, keys: &[KeyEvent]) -> Lookup {
+    let exact = lookup.get(keys).cloned();
+    let longer = lookup
+        .keys()
+        .any(|sequence| sequence.len() > keys.len() && sequence.starts_with(keys));
+    match (exact, longer) {
+        (exact, true) => Lookup::Pending(exact),
+        (Some(request), false) => Lookup::Found(request),
+        (None, false) => Lookup::NotFound,
+    }
+}
+
+// what each action in the config does
+fn action_request(action: &str) -> Option<AppRequests> {
+    let request = match action {
+        "quit" => AppRequests::Quit,
+        "down" => AppRequests::UIRequests(UIRequests::Down),
+        "up" => AppRequests::UIRequests(UIRequests::Up),
+        "top" => AppRequests::UIRequests(UIRequests::Top),
+        "bottom" => AppRequests::UIRequests(UIRequests::Bottom),
+        "page_up" => AppRequests::UIRequests(UIRequests::PageUp),
+        "page_down" => AppRequests::UIRequests(UIRequests::PageDown),
+        "jump_to_letter" => AppRequests::UIRequests(UIRequests::StartJump),
+        "enter" => AppRequests::UIRequests(UIRequests::Enter),
+        "play" => AppRequests::UIRequests(UIRequests::Play),
+        "restart" => AppRequests::PlayerRequests(PlayerRequests::Restart),
+        "back" | "eq_close" => AppRequests::UIRequests(UIRequests::GoBack),
+        "search" => AppRequests::UIRequests(UIRequests::ShowSearch),
+        "next_tab" => AppRequests::UIRequests(UIRequests::NextTab),
+        "library_tab" => AppRequests::UIRequests(UIRequests::ShowTab(AppViews::LIBRARY)),
+        "queue_tab" => AppRequests::UIRequests(UIRequests::ShowTab(AppViews::QUEUE)),
+        "playlists_tab" => AppRequests::UIRequests(UIRequests::ShowTab(AppViews::PLAYLISTS)),
+        "search_tab" => AppRequests::UIRequests(UIRequests::ShowTab(AppViews::SEARCH)),
+        "settings_tab" => AppRequests::UIRequests(UIRequests::ShowTab(AppViews::SETTINGS)),
+        "next_view" => AppRequests::UIRequests(UIRequests::NextView),
+        "songs_view" => AppRequests::UIRequests(UIRequests::ShowView(LibraryViews::SONGS)),
+        "artists_view" => AppRequests::UIRequests(UIRequests::ShowView(LibraryViews::ARTISTS)),
+        "albums_view" => AppRequests::UIRequests(UIRequests::ShowView(LibraryViews::ALBUMS)),
+        "playlists_view" => AppRequests::UIRequests(UIRequests::ShowView(LibraryViews::PLAYLISTS)),
+        "duplicates_view" => {
+            AppRequests::UIRequests(UIRequests::ShowView(LibraryViews::DUPLICATES))
+        }
+        "most_played_view" => {
+            AppRequests::UIRequests(UIRequests::ShowView(LibraryViews::MOST_PLAYED))
+        }
+        "recently_played_view" => {
+            AppRequests::UIRequests(UIRequests::ShowView(LibraryViews::RECENTLY_PLAYED))
+        }
+        "recently_added_view" => {
+            AppRequests::UIRequests(UIRequests::ShowView(LibraryViews::RECENTLY_ADDED))
+        }
+        "podcasts_view" => AppRequests::UIRequests(UIRequests::ShowView(LibraryViews::PODCASTS)),
+        "files_view" => AppRequests::UIRequests(UIRequests::ShowView(LibraryViews::FILES)),
+        "jump_to_song" => AppRequests::UIRequests(UIRequests::JumpToSong),
+        "jump_to_playing" => AppRequests::UIRequests(UIRequests::JumpToPlaying),
+        "toggle_favorite" => AppRequests::UIRequests(UIRequests::ToggleFavorite),
+        "cycle_sort" => AppRequests::UIRequests(UIRequests::CycleSort),
+        "toggle_lyrics" => AppRequests::UIRequests(UIRequests::ToggleLyrics),
+        "toggle_visualizer" => AppRequests::UIRequests(UIRequests::ToggleVisualizer),
+        "toggle_stats" => AppRequests::UIRequests(UIRequests::ToggleStats),
+        "toggle_log" => AppRequests::UIRequests(UIRequests::ToggleLog),
+        "clear_rating" => AppRequests::UIRequests(UIRequests::Rate(0)),
+        "rate_1" => AppRequests::UIRequests(UIRequests::Rate(1)),
+        "rate_2" => AppRequests::UIRequests(UIRequests::Rate(2)),
+        "rate_3" => AppRequests::UIRequests(UIRequests::Rate(3)),
+        "rate_4" => AppRequests::UIRequests(UIRequests::Rate(4)),
+        "rate_5" => AppRequests::UIRequests(UIRequests::Rate(5)),
+        "rate_up" => AppRequests::UIRequests(UIRequests::ChangeRating(1)),
+        "rate_down" => AppRequests::UIRequests(UIRequests::ChangeRating(-1)),
+        "edit_tags" => AppRequests::UIRequests(UIRequests::EditTags),
+        "song_menu" => AppRequests::UIRequests(UIRequests::ShowMenu),
+        "bookmarks" => AppRequests::UIRequests(UIRequests::ShowBookmarks),
+        "file_info" => AppRequests::UIRequests(UIRequests::ShowFileInfo),
+        "pause" => AppRequests::PlayerRequests(PlayerRequests::Pause),
+        "resume" => AppRequests::PlayerRequests(PlayerRequests::Resume),
+        "play_pause" => AppRequests::PlayerRequests(PlayerRequests::PlayPause),
+        "stop" => AppRequests::PlayerRequests(PlayerRequests::Stop),
+        "stop_after_current" => AppRequests::PlayerRequests(PlayerRequests::ToggleStopAfterCurrent),
+        "next" => AppRequests::PlayerRequests(PlayerRequests::Next),
+        "seek_backward" => AppRequests::PlayerRequests(PlayerRequests::SeekRelative(-5)),
+        "seek_forward" => AppRequests::PlayerRequests(PlayerRequests::SeekRelative(5)),
+        "toggle_shuffle" => AppRequests::PlayerRequests(PlayerRequests::ToggleShuffle),
+        "cycle_repeat" => AppRequests::PlayerRequests(PlayerRequests::CycleRepeat),
+        "speed_up" => AppRequests::UIRequests(UIRequests::SpeedUp),
+        "slow_down" => AppRequests::UIRequests(UIRequests::SlowDown),
+        "mark_loop" => AppRequests::PlayerRequests(PlayerRequests::MarkLoop),
+        "reset_speed" => AppRequests::PlayerRequests(PlayerRequests::SetSpeed(1.)),
+        "toggle_mono" => AppRequests::PlayerRequests(PlayerRequests::ToggleMono),
+        "balance_left" => AppRequests::UIRequests(UIRequests::BalanceLeft),
+        "balance_right" => AppRequests::UIRequests(UIRequests::BalanceRight),
+        "equalizer" => AppRequests::UIRequests(UIRequests::ShowEqualizer),
+        "enqueue" => AppRequests::QueueRequests(QueueRequests::Enqueue),
+        "insert_next" => AppRequests::QueueRequests(QueueRequests::InsertNext),
+        "clear_queue" => AppRequests::QueueRequests(QueueRequests::Clear),
+        "undo" => AppRequests::QueueRequests(QueueRequests::Undo),
+        "redo" => AppRequests::QueueRequests(QueueRequests::Redo),
+        "save_queue" => AppRequests::QueueRequests(QueueRequests::SaveAsPlaylist(None)),
+        "load_playlist" => AppRequests::UIRequests(UIRequests::LoadPlaylist),
+        "purge_missing" => AppRequests::LibraryRequests(LibraryRequests::PurgeMissing),
+        "reload_config" => AppRequests::ReloadConfig,
+        "command_mode" => AppRequests::UIRequests(UIRequests::ShowCommand),
+        "visual_mode" => AppRequests::UIRequests(UIRequests::ToggleVisual),
+        "toggle_group" => AppRequests::UIRequests(UIRequests::ToggleGroup),
+        "eq_lower" => AppRequests::UIRequests(UIRequests::EqLower),
+        "eq_raise" => AppRequests::UIRequests(UIRequests::EqRaise),
+        "eq_next_preset" => AppRequests::UIRequests(UIRequests::EqNextPreset),
+        "columns_left" => AppRequests::UIRequests(UIRequests::FocusLeft),
+        "columns_right" => AppRequests::UIRequests(UIRequests::FocusRight),
+        "panes_cycle" => AppRequests::UIRequests(UIRequests::CyclePane),
+        "queue_move_up" => AppRequests::UIRequests(UIRequests::MoveInQueue(false)),
+        "queue_move_down" => AppRequests::UIRequests(UIRequests::MoveInQueue(true)),
+        "queue_remove" => AppRequests::UIRequests(UIRequests::RemoveFromQueue),
+        "queue_clear" => AppRequests::QueueRequests(QueueRequests::Clear),
+        "library_half_page_down" => AppRequests::UIRequests(UIRequests::HalfPageDown),
+        "library_half_page_up" => AppRequests::UIRequests(UIRequests::HalfPageUp),
+        "search_next" => AppRequests::UIRequests(UIRequests::NextMatch),
+        "search_previous" => AppRequests::UIRequests(UIRequests::PreviousMatch),
+        _ => return None,
+    };
+    Some(request)
+}
+
+fn parse_sequence(sequence: &str) -> Result<KeySequence, String> {
+    let keys = sequence
+        .split_whitespace()
+        .map(parse_key)
+        .collect::<Result<KeySequence, String>>()?;
+    if keys.is_empty() {
+        return Err("empty key".to_string());
+    }
+    Ok(keys)
+}
+
+// eg. "ctrl+shift+left". a single character is always the key itself, so "+" is the plus key
+fn parse_key(key: &str) -> Result<KeyEvent, String> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut parts: Vec<&str> = if key.chars().count() == 1 {
+        vec![key]
+    } else {
+        key.split('+').collect()
+    };
+    let name = parts.pop().unwrap_or_default();
+    for modifier in parts {
+        modifiers |= match modifier.to_lowercase().as_str() {
+            "ctrl" | "control" => KeyModifiers::CONTROL,
+            "alt" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => return Err(format!("unknown modifier \"{}\" in \"{}\"", modifier, key)),
+        };
+    }
+    let code = if name.chars().count() == 1 {
+        let ch = name.chars().next().unwrap_or_default();
+        // shift + a is the same as A
+        if modifiers.contains(KeyModifiers::SHIFT) {
+            KeyCode::Char(ch.to_ascii_uppercase())
+        } else {
+            KeyCode::Char(ch)
+        }
+    } else {
+        match name.to_lowercase().as_str() {
+            "space" => KeyCode::Char(' '),
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" | "ins" => KeyCode::Insert,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            function => match function.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                Some(number) if (1..=12).contains(&number) => KeyCode::F(number),
+                _ => return Err(format!("unknown key \"{}\"", key)),
             },
-            AppRequests::PlayerRequests(PlayerRequests::Stop),
-        );
+        }
+    };
+    Ok(normalize(KeyEvent { code, modifiers }))
+}
+
+// terminals report shift for capital letters but not always for other shifted characters, so
+// capitals always carry it and every other character never does. back tab is always shifted
+pub fn normalize(mut key: KeyEvent) -> KeyEvent {
+    match key.code {
+        KeyCode::Char(ch) if ch.is_uppercase() => key.modifiers |= KeyModifiers::SHIFT,
+        KeyCode::Char(_) | KeyCode::BackTab => key.modifiers -= KeyModifiers::SHIFT,
+        _ => (),
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(keybinds: &Keybinds, key: &str, active: &[&str]) -> Lookup {
+        keybinds.lookup(&parse_sequence(key).unwrap(), active)
+    }
+
+    fn shows_tab(found: Lookup, tab: AppViews) -> bool {
+        matches!(
+            found,
+            Lookup::Found(AppRequests::UIRequests(UIRequests::ShowTab(shown))) if shown == tab
+        )
+    }
+
+    #[test]
+    fn tab_keys_work_without_a_context() {
+        let keybinds = Keybinds::default();
+        assert!(shows_tab(lookup(&keybinds, "1", &[]), AppViews::LIBRARY));
+        assert!(shows_tab(lookup(&keybinds, "2", &[]), AppViews::QUEUE));
+        assert!(shows_tab(lookup(&keybinds, "4", &[]), AppViews::SEARCH));
+    }
+
+    #[test]
+    fn tab_keys_work_from_the_other_tabs() {
+        let keybinds = Keybinds::default();
+        assert!(shows_tab(
+            lookup(&keybinds, "2", &["library"]),
+            AppViews::QUEUE
+        ));
+        assert!(shows_tab(
+            lookup(&keybinds, "1", &["queue"]),
+            AppViews::LIBRARY
+        ));
+    }
+
+    #[test]
+    fn contexts_are_checked_first_while_active() {
+        let keybinds = Keybinds::default();
+        assert!(matches!(
+            lookup(&keybinds, "d", &["queue"]),
+            Lookup::Found(AppRequests::UIRequests(UIRequests::RemoveFromQueue))
+        ));
+        assert!(matches!(
+            lookup(&keybinds, "d", &["library"]),
+            Lookup::Found(AppRequests::UIRequests(UIRequests::HalfPageDown))
+        ));
+        assert!(matches!(lookup(&keybinds, "d", &[]), Lookup::NotFound));
+    }
 
-        return Keybinds { lookup };
+    #[test]
+    fn rebinding_a_tab_keeps_it_out_of_the_contexts() {
+        let bindings = HashMap::from([("queue_tab".to_string(), KeyList::One("Q".to_string()))]);
+        let keybinds = Keybinds::from_config(&bindings, &HashMap::new()).unwrap();
+        assert!(shows_tab(lookup(&keybinds, "Q", &["library"]), AppViews::QUEUE));
     }
 }
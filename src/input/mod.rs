@@ -1,53 +1,173 @@
-mod keybinds;
+pub mod commands;
+pub mod keybinds;
 
-use crossterm::event::{self, Event, KeyCode};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
 use std::{
-    sync::{mpsc::Sender, Arc, Mutex},
+    sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
+use tokio::sync::mpsc::UnboundedSender;
 
-use keybinds::Keybinds;
+use keybinds::{Keybinds, Lookup};
 
 use crate::{state::AppState, utils::constants::requests::*};
 
-pub fn listen(app_state: Arc<Mutex<AppState>>, main_tx: Sender<AppRequests>) {
+// how long to wait for the next key of a sequence like "g g" before giving up on it
+const SEQUENCE_TIMEOUT: Duration = Duration::from_millis(1000);
+// two clicks on the same spot within this long are a double click
+const DOUBLE_CLICK_TIMEOUT: Duration = Duration::from_millis(400);
+
+// the keybindings are shared so that they can be replaced when the config is reloaded
+pub fn listen(
+    app_state: Arc<Mutex<AppState>>,
+    main_tx: UnboundedSender<AppRequests>,
+    binds: Arc<Mutex<Keybinds>>,
+) {
     let tick_rate = Duration::from_millis(250);
     let mut last_tick = Instant::now();
-    let binds = Keybinds::default();
+    // the keys of a sequence pressed so far, the binding they make on their own and when the
+    // last of them was pressed
+    let mut pending: Vec<KeyEvent> = Vec::new();
+    let mut pending_request: Option<AppRequests> = None;
+    let mut pending_since = Instant::now();
+    // when and where the left button was last pressed
+    let mut last_click: Option<(Instant, u16, u16)> = None;
+    // read after a paste into the search to see where it ended, and handled next
+    let mut unread: Option<Event> = None;
 
     'input: loop {
         let timeout = tick_rate
             .checked_sub(last_tick.elapsed())
             .unwrap_or_else(|| Duration::from_secs(0));
-        if crossterm::event::poll(timeout).unwrap() {
-            if let Event::Key(key) = event::read().unwrap() {
+        // a key that is both a binding and the start of a sequence fires once nothing follows it
+        if !pending.is_empty() && pending_since.elapsed() >= SEQUENCE_TIMEOUT {
+            if let Some(request) = pending_request.take() {
+                let _ = main_tx.send(request);
+            }
+            pending.clear();
+        }
+        if unread.is_some() || crossterm::event::poll(timeout).unwrap() {
+            let event = unread.take().unwrap_or_else(|| event::read().unwrap());
+            if let Event::Resize(_, _) = event {
+                let _ = main_tx.send(AppRequests::UIRequests(UIRequests::Redraw));
+                continue 'input;
+            }
+            if let Event::Mouse(mouse) = event {
+                if let Some(request) = mouse_request(&app_state, mouse, &mut last_click) {
+                    let _ = main_tx.send(request);
+                }
+                continue 'input;
+            }
+            if let Event::Key(key) = event {
+                let key = keybinds::normalize(key);
                 //Handle search input
                 //TODO: move to another function "handle_search_input" and have it return a bool
                 //(should continue searching or not)
                 if app_state.lock().unwrap().search.searching {
-                    // TODO: refactor this so that it modifies the search variables
-                    if let KeyCode::Char(ch) = key.code {
-                        // TODO: if enter is pressed then stop reading keyboard input and display
-                        // results
-                        // TODO: backspace to remove last character from search term
-                        _ = main_tx.send(AppRequests::UIRequests(UIRequests::SearchInput(ch)));
+                    // enter plays the top match and esc closes the search through the keybindings
+                    let control = key.modifiers.contains(KeyModifiers::CONTROL);
+                    let request = match key.code {
+                        KeyCode::Char('w') if control => Some(UIRequests::SearchDeleteWord),
+                        KeyCode::Char('u') if control => Some(UIRequests::SearchClear),
+                        KeyCode::Char(_) if control => None,
+                        KeyCode::Char(ch) => {
+                            let (text, next) = pasted(ch);
+                            unread = next;
+                            Some(UIRequests::SearchInput(text))
+                        }
+                        KeyCode::Backspace => Some(UIRequests::SearchBackspace),
+                        KeyCode::Left => Some(UIRequests::SearchCursor(true)),
+                        KeyCode::Right => Some(UIRequests::SearchCursor(false)),
+                        _ => None,
+                    };
+                    if let Some(request) = request {
+                        let _ = main_tx.send(AppRequests::UIRequests(request));
+                        continue 'input;
+                    }
+                }
+
+                // the letter to jump to is whatever is typed next, anything else gives up on it
+                if app_state.lock().unwrap().ui.jumping {
+                    let letter = match key.code {
+                        KeyCode::Char(ch) => Some(ch),
+                        _ => None,
+                    };
+                    let _ = main_tx.send(AppRequests::UIRequests(UIRequests::JumpToLetter(letter)));
+                    continue 'input;
+                }
+
+                // the command line takes what's typed, enter and esc go through the keybindings
+                if app_state.lock().unwrap().ui.command.is_some() {
+                    let request = match key.code {
+                        KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            Some(UIRequests::CommandInput(ch))
+                        }
+                        KeyCode::Backspace => Some(UIRequests::CommandBackspace),
+                        KeyCode::Tab => Some(UIRequests::CompleteCommand),
+                        _ => None,
+                    };
+                    if let Some(request) = request {
+                        let _ = main_tx.send(AppRequests::UIRequests(request));
+                        continue 'input;
+                    }
+                }
+
+                // the tag editor takes every character as text
+                if app_state.lock().unwrap().ui.tag_editor.is_some() {
+                    let request = match key.code {
+                        KeyCode::Char(ch) => Some(UIRequests::TagInput(ch)),
+                        KeyCode::Backspace => Some(UIRequests::TagBackspace),
+                        KeyCode::Tab => Some(UIRequests::Down),
+                        _ => None,
+                    };
+                    if let Some(request) = request {
+                        let _ = main_tx.send(AppRequests::UIRequests(request));
                         continue 'input;
                     }
                 }
 
                 //Handle all other keyboard input and check if a kebind exists for them
-                match binds.lookup.get(&key) {
-                    Some(request) => {
+                let active = app_state.lock().unwrap().ui.key_contexts.to_owned();
+                pending.push(key);
+                let binds = binds.lock().unwrap();
+                let mut found = binds.lookup(&pending, &active);
+                // a key that doesn't continue the sequence starts a new one, after the keys
+                // before it did what they do on their own
+                if matches!(found, Lookup::NotFound) && pending.len() > 1 {
+                    if let Some(request) = pending_request.take() {
+                        let _ = main_tx.send(request);
+                    }
+                    pending = vec![key];
+                    found = binds.lookup(&pending, &active);
+                }
+                match found {
+                    Lookup::Found(request) => {
+                        pending.clear();
+                        pending_request = None;
                         let _ = main_tx.send(request.to_owned());
 
                         //TODO: maybe refactor this so that this thread listens for a Quit signal
                         //from a transmitter instead
                         match request {
                             AppRequests::Quit => break,
+                            AppRequests::Macro(requests)
+                                if requests.iter().any(|step| matches!(step, AppRequests::Quit)) =>
+                            {
+                                break
+                            }
                             _ => (),
                         }
                     }
-                    None => (),
+                    Lookup::Pending(request) => {
+                        pending_request = request;
+                        pending_since = Instant::now();
+                    }
+                    Lookup::NotFound => {
+                        pending.clear();
+                        pending_request = None;
+                    }
                 }
             }
         }
@@ -56,3 +176,63 @@ pub fn listen(app_state: Arc<Mutex<AppState>>, main_tx: Sender<AppRequests>) {
         }
     }
 }
+
+// a paste arrives as keys one right behind the other, so the characters waiting after the one
+// typed are taken along with it and searched for once. the event that ended them is returned
+fn pasted(first: char) -> (String, Option<Event>) {
+    let mut text = first.to_string();
+    while event::poll(Duration::from_secs(0)).unwrap_or(false) {
+        match event::read() {
+            Ok(Event::Key(key)) => {
+                let key = keybinds::normalize(key);
+                match key.code {
+                    KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        text.push(ch)
+                    }
+                    _ => return (text, Some(Event::Key(key))),
+                }
+            }
+            Ok(event) => return (text, Some(event)),
+            Err(_) => break,
+        }
+    }
+    (text, None)
+}
+
+// the wheel moves through the list like up and down and the ui works out what was clicked.
+// popups cover everything, so the mouse is ignored while one is open
+fn mouse_request(
+    app_state: &Arc<Mutex<AppState>>,
+    mouse: MouseEvent,
+    last_click: &mut Option<(Instant, u16, u16)>,
+) -> Option<AppRequests> {
+    let state = app_state.lock().unwrap();
+    if state.ui.error.is_some()
+        || state.ui.tag_editor.is_some()
+        || state.ui.menu_action.is_some()
+        || state.ui.bookmark.is_some()
+        || state.ui.file_info.is_some()
+        || state.ui.show_equalizer
+        || state.ui.command.is_some()
+    {
+        return None;
+    }
+    let (column, row) = (mouse.column, mouse.row);
+    let request = match mouse.kind {
+        MouseEventKind::ScrollUp => UIRequests::Up,
+        MouseEventKind::ScrollDown => UIRequests::Down,
+        MouseEventKind::Down(MouseButton::Left) => {
+            let double = matches!(*last_click, Some((at, last_column, last_row))
+                if at.elapsed() < DOUBLE_CLICK_TIMEOUT && (last_column, last_row) == (column, row));
+            if double {
+                *last_click = None;
+                UIRequests::DoubleClick(column, row)
+            } else {
+                *last_click = Some((Instant::now(), column, row));
+                UIRequests::Click(column, row)
+            }
+        }
+        _ => return None,
+    };
+    Some(AppRequests::UIRequests(request))
+}
@@ -0,0 +1,47 @@
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+
+use super::errors::IpcError;
+use serde_json::{Map, Value};
+
+// sends commands to a splay running in the background, the way a script would
+pub struct IpcClient {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl IpcClient {
+    pub fn connect(path: &Path) -> Result<IpcClient, IpcError> {
+        let writer = UnixStream::connect(path).map_err(|err| IpcError::NotRunning {
+            path: path.display().to_string(),
+            reason: err.to_string(),
+        })?;
+        let reader = BufReader::new(writer.try_clone()?);
+        Ok(IpcClient { reader, writer })
+    }
+
+    // the reply to a command, eg. the status. errors if it wasn't ok
+    pub fn request(&mut self, command: &str, args: Vec<(&str, Value)>) -> Result<Value, IpcError> {
+        let mut fields = Map::new();
+        fields.insert("command".to_string(), command.into());
+        fields.extend(args.into_iter().map(|(name, value)| (name.to_string(), value)));
+        writeln!(self.writer, "{}", Value::Object(fields))?;
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(IpcError::Protocol("the connection was closed".to_string()));
+        }
+        let reply: Value =
+            serde_json::from_str(&line).map_err(|err| IpcError::Protocol(err.to_string()))?;
+        match reply.get("ok") {
+            Some(Value::Bool(true)) => Ok(reply),
+            _ => Err(IpcError::Refused(
+                reply
+                    .get("error")
+                    .and_then(Value::as_str)
+                    .unwrap_or("no reason given")
+                    .to_string(),
+            )),
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use std::io;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum IpcError {
+    #[error("The socket failed: {0}")]
+    Socket(#[from] io::Error),
+    #[error("{0} is taken by another splay that's running")]
+    InUse(String),
+    #[error("No splay is running in the background at {path}: {reason}")]
+    NotRunning { path: String, reason: String },
+    #[error("The other splay did not answer like it should: {0}")]
+    Protocol(String),
+    #[error("The other splay refused it: {0}")]
+    Refused(String),
+}
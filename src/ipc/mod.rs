@@ -0,0 +1,300 @@
+#[cfg(unix)]
+pub mod client;
+pub mod errors;
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::UnboundedSender;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(unix)]
+use std::{
+    fs,
+    os::unix::fs::PermissionsExt,
+    os::unix::net::{UnixListener, UnixStream},
+    path::Path,
+};
+
+use crate::config::IpcConfig;
+use crate::input::commands::expand_home;
+use crate::library::song::{self, Song};
+use crate::state::AppState;
+use crate::utils::constants::requests::*;
+use crate::utils::constants::{PlayerStates, RepeatModes};
+use errors::IpcError;
+use serde_json::{json, Value};
+
+// scripts and status bar modules control a running splay by writing a json command per line, eg.
+// {"command": "enqueue", "path": "~/music/album"}, to the socket. each one is answered with a line
+// of json that has "ok" and either "error" or what was asked for. returns the socket, which is
+// removed on quit
+pub fn listen(
+    config: &IpcConfig,
+    state: &Arc<Mutex<AppState>>,
+    main_tx: &UnboundedSender<AppRequests>,
+) -> Option<PathBuf> {
+    if !config.enabled {
+        return None;
+    }
+    if let Some(port) = config.tcp_port {
+        // only from this machine, nothing checks who's sending the commands
+        match TcpListener::bind(("127.0.0.1", port)) {
+            Ok(listener) => {
+                info!("Listening for commands on localhost:{}", port);
+                let state = state.clone();
+                let main_tx = main_tx.clone();
+                thread::spawn(move || {
+                    for stream in listener.incoming().flatten() {
+                        let reader = match stream.try_clone() {
+                            Ok(reader) => BufReader::new(reader),
+                            Err(_) => continue,
+                        };
+                        spawn_client(reader, stream, &state, &main_tx);
+                    }
+                });
+            }
+            Err(err) => error!("Could not listen on localhost:{}. Reason: {}", port, err),
+        }
+    }
+    listen_on_socket(config.socket(), state, main_tx)
+}
+
+#[cfg(unix)]
+fn listen_on_socket(
+    path: PathBuf,
+    state: &Arc<Mutex<AppState>>,
+    main_tx: &UnboundedSender<AppRequests>,
+) -> Option<PathBuf> {
+    let listener = match bind(&path) {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!("Could not listen on {}. Reason: {}", path.display(), err);
+            return None;
+        }
+    };
+    info!("Listening for commands on {}", path.display());
+    let state = state.clone();
+    let main_tx = main_tx.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let reader = match stream.try_clone() {
+                Ok(reader) => BufReader::new(reader),
+                Err(_) => continue,
+            };
+            spawn_client(reader, stream, &state, &main_tx);
+        }
+    });
+    Some(path)
+}
+
+#[cfg(not(unix))]
+fn listen_on_socket(
+    _path: PathBuf,
+    _state: &Arc<Mutex<AppState>>,
+    _main_tx: &UnboundedSender<AppRequests>,
+) -> Option<PathBuf> {
+    None
+}
+
+// a socket left behind by a splay that didn't quit cleanly is replaced, one that's still
+// answering belongs to another splay
+#[cfg(unix)]
+fn bind(path: &Path) -> Result<UnixListener, IpcError> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(IpcError::InUse(path.display().to_string()));
+        }
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    // the commands can add any file to the queue, so only this user gets to send them
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+fn spawn_client(
+    reader: impl BufRead + Send + 'static,
+    mut writer: impl Write + Send + 'static,
+    state: &Arc<Mutex<AppState>>,
+    main_tx: &UnboundedSender<AppRequests>,
+) {
+    let state = state.clone();
+    let main_tx = main_tx.clone();
+    thread::spawn(move || {
+        for line in reader.lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => return,
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let reply = match serde_json::from_str(&line) {
+                Ok(request) => handle(&request, &state, &main_tx),
+                Err(reason) => failure(format!("Could not read the command: {}", reason)),
+            };
+            if writeln!(writer, "{}", reply).is_err() {
+                return;
+            }
+        }
+    });
+}
+
+// commands go out as the same requests their keys send
+fn handle(request: &Value, state: &Arc<Mutex<AppState>>, main_tx: &UnboundedSender<AppRequests>) -> Value {
+    let command = match request.get("command").and_then(Value::as_str) {
+        Some(command) => command,
+        None => return failure("There's no \"command\""),
+    };
+    let field = |name: &str| request.get(name).and_then(Value::as_str);
+    let request = match command {
+        "status" => return status(&state.lock().unwrap()),
+        "queue" => return queue(&state.lock().unwrap()),
+        // a song given by its path starts right away, from a position if there is one
+        "play" => match field("path") {
+            Some(path) => {
+                let start_ms = request.get("start_ms").and_then(Value::as_f64).unwrap_or(0.);
+                let position = request.get("position").and_then(Value::as_f64);
+                let mut guard = state.lock().unwrap();
+                match find_song(&guard, path, start_ms as u64) {
+                    Some(song) => {
+                        guard.ui.selected_song = Some(song);
+                        guard.player.resume_at =
+                            position.map(|secs| Duration::from_secs_f64(secs.max(0.)));
+                        AppRequests::PlayerRequests(PlayerRequests::Start)
+                    }
+                    None => return failure(format!("There's nothing to play at {}", path)),
+                }
+            }
+            // a stopped player starts the song under the cursor instead
+            None => match state.lock().unwrap().player.curr_state {
+                PlayerStates::STOPPED => AppRequests::PlayerRequests(PlayerRequests::Start),
+                _ => AppRequests::PlayerRequests(PlayerRequests::Resume),
+            },
+        },
+        "pause" => AppRequests::PlayerRequests(PlayerRequests::Pause),
+        "toggle" => AppRequests::PlayerRequests(PlayerRequests::PlayPause),
+        "stop" => AppRequests::PlayerRequests(PlayerRequests::Stop),
+        "next" => AppRequests::PlayerRequests(PlayerRequests::Next),
+        "seek" => match (request.get("position"), request.get("offset")) {
+            (Some(position), _) => match position.as_f64().filter(|secs| *secs >= 0.) {
+                Some(secs) => AppRequests::PlayerRequests(PlayerRequests::SeekTo(
+                    Duration::from_secs_f64(secs),
+                )),
+                None => return failure("\"position\" has to be a number of seconds"),
+            },
+            (None, Some(offset)) => match offset.as_f64() {
+                Some(secs) => {
+                    AppRequests::PlayerRequests(PlayerRequests::SeekRelative(secs.round() as i64))
+                }
+                None => return failure("\"offset\" has to be a number of seconds"),
+            },
+            // or a bookmark of the song playing, by its name
+            (None, None) => {
+                let guard = state.lock().unwrap();
+                let bookmarks = match &guard.player.curr_song {
+                    Some(song) => guard.library.bookmarks(song),
+                    None => &[],
+                };
+                match field("bookmark").and_then(|name| bookmarks.iter().find(|b| b.name == name)) {
+                    Some(bookmark) => {
+                        AppRequests::PlayerRequests(PlayerRequests::SeekTo(bookmark.position))
+                    }
+                    None => {
+                        return failure("seek needs a \"position\", an \"offset\" or a \"bookmark\"")
+                    }
+                }
+            }
+        },
+        // only the song starting there with a start_ms, eg. one track of a cue sheet
+        "enqueue" => match (field("path"), request.get("start_ms").and_then(Value::as_f64)) {
+            (Some(path), Some(start_ms)) => {
+                let mut guard = state.lock().unwrap();
+                match find_song(&guard, path, start_ms as u64) {
+                    Some(song) => guard.queue.add_upcoming(song),
+                    None => return failure(format!("There's nothing to play at {}", path)),
+                }
+                return json!({"ok": true});
+            }
+            (Some(path), None) => {
+                AppRequests::QueueRequests(QueueRequests::AddPath(expand_home(path)))
+            }
+            _ => return failure("enqueue needs the \"path\" of a file, directory or playlist"),
+        },
+        "clear" => AppRequests::QueueRequests(QueueRequests::Clear),
+        // played like the command line does, since there might not be an interface to do it
+        "open" => match field("url") {
+            Some(url) if song::is_stream_url(url) => {
+                state.lock().unwrap().ui.selected_song = Some(Song::stream(url.to_string()));
+                AppRequests::PlayerRequests(PlayerRequests::Start)
+            }
+            _ => return failure("open needs the http or https \"url\" of a stream"),
+        },
+        "quit" => AppRequests::Quit,
+        command => return failure(format!("Unknown command \"{}\"", command)),
+    };
+    let _ = main_tx.send(request);
+    json!({"ok": true})
+}
+
+// what's playing, where it is and how the player is set up
+fn status(state: &AppState) -> Value {
+    let player = &state.player;
+    let playing = match player.curr_state {
+        PlayerStates::STOPPED => "stopped",
+        PlayerStates::BUFFERING => "buffering",
+        PlayerStates::PLAYING => "playing",
+        PlayerStates::PAUSED => "paused",
+    };
+    let repeat = match player.repeat {
+        RepeatModes::OFF => "off",
+        RepeatModes::ONE => "one",
+        RepeatModes::ALL => "all",
+    };
+    let song = player.curr_song.as_ref().map_or(Value::Null, song_json);
+    json!({
+        "ok": true,
+        "state": playing,
+        "song": song,
+        "position": player.progress.as_secs_f64(),
+        "stream_title": player.stream_title,
+        "shuffle": player.shuffle,
+        "repeat": repeat,
+        "queue_length": state.queue.len(),
+    })
+}
+
+// what's queued, in play order
+fn queue(state: &AppState) -> Value {
+    let songs: Vec<Value> = state.queue.iter().map(song_json).collect();
+    json!({"ok": true, "queue": songs})
+}
+
+// the path and start are what the song is found again by
+fn song_json(song: &Song) -> Value {
+    json!({
+        "title": song.title,
+        "artist": song.track_artist,
+        "album": song.album_title,
+        "path": song.path,
+        "start_ms": song.start_ms,
+        "duration": song.duration_secs,
+    })
+}
+
+// podcast episodes and streams aren't in the library, but an episode's title is known
+pub fn find_song(state: &AppState, path: &str, start_ms: u64) -> Option<Song> {
+    let stream = Song::stream(path.to_string());
+    match state.podcasts.episode(&stream) {
+        Some(episode) => Some(episode.song.to_owned()),
+        None if song::is_stream_url(path) => Some(stream),
+        None => state.library.find_song(path, start_ms),
+    }
+}
+
+fn failure(reason: impl Into<String>) -> Value {
+    json!({"ok": false, "error": reason.into() as String})
+}
@@ -0,0 +1,12 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+// a named point in a song to seek back to, like a track of a DJ mix or a chapter of an audiobook
+#[derive(Debug, Clone)]
+pub struct Bookmark {
+    pub name: String,
+    pub position: Duration,
+}
+
+// of each song by its path and start, in the order they come in it
+pub type Bookmarks = HashMap<(String, u64), Vec<Bookmark>>;
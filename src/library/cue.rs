@@ -0,0 +1,243 @@
+use lofty::read_from_path;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::errors::ImportError;
+use super::song::Song;
+use super::tag;
+
+// cue sheet timestamps are mm:ss:ff with 75 frames a second
+const FRAMES_PER_SEC: u64 = 75;
+
+// a track as described by the cue sheet, before its end is known
+struct CueTrack {
+    file: PathBuf,
+    number: String,
+    title: Option<String>,
+    performer: Option<String>,
+    start_ms: u64,
+}
+
+// split the audio files referenced by a cue sheet into one song per track
+pub fn parse_cue(cue_path: &Path) -> Result<Vec<Song>, Box<dyn Error>> {
+    // cue sheets are often not utf-8, keep whatever can be read
+    let contents = String::from_utf8_lossy(&fs::read(cue_path)?).into_owned();
+    let dir = cue_path.parent().unwrap_or_else(|| Path::new("."));
+
+    let mut album_title = None;
+    let mut album_artist = None;
+    let mut genre = None;
+    let mut year = None;
+    let mut curr_file: Option<PathBuf> = None;
+    let mut tracks: Vec<CueTrack> = Vec::new();
+
+    for line in contents.lines() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        let (command, rest) = match line.split_once(' ') {
+            Some(split) => split,
+            None => continue,
+        };
+        let in_track = tracks.last().is_some();
+        match command {
+            "FILE" => curr_file = Some(dir.join(unquote(strip_file_type(rest)))),
+            "TRACK" => {
+                let file = match &curr_file {
+                    Some(file) => file.to_owned(),
+                    None => return Err(Box::new(ImportError::Parsing)),
+                };
+                let number = rest.split_whitespace().next().unwrap_or_default();
+                tracks.push(CueTrack {
+                    file,
+                    number: number.trim_start_matches('0').to_string(),
+                    title: None,
+                    performer: None,
+                    start_ms: 0,
+                });
+            }
+            "TITLE" if in_track => tracks.last_mut().unwrap().title = Some(unquote(rest)),
+            "TITLE" => album_title = Some(unquote(rest)),
+            "PERFORMER" if in_track => tracks.last_mut().unwrap().performer = Some(unquote(rest)),
+            "PERFORMER" => album_artist = Some(unquote(rest)),
+            "INDEX" if in_track => {
+                // INDEX 01 is where the track starts, 00 is the pregap before it
+                let mut parts = rest.split_whitespace();
+                if parts.next() == Some("01") {
+                    if let Some(start_ms) = parts.next().and_then(parse_timestamp) {
+                        tracks.last_mut().unwrap().start_ms = start_ms;
+                    }
+                }
+            }
+            "REM" => match rest.split_once(' ') {
+                Some(("GENRE", value)) => genre = Some(unquote(value)),
+                Some(("DATE", value)) => year = Some(unquote(value)),
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+
+    let mut songs = Vec::new();
+    let mut audio_files: HashMap<PathBuf, AudioFile> = HashMap::new();
+    for (idx, track) in tracks.iter().enumerate() {
+        let path = match fs::canonicalize(&track.file)?.to_str() {
+            Some(path) => path.to_string(),
+            None => return Err(Box::new(ImportError::Parsing)),
+        };
+        // a track ends where the next one in the same file starts, or at the end of the file
+        let end_ms = tracks
+            .get(idx + 1)
+            .filter(|next| next.file == track.file)
+            .map(|next| next.start_ms);
+        let audio_file = match audio_files.get(&track.file) {
+            Some(audio_file) => audio_file.clone(),
+            None => {
+                let audio_file = read_audio_file(&track.file)?;
+                audio_files.insert(track.file.to_owned(), audio_file.clone());
+                audio_file
+            }
+        };
+        let length_ms = end_ms
+            .unwrap_or(audio_file.length_ms)
+            .saturating_sub(track.start_ms);
+
+        let title = match &track.title {
+            Some(title) => title.to_owned(),
+            None => format!("Track {}", track.number),
+        };
+        let mut song = Song::new(title, path);
+        song.album_title = album_title
+            .to_owned()
+            .unwrap_or_else(|| tag::UNKNOWN_ALBUM.to_string());
+        song.album_artist = album_artist
+            .to_owned()
+            .unwrap_or_else(|| tag::UNKNOWN_ARTIST.to_string());
+        song.track_artist = track
+            .performer
+            .to_owned()
+            .unwrap_or_else(|| song.album_artist.to_owned());
+        song.genre = genre.to_owned();
+        song.year = year.to_owned();
+        song.track_number = Some(track.number.to_owned());
+        song.duration_secs = length_ms / 1000;
+        song.start_ms = track.start_ms;
+        song.end_ms = end_ms;
+        song.bitrate_kbps = audio_file.bitrate_kbps;
+        song.sample_rate = audio_file.sample_rate;
+        song.channels = audio_file.channels;
+        // each track counts for its share of the file
+        if audio_file.length_ms != 0 {
+            song.size_bytes = audio_file.size_bytes * length_ms / audio_file.length_ms;
+        }
+        songs.push(song);
+    }
+    Ok(songs)
+}
+
+// every audio file a cue sheet splits up, so that they aren't also imported whole
+pub fn referenced_files(cue_path: &Path) -> Vec<PathBuf> {
+    let contents = match fs::read(cue_path) {
+        Ok(contents) => String::from_utf8_lossy(&contents).into_owned(),
+        Err(_) => return Vec::new(),
+    };
+    let dir = cue_path.parent().unwrap_or_else(|| Path::new("."));
+    contents
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("FILE "))
+        .map(|rest| dir.join(unquote(strip_file_type(rest))))
+        .collect()
+}
+
+// what the tracks of a cue sheet need to know about the file they're in
+#[derive(Clone)]
+struct AudioFile {
+    length_ms: u64,
+    bitrate_kbps: Option<u32>,
+    sample_rate: u32,
+    channels: u8,
+    size_bytes: u64,
+}
+
+fn read_audio_file(path: &Path) -> Result<AudioFile, Box<dyn Error>> {
+    match read_from_path(path, false) {
+        Ok(file) => Ok(AudioFile {
+            length_ms: file.properties().duration().as_millis() as u64,
+            bitrate_kbps: file.properties().audio_bitrate(),
+            sample_rate: file.properties().sample_rate().unwrap_or(0),
+            channels: file.properties().channels().unwrap_or(0),
+            size_bytes: fs::metadata(path)?.len(),
+        }),
+        Err(_) => Err(Box::new(ImportError::Parsing)),
+    }
+}
+
+// FILE lines end with the type of the file, eg. FILE "album.flac" WAVE
+fn strip_file_type(rest: &str) -> &str {
+    match rest.rsplit_once(' ') {
+        Some((file, _)) => file,
+        None => rest,
+    }
+}
+
+fn unquote(value: &str) -> String {
+    value.trim().trim_matches('"').to_string()
+}
+
+fn parse_timestamp(timestamp: &str) -> Option<u64> {
+    let mut parts = timestamp.split(':').map(|part| part.parse::<u64>());
+    let mins = parts.next()?.ok()?;
+    let secs = parts.next()?.ok()?;
+    let frames = parts.next()?.ok()?;
+    Some((mins * 60 + secs) * 1000 + frames * 1000 / FRAMES_PER_SEC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    // a cue sheet in a directory of its own under the temp directory
+    fn write_cue(name: &str, contents: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("splay-cue-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("album.cue");
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn timestamps_are_minutes_seconds_and_frames() {
+        assert_eq!(parse_timestamp("00:00:00"), Some(0));
+        assert_eq!(parse_timestamp("01:02:75"), Some(63_000));
+        assert_eq!(parse_timestamp("03:30:15"), Some(210_200));
+        assert_eq!(parse_timestamp("03:30"), None);
+        assert_eq!(parse_timestamp("aa:00:00"), None);
+    }
+
+    #[test]
+    fn file_lines() {
+        assert_eq!(unquote(strip_file_type("\"Album Name.flac\" WAVE")), "Album Name.flac");
+        assert_eq!(unquote(strip_file_type("album.flac")), "album.flac");
+    }
+
+    #[test]
+    fn referenced_files_are_next_to_the_sheet() {
+        let cue = "\u{feff}REM GENRE Rock\nFILE \"one side.flac\" WAVE\n  TRACK 01 AUDIO\n\
+            FILE \"two.flac\" WAVE\n  TRACK 02 AUDIO\n";
+        let path = write_cue("referenced", cue);
+        let dir = path.parent().unwrap();
+        assert_eq!(
+            referenced_files(&path),
+            [dir.join("one side.flac"), dir.join("two.flac")]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn track_before_a_file_is_an_error() {
+        let path = write_cue("no-file", "TITLE \"Album\"\nTRACK 01 AUDIO\n  INDEX 01 00:00:00\n");
+        assert!(parse_cue(&path).is_err());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
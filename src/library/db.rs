@@ -0,0 +1,482 @@
+use rusqlite::{params, Connection, Row};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::bookmark::{Bookmark, Bookmarks};
+use super::search::SavedSearch;
+use super::song::Song;
+
+use crate::utils::data;
+
+const DB_FILE: &str = "library.db";
+
+// bumped whenever the tables change, which throws away the old cache and rescans everything.
+// song_stats is kept since it can't be read back from the files
+const SCHEMA_VERSION: i32 = 6;
+
+const SONG_COLUMNS: &str = "title, album_title, track_artist, album_artist, genre, year, \
+    duration_secs, track_number, track_gain, album_gain, path, start_ms, end_ms, disc_number, \
+    has_cover_art, added, bitrate_kbps, size_bytes, sample_rate, channels";
+
+const STATS_COLUMNS: &str = "rating, favorite, play_count, skip_count, last_played";
+
+// song metadata cached between runs, so that only files that changed since the last scan are
+// read again
+pub struct LibraryDb {
+    conn: Connection,
+    // files seen during the current scan, everything else has been deleted or moved
+    scanned: HashSet<String>,
+}
+
+// the library database in the data directory, which the metadata and scrobble caches share
+pub fn path() -> PathBuf {
+    data::path(DB_FILE)
+}
+
+impl LibraryDb {
+    pub fn open(path: &Path) -> Result<LibraryDb, Box<dyn Error>> {
+        let conn = Connection::open(path)?;
+        let version: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        // the first version of song_stats only had ratings
+        if version == 3 {
+            conn.execute_batch(
+                "ALTER TABLE song_stats ADD COLUMN play_count INTEGER NOT NULL DEFAULT 0;
+                ALTER TABLE song_stats ADD COLUMN skip_count INTEGER NOT NULL DEFAULT 0;
+                ALTER TABLE song_stats ADD COLUMN last_played INTEGER NOT NULL DEFAULT 0;",
+            )?;
+        }
+        if version != SCHEMA_VERSION {
+            conn.execute_batch(&format!(
+                "DROP TABLE IF EXISTS songs;
+                DROP TABLE IF EXISTS files;
+                PRAGMA user_version = {};",
+                SCHEMA_VERSION
+            ))?;
+        }
+        // `source` is the file a song was read from, which is the cue sheet for tracks split out
+        // of one
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS files (
+                path TEXT PRIMARY KEY,
+                mtime INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS songs (
+                source TEXT NOT NULL REFERENCES files(path) ON DELETE CASCADE,
+                title TEXT NOT NULL,
+                album_title TEXT NOT NULL,
+                track_artist TEXT NOT NULL,
+                album_artist TEXT NOT NULL,
+                genre TEXT,
+                year TEXT,
+                duration_secs INTEGER NOT NULL,
+                track_number TEXT,
+                track_gain REAL,
+                album_gain REAL,
+                path TEXT NOT NULL,
+                start_ms INTEGER NOT NULL,
+                end_ms INTEGER,
+                disc_number TEXT,
+                has_cover_art INTEGER NOT NULL,
+                added INTEGER NOT NULL,
+                bitrate_kbps INTEGER,
+                size_bytes INTEGER NOT NULL,
+                sample_rate INTEGER NOT NULL,
+                channels INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS songs_source ON songs(source);
+            CREATE TABLE IF NOT EXISTS song_stats (
+                path TEXT NOT NULL,
+                start_ms INTEGER NOT NULL,
+                rating INTEGER NOT NULL DEFAULT 0,
+                favorite INTEGER NOT NULL DEFAULT 0,
+                play_count INTEGER NOT NULL DEFAULT 0,
+                skip_count INTEGER NOT NULL DEFAULT 0,
+                last_played INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (path, start_ms)
+            );
+            CREATE TABLE IF NOT EXISTS episode_positions (
+                url TEXT PRIMARY KEY,
+                position_ms INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS search_history (
+                id INTEGER PRIMARY KEY,
+                query TEXT NOT NULL UNIQUE
+            );
+            CREATE TABLE IF NOT EXISTS saved_searches (
+                name TEXT PRIMARY KEY,
+                query TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS bookmarks (
+                path TEXT NOT NULL,
+                start_ms INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                position_ms INTEGER NOT NULL,
+                PRIMARY KEY (path, start_ms, name)
+            );
+            CREATE TABLE IF NOT EXISTS measured_gains (
+                path TEXT NOT NULL,
+                start_ms INTEGER NOT NULL,
+                track REAL NOT NULL,
+                album REAL,
+                PRIMARY KEY (path, start_ms)
+            );
+            PRAGMA foreign_keys = ON;",
+        )?;
+        Ok(LibraryDb {
+            conn,
+            scanned: HashSet::new(),
+        })
+    }
+
+    // forget everything so that the next scan reads every file again
+    pub fn clear(&mut self) -> Result<(), Box<dyn Error>> {
+        self.conn.execute_batch("DELETE FROM songs; DELETE FROM files;")?;
+        Ok(())
+    }
+
+    pub fn load_songs(&self) -> Result<Vec<Song>, Box<dyn Error>> {
+        // stats are stored apart from the songs so that they survive a file being read again.
+        // so are the gains that were measured, which fill in for the ones the tags didn't have
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT {}, IFNULL(rating, 0), IFNULL(favorite, 0), IFNULL(play_count, 0), \
+            IFNULL(skip_count, 0), IFNULL(last_played, 0), \
+            measured_gains.track, measured_gains.album \
+            FROM songs LEFT JOIN song_stats USING (path, start_ms) \
+            LEFT JOIN measured_gains USING (path, start_ms)",
+            SONG_COLUMNS
+        ))?;
+        let songs = statement
+            .query_map([], song_from_row)?
+            .collect::<Result<Vec<Song>, rusqlite::Error>>()?;
+        Ok(songs)
+    }
+
+    // whether the file hasn't changed since it was last saved. marks it as scanned either way
+    pub fn is_up_to_date(&mut self, path: &Path) -> bool {
+        let path = path.to_string_lossy().into_owned();
+        let saved_mtime: Option<i64> = self
+            .conn
+            .query_row("SELECT mtime FROM files WHERE path = ?1", [&path], |row| {
+                row.get(0)
+            })
+            .ok();
+        let up_to_date = saved_mtime.is_some() && saved_mtime == modified_secs(Path::new(&path));
+        self.scanned.insert(path);
+        up_to_date
+    }
+
+    // replace whatever was saved for a file with the songs read from it
+    pub fn save_file(&mut self, path: &Path, songs: &[Song]) -> Result<(), Box<dyn Error>> {
+        let source = path.to_string_lossy().into_owned();
+        let mtime = modified_secs(path).unwrap_or(0);
+        let tx = self.conn.transaction()?;
+        // a changed file keeps the time it was first found, so it doesn't count as newly added
+        let added: Option<i64> = tx.query_row(
+            "SELECT MIN(added) FROM songs WHERE source = ?1",
+            [&source],
+            |row| row.get(0),
+        )?;
+        let added = match added {
+            Some(added) => added,
+            None => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |now| now.as_secs() as i64),
+        };
+        tx.execute("DELETE FROM files WHERE path = ?1", [&source])?;
+        tx.execute(
+            "INSERT INTO files (path, mtime) VALUES (?1, ?2)",
+            params![source, mtime],
+        )?;
+        for song in songs {
+            tx.execute(
+                &format!(
+                    "INSERT INTO songs (source, {}) VALUES \
+                    (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, \
+                    ?19, ?20, ?21)",
+                    SONG_COLUMNS
+                ),
+                params![
+                    source,
+                    song.title,
+                    song.album_title,
+                    song.track_artist,
+                    song.album_artist,
+                    song.genre,
+                    song.year,
+                    song.duration_secs as i64,
+                    song.track_number,
+                    song.track_gain,
+                    song.album_gain,
+                    song.path,
+                    song.start_ms as i64,
+                    song.end_ms.map(|end_ms| end_ms as i64),
+                    song.disc_number,
+                    song.has_cover_art,
+                    added,
+                    song.bitrate_kbps,
+                    song.size_bytes as i64,
+                    song.sample_rate,
+                    song.channels,
+                ],
+            )?;
+        }
+        tx.commit()?;
+        self.scanned.insert(source);
+        Ok(())
+    }
+
+    pub fn save_stats(&mut self, song: &Song) -> Result<(), Box<dyn Error>> {
+        self.conn.execute(
+            &format!(
+                "INSERT OR REPLACE INTO song_stats (path, start_ms, {}) \
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                STATS_COLUMNS
+            ),
+            params![
+                song.path,
+                song.start_ms as i64,
+                song.rating,
+                song.favorite,
+                song.play_count,
+                song.skip_count,
+                song.last_played_secs as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    // where podcast episodes were left, by the url of their audio
+    pub fn load_positions(&self) -> Result<HashMap<String, Duration>, Box<dyn Error>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT url, position_ms FROM episode_positions")?;
+        let positions = statement
+            .query_map([], |row| {
+                let position_ms = row.get::<_, i64>(1)? as u64;
+                Ok((row.get(0)?, Duration::from_millis(position_ms)))
+            })?
+            .collect::<Result<HashMap<String, Duration>, rusqlite::Error>>()?;
+        Ok(positions)
+    }
+
+    // none once the episode was played to the end
+    pub fn save_position(
+        &mut self,
+        url: &str,
+        position: Option<Duration>,
+    ) -> Result<(), Box<dyn Error>> {
+        match position {
+            Some(position) => self.conn.execute(
+                "INSERT OR REPLACE INTO episode_positions (url, position_ms) VALUES (?1, ?2)",
+                params![url, position.as_millis() as i64],
+            )?,
+            None => self
+                .conn
+                .execute("DELETE FROM episode_positions WHERE url = ?1", [url])?,
+        };
+        Ok(())
+    }
+
+    // the searches made before, oldest first
+    pub fn load_search_history(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT query FROM search_history ORDER BY id")?;
+        let history = statement
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<String>, rusqlite::Error>>()?;
+        Ok(history)
+    }
+
+    // made the newest, keeping only the last `limit`
+    pub fn add_search(&mut self, query: &str, limit: usize) -> Result<(), Box<dyn Error>> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM search_history WHERE query = ?1", [query])?;
+        tx.execute("INSERT INTO search_history (query) VALUES (?1)", [query])?;
+        tx.execute(
+            "DELETE FROM search_history WHERE id NOT IN \
+            (SELECT id FROM search_history ORDER BY id DESC LIMIT ?1)",
+            [limit as i64],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn load_saved_searches(&self) -> Result<Vec<SavedSearch>, Box<dyn Error>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT name, query FROM saved_searches ORDER BY name")?;
+        let searches = statement
+            .query_map([], |row| {
+                Ok(SavedSearch {
+                    name: row.get(0)?,
+                    query: row.get(1)?,
+                })
+            })?
+            .collect::<Result<Vec<SavedSearch>, rusqlite::Error>>()?;
+        Ok(searches)
+    }
+
+    // none forgets the search by that name
+    pub fn save_search(&mut self, name: &str, query: Option<&str>) -> Result<(), Box<dyn Error>> {
+        match query {
+            Some(query) => self.conn.execute(
+                "INSERT OR REPLACE INTO saved_searches (name, query) VALUES (?1, ?2)",
+                [name, query],
+            )?,
+            None => self
+                .conn
+                .execute("DELETE FROM saved_searches WHERE name = ?1", [name])?,
+        };
+        Ok(())
+    }
+
+    pub fn load_bookmarks(&self) -> Result<Bookmarks, Box<dyn Error>> {
+        let mut statement = self.conn.prepare(
+            "SELECT path, start_ms, name, position_ms FROM bookmarks ORDER BY position_ms",
+        )?;
+        let rows = statement.query_map([], |row| {
+            let key = (row.get(0)?, row.get::<_, i64>(1)? as u64);
+            let bookmark = Bookmark {
+                name: row.get(2)?,
+                position: Duration::from_millis(row.get::<_, i64>(3)? as u64),
+            };
+            Ok((key, bookmark))
+        })?;
+        let mut bookmarks = Bookmarks::new();
+        for row in rows {
+            let (key, bookmark) = row?;
+            bookmarks.entry(key).or_default().push(bookmark);
+        }
+        Ok(bookmarks)
+    }
+
+    // none deletes the song's bookmark by that name
+    pub fn save_bookmark(
+        &mut self,
+        song: &Song,
+        name: &str,
+        position: Option<Duration>,
+    ) -> Result<(), Box<dyn Error>> {
+        match position {
+            Some(position) => self.conn.execute(
+                "INSERT OR REPLACE INTO bookmarks (path, start_ms, name, position_ms) \
+                VALUES (?1, ?2, ?3, ?4)",
+                params![song.path, song.start_ms as i64, name, position.as_millis() as i64],
+            )?,
+            None => self.conn.execute(
+                "DELETE FROM bookmarks WHERE path = ?1 AND start_ms = ?2 AND name = ?3",
+                params![song.path, song.start_ms as i64, name],
+            )?,
+        };
+        Ok(())
+    }
+
+    // the gains worked out by measuring the song's loudness
+    pub fn save_gains(&mut self, song: &Song) -> Result<(), Box<dyn Error>> {
+        let track_gain = match song.track_gain {
+            Some(track_gain) => track_gain,
+            None => return Ok(()),
+        };
+        self.conn.execute(
+            "INSERT OR REPLACE INTO measured_gains (path, start_ms, track, album) \
+            VALUES (?1, ?2, ?3, ?4)",
+            params![song.path, song.start_ms as i64, track_gain, song.album_gain],
+        )?;
+        Ok(())
+    }
+
+    // forget a song for good, along with the file it was read from so that a cue sheet whose
+    // audio went missing is read again if it comes back
+    pub fn remove_song(&mut self, song: &Song) -> Result<(), Box<dyn Error>> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "DELETE FROM files WHERE path IN (SELECT source FROM songs WHERE path = ?1)",
+            [&song.path],
+        )?;
+        tx.execute("DELETE FROM song_stats WHERE path = ?1", [&song.path])?;
+        tx.execute("DELETE FROM bookmarks WHERE path = ?1", [&song.path])?;
+        tx.execute("DELETE FROM measured_gains WHERE path = ?1", [&song.path])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    // the file was moved, so everything saved about it goes with it
+    pub fn move_file(&mut self, from: &str, to: &str) -> Result<(), Box<dyn Error>> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO files (path, mtime) \
+            SELECT ?2, mtime FROM files WHERE path = ?1",
+            [from, to],
+        )?;
+        tx.execute("UPDATE songs SET source = ?2, path = ?2 WHERE source = ?1", [from, to])?;
+        tx.execute("DELETE FROM files WHERE path = ?1", [from])?;
+        for table in ["song_stats", "bookmarks", "measured_gains"] {
+            tx.execute(&format!("UPDATE {} SET path = ?2 WHERE path = ?1", table), [from, to])?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    // keep everything saved under a directory that wasn't scanned
+    pub fn keep_dir(&mut self, dir: &Path) -> Result<(), Box<dyn Error>> {
+        let mut statement = self.conn.prepare("SELECT path FROM files")?;
+        let saved = statement
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<String>, rusqlite::Error>>()?;
+        self.scanned
+            .extend(saved.into_iter().filter(|path| Path::new(path).starts_with(dir)));
+        Ok(())
+    }
+
+    // drop the songs of files that weren't seen during the scan
+    pub fn remove_unscanned(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut statement = self.conn.prepare("SELECT path FROM files")?;
+        let saved = statement
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<String>, rusqlite::Error>>()?;
+        drop(statement);
+        for path in saved.iter().filter(|path| !self.scanned.contains(*path)) {
+            self.conn.execute("DELETE FROM files WHERE path = ?1", [path])?;
+        }
+        Ok(())
+    }
+}
+
+fn song_from_row(row: &Row) -> Result<Song, rusqlite::Error> {
+    let mut song = Song::new(row.get(0)?, row.get(10)?);
+    song.album_title = row.get(1)?;
+    song.track_artist = row.get(2)?;
+    song.album_artist = row.get(3)?;
+    song.genre = row.get(4)?;
+    song.year = row.get(5)?;
+    song.duration_secs = row.get::<_, i64>(6)? as u64;
+    song.track_number = row.get(7)?;
+    song.track_gain = row.get(8)?;
+    song.album_gain = row.get(9)?;
+    song.start_ms = row.get::<_, i64>(11)? as u64;
+    song.end_ms = row.get::<_, Option<i64>>(12)?.map(|end_ms| end_ms as u64);
+    song.disc_number = row.get(13)?;
+    song.has_cover_art = row.get(14)?;
+    song.added_secs = row.get::<_, i64>(15)? as u64;
+    song.bitrate_kbps = row.get(16)?;
+    song.size_bytes = row.get::<_, i64>(17)? as u64;
+    song.sample_rate = row.get(18)?;
+    song.channels = row.get(19)?;
+    song.rating = row.get(20)?;
+    song.favorite = row.get(21)?;
+    song.play_count = row.get(22)?;
+    song.skip_count = row.get(23)?;
+    song.last_played_secs = row.get::<_, i64>(24)? as u64;
+    song.track_gain = song.track_gain.or(row.get(25)?);
+    song.album_gain = song.album_gain.or(row.get(26)?);
+    Ok(song)
+}
+
+fn modified_secs(path: &Path) -> Option<i64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64)
+}
@@ -0,0 +1,114 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use super::song::Song;
+
+// songs whose lengths differ by at most this much can still be the same recording, since
+// encoders pad the start and end differently
+const MAX_DURATION_DIFF_SECS: u64 = 2;
+
+// groups of songs that are likely copies of each other, either because they have the same
+// artist, title and length, or because their audio is byte for byte the same
+pub fn find_duplicates(songs: &[Song]) -> Vec<Vec<Song>> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut grouped = vec![false; songs.len()];
+
+    // same artist and title, with about the same length
+    let mut by_name: BTreeMap<(String, String), Vec<usize>> = BTreeMap::new();
+    for (idx, song) in songs.iter().enumerate() {
+        let key = (normalize(&song.track_artist), normalize(&song.title));
+        by_name.entry(key).or_default().push(idx);
+    }
+    for indices in by_name.values().filter(|indices| indices.len() > 1) {
+        for group in split_by_duration(songs, indices) {
+            group.iter().for_each(|idx| grouped[*idx] = true);
+            groups.push(group);
+        }
+    }
+
+    // the same audio tagged differently. the audio can only match if the length does, so only
+    // those files are read
+    let mut by_duration: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (idx, song) in songs.iter().enumerate() {
+        // tracks of a cue sheet share their file with the rest of the album
+        if !grouped[idx] && song.start_ms == 0 && song.end_ms.is_none() {
+            by_duration.entry(song.duration_secs).or_default().push(idx);
+        }
+    }
+    for indices in by_duration.values().filter(|indices| indices.len() > 1) {
+        let mut by_checksum: HashMap<u64, Vec<usize>> = HashMap::new();
+        for idx in indices {
+            match audio_checksum(Path::new(&songs[*idx].path)) {
+                Ok(checksum) => by_checksum.entry(checksum).or_default().push(*idx),
+                Err(e) => warn!(
+                    "Could not read the audio of {}. Reason: {}",
+                    songs[*idx].path, e
+                ),
+            }
+        }
+        groups.extend(by_checksum.into_values().filter(|group| group.len() > 1));
+    }
+
+    let mut groups: Vec<Vec<Song>> = groups
+        .into_iter()
+        .map(|group| group.into_iter().map(|idx| songs[idx].clone()).collect())
+        .collect();
+    groups
+        .sort_by(|a, b| (&a[0].track_artist, &a[0].title).cmp(&(&b[0].track_artist, &b[0].title)));
+    groups
+}
+
+fn normalize(value: &str) -> String {
+    value.trim().to_lowercase()
+}
+
+// runs of songs whose lengths are each close to the one before
+fn split_by_duration(songs: &[Song], indices: &[usize]) -> Vec<Vec<usize>> {
+    let mut indices = indices.to_vec();
+    indices.sort_by_key(|idx| songs[*idx].duration_secs);
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut curr: Vec<usize> = Vec::new();
+    for idx in indices {
+        if let Some(last) = curr.last() {
+            let diff = songs[idx].duration_secs - songs[*last].duration_secs;
+            if diff > MAX_DURATION_DIFF_SECS {
+                groups.push(curr);
+                curr = Vec::new();
+            }
+        }
+        curr.push(idx);
+    }
+    groups.push(curr);
+    groups.into_iter().filter(|group| group.len() > 1).collect()
+}
+
+// a hash of the encoded audio packets, leaving out the tags so that retagged copies still match
+fn audio_checksum(path: &Path) -> Result<u64, Box<dyn Error>> {
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+        hint.with_extension(extension);
+    }
+    let media_source_stream =
+        MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+    let mut format = symphonia::default::get_probe()
+        .format(
+            &hint,
+            media_source_stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?
+        .format;
+    let mut hasher = DefaultHasher::new();
+    while let Ok(packet) = format.next_packet() {
+        packet.data.hash(&mut hasher);
+    }
+    Ok(hasher.finish())
+}
@@ -0,0 +1,102 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::cue;
+use super::playlist::PlaylistFormats;
+use super::scanner;
+
+// a row of the file browser, a directory or a file that can be played
+#[derive(Clone)]
+pub struct FileEntry {
+    pub path: PathBuf,
+    pub name: String,
+    pub is_dir: bool,
+}
+
+impl FileEntry {
+    // the library directories are shown by their whole path
+    pub fn root(path: &str) -> FileEntry {
+        FileEntry {
+            path: PathBuf::from(path),
+            name: path.to_string(),
+            is_dir: true,
+        }
+    }
+}
+
+// the directories in this one and the files in it that can be played, directories first and each
+// by name whatever its case. hidden ones are left out, and so are the files split up by a cue
+// sheet next to them
+pub fn list(dir: &Path) -> io::Result<Vec<FileEntry>> {
+    let mut paths = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            !path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        })
+        .collect::<Vec<PathBuf>>();
+    paths.sort_by_key(|path| path.to_string_lossy().to_lowercase());
+    let split_files: Vec<PathBuf> = paths
+        .iter()
+        .filter(|path| scanner::is_cue_sheet(path))
+        .flat_map(|path| cue::referenced_files(path))
+        .collect();
+    let (dirs, files): (Vec<PathBuf>, Vec<PathBuf>) =
+        paths.into_iter().partition(|path| path.is_dir());
+    let files = files.into_iter().filter(|path| {
+        (scanner::has_supported_extension(path) && !split_files.contains(path))
+            || scanner::is_cue_sheet(path)
+            || PlaylistFormats::from_path(path).is_some()
+    });
+    let entries = dirs
+        .into_iter()
+        .map(|path| (path, true))
+        .chain(files.map(|path| (path, false)))
+        .map(|(path, is_dir)| FileEntry {
+            name: path
+                .file_name()
+                .map_or(String::new(), |name| name.to_string_lossy().into_owned()),
+            path,
+            is_dir,
+        })
+        .collect();
+    Ok(entries)
+}
+
+// the library directory the path is under, if any
+pub fn root_of<'a>(roots: &'a [String], path: &Path) -> Option<&'a String> {
+    roots.iter().find(|root| path.starts_with(root))
+}
+
+// move the file into the directory under the same name, copying it over when it's on another
+// filesystem. a file already there by that name is left alone
+pub fn move_to(path: &Path, dir: &Path) -> io::Result<PathBuf> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file"))?;
+    let target = dir.join(name);
+    if target.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} is already there", target.display()),
+        ));
+    }
+    fs::create_dir_all(dir)?;
+    move_file(path, &target)?;
+    Ok(target)
+}
+
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)?;
+    fs::remove_file(from)
+}
+
+// put the file in the trash the way the desktop does, so it can be put back from its file manager
+pub fn trash(path: &Path) -> io::Result<()> {
+    trash::delete(path).map_err(|err| io::Error::other(err.to_string()))
+}
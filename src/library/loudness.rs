@@ -0,0 +1,330 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::f64::consts::PI;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::UnboundedSender;
+use std::thread;
+
+use symphonia::core::audio::SignalSpec;
+
+use super::song::Song;
+use super::tag;
+use super::AlbumKey;
+use crate::player::errors::PlayerError;
+use crate::player::symphonia_player::DecodingSong;
+use crate::state::AppState;
+use crate::utils::constants::requests::{AppRequests, LibraryRequests, UIRequests};
+
+// what ReplayGain 2.0 brings every song to, in LUFS
+const REFERENCE_LOUDNESS: f64 = -18.;
+// blocks quieter than this are silence and don't count towards the loudness
+const ABSOLUTE_GATE: f64 = -70.;
+// and neither do blocks this much quieter than the rest
+const RELATIVE_GATE: f64 = -10.;
+// loudness is measured over 400ms blocks that overlap by 300ms
+const STEPS_PER_BLOCK: usize = 4;
+
+// one stage of the K-weighting filter, which weighs frequencies the way they're heard
+#[derive(Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+// the high shelf then the high pass of ITU-R BS.1770, worked out for any sample rate
+fn k_weighting(rate: u32) -> [Biquad; 2] {
+    let rate = rate as f64;
+
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain_db / 20.);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1. + k / q + k * k;
+    let shelf = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2. * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [2. * (k * k - 1.) / a0, (1. - k / q + k * k) / a0],
+        z: [0.; 2],
+    };
+
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1. + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1., -2., 1.],
+        a: [2. * (k * k - 1.) / a0, (1. - k / q + k * k) / a0],
+        z: [0.; 2],
+    };
+    [shelf, high_pass]
+}
+
+// the low frequency channel of 5.1 audio is left out and the surround channels count for more
+fn channel_weight(channels: usize, channel: usize) -> f64 {
+    match (channels, channel) {
+        (5.., 3) => 0.,
+        (5.., 4..) => 1.41,
+        _ => 1.,
+    }
+}
+
+// measures the loudness of audio the way EBU R128 does. what's measured is kept as the power of
+// each block, so that the blocks of a whole album can be pooled for its gain
+pub struct Meter {
+    spec: Option<SignalSpec>,
+    filters: Vec<[Biquad; 2]>,
+    // weighted sum of squares of the 100ms being measured, and how many frames are in it so far
+    step_sum: f64,
+    step_frames: usize,
+    steps: VecDeque<f64>, // the mean squares of the last steps, which make up a block
+    pub blocks: Vec<f64>,
+}
+
+impl Default for Meter {
+    fn default() -> Self {
+        Meter::new()
+    }
+}
+
+impl Meter {
+    pub fn new() -> Meter {
+        Meter {
+            spec: None,
+            filters: Vec::new(),
+            step_sum: 0.,
+            step_frames: 0,
+            steps: VecDeque::with_capacity(STEPS_PER_BLOCK),
+            blocks: Vec::new(),
+        }
+    }
+
+    // interleaved samples with that spec. the filters start over if it changes
+    pub fn push(&mut self, spec: SignalSpec, samples: &[f32]) {
+        let channels = spec.channels.count();
+        if self.spec != Some(spec) {
+            self.spec = Some(spec);
+            self.filters = vec![k_weighting(spec.rate); channels];
+            self.step_sum = 0.;
+            self.step_frames = 0;
+            self.steps.clear();
+        }
+        let step_len = (spec.rate as usize / 10).max(1);
+        for frame in samples.chunks_exact(channels) {
+            for (channel, (sample, filters)) in frame.iter().zip(&mut self.filters).enumerate() {
+                let weighted = filters
+                    .iter_mut()
+                    .fold(*sample as f64, |sample, filter| filter.process(sample));
+                self.step_sum += channel_weight(channels, channel) * weighted * weighted;
+            }
+            self.step_frames += 1;
+            if self.step_frames == step_len {
+                if self.steps.len() == STEPS_PER_BLOCK {
+                    self.steps.pop_front();
+                }
+                self.steps.push_back(self.step_sum / step_len as f64);
+                if self.steps.len() == STEPS_PER_BLOCK {
+                    self.blocks
+                        .push(self.steps.iter().sum::<f64>() / STEPS_PER_BLOCK as f64);
+                }
+                self.step_sum = 0.;
+                self.step_frames = 0;
+            }
+        }
+    }
+}
+
+fn to_lufs(power: f64) -> f64 {
+    -0.691 + 10. * power.log10()
+}
+
+fn mean(blocks: &[f64]) -> f64 {
+    blocks.iter().sum::<f64>() / blocks.len() as f64
+}
+
+// the gated loudness of the blocks in LUFS, none if they're all silent
+pub fn integrated_loudness(blocks: &[f64]) -> Option<f64> {
+    let loud: Vec<f64> = blocks
+        .iter()
+        .copied()
+        .filter(|power| to_lufs(*power) > ABSOLUTE_GATE)
+        .collect();
+    if loud.is_empty() {
+        return None;
+    }
+    let threshold = to_lufs(mean(&loud)) + RELATIVE_GATE;
+    let gated: Vec<f64> = loud
+        .into_iter()
+        .filter(|power| to_lufs(*power) > threshold)
+        .collect();
+    Some(to_lufs(mean(&gated)))
+}
+
+// the ReplayGain that brings blocks this loud to the reference loudness
+pub fn gain(blocks: &[f64]) -> Option<f32> {
+    let loudness = integrated_loudness(blocks)?;
+    Some(((REFERENCE_LOUDNESS - loudness) * 100.).round() as f32 / 100.)
+}
+
+// decode a whole song, handing back the power of each of its blocks
+fn measure(app_state: &Arc<Mutex<AppState>>, song: &Song) -> Result<Vec<f64>, PlayerError> {
+    let mut decoding = DecodingSong::open(app_state, song, 1.)?;
+    let mut meter = Meter::new();
+    let mut samples = Vec::new();
+    while decoding.decode_next(&mut samples) {
+        if let Some(spec) = decoding.spec {
+            meter.push(spec, &samples);
+        }
+        samples.clear();
+    }
+    match decoding.error.take() {
+        Some(err) => Err(err),
+        None => Ok(meter.blocks),
+    }
+}
+
+// the songs of the library missing a track or album gain, grouped by album artist and album
+// since the album gain needs all of its tracks. every track of such an album is measured. a song
+// without an album is a group of its own, so its album gain is its track gain
+pub fn songs_to_measure(songs: &[Song]) -> Vec<Vec<Song>> {
+    let mut albums: BTreeMap<AlbumKey, Vec<Song>> = BTreeMap::new();
+    let mut singles = Vec::new();
+    for song in songs.iter().filter(|song| !song.is_stream() && !song.missing) {
+        // songs without an album tag are all put under the same unknown album when scanned
+        if song.album_title.trim().is_empty() || song.album_title == tag::UNKNOWN_ALBUM {
+            singles.push(vec![song.clone()]);
+            continue;
+        }
+        let key = AlbumKey {
+            artist: song.album_artist.to_owned(),
+            title: song.album_title.to_owned(),
+        };
+        albums.entry(key).or_default().push(song.clone());
+    }
+    albums
+        .into_values()
+        .chain(singles)
+        .filter(|tracks| {
+            tracks
+                .iter()
+                .any(|song| song.track_gain.is_none() || song.album_gain.is_none())
+        })
+        .collect()
+}
+
+// measure the albums in the background, one at a time. the gains that were missing are filled
+// in and sent to be saved, and written to the files too if asked to. not joined on quit
+pub fn spawn_scan(
+    app_state: &Arc<Mutex<AppState>>,
+    main_tx: &UnboundedSender<AppRequests>,
+    albums: Vec<Vec<Song>>,
+    write_tags: bool,
+) {
+    let cloned_state = app_state.clone();
+    let cloned_main_tx = main_tx.clone();
+    let total: usize = albums.iter().map(Vec::len).sum();
+    thread::spawn(move || {
+        let progress = |measured| {
+            let _ = cloned_main_tx.send(AppRequests::UIRequests(UIRequests::GainProgress(
+                measured, total,
+            )));
+        };
+        let (mut measured, mut failed, mut not_written) = (0, 0, 0);
+        for mut tracks in albums {
+            let mut album_blocks = Vec::new();
+            let mut track_gains = Vec::new();
+            for song in &tracks {
+                match measure(&cloned_state, song) {
+                    Ok(blocks) => {
+                        track_gains.push(gain(&blocks));
+                        album_blocks.extend(blocks);
+                    }
+                    Err(err) => {
+                        warn!("Could not measure the loudness of {}. Reason: {}", song.path, err);
+                        track_gains.push(None);
+                        failed += 1;
+                    }
+                }
+                measured += 1;
+                progress(measured);
+            }
+            let album_gain = gain(&album_blocks);
+            let mut changed = Vec::new();
+            for (song, track_gain) in tracks.iter_mut().zip(track_gains) {
+                if track_gain.is_none() {
+                    continue;
+                }
+                song.track_gain = song.track_gain.or(track_gain);
+                song.album_gain = song.album_gain.or(album_gain);
+                if write_tags {
+                    if let Err(err) = tag::write_replay_gain(song) {
+                        warn!("Could not write the ReplayGain of {}. Reason: {}", song.path, err);
+                        not_written += 1;
+                    }
+                }
+                changed.push(song.clone());
+            }
+            if !changed.is_empty() {
+                let _ = cloned_main_tx.send(AppRequests::LibraryRequests(
+                    LibraryRequests::SaveGains(changed),
+                ));
+            }
+        }
+        let mut message = format!("Measured the loudness of {} songs", measured - failed);
+        if failed > 0 {
+            message.push_str(&format!(", {} couldn't be read", failed));
+        }
+        if not_written > 0 {
+            message.push_str(&format!(", {} files couldn't be written to", not_written));
+        }
+        info!("{}", message);
+        cloned_state.lock().unwrap().ui.notify(message);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song(path: &str, artist: &str, album: &str) -> Song {
+        let mut song = Song::new(path.to_string(), path.to_string());
+        song.album_artist = artist.to_string();
+        song.album_title = album.to_string();
+        song
+    }
+
+    fn paths(albums: &[Vec<Song>]) -> Vec<Vec<&str>> {
+        albums
+            .iter()
+            .map(|tracks| tracks.iter().map(|song| song.path.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn songs_without_an_album_are_measured_on_their_own() {
+        let songs = [
+            song("a.flac", "A", "First"),
+            song("b.flac", "A", "First"),
+            song("c.mp3", "B", ""),
+            song("d.mp3", "C", tag::UNKNOWN_ALBUM),
+        ];
+        let albums = songs_to_measure(&songs);
+        assert_eq!(paths(&albums), [vec!["a.flac", "b.flac"], vec!["c.mp3"], vec!["d.mp3"]]);
+    }
+
+    #[test]
+    fn albums_of_the_same_name_by_other_artists_are_apart() {
+        let songs = [song("a.flac", "A", "Greatest Hits"), song("b.flac", "B", "Greatest Hits")];
+        assert_eq!(paths(&songs_to_measure(&songs)), [vec!["a.flac"], vec!["b.flac"]]);
+    }
+}
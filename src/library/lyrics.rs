@@ -0,0 +1,181 @@
+use lofty::read_from_path;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use super::song::Song;
+use super::tag;
+
+// a song's lyrics, each line with the time it's sung at if they're synchronized
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lyrics {
+    pub lines: Vec<LyricLine>,
+    pub synced: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LyricLine {
+    pub time: Option<Duration>,
+    pub text: String,
+}
+
+impl Lyrics {
+    // an .lrc file next to the song with the same name, or the lyrics tag of the song itself
+    pub fn load(song: &Song) -> Option<Lyrics> {
+        let lrc_path = Path::new(&song.path).with_extension("lrc");
+        let text = fs::read(&lrc_path)
+            .ok()
+            .map(|lrc| String::from_utf8_lossy(&lrc).into_owned())
+            .or_else(|| {
+                let file = read_from_path(&song.path, false).ok()?;
+                file.tags().iter().find_map(tag::get_lyrics)
+            })?;
+        let lyrics = Lyrics::parse(&text);
+        if lyrics.lines.is_empty() {
+            None
+        } else {
+            Some(lyrics)
+        }
+    }
+
+    // lrc lines look like [mm:ss.xx]text, and can have several timestamps when a line repeats.
+    // lyrics without any timestamps are kept as plain text
+    pub fn parse(text: &str) -> Lyrics {
+        let mut offset_ms: i64 = 0;
+        let mut synced_lines: Vec<LyricLine> = Vec::new();
+        let mut plain_lines: Vec<LyricLine> = Vec::new();
+        for line in text.lines() {
+            let line = line.trim().trim_start_matches('\u{feff}');
+            let mut rest = line;
+            let mut times: Vec<Duration> = Vec::new();
+            while let Some((tag, after)) =
+                rest.strip_prefix('[').and_then(|tag| tag.split_once(']'))
+            {
+                match parse_timestamp(tag) {
+                    Some(time) => times.push(time),
+                    None => {
+                        // [offset:+/-ms] shifts every line, other tags like [ar:] are skipped
+                        if let Some(offset) = tag.strip_prefix("offset:") {
+                            offset_ms = offset.trim().parse().unwrap_or(0);
+                        }
+                    }
+                }
+                rest = after;
+            }
+            let text = rest.trim().to_string();
+            if times.is_empty() {
+                if rest.len() == line.len() {
+                    plain_lines.push(LyricLine { time: None, text });
+                }
+            } else {
+                synced_lines.extend(times.into_iter().map(|time| LyricLine {
+                    time: Some(time),
+                    text: text.to_owned(),
+                }));
+            }
+        }
+
+        if synced_lines.is_empty() {
+            // leading and trailing blank lines would only push the lyrics around
+            while plain_lines
+                .first()
+                .map_or(false, |line| line.text.is_empty())
+            {
+                plain_lines.remove(0);
+            }
+            while plain_lines
+                .last()
+                .map_or(false, |line| line.text.is_empty())
+            {
+                plain_lines.pop();
+            }
+            return Lyrics {
+                lines: plain_lines,
+                synced: false,
+            };
+        }
+        // a positive offset makes the lyrics show up sooner
+        for line in synced_lines.iter_mut() {
+            if let Some(time) = line.time {
+                let time_ms = time.as_millis() as i64 - offset_ms;
+                line.time = Some(Duration::from_millis(time_ms.max(0) as u64));
+            }
+        }
+        synced_lines.sort_by_key(|line| line.time);
+        Lyrics {
+            lines: synced_lines,
+            synced: true,
+        }
+    }
+
+    // the last line that has started by `progress`
+    pub fn current_line(&self, progress: Duration) -> Option<usize> {
+        if !self.synced {
+            return None;
+        }
+        self.lines
+            .iter()
+            .rposition(|line| line.time.map_or(false, |time| time <= progress))
+    }
+}
+
+// mm:ss, mm:ss.xx or mm:ss.xxx
+fn parse_timestamp(tag: &str) -> Option<Duration> {
+    let (mins, secs) = tag.split_once(':')?;
+    let mins: u64 = mins.trim().parse().ok()?;
+    let (secs, fraction) = secs.split_once('.').unwrap_or((secs, "0"));
+    let secs: u64 = secs.trim().parse().ok()?;
+    if fraction.is_empty() || !fraction.chars().all(|ch| ch.is_ascii_digit()) {
+        return None;
+    }
+    // pad or cut the fraction to milliseconds
+    let millis: u64 = format!("{:0<3}", fraction).get(..3)?.parse().ok()?;
+    Some(Duration::from_millis((mins * 60 + secs) * 1000 + millis))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn times(lyrics: &Lyrics) -> Vec<(u64, &str)> {
+        lyrics
+            .lines
+            .iter()
+            .map(|line| (line.time.unwrap().as_millis() as u64, line.text.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(parse_timestamp("01:02"), Some(Duration::from_millis(62_000)));
+        assert_eq!(parse_timestamp("00:01.5"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_timestamp("00:01.25"), Some(Duration::from_millis(1250)));
+        assert_eq!(parse_timestamp("00:01.2345"), Some(Duration::from_millis(1234)));
+        assert_eq!(parse_timestamp("00:01."), None);
+        assert_eq!(parse_timestamp("ar:Someone"), None);
+    }
+
+    #[test]
+    fn repeated_lines_are_sorted_by_time() {
+        let lyrics = Lyrics::parse("[ar:Someone]\n[00:05.00][00:01.00]Chorus\n[00:03.00] Verse\n");
+        assert!(lyrics.synced);
+        assert_eq!(times(&lyrics), [(1000, "Chorus"), (3000, "Verse"), (5000, "Chorus")]);
+        assert_eq!(lyrics.current_line(Duration::from_millis(500)), None);
+        assert_eq!(lyrics.current_line(Duration::from_millis(3000)), Some(1));
+    }
+
+    #[test]
+    fn offset_shifts_every_line() {
+        let lyrics = Lyrics::parse("[offset:+500]\n[00:01.00]One\n[00:00.20]Zero\n");
+        assert_eq!(times(&lyrics), [(0, "Zero"), (500, "One")]);
+    }
+
+    #[test]
+    fn plain_lyrics_without_blank_edges() {
+        let lyrics = Lyrics::parse("\n\nFirst\n\nSecond\n\n");
+        assert!(!lyrics.synced);
+        let lines: Vec<&str> = lyrics.lines.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(lines, ["First", "", "Second"]);
+        assert_eq!(lyrics.current_line(Duration::from_secs(10)), None);
+    }
+}
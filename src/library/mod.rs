@@ -1,34 +1,414 @@
+pub mod bookmark;
+pub mod cue;
+pub mod db;
+pub mod duplicates;
 pub mod errors;
+pub mod files;
+pub mod loudness;
+pub mod lyrics;
+pub mod playlist;
+pub mod scanner;
 pub mod search;
+pub mod smart_playlist;
 pub mod song;
+pub mod stats;
 pub mod tag;
 
 use crate::library::song::Song;
-use bincode;
+use bookmark::{Bookmark, Bookmarks};
+use crate::state::{AppState, SEARCH_HISTORY_LEN};
+use crate::ui::widgets::curr_playing_bar::readable_time;
+use crate::utils::constants::requests::{AppRequests, FileActions, LibraryRequests, UIRequests};
+use db::LibraryDb;
 use errors::ImportError;
 use lofty::read_from_path;
+use playlist::{PlaylistEntry, PlaylistFormats};
+use search::SavedSearch;
+use smart_playlist::SmartPlaylist;
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fs;
 use std::path::Path;
-use std::time::Instant;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::UnboundedSender;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// how many songs the most and recently played views show
+const HISTORY_LEN: usize = 100;
+
+// albums are told apart by their artist as well, since plenty of albums share a title
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AlbumKey {
+    pub artist: String,
+    pub title: String,
+}
 
 pub struct Library {
     pub songs: Vec<Song>,
+    // positions in `songs` of each album's tracks, in track order
+    albums: BTreeMap<AlbumKey, Vec<usize>>,
+    pub smart_playlists: Vec<SmartPlaylist>,
+    pub saved_searches: Vec<SavedSearch>, // by name
+    pub bookmarks: Bookmarks,
+    // where playlist files are read from and the queue is saved to
+    pub playlist_dir: Option<String>,
+    // the library directories, where the file browser starts. none while playing through mpd
+    pub roots: Vec<String>,
 }
 
 impl Default for Library {
     fn default() -> Self {
-        Library { songs: vec![] }
+        Library {
+            songs: vec![],
+            albums: BTreeMap::new(),
+            smart_playlists: Vec::new(),
+            saved_searches: Vec::new(),
+            bookmarks: Bookmarks::new(),
+            playlist_dir: None,
+            roots: Vec::new(),
+        }
     }
 }
 
 impl Library {
     pub fn new() -> Library {
-        Library { songs: Vec::new() }
+        Library {
+            songs: Vec::new(),
+            albums: BTreeMap::new(),
+            smart_playlists: Vec::new(),
+            saved_searches: Vec::new(),
+            bookmarks: Bookmarks::new(),
+            playlist_dir: None,
+            roots: Vec::new(),
+        }
+    }
+
+    pub fn load(&mut self, db: &LibraryDb) -> Result<(), Box<dyn Error>> {
+        self.set_songs(db.load_songs()?);
+        Ok(())
+    }
+
+    pub fn set_songs(&mut self, songs: Vec<Song>) {
+        self.songs = songs;
+        self.build_index();
+    }
+
+    fn build_index(&mut self) {
+        self.albums.clear();
+        for (idx, song) in self.songs.iter().enumerate() {
+            let key = AlbumKey {
+                artist: song.album_artist.to_owned(),
+                title: song.album_title.to_owned(),
+            };
+            self.albums.entry(key).or_default().push(idx);
+        }
+        let songs = &self.songs;
+        for tracks in self.albums.values_mut() {
+            tracks.sort_by_key(|idx| {
+                let song = &songs[*idx];
+                (
+                    leading_number(&song.disc_number),
+                    leading_number(&song.track_number),
+                    song.start_ms,
+                )
+            });
+        }
+    }
+
+    // drop the songs that were missing, handing them back so they can be removed from the
+    // database too
+    pub fn remove_missing(&mut self) -> Vec<Song> {
+        let (missing, found): (Vec<Song>, Vec<Song>) =
+            self.songs.drain(..).partition(|song| song.missing);
+        self.set_songs(found);
+        missing
+    }
+
+    // drop these songs, eg. when they were removed from the library by hand
+    pub fn remove_songs(&mut self, songs: &[Song]) {
+        let mut kept = std::mem::take(&mut self.songs);
+        kept.retain(|song| !songs.iter().any(|removed| removed.is_same(song)));
+        self.set_songs(kept);
+    }
+
+    // swap in a song whose tags were edited. its stats are kept since the edited copy may be
+    // older than them
+    pub fn update_song(&mut self, song: Song) {
+        if let Some(old) = self.songs.iter_mut().find(|old| old.is_same(&song)) {
+            let stats = (
+                old.rating,
+                old.favorite,
+                old.play_count,
+                old.skip_count,
+                old.last_played_secs,
+            );
+            *old = song;
+            (
+                old.rating,
+                old.favorite,
+                old.play_count,
+                old.skip_count,
+                old.last_played_secs,
+            ) = stats;
+            self.build_index();
+        }
+    }
+
+    // the songs of a file that was moved, and their bookmarks, are found where it is now
+    pub fn move_file(&mut self, from: &str, to: &str) {
+        for song in self.songs.iter_mut().filter(|song| song.path == from) {
+            song.path = to.to_string();
+        }
+        let moved: Vec<(String, u64)> = self
+            .bookmarks
+            .keys()
+            .filter(|(path, _)| path == from)
+            .cloned()
+            .collect();
+        for key in moved {
+            if let Some(bookmarks) = self.bookmarks.remove(&key) {
+                self.bookmarks.insert((to.to_string(), key.1), bookmarks);
+            }
+        }
+    }
+
+    // fill in the gains that were measured, keeping everything else about the songs
+    pub fn update_gains(&mut self, songs: &[Song]) {
+        for song in songs {
+            if let Some(old) = self.songs.iter_mut().find(|old| old.is_same(song)) {
+                old.track_gain = song.track_gain;
+                old.album_gain = song.album_gain;
+            }
+        }
+    }
+
+    // change the stats of a song in place and hand back the changed song
+    pub fn change_stats(&mut self, song: &Song, change: impl FnOnce(&mut Song)) -> Option<Song> {
+        let song = self.songs.iter_mut().find(|old| old.is_same(song))?;
+        change(song);
+        Some(song.clone())
+    }
+
+    pub fn most_played(&self) -> Vec<Song> {
+        let mut songs: Vec<Song> = self
+            .songs
+            .iter()
+            .filter(|song| song.play_count > 0)
+            .cloned()
+            .collect();
+        songs.sort_by(|a, b| b.play_count.cmp(&a.play_count).then_with(|| a.title.cmp(&b.title)));
+        songs.truncate(HISTORY_LEN);
+        songs
+    }
+
+    pub fn recently_played(&self) -> Vec<Song> {
+        let mut songs: Vec<Song> = self
+            .songs
+            .iter()
+            .filter(|song| song.last_played_secs > 0)
+            .cloned()
+            .collect();
+        songs.sort_by(|a, b| b.last_played_secs.cmp(&a.last_played_secs));
+        songs.truncate(HISTORY_LEN);
+        songs
+    }
+
+    // newest first by when they were first scanned, so the songs of an album found together stay
+    // together in track order
+    pub fn recently_added(&self) -> Vec<Song> {
+        let mut songs: Vec<Song> = self
+            .songs
+            .iter()
+            .filter(|song| song.added_secs > 0)
+            .cloned()
+            .collect();
+        songs.sort_by(|a, b| {
+            b.added_secs
+                .cmp(&a.added_secs)
+                .then_with(|| a.album_artist.cmp(&b.album_artist))
+                .then_with(|| a.album_title.cmp(&b.album_title))
+                .then_with(|| leading_number(&a.disc_number).cmp(&leading_number(&b.disc_number)))
+                .then_with(|| {
+                    leading_number(&a.track_number).cmp(&leading_number(&b.track_number))
+                })
+                .then_with(|| a.start_ms.cmp(&b.start_ms))
+        });
+        songs.truncate(HISTORY_LEN);
+        songs
+    }
+
+    pub fn artists(&self) -> Vec<String> {
+        let mut artists: Vec<String> = self
+            .albums
+            .keys()
+            .map(|key| key.artist.to_owned())
+            .collect();
+        artists.dedup();
+        artists
+    }
+
+    pub fn albums(&self) -> Vec<AlbumKey> {
+        self.albums.keys().cloned().collect()
+    }
+
+    pub fn albums_by(&self, artist: &str) -> Vec<AlbumKey> {
+        self.albums
+            .keys()
+            .filter(|key| key.artist == artist)
+            .cloned()
+            .collect()
     }
 
-    // only supports wav, mp3, flac
-    pub fn import_file(&mut self, filepath: &str) -> Result<(), Box<dyn Error>> {
+    pub fn album_tracks(&self, album: &AlbumKey) -> Vec<Song> {
+        match self.albums.get(album) {
+            Some(tracks) => tracks.iter().map(|idx| self.songs[*idx].clone()).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    // smart playlists first, then saved searches and the files in the playlist directory
+    pub fn playlists(&self) -> Vec<PlaylistEntry> {
+        let mut playlists: Vec<PlaylistEntry> = self
+            .smart_playlists
+            .iter()
+            .map(|playlist| PlaylistEntry::Smart(playlist.name.to_owned()))
+            .collect();
+        playlists.extend(
+            self.saved_searches
+                .iter()
+                .map(|search| PlaylistEntry::Search(search.name.to_owned())),
+        );
+        if let Some(dir) = &self.playlist_dir {
+            playlists.extend(
+                playlist::find_playlists(Path::new(dir))
+                    .into_iter()
+                    .map(PlaylistEntry::File),
+            );
+        }
+        playlists
+    }
+
+    // playlists are filled in whenever they are opened, so they always match the library
+    pub fn playlist_songs(&self, playlist: &PlaylistEntry) -> Result<Vec<Song>, Box<dyn Error>> {
+        match playlist {
+            PlaylistEntry::Smart(name) => {
+                let playlist = match self.smart_playlists.iter().find(|p| &p.name == name) {
+                    Some(playlist) => playlist,
+                    None => return Ok(Vec::new()),
+                };
+                let mut songs: Vec<Song> = self
+                    .songs
+                    .iter()
+                    .filter(|song| playlist.matches(song))
+                    .cloned()
+                    .collect();
+                songs.sort_by(|a, b| a.title.cmp(&b.title));
+                Ok(songs)
+            }
+            // best match first, like the search itself
+            PlaylistEntry::Search(name) => {
+                let query = match self.saved_searches.iter().find(|s| &s.name == name) {
+                    Some(search) => &search.query,
+                    None => return Ok(Vec::new()),
+                };
+                let songs = search::search(query, &self.songs)
+                    .into_iter()
+                    .map(|(song, _)| song)
+                    .collect();
+                Ok(songs)
+            }
+            PlaylistEntry::File(path) => {
+                let mut songs = Vec::new();
+                for entry in playlist::read(path)? {
+                    let entry = entry.to_string_lossy();
+                    // files outside of the library are read as they are
+                    match self.songs.iter().find(|song| song.path == entry) {
+                        Some(song) => songs.push(song.clone()),
+                        None if song::is_stream_url(&entry) => {
+                            songs.push(Song::stream(entry.into_owned()))
+                        }
+                        None => match Library::read_file(&entry) {
+                            Ok(song) => songs.push(song),
+                            Err(e) => warn!("Skipping {} in {:?}. Reason: {}", entry, path, e),
+                        },
+                    }
+                }
+                Ok(songs)
+            }
+        }
+    }
+
+    // replaces the search by that name, if there's one
+    pub fn save_search(&mut self, search: SavedSearch) {
+        self.saved_searches.retain(|saved| saved.name != search.name);
+        self.saved_searches.push(search);
+        self.saved_searches.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    pub fn bookmarks(&self, song: &Song) -> &[Bookmark] {
+        self.bookmarks
+            .get(&(song.path.to_owned(), song.start_ms))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    // replaces the song's bookmark by that name, if it has one
+    pub fn add_bookmark(&mut self, song: &Song, bookmark: Bookmark) {
+        let key = (song.path.to_owned(), song.start_ms);
+        let bookmarks = self.bookmarks.entry(key).or_default();
+        bookmarks.retain(|saved| saved.name != bookmark.name);
+        bookmarks.push(bookmark);
+        bookmarks.sort_by_key(|bookmark| bookmark.position);
+    }
+
+    // false if the song has no bookmark by that name
+    pub fn remove_bookmark(&mut self, song: &Song, name: &str) -> bool {
+        let bookmarks = match self.bookmarks.get_mut(&(song.path.to_owned(), song.start_ms)) {
+            Some(bookmarks) => bookmarks,
+            None => return false,
+        };
+        let len = bookmarks.len();
+        bookmarks.retain(|bookmark| bookmark.name != name);
+        len != bookmarks.len()
+    }
+
+    // the songs to play for a path given on the command line. songs that are in the library are
+    // taken from it so that their stats are kept
+    pub fn songs_at(&self, path: &Path) -> Result<Vec<Song>, Box<dyn Error>> {
+        let path_str = path.to_string_lossy();
+        if song::is_stream_url(&path_str) {
+            return Ok(vec![Song::stream(path_str.into_owned())]);
+        }
+        if PlaylistFormats::from_path(path).is_some() {
+            return self.playlist_songs(&PlaylistEntry::File(path.to_path_buf()));
+        }
+        let songs = scanner::read_path(path)?
+            .into_iter()
+            .map(|song| match self.songs.iter().find(|known| known.is_same(&song)) {
+                Some(known) => known.clone(),
+                None => song,
+            })
+            .collect();
+        Ok(songs)
+    }
+
+    // a song by its path and where it starts in the file, eg. one another splay is playing. read
+    // from the file if it isn't in the library
+    pub fn find_song(&self, path: &str, start_ms: u64) -> Option<Song> {
+        let known = self
+            .songs
+            .iter()
+            .find(|song| song.path == path && song.start_ms == start_ms);
+        if let Some(song) = known {
+            return Some(song.clone());
+        }
+        self.songs_at(Path::new(path))
+            .ok()?
+            .into_iter()
+            .find(|song| song.start_ms == start_ms)
+    }
+
+    // read the song from a single audio file
+    pub fn read_file(filepath: &str) -> Result<Song, Box<dyn Error>> {
         let path = if Path::new(filepath).exists() {
             match fs::canonicalize(filepath) {
                 Ok(path) => match path.to_str() {
@@ -44,99 +424,301 @@ impl Library {
 
         match read_from_path(filepath, false) {
             Ok(file) => match file.primary_tag() {
-                Some(tag) => {
-                    let song = match Song::from_tag(tag, path) {
-                        Ok(song) => song,
-                        Err(err) => return Err(err),
-                    };
-                    self.songs.push(song);
-                }
-                _ => {
-                    return Err(Box::new(ImportError::Parsing));
-                }
+                Some(tag) => match Song::from_tag(tag, path) {
+                    Ok(mut song) => {
+                        // few files have a length tag, the audio properties are more reliable
+                        let duration_secs = file.properties().duration().as_secs();
+                        if duration_secs != 0 {
+                            song.duration_secs = duration_secs;
+                        }
+                        song.bitrate_kbps = file.properties().audio_bitrate();
+                        song.sample_rate = file.properties().sample_rate().unwrap_or(0);
+                        song.channels = file.properties().channels().unwrap_or(0);
+                        song.size_bytes =
+                            fs::metadata(&song.path).map_or(0, |metadata| metadata.len());
+                        Ok(song)
+                    }
+                    Err(err) => Err(err),
+                },
+                _ => Err(Box::new(ImportError::Parsing)),
             },
-            Err(_) => {
-                return Err(Box::new(ImportError::Parsing));
-            }
+            Err(_) => Err(Box::new(ImportError::Parsing)),
         }
+    }
+}
 
-        Ok(())
+// track and disc numbers are often stored as "3/12"
+fn leading_number(value: &Option<String>) -> u32 {
+    match value {
+        Some(value) => value
+            .split('/')
+            .next()
+            .and_then(|number| number.trim().parse().ok())
+            .unwrap_or(0),
+        None => 0,
     }
+}
 
-    pub fn import_dir(&mut self, dir_path: &str) -> Result<(), Box<dyn Error>> {
-        let now = Instant::now();
-        match self._import_dir(dir_path) {
-            Err(e) => return Err(e),
-            _ => (),
-        }
-        let elapsed = now.elapsed();
-        info!(
-            "Took {:.3?} to import {} files from {}",
-            elapsed,
-            self.songs.len(),
-            dir_path
-        );
-        Ok(())
+// mark the songs whose files are gone. songs on a root that was skipped because it isn't
+// mounted are kept by the scan, so they would otherwise fail once played
+pub fn check_missing(songs: &mut [Song]) {
+    for song in songs.iter_mut() {
+        song.missing = !Path::new(&song.path).exists();
     }
+}
 
-    // recursive helper function for import_dir
-    fn _import_dir(&mut self, dir_path: &str) -> Result<(), Box<dyn Error>> {
-        let entries = fs::read_dir(dir_path);
-        for entry in entries {
-            for tmp in entry {
-                match tmp {
-                    Ok(file) => {
-                        if file.path().is_dir() {
-                            match self._import_dir(file.path().to_str().unwrap()) {
-                                Err(e) => error!("{:?}", e),
-                                _ => (),
-                            }
-                        } else {
-                            let _ = self.import_file(file.path().to_str().unwrap());
-                        }
-                    }
-                    Err(e) => {
-                        return Err(Box::new(e));
-                    }
+// write changes to songs to the database, on the main thread so that neither the UI nor the
+// player wait on it
+pub fn handle(
+    app_state: &Arc<Mutex<AppState>>,
+    db: &mut LibraryDb,
+    main_tx: &UnboundedSender<AppRequests>,
+    request: LibraryRequests,
+) {
+    let song = match request {
+        LibraryRequests::PurgeMissing => {
+            let missing = app_state.lock().unwrap().library.remove_missing();
+            for song in &missing {
+                if let Err(e) = db.remove_song(song) {
+                    error!("Could not remove {} from the library. Reason: {}", song.path, e);
                 }
             }
+            info!("Removed {} missing songs from the library", missing.len());
+            app_state.lock().unwrap().ui.notify(format!(
+                "Removed {} missing songs from the library",
+                missing.len()
+            ));
+            let _ = main_tx.send(AppRequests::UIRequests(UIRequests::LibraryUpdated));
+            None
         }
-        Ok(())
-    }
-
-    pub fn save_to_file(&self, path: String) -> Result<(), Box<dyn Error>> {
-        let db_file = match fs::File::create(path) {
-            Ok(f) => f,
-            Err(err) => return Err(Box::new(err)), //TODO: replace this with library DB error type
-        };
-        for song in self.songs.iter() {
-            match bincode::serialize_into(&db_file, song) {
-                Err(err) => return Err(Box::new(err)), //TODO: replace this with library DB error type
-                _ => (),
+        LibraryRequests::ChangeFiles(songs, action) => {
+            change_files(app_state, db, &songs, &action);
+            let _ = main_tx.send(AppRequests::UIRequests(UIRequests::LibraryUpdated));
+            None
+        }
+        LibraryRequests::Remove(songs) => {
+            app_state.lock().unwrap().library.remove_songs(&songs);
+            for song in &songs {
+                if let Err(e) = db.remove_song(song) {
+                    error!("Could not remove {} from the library. Reason: {}", song.path, e);
+                }
             }
+            let message = match songs.as_slice() {
+                [song] => format!("Removed {} from the library", song.title),
+                songs => format!("Removed {} songs from the library", songs.len()),
+            };
+            info!("{}", message);
+            app_state.lock().unwrap().ui.notify(message);
+            let _ = main_tx.send(AppRequests::UIRequests(UIRequests::LibraryUpdated));
+            None
+        }
+        LibraryRequests::SavePosition(song, position) => {
+            let left = app_state.lock().unwrap().podcasts.leave(&song, position);
+            if let Some((url, position)) = left {
+                if let Err(e) = db.save_position(&url, position) {
+                    error!("Could not save where {} was left. Reason: {}", song.title, e);
+                }
+            }
+            None
+        }
+        LibraryRequests::RememberSearch(query) => {
+            if let Err(e) = db.add_search(&query, SEARCH_HISTORY_LEN) {
+                error!("Could not save the search \"{}\". Reason: {}", query, e);
+            }
+            None
+        }
+        // searches are saved after they're made since the prompt takes every key while it's open
+        LibraryRequests::SaveSearch(name) => {
+            let mut guard = app_state.lock().unwrap();
+            let query = match guard.search.history.last() {
+                Some(query) => query.to_owned(),
+                None => {
+                    guard.ui.notify_error("Search for something first, then save it");
+                    return;
+                }
+            };
+            if let Err(e) = db.save_search(&name, Some(&query)) {
+                error!("Could not save the search {}. Reason: {}", name, e);
+            }
+            guard.ui.notify(format!("Saved the search \"{}\" as {}", query, name));
+            guard.library.save_search(SavedSearch { name, query });
+            let _ = main_tx.send(AppRequests::UIRequests(UIRequests::PlaylistsUpdated));
+            None
+        }
+        LibraryRequests::ForgetSearch(name) => {
+            let mut guard = app_state.lock().unwrap();
+            if !guard.library.saved_searches.iter().any(|search| search.name == name) {
+                guard.ui.notify_error(format!("There's no saved search called {}", name));
+                return;
+            }
+            guard.library.saved_searches.retain(|search| search.name != name);
+            if let Err(e) = db.save_search(&name, None) {
+                error!("Could not forget the search {}. Reason: {}", name, e);
+            }
+            guard.ui.notify(format!("Forgot the search {}", name));
+            let _ = main_tx.send(AppRequests::UIRequests(UIRequests::PlaylistsUpdated));
+            None
+        }
+        LibraryRequests::AddBookmark(name) => {
+            let mut guard = app_state.lock().unwrap();
+            let song = match guard.player.curr_song.to_owned() {
+                Some(song) if !song.is_stream() => song,
+                _ => {
+                    guard.ui.notify_error("Play a song first, then bookmark it");
+                    return;
+                }
+            };
+            let position = guard.player.progress;
+            let name = name.unwrap_or_else(|| readable_time(position.as_secs()));
+            if let Err(e) = db.save_bookmark(&song, &name, Some(position)) {
+                error!("Could not save the bookmark {}. Reason: {}", name, e);
+            }
+            guard.ui.notify(format!(
+                "Bookmarked {} of {} as {}",
+                readable_time(position.as_secs()),
+                song.title,
+                name
+            ));
+            guard.library.add_bookmark(&song, Bookmark { name, position });
+            None
+        }
+        LibraryRequests::DeleteBookmark(name) => {
+            let mut guard = app_state.lock().unwrap();
+            let song = match guard.player.curr_song.to_owned() {
+                Some(song) => song,
+                None => {
+                    guard.ui.notify_error("Nothing is playing");
+                    return;
+                }
+            };
+            if !guard.library.remove_bookmark(&song, &name) {
+                guard.ui.notify_error(format!("{} has no bookmark called {}", song.title, name));
+                return;
+            }
+            if let Err(e) = db.save_bookmark(&song, &name, None) {
+                error!("Could not delete the bookmark {}. Reason: {}", name, e);
+            }
+            guard.ui.notify(format!("Deleted the bookmark {}", name));
+            None
+        }
+        LibraryRequests::MeasureGain(write_tags) => {
+            let mut guard = app_state.lock().unwrap();
+            if guard.ui.gain_progress.is_some() {
+                guard.ui.notify_error("The loudness of the library is already being measured");
+                return;
+            }
+            let albums = loudness::songs_to_measure(&guard.library.songs);
+            let total = albums.iter().map(Vec::len).sum();
+            if total == 0 {
+                guard.ui.notify("Every song already has its ReplayGain");
+                return;
+            }
+            guard.ui.gain_progress = Some((0, total));
+            drop(guard);
+            loudness::spawn_scan(app_state, main_tx, albums, write_tags);
+            None
+        }
+        LibraryRequests::SaveGains(songs) => {
+            app_state.lock().unwrap().library.update_gains(&songs);
+            for song in &songs {
+                if let Err(e) = db.save_gains(song) {
+                    error!("Could not save the gains of {}. Reason: {}", song.path, e);
+                }
+            }
+            None
+        }
+        LibraryRequests::SaveStats(song) => Some(song),
+        LibraryRequests::CountPlay(song) => {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |now| now.as_secs());
+            app_state.lock().unwrap().library.change_stats(&song, |song| {
+                song.play_count += 1;
+                song.last_played_secs = now;
+            })
+        }
+        LibraryRequests::CountSkip(song) => app_state
+            .lock()
+            .unwrap()
+            .library
+            .change_stats(&song, |song| song.skip_count += 1),
+    };
+    // songs that aren't in the library, eg. from a playlist, aren't tracked
+    if let Some(song) = song {
+        if let Err(e) = db.save_stats(&song) {
+            error!("Could not save the stats of {}. Reason: {}", song.path, e);
         }
-        Ok(())
     }
+}
 
-    pub fn load_from_file(&mut self, path: String) -> Result<(), Box<dyn Error>> {
-        let db_file = match std::fs::File::open("db") {
-            Ok(f) => f,
-            Err(e) => return Err(Box::new(e)),
+// do it to the file of each song, then forget the songs whose files are gone or were moved out of
+// the library. tracks of a cue sheet are left alone, their file has the sheet's other tracks too
+fn change_files(
+    app_state: &Arc<Mutex<AppState>>,
+    db: &mut LibraryDb,
+    songs: &[Song],
+    action: &FileActions,
+) {
+    let roots = app_state.lock().unwrap().library.roots.to_owned();
+    let (mut changed, mut cue_tracks) = (0, 0);
+    let mut failed = Vec::new();
+    let mut gone = Vec::new();
+    for song in songs {
+        if song.start_ms > 0 || song.end_ms.is_some() {
+            cue_tracks += 1;
+            continue;
+        }
+        let path = Path::new(&song.path);
+        let result = match action {
+            FileActions::MoveTo(dir) => files::move_to(path, dir).map(Some),
+            FileActions::Trash => files::trash(path).map(|_| None),
+            FileActions::Delete => fs::remove_file(path).map(|_| None),
         };
-        let mut buf_reader = std::io::BufReader::new(db_file);
-        loop {
-            let result: Result<Song, Box<bincode::ErrorKind>> =
-                bincode::deserialize_from(&mut buf_reader);
-            match result {
-                Ok(song) => {
-                    self.songs.push(song);
-                }
-                Err(e) => {
-                    error!("{:?}", e);
-                    break;
+        match result {
+            Ok(Some(moved)) if files::root_of(&roots, &moved).is_some() => {
+                let moved = moved.to_string_lossy();
+                if let Err(e) = db.move_file(&song.path, &moved) {
+                    error!("Could not move {} in the library. Reason: {}", song.path, e);
                 }
+                app_state.lock().unwrap().library.move_file(&song.path, &moved);
+            }
+            Ok(_) => gone.push(song.clone()),
+            Err(e) => {
+                warn!("Could not change {}. Reason: {}", song.path, e);
+                failed.push(e);
+                continue;
             }
         }
-        Ok(())
+        changed += 1;
+    }
+    for song in &gone {
+        if let Err(e) = db.remove_song(song) {
+            error!("Could not remove {} from the library. Reason: {}", song.path, e);
+        }
+    }
+    app_state.lock().unwrap().library.remove_songs(&gone);
+
+    let files = match changed {
+        1 => "1 file".to_string(),
+        changed => format!("{} files", changed),
+    };
+    let mut message = match action {
+        FileActions::MoveTo(dir) => format!("Moved {} to {}", files, dir.display()),
+        FileActions::Trash => format!("Moved {} to the trash", files),
+        FileActions::Delete => format!("Deleted {}", files),
+    };
+    match failed.as_slice() {
+        [] => (),
+        [e] => message.push_str(&format!(", 1 couldn't be: {}", e)),
+        failed => message.push_str(&format!(", {} couldn't be", failed.len())),
+    }
+    if cue_tracks > 0 {
+        message.push_str(&format!(", {} are tracks of a cue sheet", cue_tracks));
+    }
+    info!("{}", message);
+    let mut guard = app_state.lock().unwrap();
+    match changed {
+        0 => guard.ui.notify_error(message),
+        _ => guard.ui.notify(message),
     }
 }
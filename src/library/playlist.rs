@@ -1,11 +1,214 @@
-use crate::library::song::Song;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-struct Playlist {
-    songs: Vec<Song>,
+use super::errors::ImportError;
+use super::song::{self, Song};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlaylistFormats {
+    M3U, // .m3u and .m3u8, which are the same apart from m3u8 always being utf-8
+    PLS,
+}
+
+impl PlaylistFormats {
+    pub fn from_path(path: &Path) -> Option<PlaylistFormats> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "m3u" | "m3u8" => Some(PlaylistFormats::M3U),
+            "pls" => Some(PlaylistFormats::PLS),
+            _ => None,
+        }
+    }
+}
+
+// a playlist shown in the playlists view, either a smart playlist from the config, a saved
+// search or a file in the playlist directory
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlaylistEntry {
+    Smart(String),
+    Search(String),
+    File(PathBuf),
+}
+
+impl PlaylistEntry {
+    pub fn name(&self) -> String {
+        match self {
+            PlaylistEntry::Smart(name) => name.to_owned(),
+            PlaylistEntry::Search(name) => format!("{} (search)", name),
+            PlaylistEntry::File(path) => path
+                .file_stem()
+                .map_or(String::new(), |stem| stem.to_string_lossy().into_owned()),
+        }
+    }
+}
+
+// every playlist file directly inside a directory, sorted by name
+pub fn find_playlists(dir: &Path) -> Vec<PathBuf> {
+    let mut playlists: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file() && PlaylistFormats::from_path(path).is_some())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    playlists.sort();
+    playlists
 }
 
-impl Playlist {
-    pub fn add_song() {}
+// the files a playlist lists, in order. relative entries are relative to the playlist's directory
+// and urls of streams, eg. in the playlists radio stations hand out, are kept as they are
+pub fn read(path: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let format = match PlaylistFormats::from_path(path) {
+        Some(format) => format,
+        None => return Err(Box::new(ImportError::Parsing)),
+    };
+    // plain m3u files are often latin-1, keep whatever can be read
+    let contents = String::from_utf8_lossy(&fs::read(path)?).into_owned();
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let entries = contents
+        .lines()
+        .map(|line| line.trim().trim_start_matches('\u{feff}'))
+        .filter_map(|line| match format {
+            // everything that isn't a comment or an #EXT directive is a file
+            PlaylistFormats::M3U => {
+                Some(line).filter(|line| !line.is_empty() && !line.starts_with('#'))
+            }
+            // File1=..., File2=... in the order they're listed
+            PlaylistFormats::PLS => line
+                .split_once('=')
+                .filter(|(key, _)| key.to_lowercase().starts_with("file"))
+                .map(|(_, value)| value.trim()),
+        })
+        .map(|entry| {
+            if song::is_stream_url(entry) {
+                return PathBuf::from(entry);
+            }
+            let entry = dir.join(entry.strip_prefix("file://").unwrap_or(entry));
+            // library songs are stored by their canonical path
+            fs::canonicalize(&entry).unwrap_or(entry)
+        })
+        .collect();
+    Ok(entries)
+}
+
+// save songs as a playlist, in the format its extension asks for. songs under the playlist's
+// directory are written relative to it so that the folder can be moved around as a whole
+pub fn write(path: &Path, songs: &[Song]) -> Result<(), Box<dyn Error>> {
+    let format = match PlaylistFormats::from_path(path) {
+        Some(format) => format,
+        None => return Err(Box::new(ImportError::Parsing)),
+    };
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    let entry_path = |song: &Song| {
+        let song_path = Path::new(&song.path);
+        song_path
+            .strip_prefix(&dir)
+            .unwrap_or(song_path)
+            .to_string_lossy()
+            .into_owned()
+    };
+
+    let mut contents = String::new();
+    match format {
+        PlaylistFormats::M3U => {
+            contents.push_str("#EXTM3U\n");
+            for song in songs {
+                contents.push_str(&format!(
+                    "#EXTINF:{},{} - {}\n{}\n",
+                    song.duration_secs,
+                    song.track_artist,
+                    song.title,
+                    entry_path(song)
+                ));
+            }
+        }
+        PlaylistFormats::PLS => {
+            contents.push_str("[playlist]\n");
+            for (idx, song) in songs.iter().enumerate() {
+                let number = idx + 1;
+                contents.push_str(&format!(
+                    "File{}={}\nTitle{}={} - {}\nLength{}={}\n",
+                    number,
+                    entry_path(song),
+                    number,
+                    song.track_artist,
+                    song.title,
+                    number,
+                    song.duration_secs
+                ));
+            }
+            contents.push_str(&format!("NumberOfEntries={}\nVersion=2\n", songs.len()));
+        }
+    }
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("splay-playlist-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::canonicalize(dir).unwrap()
+    }
+
+    #[test]
+    fn formats_from_the_extension() {
+        assert_eq!(PlaylistFormats::from_path(Path::new("a.M3U8")), Some(PlaylistFormats::M3U));
+        assert_eq!(PlaylistFormats::from_path(Path::new("a.pls")), Some(PlaylistFormats::PLS));
+        assert_eq!(PlaylistFormats::from_path(Path::new("a.txt")), None);
+        assert_eq!(PlaylistFormats::from_path(Path::new("m3u")), None);
+    }
+
+    #[test]
+    fn m3u_skips_comments_and_keeps_streams() {
+        let dir = temp_dir("m3u");
+        let path = dir.join("list.m3u");
+        let contents = "\u{feff}#EXTM3U\n#EXTINF:10,A - B\nsongs/a.mp3\n\n\
+            file:///music/b.flac\nhttp://radio.example/stream\n";
+        fs::write(&path, contents).unwrap();
+        assert_eq!(
+            read(&path).unwrap(),
+            [
+                dir.join("songs/a.mp3"),
+                PathBuf::from("/music/b.flac"),
+                PathBuf::from("http://radio.example/stream"),
+            ]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn pls_reads_the_file_entries_in_order() {
+        let dir = temp_dir("pls");
+        let path = dir.join("list.pls");
+        let contents = "[playlist]\nFile1=a.mp3\nTitle1=A\nfile2=/music/b.mp3\nNumberOfEntries=2\n";
+        fs::write(&path, contents).unwrap();
+        assert_eq!(read(&path).unwrap(), [dir.join("a.mp3"), PathBuf::from("/music/b.mp3")]);
+        fs::remove_dir_all(dir).unwrap();
+    }
 
-    pub fn write_to_file() {}
+    #[test]
+    fn written_playlists_read_back() {
+        let dir = temp_dir("write");
+        let songs = [
+            Song::new("A".to_string(), dir.join("a.flac").to_string_lossy().into_owned()),
+            Song::new("B".to_string(), "/elsewhere/b.flac".to_string()),
+        ];
+        for name in ["list.m3u8", "list.pls"] {
+            let path = dir.join(name);
+            write(&path, &songs).unwrap();
+            let expected = [dir.join("a.flac"), PathBuf::from("/elsewhere/b.flac")];
+            assert_eq!(read(&path).unwrap(), expected);
+        }
+        // the song in the playlist's directory is written relative to it
+        let m3u = fs::read_to_string(dir.join("list.m3u8")).unwrap();
+        assert!(m3u.lines().any(|line| line == "a.flac"));
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -0,0 +1,232 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::db::LibraryDb;
+use super::errors::ImportError;
+use super::song::Song;
+use super::{cue, Library};
+use crate::config::MediaDirectory;
+
+// files with any other extension are skipped when scanning a directory
+pub const SUPPORTED_EXTENSIONS: [&str; 14] = [
+    "mp3", "flac", "wav", "ogg", "oga", "opus", "m4a", "mp4", "aac", "alac", "aiff", "aif",
+    "aifc", "wv",
+];
+
+// how long to wait on a library root before treating it as unmounted
+const MOUNT_TIMEOUT: Duration = Duration::from_secs(3);
+
+// a file that is new or changed since the last scan
+enum ScanJob {
+    Audio(PathBuf),
+    CueSheet(PathBuf),
+}
+
+// scan every library root, then drop the songs of files that have since been removed. `progress`
+// is called with how many of the changed files have been read so far and how many there are
+pub fn scan(roots: &[MediaDirectory], db: &mut LibraryDb, progress: impl Fn(usize, usize)) {
+    let now = Instant::now();
+    let mut jobs = Vec::new();
+    for root in roots {
+        let path = root.path();
+        let result = if is_mounted(&path) {
+            collect_jobs(Path::new(&path), db, &mut jobs)
+        } else if root.skip_if_unmounted() {
+            info!("Skipping {} since it isn't mounted", path);
+            db.keep_dir(Path::new(&path))
+        } else {
+            // songs of a root that was removed are dropped from the library with it
+            error!("Could not read {}", path);
+            Ok(())
+        };
+        if let Err(e) = result {
+            error!("{}", e);
+            // keep what was saved for the part of the root that couldn't be read
+            if let Err(e) = db.keep_dir(Path::new(&path)) {
+                error!("{}", e);
+            }
+        }
+    }
+
+    let n_jobs = jobs.len();
+    read_jobs(jobs, db, progress);
+    if let Err(e) = db.remove_unscanned() {
+        error!("Could not remove deleted files from the library. Reason: {}", e);
+    }
+    info!(
+        "Took {:.3?} to scan the library, {} new or changed files",
+        now.elapsed(),
+        n_jobs
+    );
+}
+
+// walk a directory for files that changed since they were last saved to the database
+fn collect_jobs(
+    dir_path: &Path,
+    db: &mut LibraryDb,
+    jobs: &mut Vec<ScanJob>,
+) -> Result<(), Box<dyn Error>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir_path)? {
+        match entry {
+            Ok(file) => paths.push(file.path()),
+            Err(e) => {
+                return Err(Box::new(e));
+            }
+        }
+    }
+
+    // audio files split up by a cue sheet are imported as the cue sheet's tracks instead
+    let mut split_files = Vec::new();
+    for path in paths.iter().filter(|path| is_cue_sheet(path)) {
+        split_files.extend(cue::referenced_files(path));
+        if !db.is_up_to_date(path) {
+            jobs.push(ScanJob::CueSheet(path.to_owned()));
+        }
+    }
+
+    for path in paths {
+        if path.is_dir() {
+            match collect_jobs(&path, db, jobs) {
+                Err(e) => error!("{:?}", e),
+                _ => (),
+            }
+        } else if has_supported_extension(&path)
+            && !split_files.contains(&path)
+            && !db.is_up_to_date(&path)
+        {
+            jobs.push(ScanJob::Audio(path));
+        }
+    }
+    Ok(())
+}
+
+// read the files on a worker per core while saving what they read to the database
+fn read_jobs(jobs: Vec<ScanJob>, db: &mut LibraryDb, progress: impl Fn(usize, usize)) {
+    let total = jobs.len();
+    progress(0, total);
+    if total == 0 {
+        return;
+    }
+
+    let n_workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(total);
+    let jobs = Arc::new(Mutex::new(jobs));
+    let (tx, rx) = mpsc::channel();
+    for _ in 0..n_workers {
+        let jobs = jobs.clone();
+        let tx = tx.clone();
+        thread::spawn(move || loop {
+            let job = match jobs.lock().unwrap().pop() {
+                Some(job) => job,
+                None => return,
+            };
+            if tx.send(read_job(job)).is_err() {
+                return;
+            }
+        });
+    }
+    // the channel closes once every worker is done
+    drop(tx);
+
+    for (idx, (path, songs)) in rx.iter().enumerate() {
+        if let Err(e) = db.save_file(&path, &songs) {
+            error!("Could not save {:?} to the library. Reason: {}", path, e);
+        }
+        // only report whole percents so that big libraries don't flood the UI
+        let done = idx + 1;
+        if done * 100 / total != idx * 100 / total {
+            progress(done, total);
+        }
+    }
+}
+
+fn read_job(job: ScanJob) -> (PathBuf, Vec<Song>) {
+    match job {
+        ScanJob::CueSheet(path) => {
+            let songs = match cue::parse_cue(&path) {
+                Ok(songs) => songs,
+                Err(e) => {
+                    error!("Could not import cue sheet {:?}: {:?}", path, e);
+                    Vec::new()
+                }
+            };
+            (path, songs)
+        }
+        // files without usable tags are saved without songs so they aren't read again until
+        // they change
+        ScanJob::Audio(path) => match Library::read_file(path.to_str().unwrap()) {
+            Ok(song) => (path, vec![song]),
+            Err(_) => (path, Vec::new()),
+        },
+    }
+}
+
+// the songs of an audio file, a cue sheet or everything under a directory, read without the
+// database. directories are walked in name order so that numbered tracks play in order
+pub fn read_path(path: &Path) -> Result<Vec<Song>, Box<dyn Error>> {
+    if is_cue_sheet(path) {
+        return cue::parse_cue(path);
+    }
+    if !path.is_dir() {
+        let path = path.to_str().ok_or(ImportError::Parsing)?;
+        return Ok(vec![Library::read_file(path)?]);
+    }
+
+    let mut paths = fs::read_dir(path)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect::<Vec<PathBuf>>();
+    paths.sort();
+    let split_files: Vec<PathBuf> = paths
+        .iter()
+        .filter(|path| is_cue_sheet(path))
+        .flat_map(|path| cue::referenced_files(path))
+        .collect();
+    let mut songs = Vec::new();
+    for path in paths {
+        let is_audio = has_supported_extension(&path) && !split_files.contains(&path);
+        if !path.is_dir() && !is_audio && !is_cue_sheet(&path) {
+            continue;
+        }
+        match read_path(&path) {
+            Ok(read) => songs.extend(read),
+            Err(e) => warn!("Skipping {:?}. Reason: {}", path, e),
+        }
+    }
+    Ok(songs)
+}
+
+// whether the directory can be read and has anything in it. an unreachable network mount can
+// block any filesystem call, so it is checked from another thread that is given up on
+fn is_mounted(path: &str) -> bool {
+    let (tx, rx) = mpsc::channel();
+    let path = path.to_string();
+    thread::spawn(move || {
+        let has_entries = match fs::read_dir(path) {
+            Ok(mut entries) => entries.next().is_some(),
+            Err(_) => false,
+        };
+        let _ = tx.send(has_entries);
+    });
+    rx.recv_timeout(MOUNT_TIMEOUT).unwrap_or(false)
+}
+
+pub fn has_supported_extension(path: &Path) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => SUPPORTED_EXTENSIONS.contains(&ext.to_lowercase().as_str()),
+        None => false,
+    }
+}
+
+pub fn is_cue_sheet(path: &Path) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => ext.eq_ignore_ascii_case("cue"),
+        None => false,
+    }
+}
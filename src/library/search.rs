@@ -1,44 +1,284 @@
-// TODO: make SearchDB a trait and have different variations that implement it (songs, artists, albums, playlists, genres ...)
+use std::cmp::Reverse;
 
-struct node {
-    is_word: bool,
-    left_child: Box<node>,
-    middle_child: Box<node>,
-    right_child: Box<node>,
+use super::song::Song;
+
+// fzf-like scores. every matched character is worth the same, starting a word or following the
+// previous match is worth more and skipping characters in between costs a little
+const SCORE_MATCH: i64 = 16;
+const SCORE_GAP_START: i64 = -3;
+const SCORE_GAP_EXTENSION: i64 = -1;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_CONSECUTIVE: i64 = 4;
+const BONUS_FIRST_CHAR_MULTIPLIER: i64 = 2;
+
+// where the characters of a pattern were found in a text
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    pub positions: Vec<usize>, // char indices, in order
+}
+
+// how well a song matches a search, with the characters that matched in each field
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SongMatch {
+    pub score: i64,
+    pub title: Vec<usize>,
+    pub artist: Vec<usize>,
+    pub album: Vec<usize>,
+}
+
+// what a word of the query can be limited to with a prefix, eg. a:coltrane
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scope {
+    ANY, // the title, artist or album
+    TITLE,
+    ARTIST,
+    ALBUM,
+    PATH,
+}
+
+// the prefixes, with what they're for as the search popup shows them
+pub const SCOPES: [(&str, &str); 4] = [
+    ("t:", "title"),
+    ("a:", "artist"),
+    ("b:", "album"),
+    ("p:", "path"),
+];
+
+// a search kept under a name, listed with the playlists and run again whenever it's opened
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: String,
+}
+
+// the songs matching the query, best match first. songs that match equally well keep the order
+// they were given in
+pub fn search(query: &str, songs: &[Song]) -> Vec<(Song, SongMatch)> {
+    let mut results: Vec<(Song, SongMatch)> = songs
+        .iter()
+        .filter_map(|song| match_song(query, song).map(|found| (song.clone(), found)))
+        .collect();
+    results.sort_by_key(|(_, found)| Reverse(found.score));
+    results
+}
+
+// every word of the query has to be found in the title, artist or album, so "beatles help"
+// finds Help! by the Beatles. each word counts towards the field it matches best. a prefix limits
+// the word and the ones after it to one field, so "b:blue train" only looks in the album
+pub fn match_song(query: &str, song: &Song) -> Option<SongMatch> {
+    let mut found = SongMatch::default();
+    let mut scope = Scope::ANY;
+    for word in query.split_whitespace() {
+        let word = match split_scope(word) {
+            Some((prefix, rest)) => {
+                scope = prefix;
+                rest
+            }
+            None => word,
+        };
+        if word.is_empty() {
+            continue;
+        }
+        let fields = match scope {
+            Scope::ANY => [
+                fuzzy_match(word, &song.title),
+                fuzzy_match(word, &song.track_artist),
+                fuzzy_match(word, &song.album_title),
+                None,
+            ],
+            Scope::TITLE => [fuzzy_match(word, &song.title), None, None, None],
+            Scope::ARTIST => [None, fuzzy_match(word, &song.track_artist), None, None],
+            Scope::ALBUM => [None, None, fuzzy_match(word, &song.album_title), None],
+            Scope::PATH => [None, None, None, fuzzy_match(word, &song.path)],
+        };
+        let (field, best) = fields
+            .into_iter()
+            .enumerate()
+            .filter_map(|(field, found)| found.map(|found| (field, found)))
+            .max_by(|a, b| a.1.score.cmp(&b.1.score).then_with(|| b.0.cmp(&a.0)))?;
+        found.score += best.score;
+        let positions = match field {
+            0 => &mut found.title,
+            1 => &mut found.artist,
+            2 => &mut found.album,
+            // the path isn't shown, so there's nothing to highlight
+            _ => continue,
+        };
+        positions.extend(best.positions);
+        positions.sort_unstable();
+        positions.dedup();
+    }
+    Some(found)
+}
+
+// the field a word is limited to and the rest of it, if it starts with one of the prefixes
+fn split_scope(word: &str) -> Option<(Scope, &str)> {
+    let (prefix, rest) = word.split_at_checked(2)?;
+    let scope = match prefix {
+        "t:" => Scope::TITLE,
+        "a:" => Scope::ARTIST,
+        "b:" => Scope::ALBUM,
+        "p:" => Scope::PATH,
+        _ => return None,
+    };
+    Some((scope, rest))
 }
 
-pub struct SearchDB {
-    path_to_db: String,
-    words: Vec<String>,
+// the characters of the pattern in order, anywhere in the text. the pattern only has to match
+// the case of the text if it has capitals in it
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<FuzzyMatch> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    if pattern.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+    let case_sensitive = pattern.iter().any(|ch| ch.is_uppercase());
+    let same = |a: char, b: char| {
+        if case_sensitive {
+            a == b
+        } else {
+            a.to_lowercase().eq(b.to_lowercase())
+        }
+    };
+
+    // from every place the pattern could start, the shortest match ending where the pattern
+    // first fits in after it. the best scoring of them wins
+    let mut best: Option<FuzzyMatch> = None;
+    for start in 0..text.len() {
+        if !same(text[start], pattern[0]) {
+            continue;
+        }
+        let positions = match shortest_match(&text, &pattern, start, same) {
+            Some(positions) => positions,
+            // the pattern doesn't fit after this start, so it won't after any later one
+            None => break,
+        };
+        let score = score(&text, &positions);
+        if best.as_ref().is_none_or(|best| score > best.score) {
+            best = Some(FuzzyMatch { score, positions });
+        }
+    }
+    best
 }
 
-impl SearchDB {
-    pub fn new() -> SearchDB {
-        SearchDB {
-            path_to_db: String::new(),
-            words: Vec::new(),
+fn shortest_match(
+    text: &[char],
+    pattern: &[char],
+    start: usize,
+    same: impl Fn(char, char) -> bool,
+) -> Option<Vec<usize>> {
+    let mut pattern_idx = 0;
+    let mut end = None;
+    for (idx, ch) in text.iter().enumerate().skip(start) {
+        if same(*ch, pattern[pattern_idx]) {
+            pattern_idx += 1;
+            if pattern_idx == pattern.len() {
+                end = Some(idx);
+                break;
+            }
+        }
+    }
+    let end = end?;
+    let mut positions = Vec::with_capacity(pattern.len());
+    let mut pattern_idx = pattern.len();
+    for idx in (start..=end).rev() {
+        if same(text[idx], pattern[pattern_idx - 1]) {
+            positions.push(idx);
+            pattern_idx -= 1;
+            if pattern_idx == 0 {
+                break;
+            }
         }
     }
+    positions.reverse();
+    Some(positions)
+}
 
-    pub fn find_matches(self, input: String) -> (Vec<String>, Vec<String>, Vec<String>) {
-        return (Vec::new(), Vec::new(), Vec::new());
+// characters following the previous match carry the bonus of the first one, so a whole word
+// beats the same letters spread over several words
+fn score(text: &[char], positions: &[usize]) -> i64 {
+    let mut score = 0;
+    let mut prev: Option<usize> = None;
+    let mut chunk_bonus = 0;
+    for (nth, pos) in positions.iter().enumerate() {
+        let mut bonus = if is_boundary(text, *pos) {
+            BONUS_BOUNDARY
+        } else {
+            0
+        };
+        match prev {
+            Some(prev) if *pos == prev + 1 => {
+                bonus = bonus.max(chunk_bonus).max(BONUS_CONSECUTIVE);
+            }
+            Some(prev) => {
+                score += SCORE_GAP_START + SCORE_GAP_EXTENSION * (*pos - prev - 2) as i64;
+                chunk_bonus = bonus;
+            }
+            None => chunk_bonus = bonus,
+        }
+        if nth == 0 {
+            score += bonus * BONUS_FIRST_CHAR_MULTIPLIER;
+        } else {
+            score += bonus;
+        }
+        score += SCORE_MATCH;
+        prev = Some(*pos);
+    }
+    score
+}
+
+// the start of a word, eg. after a space or a dash, or a capital after a lowercase letter
+fn is_boundary(text: &[char], pos: usize) -> bool {
+    match pos.checked_sub(1).map(|prev| text[prev]) {
+        None => true,
+        Some(prev) => !prev.is_alphanumeric() || (prev.is_lowercase() && text[pos].is_uppercase()),
     }
+}
 
-    fn find_match(self, input: String) {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song(title: &str, artist: &str, album: &str) -> Song {
+        let mut song = Song::new(title.to_string(), format!("/music/{}.flac", title));
+        song.track_artist = artist.to_string();
+        song.album_title = album.to_string();
+        song
+    }
 
-    pub fn insert(self, input: String) -> Result<(), String> {
-        return Ok(());
+    #[test]
+    fn scopes() {
+        assert_eq!(split_scope("a:coltrane"), Some((Scope::ARTIST, "coltrane")));
+        assert_eq!(split_scope("b:"), Some((Scope::ALBUM, "")));
+        assert_eq!(split_scope("x:y"), None);
+        assert_eq!(split_scope("é"), None);
     }
 
-    fn remove(self, input: String) -> Result<(), String> {
-        return Ok(());
+    #[test]
+    fn a_prefix_limits_the_words_after_it() {
+        let train = song("Moment's Notice", "John Coltrane", "Blue Train");
+        assert!(match_song("b:blue train", &train).is_some());
+        assert!(match_song("t:blue", &train).is_none());
+        assert!(match_song("coltrane t:notice", &train).is_some());
+        assert!(match_song("t:notice coltrane", &train).is_none());
+        assert!(match_song("p:music", &train).is_some());
     }
 
-    fn save(self) -> Result<(), String> {
-        unimplemented!()
+    #[test]
+    fn every_word_has_to_match() {
+        let help = song("Help!", "The Beatles", "Help!");
+        assert!(match_song("beatles help", &help).is_some());
+        assert!(match_song("beatles yesterday", &help).is_none());
     }
 
-    fn load() -> Result<(), String> {
-        unimplemented!()
+    #[test]
+    fn capitals_make_it_case_sensitive() {
+        assert!(fuzzy_match("abc", "ABC").is_some());
+        assert!(fuzzy_match("Abc", "abc").is_none());
+        assert_eq!(fuzzy_match("ac", "abc").unwrap().positions, [0, 2]);
     }
 }
@@ -0,0 +1,192 @@
+use serde::Deserialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::song::Song;
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+// a playlist of every song in the library that matches its rules, so it grows and shrinks with
+// the library instead of being saved
+#[derive(Clone, Debug, Deserialize)]
+pub struct SmartPlaylist {
+    pub name: String,
+    #[serde(default, rename = "match")]
+    pub match_mode: MatchModes,
+    pub rules: Vec<Rule>,
+}
+
+// whether a song has to pass every rule or just one of them
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchModes {
+    ALL,
+    ANY,
+}
+
+impl Default for MatchModes {
+    fn default() -> Self {
+        MatchModes::ALL
+    }
+}
+
+// eg. { field = "genre", op = "==", value = "jazz" } or
+// { field = "added", op = "in_last_days", value = 30 }. in_last_days works on added and
+// last_played
+#[derive(Clone, Debug, Deserialize)]
+pub struct Rule {
+    pub field: RuleFields,
+    pub op: RuleOps,
+    pub value: RuleValue,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleFields {
+    TITLE,
+    ARTIST, // the track artist
+    ALBUM,
+    ALBUM_ARTIST,
+    GENRE,
+    YEAR,
+    PLAY_COUNT,
+    DURATION, // seconds
+    ADDED,    // when the song was first found by a scan
+    RATING,
+    FAVORITE,
+    SKIP_COUNT,
+    LAST_PLAYED,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum RuleOps {
+    #[serde(rename = "==")]
+    EQUALS,
+    #[serde(rename = "!=")]
+    NOT_EQUALS,
+    #[serde(rename = "contains")]
+    CONTAINS,
+    #[serde(rename = ">")]
+    GREATER,
+    #[serde(rename = ">=")]
+    GREATER_OR_EQUAL,
+    #[serde(rename = "<")]
+    LESS,
+    #[serde(rename = "<=")]
+    LESS_OR_EQUAL,
+    #[serde(rename = "in_last_days")]
+    IN_LAST_DAYS,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum RuleValue {
+    Bool(bool),
+    Number(i64),
+    Text(String),
+}
+
+impl SmartPlaylist {
+    pub fn matches(&self, song: &Song) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs());
+        match self.match_mode {
+            MatchModes::ALL => self.rules.iter().all(|rule| rule.matches(song, now)),
+            MatchModes::ANY => self.rules.iter().any(|rule| rule.matches(song, now)),
+        }
+    }
+}
+
+impl Rule {
+    fn matches(&self, song: &Song, now: u64) -> bool {
+        if self.op == RuleOps::IN_LAST_DAYS {
+            let days = match self.value.number() {
+                Some(days) if days >= 0 => days as u64,
+                _ => return false,
+            };
+            let since = now.saturating_sub(days * SECS_PER_DAY);
+            let time = match self.field {
+                RuleFields::ADDED => song.added_secs,
+                RuleFields::LAST_PLAYED => song.last_played_secs,
+                _ => return false,
+            };
+            return time != 0 && time >= since;
+        }
+        match self.field {
+            RuleFields::TITLE => self.compare_text(Some(&song.title)),
+            RuleFields::ARTIST => self.compare_text(Some(&song.track_artist)),
+            RuleFields::ALBUM => self.compare_text(Some(&song.album_title)),
+            RuleFields::ALBUM_ARTIST => self.compare_text(Some(&song.album_artist)),
+            RuleFields::GENRE => self.compare_text(song.genre.as_ref()),
+            // years are often full dates, eg. 2004-05-11
+            RuleFields::YEAR => self.compare_number(
+                song.year
+                    .as_ref()
+                    .and_then(|year| year.get(..4))
+                    .and_then(|year| year.parse().ok()),
+            ),
+            RuleFields::PLAY_COUNT => self.compare_number(Some(song.play_count as i64)),
+            RuleFields::DURATION => self.compare_number(Some(song.duration_secs as i64)),
+            RuleFields::ADDED => self.compare_number(Some(song.added_secs as i64)),
+            RuleFields::RATING => self.compare_number(Some(song.rating as i64)),
+            RuleFields::FAVORITE => self.compare_number(Some(song.favorite as i64)),
+            RuleFields::SKIP_COUNT => self.compare_number(Some(song.skip_count as i64)),
+            RuleFields::LAST_PLAYED => self.compare_number(Some(song.last_played_secs as i64)),
+        }
+    }
+
+    // text is compared ignoring case, and a missing tag never matches
+    fn compare_text(&self, field: Option<&String>) -> bool {
+        let field = match field {
+            Some(field) => field.to_lowercase(),
+            None => return false,
+        };
+        let value = self.value.text().to_lowercase();
+        match self.op {
+            RuleOps::EQUALS => field == value,
+            RuleOps::NOT_EQUALS => field != value,
+            RuleOps::CONTAINS => field.contains(&value),
+            RuleOps::GREATER => field > value,
+            RuleOps::GREATER_OR_EQUAL => field >= value,
+            RuleOps::LESS => field < value,
+            RuleOps::LESS_OR_EQUAL => field <= value,
+            RuleOps::IN_LAST_DAYS => false,
+        }
+    }
+
+    fn compare_number(&self, field: Option<i64>) -> bool {
+        let (field, value) = match (field, self.value.number()) {
+            (Some(field), Some(value)) => (field, value),
+            _ => return false,
+        };
+        match self.op {
+            RuleOps::EQUALS => field == value,
+            RuleOps::NOT_EQUALS => field != value,
+            RuleOps::GREATER => field > value,
+            RuleOps::GREATER_OR_EQUAL => field >= value,
+            RuleOps::LESS => field < value,
+            RuleOps::LESS_OR_EQUAL => field <= value,
+            RuleOps::CONTAINS | RuleOps::IN_LAST_DAYS => false,
+        }
+    }
+}
+
+impl RuleValue {
+    fn number(&self) -> Option<i64> {
+        match self {
+            RuleValue::Bool(value) => Some(*value as i64),
+            RuleValue::Number(number) => Some(*number),
+            RuleValue::Text(text) => text.trim().parse().ok(),
+        }
+    }
+
+    fn text(&self) -> String {
+        match self {
+            RuleValue::Bool(value) => value.to_string(),
+            RuleValue::Number(number) => number.to_string(),
+            RuleValue::Text(text) => text.to_owned(),
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use lofty::Tag;
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 
 use super::{errors::ImportError, tag};
 
@@ -14,8 +15,29 @@ pub struct Song {
     pub year: Option<String>,
     pub duration_secs: u64,
     pub play_count: u32,
+    pub skip_count: u32,
+    pub last_played_secs: u64, // unix time, 0 if never played
     pub track_number: Option<String>,
+    pub disc_number: Option<String>,
+    pub has_cover_art: bool, // embedded in the file's tags
+    pub bitrate_kbps: Option<u32>,
+    #[serde(default)]
+    pub sample_rate: u32, // Hz, 0 if unknown
+    #[serde(default)]
+    pub channels: u8, // 0 if unknown
+    pub size_bytes: u64, // a cue sheet track's share of its file
+    pub track_gain: Option<f32>, // ReplayGain in dB
+    pub album_gain: Option<f32>,
     pub path: String,
+    // where the song starts and ends within the file, only set for tracks of a cue sheet
+    pub start_ms: u64,
+    pub end_ms: Option<u64>,
+    pub added_secs: u64, // unix time the song was first scanned, 0 until it is saved
+    pub rating: u8, // 1 to 5 stars, 0 if unrated
+    pub favorite: bool,
+    // the file couldn't be found when the library was last checked, eg. on an unmounted drive
+    #[serde(skip)]
+    pub missing: bool,
 }
 
 impl Song {
@@ -29,11 +51,50 @@ impl Song {
             year: None,
             duration_secs: 0,
             play_count: 0,
+            skip_count: 0,
+            last_played_secs: 0,
             track_number: None,
+            disc_number: None,
+            has_cover_art: false,
+            bitrate_kbps: None,
+            sample_rate: 0,
+            channels: 0,
+            size_bytes: 0,
+            track_gain: None,
+            album_gain: None,
             path,
+            start_ms: 0,
+            end_ms: None,
+            added_secs: 0,
+            rating: 0,
+            favorite: false,
+            missing: false,
         }
     }
 
+    // cue sheet tracks share a file, so the path alone doesn't identify a song
+    // the file's extension, eg. FLAC
+    pub fn format(&self) -> String {
+        Path::new(&self.path)
+            .extension()
+            .map_or(String::new(), |extension| {
+                extension.to_string_lossy().to_uppercase()
+            })
+    }
+
+    // an http stream, eg. an internet radio station. it has no tags, so it's named by its url
+    pub fn stream(url: String) -> Self {
+        Song::new(url.to_owned(), url)
+    }
+
+    pub fn is_stream(&self) -> bool {
+        is_stream_url(&self.path)
+    }
+
+    pub fn is_same(&self, other: &Song) -> bool {
+        self.path == other.path && self.start_ms == other.start_ms
+    }
+
     pub fn from_tag(tag: &Tag, path: String) -> Result<Self, Box<ImportError>> {
         let title = match tag::get_title(tag) {
             Ok(title) => title,
@@ -45,8 +106,16 @@ impl Song {
         s.album_artist = tag::get_album_artist(tag);
         s.year = tag::get_year(tag);
         s.track_number = tag::get_track_number(tag);
+        s.disc_number = tag::get_disc_number(tag);
+        s.has_cover_art = tag::has_cover_art(tag);
         s.genre = tag::get_genre(tag);
         s.duration_secs = tag::get_total_dur_sec(tag);
+        s.track_gain = tag::get_track_gain(tag);
+        s.album_gain = tag::get_album_gain(tag);
         Ok(s)
     }
 }
+
+pub fn is_stream_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use super::song::Song;
+
+// genres beyond this many are counted as "Other"
+const MAX_GENRES: usize = 8;
+
+// lower bounds of the bitrate buckets in kbps
+const BITRATE_BUCKETS: [u32; 6] = [0, 128, 192, 256, 320, 500];
+
+// what the library is made of, shown in the stats dashboard
+#[derive(Debug, Clone, PartialEq)]
+pub struct LibraryStats {
+    pub song_count: usize,
+    pub total_duration_secs: u64,
+    pub total_size_bytes: u64,
+    // each list is (label, number of songs), in the order they're shown
+    pub genres: Vec<(String, u64)>,
+    pub decades: Vec<(String, u64)>,
+    pub formats: Vec<(String, u64)>,
+    pub bitrates: Vec<(String, u64)>,
+}
+
+impl LibraryStats {
+    pub fn compute(songs: &[Song]) -> LibraryStats {
+        let mut genres: HashMap<String, u64> = HashMap::new();
+        let mut decades: HashMap<u32, u64> = HashMap::new();
+        let mut formats: HashMap<String, u64> = HashMap::new();
+        let mut bitrates = vec![0; BITRATE_BUCKETS.len()];
+        let mut unknown_bitrate = 0;
+        for song in songs {
+            let genre = match &song.genre {
+                Some(genre) if !genre.trim().is_empty() => genre.trim().to_string(),
+                _ => "Unknown".to_string(),
+            };
+            *genres.entry(genre).or_default() += 1;
+            // years are often full dates, eg. 2004-05-11
+            if let Some(year) = song
+                .year
+                .as_ref()
+                .and_then(|year| year.get(..4))
+                .and_then(|year| year.parse::<u32>().ok())
+            {
+                *decades.entry(year / 10 * 10).or_default() += 1;
+            }
+            *formats.entry(song.format()).or_default() += 1;
+            match song.bitrate_kbps {
+                Some(bitrate) => {
+                    let bucket = BITRATE_BUCKETS
+                        .iter()
+                        .rposition(|lower| bitrate >= *lower)
+                        .unwrap_or(0);
+                    bitrates[bucket] += 1;
+                }
+                None => unknown_bitrate += 1,
+            }
+        }
+
+        // the most common genres, the rest lumped together
+        let mut genres: Vec<(String, u64)> = genres.into_iter().collect();
+        genres.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        if genres.len() > MAX_GENRES {
+            let other = genres
+                .split_off(MAX_GENRES - 1)
+                .iter()
+                .map(|(_, count)| count)
+                .sum();
+            genres.push(("Other".to_string(), other));
+        }
+
+        let mut decades: Vec<(u32, u64)> = decades.into_iter().collect();
+        decades.sort();
+        let decades = decades
+            .into_iter()
+            .map(|(decade, count)| (format!("{}s", decade), count))
+            .collect();
+
+        let mut formats: Vec<(String, u64)> = formats.into_iter().collect();
+        formats.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let mut bitrates: Vec<(String, u64)> = BITRATE_BUCKETS
+            .iter()
+            .enumerate()
+            .map(|(idx, lower)| match BITRATE_BUCKETS.get(idx + 1) {
+                Some(upper) => format!("{}-{}", lower, upper - 1),
+                None => format!("{}+", lower),
+            })
+            .zip(bitrates)
+            .collect();
+        if unknown_bitrate != 0 {
+            bitrates.push(("?".to_string(), unknown_bitrate));
+        }
+
+        LibraryStats {
+            song_count: songs.len(),
+            total_duration_secs: songs.iter().map(|song| song.duration_secs).sum(),
+            total_size_bytes: songs.iter().map(|song| song.size_bytes).sum(),
+            genres,
+            decades,
+            formats,
+            bitrates,
+        }
+    }
+}
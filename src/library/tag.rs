@@ -1,11 +1,25 @@
-use lofty::{ItemKey, Tag};
+use lofty::{read_from_path, ItemKey, ItemValue, Picture, PictureType, Tag, TagItem, TagType};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
 use std::time::Duration;
 
 use super::errors::ImportError;
+use super::song::Song;
 
 pub const UNKNOWN_ARTIST: &str = "Unknown Artist";
 pub const UNKNOWN_ALBUM: &str = "Unkwon Album";
 
+// images in an album's folder that are used when the files have no art of their own
+const COVER_FILES: [&str; 6] = [
+    "cover.jpg",
+    "cover.png",
+    "folder.jpg",
+    "folder.png",
+    "front.jpg",
+    "front.png",
+];
+
 pub fn get_title(tag: &Tag) -> Result<String, Box<ImportError>> {
     match tag.get_string(&ItemKey::TrackTitle) {
         Some(title) => Ok(title.to_string()),
@@ -53,6 +67,16 @@ pub fn get_track_number(tag: &Tag) -> Option<String> {
     }
 }
 
+pub fn get_disc_number(tag: &Tag) -> Option<String> {
+    tag.get_string(&ItemKey::DiscNumber)
+        .map(|disc_number| disc_number.to_string())
+}
+
+// the art itself is read from the file when it is shown
+pub fn has_cover_art(tag: &Tag) -> bool {
+    !tag.pictures().is_empty()
+}
+
 pub fn get_genre(tag: &Tag) -> Option<String> {
     match tag.get_string(&ItemKey::Genre) {
         Some(genre) => Some(genre.to_string()),
@@ -60,9 +84,110 @@ pub fn get_genre(tag: &Tag) -> Option<String> {
     }
 }
 
+// there is no generic key for ReplayGain, so look for the vorbis/APE style key names
+// (REPLAYGAIN_TRACK_GAIN, REPLAYGAIN_ALBUM_GAIN) among the unmapped items. values look like "-6.50 dB"
+fn get_replay_gain(tag: &Tag, key_suffix: &str) -> Option<f32> {
+    tag.items().iter().find_map(|item| match (item.key(), item.value()) {
+        (ItemKey::Unknown(key), ItemValue::Text(value))
+            if key.to_lowercase().ends_with(key_suffix) =>
+        {
+            let value = value.trim();
+            let value = value
+                .strip_suffix("dB")
+                .or_else(|| value.strip_suffix("db"))
+                .unwrap_or(value);
+            value.trim().parse().ok()
+        }
+        _ => None,
+    })
+}
+
+pub fn get_track_gain(tag: &Tag) -> Option<f32> {
+    get_replay_gain(tag, "replaygain_track_gain")
+}
+
+pub fn get_album_gain(tag: &Tag) -> Option<f32> {
+    get_replay_gain(tag, "replaygain_album_gain")
+}
+
 pub fn get_total_dur_sec(tag: &Tag) -> u64 {
     match tag.get_string(&ItemKey::Length) {
         Some(ms_str) => Duration::from_millis(ms_str.parse().unwrap_or_default()).as_secs(),
         None => 0,
     }
 }
+
+// write the tags that can be edited from the UI back to the song's file, leaving the rest of
+// the tag alone
+pub fn write_tags(song: &Song) -> Result<(), Box<dyn Error>> {
+    // pictures have to be read too, otherwise saving the tag would drop the cover art
+    let mut file = read_from_path(&song.path, true)?;
+    if file.primary_tag().is_none() {
+        file.insert_tag(Tag::new(file.primary_tag_type()));
+    }
+    let tag = match file.primary_tag_mut() {
+        Some(tag) => tag,
+        None => return Err(Box::new(ImportError::MissingData)),
+    };
+    tag.insert_text(ItemKey::TrackTitle, song.title.to_owned());
+    tag.insert_text(ItemKey::TrackArtist, song.track_artist.to_owned());
+    tag.insert_text(ItemKey::AlbumTitle, song.album_title.to_owned());
+    set_optional(tag, ItemKey::TrackNumber, &song.track_number);
+    set_optional(tag, ItemKey::Genre, &song.genre);
+    tag.save_to_path(&song.path)?;
+    Ok(())
+}
+
+// the measured gains are written with the vorbis/APE style key names they're read back with.
+// other tags, like ID3v2, can't hold those keys
+pub fn write_replay_gain(song: &Song) -> Result<(), Box<dyn Error>> {
+    let mut file = read_from_path(&song.path, true)?;
+    if file.primary_tag().is_none() {
+        file.insert_tag(Tag::new(file.primary_tag_type()));
+    }
+    let tag = match file.primary_tag_mut() {
+        Some(tag) if matches!(tag.tag_type(), TagType::VorbisComments | TagType::Ape) => tag,
+        _ => return Err("only vorbis comments and APE tags can hold ReplayGain".into()),
+    };
+    let gains = [
+        ("REPLAYGAIN_TRACK_GAIN", song.track_gain),
+        ("REPLAYGAIN_ALBUM_GAIN", song.album_gain),
+    ];
+    for (key, gain) in gains {
+        if let Some(gain) = gain {
+            let value = ItemValue::Text(format!("{:.2} dB", gain));
+            // the checked insert turns down keys it doesn't know
+            tag.insert_item_unchecked(TagItem::new(ItemKey::Unknown(key.to_string()), value));
+        }
+    }
+    tag.save_to_path(&song.path)?;
+    Ok(())
+}
+
+fn set_optional(tag: &mut Tag, key: ItemKey, value: &Option<String>) {
+    match value {
+        Some(value) => {
+            tag.insert_text(key, value.to_owned());
+        }
+        None => tag.remove_key(&key),
+    }
+}
+
+// the front cover embedded in a file, or any picture if there's no front cover. falls back to a
+// cover image next to the file
+pub fn read_cover_art(path: &str) -> Option<Vec<u8>> {
+    let embedded = read_from_path(path, true).ok().and_then(|file| {
+        let pictures: Vec<&Picture> = file.tags().iter().flat_map(|tag| tag.pictures()).collect();
+        pictures
+            .iter()
+            .find(|picture| picture.pic_type() == PictureType::CoverFront)
+            .or_else(|| pictures.first())
+            .map(|picture| picture.data().to_vec())
+    });
+    embedded.or_else(|| {
+        let dir = Path::new(path).parent()?;
+        COVER_FILES
+            .iter()
+            .find_map(|name| fs::read(dir.join(name)).ok())
+    })
+}
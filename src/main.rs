@@ -1,132 +1,481 @@
+mod app;
+mod cli;
+mod config;
 mod input;
+mod ipc;
 mod library;
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+mod media_keys;
+mod metadata;
+mod mpd;
+#[cfg(target_os = "linux")]
+mod mpris;
+mod notifications;
 mod player;
+mod podcasts;
 mod queue;
+mod scrobbler;
 mod state;
 mod ui;
 mod utils;
 
+use crate::app::{
+    apply_config, library_dirs, spawn_mpd_library, spawn_player, spawn_podcasts, spawn_scan,
+    Dispatcher, NewPlayer,
+};
+use crate::cli::{Args, Commands};
+use crate::config::{MediaDirectory, MpdConfig};
+use crate::library::db::{self, LibraryDb};
 use crate::library::Library;
-use crate::player::rodio_player::RodioPlayer;
+use crate::player::mpd_player::MpdPlayer;
+#[cfg(unix)]
+use crate::player::remote_player::RemotePlayer;
 use crate::player::symphonia_player::SymphoniaPlayer;
+use crate::player::visualizer::SampleTap;
 use crate::player::Player;
+use crate::state::session::Session;
 use crate::state::AppState;
+use crate::input::keybinds::Keybinds;
+use crate::ui::theme::Theme;
 use crate::utils::constants::requests::*;
+use crate::utils::constants::{AudioBackends, PlayerModes};
+use crate::utils::logs;
 
 #[macro_use]
 extern crate log;
-use simplelog::*;
-use std::fs::File;
-use std::sync::mpsc::{Receiver, Sender};
-use std::sync::{mpsc, Arc, Mutex};
+use clap::Parser;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::io::Read;
-use serde::Deserialize;
+use tokio::runtime::Handle;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 
-use toml::Table;
+#[cfg(unix)]
+use crate::ipc::client::IpcClient;
+#[cfg(unix)]
+use std::{
+    ffi::OsString,
+    os::unix::process::CommandExt,
+    process::{Command, Stdio},
+};
 
-#[derive(Deserialize)]
-struct SplayConfig {
-    media: Media
+#[cfg(target_os = "macos")]
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(unix)]
+use signal_hook::{
+    consts::{SIGHUP, SIGINT, SIGTERM},
+    iterator::Signals,
+};
+
+#[cfg(not(target_os = "macos"))]
+#[tokio::main]
+async fn main() {
+    run().await
 }
 
-#[derive(Deserialize)]
-struct Media {
-    directories: Vec<String>
+// macos hands the media keys to the main thread, so splay runs on a thread beside it
+#[cfg(target_os = "macos")]
+fn main() {
+    media_keys::run_beside(|| {
+        let runtime = tokio::runtime::Runtime::new().expect("Could not start the runtime");
+        let ran = panic::catch_unwind(AssertUnwindSafe(|| runtime.block_on(run())));
+        drop(runtime);
+        std::process::exit(if ran.is_ok() { 0 } else { 101 });
+    })
 }
 
+async fn run() {
+    let args = Args::parse();
+    let attach = matches!(args.command, Some(Commands::Attach));
 
-fn main() {
-    let _ = WriteLogger::init(
-        LevelFilter::Info,
-        Config::default(),
-        File::create("splay.log").unwrap(),
-    );
-    info!("Starting splay...");
+    if let Some(path) = &args.config {
+        config::set_path(path.to_owned());
+    }
+    if args.daemon && !args.detached {
+        start_daemon();
+    }
 
-    // let args: Vec<String> = env::args().collect();
+    // the daemon's log is left alone
+    let log_name = if attach { "splay-attach.log" } else { "splay.log" };
+    match logs::init(log_name) {
+        Ok(log_path) => info!("Starting splay, logging to {}", log_path.display()),
+        Err(err) => eprintln!("Could not open the log. {}", err),
+    }
+    let library_override: Vec<MediaDirectory> = args
+        .libraries
+        .iter()
+        .map(|dir| MediaDirectory::Path(dir.to_owned()))
+        .collect();
+    let mut media_dirs = library_override.clone();
 
     let state = Arc::new(Mutex::new(AppState::default()));
 
-    // let mut player = SymphoniaPlayer::new();
-    let mut player = RodioPlayer::new();
+    let mut mode = PlayerModes::default();
+    let mut mpd_config = MpdConfig::default();
+    let mut backend = AudioBackends::default();
     let mut lib = Library::new();
+    let mut theme = Theme::default();
+    let keybinds = Arc::new(Mutex::new(Keybinds::default()));
+    let mut loaded_config = None;
+    let mut library_db = match LibraryDb::open(&db::path()) {
+        Ok(library_db) => library_db,
+        Err(err) => {
+            error!("Could not open the library database, nothing will be cached. Reason: {}", err);
+            LibraryDb::open(Path::new(":memory:")).unwrap()
+        }
+    };
+    // read every file again instead of only the ones that changed since the last run
+    if args.rescan {
+        if let Err(err) = library_db.clear() {
+            error!("Could not clear the library database. Reason: {}", err);
+        }
+    }
 
 
-    // TODO: change to other path on system in XDG_CONFIG_HOME 
-    match File::open("config.toml") {
-        Ok(mut in_file) => {
-            let mut in_contents = String::new();
-            in_file.read_to_string(&mut in_contents).unwrap();
-            let config: SplayConfig = toml::from_str(in_contents.as_str()).unwrap();
-            
-            for dir in config.media.directories {
-                // TODO: allow to use ~
-                match lib.import_dir(dir.as_str()) {
-                    Ok(_) => {
-                        let _ = lib.save_to_file("db".to_string());
-                    }
-                    Err(e) => error!("{}", e),
-                }
-            }
+    match config::read() {
+        Ok(config) => {
+            mode = config.player.mode;
+            mpd_config = config.mpd.clone();
+            backend = config.player.backend;
+            media_dirs = library_dirs(&config, &library_override);
+            // both were checked when the config was read
+            theme = Theme::from_config(&config.theme).unwrap_or_default();
+            *keybinds.lock().unwrap() =
+                Keybinds::from_config(&config.keybindings, &config.macros).unwrap_or_default();
+            loaded_config = Some(config);
+        }
+        Err(err) => {
+            error!("Could not read config. Reason: {}", err);
+            state.lock().unwrap().ui.error = Some(format!("Could not read the config. {}", err));
         }
-        Err(_) => (),
+    }
+
+    if attach {
+        mode = PlayerModes::REMOTE;
+    }
+    let mut ipc_config = loaded_config
+        .as_ref()
+        .map(|config| config.ipc.clone())
+        .unwrap_or_default();
+    // it can't be reached otherwise
+    if args.daemon {
+        ipc_config.enabled = true;
+    }
+
+    // start with what was found last time while the library is scanned for changes. the
+    // server's library is only known once it's asked for it. the daemon scans for the interface
+    // attached to it
+    if mode != PlayerModes::MPD {
+        if let Err(err) = lib.load(&library_db) {
+            error!("Could not load the library. Reason: {}", err);
+        }
+    }
+    match library_db.load_positions() {
+        Ok(positions) => state.lock().unwrap().podcasts.positions = positions,
+        Err(err) => error!("Could not load where episodes were left. Reason: {}", err),
+    }
+    match library_db.load_saved_searches() {
+        Ok(searches) => lib.saved_searches = searches,
+        Err(err) => error!("Could not load the saved searches. Reason: {}", err),
+    }
+    match library_db.load_bookmarks() {
+        Ok(bookmarks) => lib.bookmarks = bookmarks,
+        Err(err) => error!("Could not load the bookmarks. Reason: {}", err),
+    }
+    match library_db.load_search_history() {
+        Ok(history) => state.lock().unwrap().search.history = history,
+        Err(err) => error!("Could not load the search history. Reason: {}", err),
     }
 
 
     state.lock().unwrap().library = lib;
+    if let Some(config) = &loaded_config {
+        apply_config(&state, config);
+    }
+
+    // something to play given on the command line replaces the last session. the daemon plays
+    // the one it has
+    let play_now = match &args.path {
+        _ if attach => false,
+        Some(path) => queue_path(&state, path),
+        None => match Session::load() {
+            Ok(Some(session)) => {
+                let resume = loaded_config.as_ref().is_some_and(|config| config.player.resume);
+                session.restore(&state, resume)
+            }
+            Ok(None) => false,
+            Err(err) => {
+                error!("Could not read the last session. Reason: {}", err);
+                false
+            }
+        },
+    };
+    if args.no_ui && !play_now {
+        eprintln!("Nothing to play in {}", args.path.unwrap_or_default().display());
+        std::process::exit(1);
+    }
+
+    // the scan thread takes the other connection, this one saves what is changed from the UI
+    let stats_db = match LibraryDb::open(&db::path()) {
+        Ok(stats_db) => stats_db,
+        Err(err) => {
+            error!("Could not open the library database, stats won't be saved. Reason: {}", err);
+            LibraryDb::open(Path::new(":memory:")).unwrap()
+        }
+    };
+
+    let tap = Arc::new(Mutex::new(SampleTap::new()));
+    // made again by the dispatcher if the player's thread dies
+    let new_player: NewPlayer = {
+        let tap = tap.clone();
+        let mpd_config = mpd_config.clone();
+        let socket = ipc_config.socket();
+        Box::new(move || match mode {
+            PlayerModes::LOCAL => Ok(Box::new(SymphoniaPlayer::new(backend, tap.clone()))),
+            PlayerModes::MPD => Ok(Box::new(MpdPlayer::new(mpd_config.clone()))),
+            PlayerModes::REMOTE => remote_player(socket.clone()),
+        })
+    };
+    let player = match new_player() {
+        Ok(player) => player,
+        Err(err) => {
+            error!("{}", err);
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
 
     let mut join_handlers = vec![];
 
-    let (main_tx, main_rx): (Sender<AppRequests>, Receiver<AppRequests>) = mpsc::channel();
-    let (ui_tx, ui_rx): (Sender<UIRequests>, Receiver<UIRequests>) = mpsc::channel();
-    let (player_tx, player_rx): (Sender<PlayerRequests>, Receiver<PlayerRequests>) =
-        mpsc::channel();
+    let (main_tx, main_rx): (UnboundedSender<AppRequests>, UnboundedReceiver<AppRequests>) =
+        mpsc::unbounded_channel();
+    let (ui_tx, ui_rx): (UnboundedSender<UIRequests>, UnboundedReceiver<UIRequests>) =
+        mpsc::unbounded_channel();
+    let (scrobbler_tx, scrobbler_rx): (
+        UnboundedSender<ScrobblerRequests>,
+        UnboundedReceiver<ScrobblerRequests>,
+    ) = mpsc::unbounded_channel();
+    let (notification_tx, notification_rx): (
+        UnboundedSender<NotificationRequests>,
+        UnboundedReceiver<NotificationRequests>,
+    ) = mpsc::unbounded_channel();
+    let (metadata_tx, metadata_rx): (
+        UnboundedSender<MetadataRequests>,
+        UnboundedReceiver<MetadataRequests>,
+    ) = mpsc::unbounded_channel();
 
-    let cloned_state = state.clone();
-    let cloned_main_tx = main_tx.clone();
-    join_handlers.push(thread::spawn(move || {
-        ui::start(cloned_state, ui_rx, cloned_main_tx)
-    }));
+    // without the interface nothing reads the UI's requests, they are dropped as they are sent
+    if !args.no_ui && !args.daemon {
+        let cloned_state = state.clone();
+        let cloned_main_tx = main_tx.clone();
+        let runtime = Handle::current();
+        let ui_thread = thread::Builder::new().name(ui::UI_THREADS[0].to_string());
+        join_handlers.push(ui_thread.spawn(move || {
+            // it waits for its requests on the runtime in between frames
+            let _runtime = runtime.enter();
+            // the terminal is already put back, so there's somewhere to say what went wrong
+            if let Err(err) = ui::start(cloned_state, ui_rx, cloned_main_tx.clone(), theme, tap) {
+                error!("The UI stopped. Reason: {}", err);
+                eprintln!("{}", err);
+                let _ = cloned_main_tx.send(AppRequests::Quit);
+            }
+        }).unwrap());
 
+        let cloned_state = state.clone();
+        let cloned_main_tx = main_tx.clone();
+        let cloned_keybinds = keybinds.clone();
+        let input_thread = thread::Builder::new().name(ui::UI_THREADS[1].to_string());
+        join_handlers.push(
+            input_thread
+                .spawn(move || input::listen(cloned_state, cloned_main_tx, cloned_keybinds))
+                .unwrap(),
+        );
+    }
+
+    let (player_tx, player_handle) = spawn_player(&state, player, &main_tx);
+
+    // the services wait on the network or the notification daemon for each request, and
+    // musicbrainz is only asked once a second, so they keep threads of their own rather than
+    // tying up the runtime's. not joined on quit, whatever the scrobbler hasn't sent yet is in
+    // its cache for next time
+    let scrobbling = loaded_config
+        .as_ref()
+        .map(|config| config.scrobbling.clone())
+        .unwrap_or_default();
+    thread::spawn(move || scrobbler::listen(scrobbling, scrobbler_rx));
+
+    let notifications = loaded_config
+        .as_ref()
+        .map(|config| config.notifications.clone())
+        .unwrap_or_default();
+    thread::spawn(move || notifications::listen(notifications, notification_rx));
+
+    let metadata_config = loaded_config
+        .as_ref()
+        .map(|config| config.metadata())
+        .unwrap_or_default();
     let cloned_state = state.clone();
     let cloned_main_tx = main_tx.clone();
-    join_handlers.push(thread::spawn(move || {
-        input::listen(cloned_state, cloned_main_tx)
-    }));
+    thread::spawn(move || {
+        metadata::listen(metadata_config, cloned_state, cloned_main_tx, metadata_rx)
+    });
 
-    let cloned_state = state.clone();
-    // let cloned_main_tx = main_tx.clone();
-    join_handlers.push(thread::spawn(move || {
-        player.listen(cloned_state, player_rx)
-    }));
+    let scan = match mode {
+        PlayerModes::LOCAL => Some(spawn_scan(&state, media_dirs.clone(), library_db, &main_tx)),
+        PlayerModes::MPD => Some(spawn_mpd_library(&state, mpd_config, &main_tx)),
+        PlayerModes::REMOTE => None,
+    };
 
-    loop {
-        match main_rx.recv() {
-            Err(err) => {
-                error!(
-                    "Could not receive request to modify app state. Reason: {}",
-                    err.to_string()
-                );
-            }
-            Ok(request) => match request {
-                AppRequests::Quit => {
-                    let _ = ui_tx.send(UIRequests::Quit);
-                    let _ = player_tx.send(PlayerRequests::Stop);
-                    let _ = player_tx.send(PlayerRequests::Quit);
-                    for handler in join_handlers {
-                        let _ = handler.join();
-                    }
-                    info!("Gracefully shutting down");
-                    std::process::exit(0);
-                }
-                AppRequests::UIRequests(request) => {
-                    let _ = ui_tx.send(request);
-                }
-                AppRequests::PlayerRequests(request) => {
-                    let _ = player_tx.send(request);
+    let podcasts = loaded_config
+        .as_ref()
+        .map(|config| config.podcasts.clone())
+        .unwrap_or_default();
+    spawn_podcasts(&state, podcasts, &main_tx);
+
+    let ipc_socket = match attach {
+        true => None,
+        false => ipc::listen(&ipc_config, &state, &main_tx),
+    };
+    #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+    {
+        let mpris_config = loaded_config
+            .as_ref()
+            .map(|config| config.mpris.clone())
+            .unwrap_or_default();
+        #[cfg(target_os = "linux")]
+        mpris::serve(&mpris_config, &state, &main_tx);
+        #[cfg(any(target_os = "macos", target_os = "windows"))]
+        media_keys::listen(&mpris_config, &state, &main_tx);
+    }
+    if args.daemon && ipc_socket.is_none() {
+        error!("Nothing could control the daemon, quitting");
+        std::process::exit(1);
+    }
+
+    if play_now {
+        let _ = main_tx.send(AppRequests::PlayerRequests(PlayerRequests::Start));
+    }
+
+    // kill -HUP reloads the config like the key does. ctrl+c only reaches here without the
+    // interface, since the terminal is in raw mode otherwise
+    #[cfg(unix)]
+    match Signals::new([SIGHUP, SIGINT, SIGTERM]) {
+        Ok(mut signals) => {
+            let cloned_main_tx = main_tx.clone();
+            thread::spawn(move || {
+                for signal in signals.forever() {
+                    let request = match signal {
+                        SIGHUP => AppRequests::ReloadConfig,
+                        _ => AppRequests::Quit,
+                    };
+                    let _ = cloned_main_tx.send(request);
                 }
-            },
+            });
+        }
+        Err(err) => error!("Could not listen for signals. Reason: {}", err),
+    }
+
+    Dispatcher {
+        state,
+        main_tx,
+        ui_tx,
+        player_tx,
+        player_handle: Some(player_handle),
+        new_player,
+        scrobbler_tx,
+        notification_tx,
+        metadata_tx,
+        stats_db: Arc::new(Mutex::new(stats_db)),
+        keybinds,
+        join_handlers,
+        scan,
+        mode,
+        backend,
+        media_dirs,
+        library_override,
+        ipc_socket,
+        no_ui: args.no_ui,
+        clear_asked: None,
+    }
+    .run(main_rx)
+    .await;
+}
+
+// run this again as the daemon, away from the terminal so closing it doesn't stop the music
+#[cfg(unix)]
+fn start_daemon() -> ! {
+    let socket = config::read()
+        .map(|config| config.ipc)
+        .unwrap_or_default()
+        .socket();
+    if IpcClient::connect(&socket).is_ok() {
+        eprintln!("splay is already playing in the background, `splay attach` shows it");
+        std::process::exit(1);
+    }
+    let mut args: Vec<OsString> = std::env::args_os().skip(1).collect();
+    args.push("--detached".into());
+    let started = std::env::current_exe().and_then(|exe| {
+        Command::new(exe)
+            .args(args)
+            .process_group(0)
+            // it's not in the terminal's window anymore, see notifications::terminal_focused
+            .env_remove("WINDOWID")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+    });
+    match started {
+        Ok(_) => {
+            println!("splay is playing in the background, `splay attach` shows the interface");
+            std::process::exit(0);
+        }
+        Err(err) => {
+            eprintln!("Could not start the daemon. {}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn start_daemon() -> ! {
+    eprintln!("splay can only play in the background on unix");
+    std::process::exit(1);
+}
+
+// the daemon has to be running already, there's nothing to show otherwise
+#[cfg(unix)]
+fn remote_player(socket: PathBuf) -> Result<Box<dyn Player + Send>, String> {
+    match IpcClient::connect(&socket) {
+        Ok(client) => Ok(Box::new(RemotePlayer::new(socket, client))),
+        Err(err) => Err(format!("{}. Start it with `splay --daemon`", err)),
+    }
+}
+
+#[cfg(not(unix))]
+fn remote_player(_socket: PathBuf) -> Result<Box<dyn Player + Send>, String> {
+    Err("splay can only attach to a daemon on unix".to_string())
+}
+
+// queue up the songs at a path given on the command line, the first one to be played once the
+// player is listening. false when there's nothing to play there
+fn queue_path(state: &Arc<Mutex<AppState>>, path: &Path) -> bool {
+    let mut guard = state.lock().unwrap();
+    let mut songs = match guard.library.songs_at(path) {
+        Ok(songs) => songs.into_iter(),
+        Err(err) => {
+            error!("Could not read {:?}. Reason: {}", path, err);
+            guard.ui.error = Some(format!("Could not read {}. {}", path.display(), err));
+            return false;
         }
+    };
+    let first = match songs.next() {
+        Some(song) => song,
+        None => return false,
+    };
+    guard.ui.selected_song = Some(first);
+    for song in songs {
+        guard.queue.add_upcoming(song);
     }
+    true
 }
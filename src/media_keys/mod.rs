@@ -0,0 +1,162 @@
+// the keyboard's play/pause, next and previous keys go out as the same requests wherever they come
+// from. on linux the desktop hands them over through mpris, macos and windows let splay take them
+// itself even while the terminal isn't focused
+
+#[cfg(target_os = "macos")]
+use std::sync::{mpsc, OnceLock};
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use std::sync::{Arc, Mutex};
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use std::thread;
+use std::time::Duration;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use tokio::sync::mpsc::UnboundedSender;
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use crate::config::MprisConfig;
+use crate::state::AppState;
+use crate::utils::constants::requests::*;
+use crate::utils::constants::PlayerStates;
+#[cfg(target_os = "macos")]
+use core_foundation::runloop::CFRunLoop;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use global_hotkey::hotkey::{Code, HotKey};
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+
+// previous goes back to the start of a song that's played longer than this, otherwise to the
+// song before it
+const RESTART_AFTER: Duration = Duration::from_secs(3);
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+const KEYS: [Code; 3] = [
+    Code::MediaPlayPause,
+    Code::MediaTrackNext,
+    Code::MediaTrackPrevious,
+];
+
+// the main thread is told to take the keys through this, see run_beside
+#[cfg(target_os = "macos")]
+static START: OnceLock<mpsc::SyncSender<()>> = OnceLock::new();
+
+// the keys are registered with the system for as long as splay runs, turned off with [mpris]
+// like they are on linux
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+pub fn listen(
+    config: &MprisConfig,
+    state: &Arc<Mutex<AppState>>,
+    main_tx: &UnboundedSender<AppRequests>,
+) {
+    if !config.enabled {
+        return;
+    }
+    let state = state.clone();
+    let main_tx = main_tx.clone();
+    GlobalHotKeyEvent::set_event_handler(Some(move |event: GlobalHotKeyEvent| {
+        if event.state != HotKeyState::Pressed {
+            return;
+        }
+        let key = KEYS
+            .into_iter()
+            .find(|key| HotKey::new(None, *key).id() == event.id);
+        let request = match key {
+            Some(Code::MediaPlayPause) => Some(play_pause(&state.lock().unwrap())),
+            Some(Code::MediaTrackNext) => Some(AppRequests::PlayerRequests(PlayerRequests::Next)),
+            Some(Code::MediaTrackPrevious) => previous(&mut state.lock().unwrap()),
+            _ => None,
+        };
+        if let Some(request) = request {
+            let _ = main_tx.send(request);
+        }
+    }));
+
+    // the keys come to a window of the thread that registered them, through its messages
+    #[cfg(target_os = "windows")]
+    thread::spawn(|| {
+        use std::{mem, ptr};
+        use windows_sys::Win32::UI::WindowsAndMessaging::{
+            DispatchMessageW, GetMessageW, TranslateMessage, MSG,
+        };
+
+        let _manager = match register() {
+            Some(manager) => manager,
+            None => return,
+        };
+        let mut message: MSG = unsafe { mem::zeroed() };
+        while unsafe { GetMessageW(&mut message, ptr::null_mut(), 0, 0) } > 0 {
+            unsafe {
+                TranslateMessage(&message);
+                DispatchMessageW(&message);
+            }
+        }
+    });
+    #[cfg(target_os = "macos")]
+    if let Some(start) = START.get() {
+        let _ = start.send(());
+    }
+}
+
+// macos sends the keys to the main thread's run loop, so splay is started on a thread of its own
+// and the main thread waits for listen to ask for them. the app exits the process when it's done
+#[cfg(target_os = "macos")]
+pub fn run_beside(app: impl FnOnce() + Send + 'static) -> ! {
+    let (start_tx, start_rx) = mpsc::sync_channel(1);
+    let _ = START.set(start_tx);
+    thread::spawn(app);
+    if start_rx.recv().is_ok() {
+        if let Some(_manager) = register() {
+            CFRunLoop::run_current();
+        }
+    }
+    loop {
+        thread::park();
+    }
+}
+
+// macos only lets splay see the keys once its terminal has been allowed to watch the keyboard
+// under accessibility in the privacy settings
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn register() -> Option<GlobalHotKeyManager> {
+    let registered = GlobalHotKeyManager::new().and_then(|manager| {
+        manager.register_all(&KEYS.map(|key| HotKey::new(None, key)))?;
+        Ok(manager)
+    });
+    match registered {
+        Ok(manager) => Some(manager),
+        Err(err) => {
+            error!("Could not take the media keys. Reason: {}", err);
+            None
+        }
+    }
+}
+
+pub fn play_pause(state: &AppState) -> AppRequests {
+    match state.player.curr_state.is_playing() {
+        true => AppRequests::PlayerRequests(PlayerRequests::Pause),
+        false => play(state),
+    }
+}
+
+// a stopped player starts the song under the cursor instead
+pub fn play(state: &AppState) -> AppRequests {
+    match state.player.curr_state {
+        PlayerStates::STOPPED => AppRequests::PlayerRequests(PlayerRequests::Start),
+        _ => AppRequests::PlayerRequests(PlayerRequests::Resume),
+    }
+}
+
+// back to the start of the song, or to the song played before it if this one only just started.
+// the song that was playing is put back at the front of the queue
+pub fn previous(state: &mut AppState) -> Option<AppRequests> {
+    if state.player.curr_state != PlayerStates::STOPPED && state.player.progress > RESTART_AFTER {
+        return Some(AppRequests::PlayerRequests(PlayerRequests::SeekTo(
+            Duration::ZERO,
+        )));
+    }
+    let song = state.queue.take_last_played()?;
+    if let Some(curr_song) = state.player.curr_song.to_owned() {
+        state.queue.add_first_immediate(curr_song);
+    }
+    state.ui.selected_song = Some(song);
+    Some(AppRequests::PlayerRequests(PlayerRequests::Start))
+}
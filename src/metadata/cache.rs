@@ -0,0 +1,184 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use std::error::Error;
+use std::path::Path;
+
+use super::{Metadata, OnlineTags};
+
+// what was looked up online, kept in the library database so nothing is asked for twice. what
+// wasn't found is kept too, as a row without a value
+pub struct MetadataCache {
+    conn: Connection,
+}
+
+impl MetadataCache {
+    pub fn open(path: &Path) -> Result<MetadataCache, Box<dyn Error>> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS online_tags (
+                path TEXT NOT NULL,
+                start_ms INTEGER NOT NULL,
+                found INTEGER NOT NULL,
+                title TEXT NOT NULL,
+                artist TEXT NOT NULL,
+                album TEXT NOT NULL,
+                year TEXT,
+                release_id TEXT,
+                PRIMARY KEY (path, start_ms)
+            );
+            CREATE TABLE IF NOT EXISTS online_bios (
+                artist TEXT PRIMARY KEY,
+                bio TEXT
+            );
+            CREATE TABLE IF NOT EXISTS online_covers (
+                album_artist TEXT NOT NULL,
+                album TEXT NOT NULL,
+                cover BLOB,
+                PRIMARY KEY (album_artist, album)
+            );",
+        )?;
+        Ok(MetadataCache { conn })
+    }
+
+    // everything that was found, for the info popup
+    pub fn load(&self) -> Result<Metadata, Box<dyn Error>> {
+        let mut metadata = Metadata::default();
+        let mut statement = self.conn.prepare(
+            "SELECT path, start_ms, title, artist, album, year, release_id FROM online_tags \
+            WHERE found = 1",
+        )?;
+        let tags = statement.query_map([], |row| {
+            let key = (row.get(0)?, row.get::<_, i64>(1)? as u64);
+            let tags = OnlineTags {
+                title: row.get(2)?,
+                artist: row.get(3)?,
+                album: row.get(4)?,
+                year: row.get(5)?,
+                release_id: row.get(6)?,
+            };
+            Ok((key, tags))
+        })?;
+        for entry in tags {
+            let (key, tags) = entry?;
+            metadata.tags.insert(key, tags);
+        }
+        let mut statement = self
+            .conn
+            .prepare("SELECT artist, bio FROM online_bios WHERE bio IS NOT NULL")?;
+        let bios = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        for entry in bios {
+            let (artist, bio) = entry?;
+            metadata.bios.insert(artist, bio);
+        }
+        let mut statement = self
+            .conn
+            .prepare("SELECT album_artist, album FROM online_covers WHERE cover IS NOT NULL")?;
+        let covers = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        for entry in covers {
+            metadata.covers.insert(entry?);
+        }
+        Ok(metadata)
+    }
+
+    // none if the song was never looked up, some none if nothing was found for it
+    pub fn tags(
+        &self,
+        path: &str,
+        start_ms: u64,
+    ) -> Result<Option<Option<OnlineTags>>, Box<dyn Error>> {
+        let tags = self
+            .conn
+            .query_row(
+                "SELECT found, title, artist, album, year, release_id FROM online_tags \
+                WHERE path = ?1 AND start_ms = ?2",
+                params![path, start_ms as i64],
+                |row| {
+                    if !row.get::<_, bool>(0)? {
+                        return Ok(None);
+                    }
+                    Ok(Some(OnlineTags {
+                        title: row.get(1)?,
+                        artist: row.get(2)?,
+                        album: row.get(3)?,
+                        year: row.get(4)?,
+                        release_id: row.get(5)?,
+                    }))
+                },
+            )
+            .optional()?;
+        Ok(tags)
+    }
+
+    pub fn save_tags(
+        &mut self,
+        path: &str,
+        start_ms: u64,
+        tags: Option<&OnlineTags>,
+    ) -> Result<(), Box<dyn Error>> {
+        let empty = OnlineTags::default();
+        let found = tags.is_some();
+        let tags = tags.unwrap_or(&empty);
+        self.conn.execute(
+            "INSERT OR REPLACE INTO online_tags \
+            (path, start_ms, found, title, artist, album, year, release_id) \
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                path,
+                start_ms as i64,
+                found,
+                tags.title,
+                tags.artist,
+                tags.album,
+                tags.year,
+                tags.release_id,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn has_bio(&self, artist: &str) -> Result<bool, Box<dyn Error>> {
+        let count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM online_bios WHERE artist = ?1",
+            [artist],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    pub fn save_bio(&mut self, artist: &str, bio: Option<&str>) -> Result<(), Box<dyn Error>> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO online_bios (artist, bio) VALUES (?1, ?2)",
+            params![artist, bio],
+        )?;
+        Ok(())
+    }
+
+    // none if the album's art was never looked up, some none if none was found
+    pub fn cover(
+        &self,
+        album_artist: &str,
+        album: &str,
+    ) -> Result<Option<Option<Vec<u8>>>, Box<dyn Error>> {
+        let cover = self
+            .conn
+            .query_row(
+                "SELECT cover FROM online_covers WHERE album_artist = ?1 AND album = ?2",
+                [album_artist, album],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(cover)
+    }
+
+    pub fn save_cover(
+        &mut self,
+        album_artist: &str,
+        album: &str,
+        cover: Option<&[u8]>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO online_covers (album_artist, album, cover) VALUES (?1, ?2, ?3)",
+            params![album_artist, album, cover],
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,15 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum MetadataError {
+    #[error("Could not reach {service}: {reason}")]
+    Request {
+        service: &'static str,
+        reason: String,
+    },
+    #[error("{service} sent back something unexpected: {reason}")]
+    Response {
+        service: &'static str,
+        reason: String,
+    },
+}
@@ -0,0 +1,495 @@
+pub mod cache;
+pub mod errors;
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::path::Path;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::MetadataConfig;
+use crate::library::song::Song;
+use crate::library::{db, tag};
+use crate::scrobbler::url_encode;
+use crate::state::AppState;
+use crate::utils::constants::requests::{AppRequests, MetadataRequests, UIRequests};
+use serde_json::Value;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+use cache::MetadataCache;
+use errors::MetadataError;
+
+const MUSICBRAINZ_URL: &str = "https://musicbrainz.org/ws/2/recording/";
+const COVER_ART_ARCHIVE_URL: &str = "https://coverartarchive.org/release/";
+const LASTFM_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+
+// musicbrainz turns away clients that don't say who they are or ask more than once a second
+const USER_AGENT: &str = concat!(
+    "splay/",
+    env!("CARGO_PKG_VERSION"),
+    " ( splay music player )"
+);
+const MUSICBRAINZ_INTERVAL: Duration = Duration::from_secs(1);
+// for the whole of a request, cover art included
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+// matches musicbrainz is less sure of than this are taken as not found
+const MIN_SCORE: f64 = 90.;
+
+// the tags musicbrainz has for a song, which can be more correct than the file's
+#[derive(Debug, Clone, Default)]
+pub struct OnlineTags {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub year: Option<String>,
+    pub release_id: Option<String>, // of the album, to find its cover art by
+}
+
+// what was found online, shown in the info popup
+#[derive(Default)]
+pub struct Metadata {
+    pub tags: HashMap<(String, u64), OnlineTags>, // by the song's path and start
+    pub bios: HashMap<String, String>,            // by artist
+    pub covers: HashSet<(String, String)>,        // albums by their artist and title
+    pub pending: HashSet<(String, u64)>,          // songs being looked up
+}
+
+impl Metadata {
+    pub fn tags(&self, song: &Song) -> Option<&OnlineTags> {
+        self.tags.get(&(song.path.to_owned(), song.start_ms))
+    }
+
+    pub fn bio(&self, song: &Song) -> Option<&str> {
+        self.bios.get(&song.track_artist).map(String::as_str)
+    }
+
+    pub fn has_cover(&self, song: &Song) -> bool {
+        self.covers
+            .contains(&(song.album_artist.to_owned(), song.album_title.to_owned()))
+    }
+
+    pub fn is_pending(&self, song: &Song) -> bool {
+        self.pending
+            .contains(&(song.path.to_owned(), song.start_ms))
+    }
+}
+
+// the cover art fetched for the song's album, for songs without any of their own
+pub fn cached_cover(song: &Song) -> Option<Vec<u8>> {
+    let cache = MetadataCache::open(&db::path()).ok()?;
+    cache
+        .cover(&song.album_artist, &song.album_title)
+        .ok()
+        .flatten()
+        .flatten()
+}
+
+// looks up the songs it's sent on musicbrainz, the cover art archive and last.fm, whatever of
+// it isn't known already. nothing is sent anywhere unless it's enabled in the config. runs until
+// every sender is gone
+pub fn listen(
+    config: MetadataConfig,
+    state: Arc<Mutex<AppState>>,
+    main_tx: UnboundedSender<AppRequests>,
+    mut rx: UnboundedReceiver<MetadataRequests>,
+) {
+    let cache = match MetadataCache::open(&db::path()) {
+        Ok(cache) => cache,
+        Err(err) => {
+            error!(
+                "Could not open the metadata cache, nothing looked up is kept. Reason: {}",
+                err
+            );
+            MetadataCache::open(Path::new(":memory:")).unwrap()
+        }
+    };
+    match cache.load() {
+        Ok(metadata) => state.lock().unwrap().metadata = metadata,
+        Err(err) => error!("Could not read the metadata cache. Reason: {}", err),
+    }
+    let mut lookup = Lookup {
+        config,
+        cache,
+        state,
+        main_tx,
+        last_musicbrainz: None,
+    };
+    while let Some(request) = rx.blocking_recv() {
+        let (songs, asked) = match request {
+            MetadataRequests::SetConfig(config) => {
+                lookup.config = config;
+                continue;
+            }
+            MetadataRequests::Fetch(_) if !lookup.config.online => {
+                lookup.state.lock().unwrap().ui.notify_error(
+                    "Looking songs up online is off, set metadata.online in the config",
+                );
+                continue;
+            }
+            MetadataRequests::Lookup(_) if !lookup.config.online => continue,
+            MetadataRequests::Fetch(songs) => (songs, true),
+            MetadataRequests::Lookup(song) => (vec![song], false),
+        };
+        let mut failed = None;
+        let mut count = 0;
+        for song in songs {
+            if song.is_stream() || song.title.is_empty() {
+                continue;
+            }
+            count += 1;
+            if let Err(err) = lookup.song(&song) {
+                warn!("Could not look up {} online. Reason: {}", song.path, err);
+                failed = Some(err);
+            }
+        }
+        let mut guard = lookup.state.lock().unwrap();
+        match failed {
+            Some(err) => guard
+                .ui
+                .notify_error(format!("Could not look everything up online. {}", err)),
+            None if asked => guard.ui.notify(match count {
+                1 => "Looked up 1 song online".to_string(),
+                count => format!("Looked up {} songs online", count),
+            }),
+            None => (),
+        }
+    }
+}
+
+struct Lookup {
+    config: MetadataConfig,
+    cache: MetadataCache,
+    state: Arc<Mutex<AppState>>,
+    main_tx: UnboundedSender<AppRequests>,
+    last_musicbrainz: Option<Instant>,
+}
+
+impl Lookup {
+    // its tags, the cover art of its album and the bio of its artist, each unless it was looked
+    // up before
+    fn song(&mut self, song: &Song) -> Result<(), Box<dyn Error>> {
+        let tags = self.cache.tags(&song.path, song.start_ms)?;
+        let wants_cover = self.wants_cover(song)?;
+        let wants_bio = self.wants_bio(song)?;
+        if tags.is_some() && !wants_cover && !wants_bio {
+            return Ok(());
+        }
+        let key = (song.path.to_owned(), song.start_ms);
+        self.state
+            .lock()
+            .unwrap()
+            .metadata
+            .pending
+            .insert(key.to_owned());
+        self.redraw();
+        let result = self.fetch(song, tags, wants_cover, wants_bio);
+        self.state.lock().unwrap().metadata.pending.remove(&key);
+        self.redraw();
+        result
+    }
+
+    fn fetch(
+        &mut self,
+        song: &Song,
+        tags: Option<Option<OnlineTags>>,
+        wants_cover: bool,
+        wants_bio: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        let tags = match tags {
+            Some(tags) => tags,
+            None => {
+                let tags = self.musicbrainz(song)?;
+                self.cache
+                    .save_tags(&song.path, song.start_ms, tags.as_ref())?;
+                if let Some(tags) = &tags {
+                    let key = (song.path.to_owned(), song.start_ms);
+                    self.state
+                        .lock()
+                        .unwrap()
+                        .metadata
+                        .tags
+                        .insert(key, tags.to_owned());
+                }
+                tags
+            }
+        };
+        if wants_cover {
+            let cover = self.cover(song, tags.as_ref())?;
+            self.cache
+                .save_cover(&song.album_artist, &song.album_title, cover.as_deref())?;
+            if cover.is_some() {
+                let album = (song.album_artist.to_owned(), song.album_title.to_owned());
+                self.state.lock().unwrap().metadata.covers.insert(album);
+            }
+        }
+        if wants_bio {
+            let bio = self.bio(&song.track_artist)?;
+            self.cache.save_bio(&song.track_artist, bio.as_deref())?;
+            if let Some(bio) = bio {
+                let artist = song.track_artist.to_owned();
+                self.state.lock().unwrap().metadata.bios.insert(artist, bio);
+            }
+        }
+        Ok(())
+    }
+
+    // the art in the file or next to it comes first
+    fn wants_cover(&self, song: &Song) -> Result<bool, Box<dyn Error>> {
+        if song.album_title.is_empty() {
+            return Ok(false);
+        }
+        let cached = self.cache.cover(&song.album_artist, &song.album_title)?;
+        Ok(cached.is_none() && tag::read_cover_art(&song.path).is_none())
+    }
+
+    // bios are only on last.fm, which needs an api key
+    fn wants_bio(&self, song: &Song) -> Result<bool, Box<dyn Error>> {
+        if song.track_artist.is_empty() || self.config.lastfm_api_key.is_empty() {
+            return Ok(false);
+        }
+        Ok(!self.cache.has_bio(&song.track_artist)?)
+    }
+
+    fn redraw(&self) {
+        let _ = self
+            .main_tx
+            .send(AppRequests::UIRequests(UIRequests::Redraw));
+    }
+
+    // the recording that best matches the song's title and artist, from the release with the
+    // song's album title if it's on one
+    fn musicbrainz(&mut self, song: &Song) -> Result<Option<OnlineTags>, MetadataError> {
+        let query = format!(
+            "recording:{} AND artist:{}",
+            lucene_quote(&song.title),
+            lucene_quote(&song.track_artist)
+        );
+        let url = format!(
+            "{}?query={}&fmt=json&limit=1",
+            MUSICBRAINZ_URL,
+            url_encode(&query)
+        );
+        if let Some(last) = self.last_musicbrainz {
+            thread::sleep(MUSICBRAINZ_INTERVAL.saturating_sub(last.elapsed()));
+        }
+        self.last_musicbrainz = Some(Instant::now());
+        let response = match get_json("MusicBrainz", &url)? {
+            Some(response) => response,
+            None => return Ok(None),
+        };
+        let recording = match response.get("recordings") {
+            Some(Value::Array(recordings)) => recordings.first(),
+            _ => {
+                return Err(MetadataError::Response {
+                    service: "MusicBrainz",
+                    reason: "there are no recordings in it".to_string(),
+                })
+            }
+        };
+        let recording = match recording.filter(|recording| {
+            recording.get("score").and_then(Value::as_f64).unwrap_or(0.) >= MIN_SCORE
+        }) {
+            Some(recording) => recording,
+            None => return Ok(None),
+        };
+        let artist = match recording.get("artist-credit") {
+            // eg. "Artist feat. Someone", each credit followed by what joins it to the next
+            Some(Value::Array(credits)) => credits
+                .iter()
+                .map(|credit| {
+                    let name = credit.get("name").and_then(Value::as_str).unwrap_or("");
+                    let join = credit
+                        .get("joinphrase")
+                        .and_then(Value::as_str)
+                        .unwrap_or("");
+                    format!("{}{}", name, join)
+                })
+                .collect(),
+            _ => song.track_artist.to_owned(),
+        };
+        let releases = match recording.get("releases") {
+            Some(Value::Array(releases)) => releases.as_slice(),
+            _ => &[],
+        };
+        let release_title = |release: &Value| {
+            release
+                .get("title")
+                .and_then(Value::as_str)
+                .map(str::to_lowercase)
+        };
+        let album_title = song.album_title.to_lowercase();
+        let release = releases
+            .iter()
+            .find(|release| release_title(release).as_deref() == Some(album_title.as_str()))
+            .or(releases.first());
+        let field = |json: Option<&Value>, name: &str| {
+            json.and_then(|json| json.get(name))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        };
+        Ok(Some(OnlineTags {
+            title: field(Some(recording), "title").unwrap_or_else(|| song.title.to_owned()),
+            artist,
+            album: field(release, "title").unwrap_or_default(),
+            // dates are yyyy-mm-dd, or only as much of it as is known
+            year: field(Some(recording), "first-release-date")
+                .filter(|date| date.len() >= 4)
+                .map(|date| date[..4].to_string()),
+            release_id: field(release, "id"),
+        }))
+    }
+
+    // the front cover of the release from the cover art archive, or else the album's image on
+    // last.fm
+    fn cover(
+        &mut self,
+        song: &Song,
+        tags: Option<&OnlineTags>,
+    ) -> Result<Option<Vec<u8>>, MetadataError> {
+        if let Some(release_id) = tags.and_then(|tags| tags.release_id.as_deref()) {
+            let url = format!("{}{}/front-250", COVER_ART_ARCHIVE_URL, release_id);
+            if let Some(cover) = get("the Cover Art Archive", &url)?.filter(|data| is_image(data)) {
+                return Ok(Some(cover));
+            }
+        }
+        if self.config.lastfm_api_key.is_empty() {
+            return Ok(None);
+        }
+        let artist = match song.album_artist.is_empty() {
+            true => &song.track_artist,
+            false => &song.album_artist,
+        };
+        let url = format!(
+            "{}?method=album.getinfo&artist={}&album={}&api_key={}&autocorrect=1&format=json",
+            LASTFM_URL,
+            url_encode(artist),
+            url_encode(&song.album_title),
+            url_encode(&self.config.lastfm_api_key)
+        );
+        let album = match lastfm(&url)? {
+            Some(response) => response,
+            None => return Ok(None),
+        };
+        // smallest to largest
+        let image_url = match album.get("album").and_then(|album| album.get("image")) {
+            Some(Value::Array(images)) => images
+                .iter()
+                .rev()
+                .filter_map(|image| image.get("#text").and_then(Value::as_str))
+                .find(|url| !url.is_empty())
+                .map(str::to_string),
+            _ => None,
+        };
+        match image_url {
+            Some(image_url) => Ok(get("Last.fm", &image_url)?.filter(|data| is_image(data))),
+            None => Ok(None),
+        }
+    }
+
+    fn bio(&mut self, artist: &str) -> Result<Option<String>, MetadataError> {
+        let url = format!(
+            "{}?method=artist.getinfo&artist={}&api_key={}&autocorrect=1&format=json",
+            LASTFM_URL,
+            url_encode(artist),
+            url_encode(&self.config.lastfm_api_key)
+        );
+        let response = match lastfm(&url)? {
+            Some(response) => response,
+            None => return Ok(None),
+        };
+        let summary = response
+            .get("artist")
+            .and_then(|artist| artist.get("bio"))
+            .and_then(|bio| bio.get("summary"))
+            .and_then(Value::as_str)
+            .unwrap_or("");
+        let bio = strip_html(summary);
+        // it ends with a link to the rest of it
+        let bio = bio
+            .trim_end()
+            .trim_end_matches("Read more on Last.fm")
+            .trim();
+        Ok(Some(bio.to_string()).filter(|bio| !bio.is_empty()))
+    }
+}
+
+// none if there's nothing there
+fn get(service: &'static str, url: &str) -> Result<Option<Vec<u8>>, MetadataError> {
+    let request_error = |reason: String| MetadataError::Request { service, reason };
+    let response = match ureq::get(url)
+        .set("User-Agent", USER_AGENT)
+        .timeout(REQUEST_TIMEOUT)
+        .call()
+    {
+        Ok(response) => response,
+        Err(ureq::Error::Status(404, _)) => return Ok(None),
+        Err(err) => return Err(request_error(err.to_string())),
+    };
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .read_to_end(&mut body)
+        .map_err(|err| request_error(err.to_string()))?;
+    Ok(Some(body))
+}
+
+fn get_json(service: &'static str, url: &str) -> Result<Option<Value>, MetadataError> {
+    let body = match get(service, url)? {
+        Some(body) => body,
+        None => return Ok(None),
+    };
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|err| MetadataError::Response { service, reason: err.to_string() })
+}
+
+// last.fm answers with an error in the json, 6 being that the artist or album isn't there
+fn lastfm(url: &str) -> Result<Option<Value>, MetadataError> {
+    let response = match get_json("Last.fm", url)? {
+        Some(response) => response,
+        None => return Ok(None),
+    };
+    match response.get("error").and_then(Value::as_u64) {
+        Some(6) => Ok(None),
+        Some(_) => Err(MetadataError::Response {
+            service: "Last.fm",
+            reason: response
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("an unknown error")
+                .to_string(),
+        }),
+        None => Ok(Some(response)),
+    }
+}
+
+fn is_image(data: &[u8]) -> bool {
+    image::load_from_memory(data).is_ok()
+}
+
+// a phrase in a musicbrainz search, where quotes and backslashes have to be escaped
+fn lucene_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// the text of last.fm's html, without the tags and with the few entities it uses read
+fn strip_html(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for ch in html.chars() {
+        match ch {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            ch if !in_tag => text.push(ch),
+            _ => (),
+        }
+    }
+    text.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,12 @@
+use std::io;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum MpdError {
+    #[error("Could not talk to mpd: {0}")]
+    Connection(#[from] io::Error),
+    #[error("mpd did not answer like a server should: {0}")]
+    Protocol(String),
+    #[error("mpd refused {command}: {reason}")]
+    Refused { command: String, reason: String },
+}
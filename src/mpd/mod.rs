@@ -0,0 +1,203 @@
+pub mod errors;
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::config::MpdConfig;
+use crate::library::song::Song;
+use crate::utils::constants::PlayerStates;
+use errors::MpdError;
+
+// how long to wait for the server before giving up on it
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+// a connection to an mpd server, which is sent commands and answers each with lines of
+// "key: value" ending with OK, or a single line starting with ACK when it refuses one
+pub struct MpdClient {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+// what the server is doing, from the status command
+pub struct MpdStatus {
+    pub state: PlayerStates,
+    pub elapsed: Duration,
+}
+
+impl MpdClient {
+    pub fn connect(config: &MpdConfig) -> Result<MpdClient, MpdError> {
+        let addr = (config.host.as_str(), config.port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| MpdError::Protocol(format!("{} has no address", config.host)))?;
+        let writer = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+        writer.set_read_timeout(Some(TIMEOUT))?;
+        let mut client = MpdClient {
+            reader: BufReader::new(writer.try_clone()?),
+            writer,
+        };
+        // the server greets with its version before taking any commands
+        let greeting = client.read_line()?;
+        if !greeting.starts_with("OK MPD") {
+            return Err(MpdError::Protocol(greeting));
+        }
+        if let Some(password) = &config.password {
+            client.command("password", &[password])?;
+        }
+        Ok(client)
+    }
+
+    // send a command and return what the server answered with, in order
+    pub fn command(
+        &mut self,
+        command: &str,
+        args: &[&str],
+    ) -> Result<Vec<(String, String)>, MpdError> {
+        let mut line = command.to_string();
+        for arg in args {
+            line.push(' ');
+            line.push_str(&quote(arg));
+        }
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())?;
+
+        let mut pairs = Vec::new();
+        loop {
+            let line = self.read_line()?;
+            if line == "OK" {
+                return Ok(pairs);
+            }
+            if let Some(ack) = line.strip_prefix("ACK ") {
+                // eg. ACK [50@0] {play} No such song
+                let reason = ack.split_once("} ").map_or(ack, |(_, reason)| reason);
+                return Err(MpdError::Refused {
+                    command: command.to_string(),
+                    reason: reason.to_string(),
+                });
+            }
+            match line.split_once(": ") {
+                Some((key, value)) => pairs.push((key.to_string(), value.to_string())),
+                None => return Err(MpdError::Protocol(line)),
+            }
+        }
+    }
+
+    pub fn status(&mut self) -> Result<MpdStatus, MpdError> {
+        let mut status = MpdStatus {
+            state: PlayerStates::STOPPED,
+            elapsed: Duration::ZERO,
+        };
+        for (key, value) in self.command("status", &[])? {
+            match key.as_str() {
+                "state" => {
+                    status.state = match value.as_str() {
+                        "play" => PlayerStates::PLAYING,
+                        "pause" => PlayerStates::PAUSED,
+                        _ => PlayerStates::STOPPED,
+                    }
+                }
+                "elapsed" => {
+                    if let Ok(secs) = value.parse::<f64>() {
+                        status.elapsed = Duration::from_secs_f64(secs.max(0.));
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(status)
+    }
+
+    // every song in the server's database. directories are listed one at a time, since the
+    // server won't send a whole large library in one answer
+    pub fn songs(&mut self) -> Result<Vec<Song>, MpdError> {
+        let mut songs = Vec::new();
+        let mut dirs = vec![String::new()];
+        while let Some(dir) = dirs.pop() {
+            let mut song: Option<Song> = None;
+            for (key, value) in self.command("lsinfo", &[&dir])? {
+                match key.as_str() {
+                    // each entry starts with what it is
+                    "file" => {
+                        songs.extend(song.take());
+                        song = Some(new_song(value));
+                    }
+                    "directory" | "playlist" => {
+                        songs.extend(song.take());
+                        if key == "directory" {
+                            dirs.push(value);
+                        }
+                    }
+                    _ => {
+                        if let Some(song) = &mut song {
+                            set_tag(song, &key, value);
+                        }
+                    }
+                }
+            }
+            songs.extend(song);
+        }
+        Ok(songs)
+    }
+
+    fn read_line(&mut self) -> Result<String, MpdError> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(MpdError::Protocol("the connection was closed".to_string()));
+        }
+        Ok(line.trim_end_matches('\n').to_string())
+    }
+}
+
+// arguments are quoted so they can have spaces in them
+fn quote(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// the path is the song's uri in the server's database, relative to its music directory. the
+// file name is the title until the song's tags say otherwise
+fn new_song(uri: String) -> Song {
+    let title = Path::new(&uri)
+        .file_stem()
+        .map_or(uri.to_owned(), |stem| stem.to_string_lossy().into_owned());
+    Song::new(title, uri)
+}
+
+fn set_tag(song: &mut Song, key: &str, value: String) {
+    match key {
+        "Title" => song.title = value,
+        "Artist" => {
+            // the album artist falls back to the track's artist until it is given
+            if song.album_artist == song.track_artist {
+                song.album_artist = value.to_owned();
+            }
+            song.track_artist = value;
+        }
+        "AlbumArtist" => song.album_artist = value,
+        "Album" => song.album_title = value,
+        "Genre" => song.genre = Some(value),
+        "Date" => song.year = Some(value),
+        "Track" => song.track_number = Some(value),
+        "Disc" => song.disc_number = Some(value),
+        "duration" => {
+            if let Ok(secs) = value.parse::<f64>() {
+                song.duration_secs = secs.round() as u64;
+            }
+        }
+        // older servers only send the length in whole seconds
+        "Time" if song.duration_secs == 0 => {
+            song.duration_secs = value.parse().unwrap_or(0);
+        }
+        // eg. 44100:24:2
+        "Format" => {
+            let mut parts = value.split(':');
+            song.sample_rate = parts.next().and_then(|rate| rate.parse().ok()).unwrap_or(0);
+            song.channels = parts
+                .nth(1)
+                .and_then(|channels| channels.parse().ok())
+                .unwrap_or(0);
+        }
+        _ => (),
+    }
+}
@@ -0,0 +1,9 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum DbusError {
+    #[error(transparent)]
+    Bus(#[from] zbus::Error),
+    #[error("Every name splay could have on the session bus is taken")]
+    NamesTaken,
+}
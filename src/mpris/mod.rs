@@ -0,0 +1,590 @@
+pub mod errors;
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+use crate::config::MprisConfig;
+use crate::ipc;
+use crate::library::song::{self, Song};
+use crate::media_keys::{play, play_pause, previous};
+use crate::player::speed;
+use crate::state::AppState;
+use crate::utils::constants::requests::*;
+use crate::utils::constants::{PlayerStates, RepeatModes};
+use errors::DbusError;
+use url::Url;
+use zbus::fdo::{self, RequestNameFlags, RequestNameReply};
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::{ObjectPath, Value};
+use zbus::{connection, interface, Connection};
+
+const PATH: &str = "/org/mpris/MediaPlayer2";
+const ROOT: &str = "org.mpris.MediaPlayer2";
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+
+// the desktop sends the media keys to the player that has an org.mpris.MediaPlayer2 name on the
+// session bus, and shows what it's playing in its widgets. calls to it go out as the same
+// requests the keys send
+pub fn serve(
+    config: &MprisConfig,
+    state: &Arc<Mutex<AppState>>,
+    main_tx: &UnboundedSender<AppRequests>,
+) {
+    if !config.enabled {
+        return;
+    }
+    let (changes_tx, changes_rx) = tokio::sync::mpsc::unbounded_channel();
+    state.lock().unwrap().player.changes_tx = Some(changes_tx);
+    let state = state.clone();
+    let main_tx = main_tx.clone();
+    // zbus answers the calls on an executor of its own, this only keeps the desktop up to date
+    tokio::spawn(async move {
+        match register(&state, &main_tx).await {
+            Ok(connection) => announce(connection, changes_rx).await,
+            Err(err) => {
+                error!(
+                    "Could not take the media keys through MPRIS. Reason: {}",
+                    err
+                );
+                state.lock().unwrap().player.changes_tx = None;
+            }
+        }
+    });
+}
+
+// another splay already has the name, so this one is told apart by its pid like the spec asks
+async fn register(
+    state: &Arc<Mutex<AppState>>,
+    main_tx: &UnboundedSender<AppRequests>,
+) -> Result<Connection, DbusError> {
+    let root = Root {
+        main_tx: main_tx.clone(),
+    };
+    let player = Player {
+        state: state.clone(),
+        main_tx: main_tx.clone(),
+    };
+    let connection = connection::Builder::session()?
+        .serve_at(PATH, root)?
+        .serve_at(PATH, player)?
+        .build()
+        .await?;
+    let names = [
+        format!("{}.splay", ROOT),
+        format!("{}.splay.instance{}", ROOT, process::id()),
+    ];
+    for name in names {
+        // don't wait in line for a name that's taken
+        let reply = connection
+            .request_name_with_flags(name.as_str(), RequestNameFlags::DoNotQueue.into())
+            .await?;
+        if reply == RequestNameReply::PrimaryOwner {
+            info!("Taking the media keys as {}", name);
+            return Ok(connection);
+        }
+    }
+    Err(DbusError::NamesTaken)
+}
+
+// what's there for every player, splay can only be quit
+struct Root {
+    main_tx: UnboundedSender<AppRequests>,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    fn raise(&self) {}
+
+    fn quit(&self) {
+        let _ = self.main_tx.send(AppRequests::Quit);
+    }
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> &str {
+        "splay"
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<&str> {
+        vec!["file", "http", "https"]
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<&str> {
+        vec![
+            "audio/mpeg",
+            "audio/flac",
+            "audio/ogg",
+            "audio/wav",
+            "audio/mp4",
+            "audio/aac",
+        ]
+    }
+}
+
+// the media keys and the widgets' buttons, each one the request its key sends
+struct Player {
+    state: Arc<Mutex<AppState>>,
+    main_tx: UnboundedSender<AppRequests>,
+}
+
+impl Player {
+    fn send(&self, request: PlayerRequests) {
+        let _ = self.main_tx.send(AppRequests::PlayerRequests(request));
+    }
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn next(&self) {
+        self.send(PlayerRequests::Next);
+    }
+
+    fn previous(&self) {
+        if let Some(request) = previous(&mut self.state.lock().unwrap()) {
+            let _ = self.main_tx.send(request);
+        }
+    }
+
+    fn pause(&self) {
+        self.send(PlayerRequests::Pause);
+    }
+
+    fn play_pause(&self) {
+        let request = play_pause(&self.state.lock().unwrap());
+        let _ = self.main_tx.send(request);
+    }
+
+    fn stop(&self) {
+        self.send(PlayerRequests::Stop);
+    }
+
+    fn play(&self) {
+        let request = play(&self.state.lock().unwrap());
+        let _ = self.main_tx.send(request);
+    }
+
+    // in microseconds, like every position here
+    fn seek(&self, offset: i64) {
+        let progress = self.state.lock().unwrap().player.progress;
+        self.send(PlayerRequests::SeekTo(seek_target(progress, offset)));
+    }
+
+    // only for the song that's playing, the call may have been made before it changed
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) {
+        let curr_track = self
+            .state
+            .lock()
+            .unwrap()
+            .player
+            .curr_song
+            .as_ref()
+            .map(self::track_id);
+        if position < 0 || curr_track.as_deref() != Some(track_id.as_str()) {
+            return;
+        }
+        self.send(PlayerRequests::SeekTo(Duration::from_micros(position as u64)));
+    }
+
+    fn open_uri(&self, uri: &str) -> fdo::Result<()> {
+        let mut guard = self.state.lock().unwrap();
+        let song = match Url::parse(uri) {
+            Ok(url) if url.scheme() == "file" => url
+                .to_file_path()
+                .ok()
+                .and_then(|path| ipc::find_song(&guard, &path.to_string_lossy(), 0)),
+            _ if song::is_stream_url(uri) => Some(Song::stream(uri.to_string())),
+            _ => None,
+        };
+        match song {
+            Some(song) => {
+                guard.ui.selected_song = Some(song);
+                self.send(PlayerRequests::Start);
+                Ok(())
+            }
+            None => Err(fdo::Error::InvalidArgs(format!(
+                "There's nothing to play at {}",
+                uri
+            ))),
+        }
+    }
+
+    // sent by `announce` once a seek is done, here so the desktop knows of it
+    #[zbus(signal)]
+    async fn seeked(emitter: &SignalEmitter<'_>, position: i64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> &str {
+        playback_status(&self.state.lock().unwrap())
+    }
+
+    #[zbus(property)]
+    fn loop_status(&self) -> &str {
+        loop_status(&self.state.lock().unwrap())
+    }
+
+    // the repeat modes go around in a circle
+    #[zbus(property)]
+    fn set_loop_status(&self, loop_status: &str) {
+        let target = match loop_status {
+            "Track" => RepeatModes::ONE,
+            "Playlist" => RepeatModes::ALL,
+            _ => RepeatModes::OFF,
+        };
+        let mut repeat = self.state.lock().unwrap().player.repeat;
+        while repeat != target {
+            repeat = repeat.cycle();
+            self.send(PlayerRequests::CycleRepeat);
+        }
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        self.state.lock().unwrap().player.speed as f64
+    }
+
+    #[zbus(property)]
+    fn set_rate(&self, rate: f64) {
+        if rate > 0. {
+            self.send(PlayerRequests::SetSpeed(rate as f32));
+        }
+    }
+
+    #[zbus(property)]
+    fn shuffle(&self) -> bool {
+        self.state.lock().unwrap().player.shuffle
+    }
+
+    #[zbus(property)]
+    fn set_shuffle(&self, shuffle: bool) {
+        if shuffle != self.state.lock().unwrap().player.shuffle {
+            self.send(PlayerRequests::ToggleShuffle);
+        }
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<&str, Value<'static>> {
+        metadata(&self.state.lock().unwrap())
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        1.
+    }
+
+    // the desktop works it out from Seeked and the rate instead of being told every second
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        self.state.lock().unwrap().player.progress.as_micros() as i64
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        speed::MIN_SPEED as f64
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        speed::MAX_SPEED as f64
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+// an offset in microseconds from where the song is, which can't go before its start
+fn seek_target(progress: Duration, offset: i64) -> Duration {
+    match offset < 0 {
+        true => progress.saturating_sub(Duration::from_micros(offset.unsigned_abs())),
+        false => progress + Duration::from_micros(offset as u64),
+    }
+}
+
+fn playback_status(state: &AppState) -> &'static str {
+    match state.player.curr_state {
+        // mpris has nothing in between
+        PlayerStates::BUFFERING | PlayerStates::PLAYING => "Playing",
+        PlayerStates::PAUSED => "Paused",
+        PlayerStates::STOPPED => "Stopped",
+    }
+}
+
+fn loop_status(state: &AppState) -> &'static str {
+    match state.player.repeat {
+        RepeatModes::OFF => "None",
+        RepeatModes::ONE => "Track",
+        RepeatModes::ALL => "Playlist",
+    }
+}
+
+// what's playing, what a stream says is on taking the place of its title
+fn metadata(state: &AppState) -> HashMap<&'static str, Value<'static>> {
+    let song = match &state.player.curr_song {
+        Some(song) => song,
+        None => return HashMap::from([("mpris:trackid", object_path(NO_TRACK.to_string()))]),
+    };
+    let title = match &state.player.stream_title {
+        Some(stream_title) if song.is_stream() => stream_title,
+        _ => &song.title,
+    };
+    let url = match song.is_stream() {
+        true => song.path.to_owned(),
+        // library paths are absolute, anything else is passed on as it is
+        false => Url::from_file_path(&song.path)
+            .map_or_else(|()| song.path.to_owned(), String::from),
+    };
+    let mut entries = HashMap::from([
+        ("mpris:trackid", object_path(track_id(song))),
+        (
+            "mpris:length",
+            Value::from(song.duration_secs as i64 * 1_000_000),
+        ),
+        ("xesam:title", Value::from(title.to_owned())),
+        ("xesam:album", Value::from(song.album_title.to_owned())),
+        ("xesam:artist", Value::from(vec![song.track_artist.to_owned()])),
+        (
+            "xesam:albumArtist",
+            Value::from(vec![song.album_artist.to_owned()]),
+        ),
+        ("xesam:url", Value::from(url)),
+    ]);
+    let track_number: Option<i32> = song
+        .track_number
+        .as_deref()
+        .and_then(|number| number.split('/').next()?.trim().parse().ok());
+    if let Some(track_number) = track_number {
+        entries.insert("xesam:trackNumber", Value::from(track_number));
+    }
+    entries
+}
+
+// made up from the path and start, which tell songs apart
+fn track_id(song: &Song) -> String {
+    let mut hasher = DefaultHasher::new();
+    (&song.path, song.start_ms).hash(&mut hasher);
+    format!("/org/splay/track/{:016x}", hasher.finish())
+}
+
+// the track ids are always made up to be valid paths
+fn object_path(path: String) -> Value<'static> {
+    Value::from(ObjectPath::try_from(path).expect("track ids are object paths"))
+}
+
+// tells the desktop about each change to the player as it's made, until the connection is gone
+async fn announce(connection: Connection, mut changes_rx: UnboundedReceiver<PlayerChanges>) {
+    let player = match connection.object_server().interface::<_, Player>(PATH).await {
+        Ok(player) => player,
+        Err(err) => {
+            error!("Could not tell the desktop what's playing. Reason: {}", err);
+            return;
+        }
+    };
+    while let Some(change) = changes_rx.recv().await {
+        let emitter = player.signal_emitter();
+        let sent = match change {
+            PlayerChanges::PlaybackStatus => {
+                player.get().await.playback_status_changed(emitter).await
+            }
+            PlayerChanges::Metadata => player.get().await.metadata_changed(emitter).await,
+            PlayerChanges::LoopStatus => player.get().await.loop_status_changed(emitter).await,
+            PlayerChanges::Shuffle => player.get().await.shuffle_changed(emitter).await,
+            PlayerChanges::Rate => player.get().await.rate_changed(emitter).await,
+            PlayerChanges::Seeked(position) => {
+                Player::seeked(emitter, position.as_micros() as i64).await
+            }
+        };
+        if let Err(err) = sent {
+            error!("Lost the session bus, media keys stop working. Reason: {}", err);
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+    fn song(title: &str) -> Song {
+        Song::new(title.to_string(), format!("/music/{}.flac", title))
+    }
+
+    fn player() -> (Player, UnboundedReceiver<AppRequests>) {
+        let (main_tx, main_rx) = mpsc::unbounded_channel();
+        let player = Player {
+            state: Arc::new(Mutex::new(AppState::default())),
+            main_tx,
+        };
+        (player, main_rx)
+    }
+
+    fn sent(main_rx: &mut UnboundedReceiver<AppRequests>) -> Vec<PlayerRequests> {
+        let mut requests = vec![];
+        while let Ok(request) = main_rx.try_recv() {
+            match request {
+                AppRequests::PlayerRequests(request) => requests.push(request),
+                request => panic!("sent {:?} instead of a player request", request),
+            }
+        }
+        requests
+    }
+
+    #[test]
+    fn play_starts_a_stopped_player_and_resumes_otherwise() {
+        let (player, mut main_rx) = player();
+        player.play();
+        player.state.lock().unwrap().player.curr_state = PlayerStates::PAUSED;
+        player.play_pause();
+        player.state.lock().unwrap().player.curr_state = PlayerStates::PLAYING;
+        player.play_pause();
+        assert!(matches!(
+            sent(&mut main_rx)[..],
+            [
+                PlayerRequests::Start,
+                PlayerRequests::Resume,
+                PlayerRequests::Pause
+            ]
+        ));
+    }
+
+    #[test]
+    fn previous_restarts_a_song_that_played_a_while() {
+        let mut state = AppState::default();
+        state.player.curr_state = PlayerStates::PLAYING;
+        state.player.curr_song = Some(song("b"));
+        state.player.progress = Duration::from_secs(10);
+        state.queue.add_to_previous(song("a"));
+        assert!(matches!(
+            previous(&mut state),
+            Some(AppRequests::PlayerRequests(PlayerRequests::SeekTo(Duration::ZERO)))
+        ));
+        assert_eq!(state.queue.take_last_played().unwrap().title, "a");
+    }
+
+    #[test]
+    fn previous_goes_back_and_queues_the_song_that_was_playing() {
+        let mut state = AppState::default();
+        state.player.curr_state = PlayerStates::PLAYING;
+        state.player.curr_song = Some(song("b"));
+        state.player.progress = Duration::from_secs(1);
+        state.queue.add_to_previous(song("a"));
+        assert!(matches!(
+            previous(&mut state),
+            Some(AppRequests::PlayerRequests(PlayerRequests::Start))
+        ));
+        assert_eq!(state.ui.selected_song.as_ref().unwrap().title, "a");
+        assert_eq!(state.queue.iter().next().unwrap().title, "b");
+
+        // nothing was played before
+        assert!(previous(&mut state).is_none());
+    }
+
+    #[test]
+    fn loop_status_cycles_the_repeat_mode_around_to_it() {
+        let (player, mut main_rx) = player();
+        player.set_loop_status("Playlist");
+        let mut repeat = RepeatModes::OFF;
+        for request in sent(&mut main_rx) {
+            assert!(matches!(request, PlayerRequests::CycleRepeat));
+            repeat = repeat.cycle();
+        }
+        assert_eq!(repeat, RepeatModes::ALL);
+
+        player.set_loop_status("None");
+        assert!(sent(&mut main_rx).is_empty());
+    }
+
+    #[test]
+    fn shuffle_and_rate_only_ask_for_a_change() {
+        let (player, mut main_rx) = player();
+        player.set_shuffle(false);
+        player.set_rate(0.);
+        assert!(sent(&mut main_rx).is_empty());
+        player.set_shuffle(true);
+        player.set_rate(1.5);
+        assert!(matches!(
+            sent(&mut main_rx)[..],
+            [PlayerRequests::ToggleShuffle, PlayerRequests::SetSpeed(speed)] if speed == 1.5
+        ));
+    }
+
+    #[test]
+    fn seeks_from_where_the_song_is_but_not_before_its_start() {
+        let progress = Duration::from_secs(5);
+        assert_eq!(seek_target(progress, 2_000_000), Duration::from_secs(7));
+        assert_eq!(seek_target(progress, -2_000_000), Duration::from_secs(3));
+        assert_eq!(seek_target(progress, -9_000_000), Duration::ZERO);
+    }
+
+    #[test]
+    fn set_position_is_only_for_the_song_playing() {
+        let (player, mut main_rx) = player();
+        let playing = song("a");
+        let id = track_id(&playing);
+        player.state.lock().unwrap().player.curr_song = Some(playing);
+        let other = ObjectPath::try_from(track_id(&song("b"))).unwrap();
+        player.set_position(other, 1_000_000);
+        player.set_position(ObjectPath::try_from(id.as_str()).unwrap(), -1);
+        assert!(sent(&mut main_rx).is_empty());
+        player.set_position(ObjectPath::try_from(id.as_str()).unwrap(), 1_000_000);
+        assert!(matches!(
+            sent(&mut main_rx)[..],
+            [PlayerRequests::SeekTo(position)] if position == Duration::from_secs(1)
+        ));
+    }
+
+    #[test]
+    fn file_urls_are_escaped() {
+        let mut state = AppState::default();
+        state.player.curr_song = Some(song("a b#c"));
+        let url = metadata(&state).remove("xesam:url").unwrap();
+        assert_eq!(url, Value::from("file:///music/a%20b%23c.flac"));
+    }
+}
@@ -0,0 +1,160 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use notify_rust::Timeout;
+use tokio::sync::mpsc::UnboundedReceiver;
+#[cfg(all(unix, not(target_os = "macos")))]
+use x11rb::connection::Connection;
+#[cfg(all(unix, not(target_os = "macos")))]
+use x11rb::protocol::xproto::{AtomEnum, ConnectionExt};
+
+use crate::config::NotificationsConfig;
+use crate::library::song::Song;
+use crate::library::tag;
+use crate::metadata;
+use crate::utils::constants::requests::NotificationRequests;
+
+// what's shown on the desktop
+struct Notification {
+    summary: String,
+    body: String,
+    icon: Option<PathBuf>, // the cover art, written out to a file
+}
+
+impl Notification {
+    fn song(song: &Song) -> Notification {
+        let body: Vec<&str> = [song.track_artist.as_str(), song.album_title.as_str()]
+            .into_iter()
+            .filter(|field| !field.is_empty())
+            .collect();
+        Notification {
+            summary: song.title.to_owned(),
+            body: body.join(" - "),
+            icon: cover_file(song),
+        }
+    }
+
+    fn error(reason: String) -> Notification {
+        Notification {
+            summary: "splay".to_string(),
+            body: reason,
+            icon: None,
+        }
+    }
+
+    fn show(&self, timeout_ms: u32) -> Result<(), notify_rust::error::Error> {
+        let timeout = match timeout_ms {
+            0 => Timeout::Never,
+            timeout_ms => Timeout::Milliseconds(timeout_ms),
+        };
+        let mut notification = notify_rust::Notification::new();
+        notification
+            .appname("splay")
+            .summary(&self.summary)
+            .body(&body_text(&self.body))
+            .timeout(timeout);
+        if let Some(icon) = &self.icon {
+            notification.icon(&icon.to_string_lossy());
+        }
+        notification.show().map(|_| ())
+    }
+}
+
+// shows a notification for each song that starts playing and each error the player runs into.
+// runs until every sender is gone
+pub fn listen(mut config: NotificationsConfig, mut rx: UnboundedReceiver<NotificationRequests>) {
+    while let Some(request) = rx.blocking_recv() {
+        let notification = match request {
+            NotificationRequests::SetConfig(new_config) => {
+                config = new_config;
+                continue;
+            }
+            _ if !config.enabled => continue,
+            // it'd only repeat what's on screen
+            _ if !config.when_focused && terminal_focused() => continue,
+            NotificationRequests::SongChanged(song) => Notification::song(&song),
+            NotificationRequests::Error(reason) => Notification::error(reason),
+        };
+        if let Err(err) = notification.show(config.timeout_ms) {
+            warn!("Could not show a notification. Reason: {}", err);
+        }
+    }
+}
+
+// the cover art of the song in a file the notification can show, one for all songs since only
+// the last one is needed
+fn cover_file(song: &Song) -> Option<PathBuf> {
+    if song.is_stream() {
+        return None;
+    }
+    let data = tag::read_cover_art(&song.path).or_else(|| metadata::cached_cover(song))?;
+    let extension = image::guess_format(&data)
+        .ok()
+        .and_then(|format| format.extensions_str().first().copied())
+        .unwrap_or("jpg");
+    let user = env::var("USER").unwrap_or_default();
+    let path = env::temp_dir().join(format!("splay-cover-{}.{}", user, extension));
+    match fs::write(&path, data) {
+        Ok(()) => Some(path),
+        Err(err) => {
+            warn!(
+                "Could not write the cover art for a notification. Reason: {}",
+                err
+            );
+            None
+        }
+    }
+}
+
+// whether the terminal's window is the active one, which can only be told on x11 in terminals
+// that set $WINDOWID
+#[cfg(all(unix, not(target_os = "macos")))]
+fn terminal_focused() -> bool {
+    let window = match env::var("WINDOWID")
+        .ok()
+        .and_then(|id| id.parse::<u32>().ok())
+    {
+        Some(window) => window,
+        None => return false,
+    };
+    active_window() == Some(window)
+}
+
+// asked of the x server, which keeps it in _NET_ACTIVE_WINDOW on the root window
+#[cfg(all(unix, not(target_os = "macos")))]
+fn active_window() -> Option<u32> {
+    let (conn, screen) = x11rb::connect(None).ok()?;
+    let root = conn.setup().roots.get(screen)?.root;
+    let atom = conn
+        .intern_atom(false, b"_NET_ACTIVE_WINDOW")
+        .ok()?
+        .reply()
+        .ok()?
+        .atom;
+    let active = conn
+        .get_property(false, root, atom, AtomEnum::WINDOW, 0, 1)
+        .ok()?
+        .reply()
+        .ok()?;
+    let window = active.value32()?.next();
+    window
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn terminal_focused() -> bool {
+    false
+}
+
+// the body can have some markup where notifications go through d-bus, the summary is only text
+#[cfg(all(unix, not(target_os = "macos")))]
+fn body_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn body_text(text: &str) -> String {
+    text.to_string()
+}
@@ -0,0 +1,25 @@
+pub const MAX_BALANCE: f32 = 1.;
+
+// mix every channel down to the same signal, for listening on a single ear
+pub fn downmix_to_mono(samples: &mut [f32], channels: usize) {
+    if channels < 2 {
+        return;
+    }
+    for frame in samples.chunks_mut(channels) {
+        let mixed = frame.iter().sum::<f32>() / channels as f32;
+        frame.iter_mut().for_each(|sample| *sample = mixed);
+    }
+}
+
+// turn down one side of a stereo signal. -1 is fully left, 1 is fully right
+pub fn apply_balance(samples: &mut [f32], channels: usize, balance: f32) {
+    if channels != 2 || balance == 0. {
+        return;
+    }
+    let left_gain = (1. - balance).min(1.);
+    let right_gain = (1. + balance).min(1.);
+    for frame in samples.chunks_mut(2) {
+        frame[0] *= left_gain;
+        frame[1] *= right_gain;
+    }
+}
@@ -0,0 +1,93 @@
+use std::f32::consts::PI;
+use symphonia::core::audio::SignalSpec;
+
+use crate::utils::constants::EQ_NUM_BANDS;
+
+pub const BAND_FREQUENCIES: [f32; EQ_NUM_BANDS] = [
+    31., 62., 125., 250., 500., 1000., 2000., 4000., 8000., 16000.,
+];
+pub const MAX_GAIN_DB: f32 = 12.;
+
+// an octave wide peak around each band
+const BAND_Q: f32 = 1.41;
+
+// a peaking filter for one band, with separate history for each channel
+struct PeakingFilter {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    // the last two inputs and outputs for every channel
+    history: Vec<[f32; 4]>,
+}
+
+impl PeakingFilter {
+    fn new(frequency: f32, gain_db: f32, rate: u32, channels: usize) -> PeakingFilter {
+        let a = 10_f32.powf(gain_db / 40.);
+        let w0 = 2. * PI * frequency / rate as f32;
+        let alpha = w0.sin() / (2. * BAND_Q);
+        let a0 = 1. + alpha / a;
+        PeakingFilter {
+            b0: (1. + alpha * a) / a0,
+            b1: (-2. * w0.cos()) / a0,
+            b2: (1. - alpha * a) / a0,
+            a1: (-2. * w0.cos()) / a0,
+            a2: (1. - alpha / a) / a0,
+            history: vec![[0.; 4]; channels],
+        }
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        let channels = self.history.len();
+        for (i, sample) in samples.iter_mut().enumerate() {
+            let [x1, x2, y1, y2] = self.history[i % channels];
+            let x0 = *sample;
+            let y0 = self.b0 * x0 + self.b1 * x1 + self.b2 * x2 - self.a1 * y1 - self.a2 * y2;
+            self.history[i % channels] = [x0, x1, y0, y1];
+            *sample = y0;
+        }
+    }
+}
+
+// the EQ stage of the player, applied to interleaved samples
+pub struct Equalizer {
+    gains: [f32; EQ_NUM_BANDS],
+    spec: Option<SignalSpec>,
+    filters: Vec<PeakingFilter>,
+}
+
+impl Equalizer {
+    pub fn new() -> Equalizer {
+        Equalizer {
+            gains: [0.; EQ_NUM_BANDS],
+            spec: None,
+            filters: Vec::new(),
+        }
+    }
+
+    pub fn process(&mut self, gains: [f32; EQ_NUM_BANDS], samples: &mut [f32], spec: SignalSpec) {
+        if self.gains != gains || self.spec != Some(spec) {
+            self.gains = gains;
+            self.spec = Some(spec);
+            // flat bands and ones above what the sample rate can hold are left out
+            self.filters = BAND_FREQUENCIES
+                .iter()
+                .zip(gains.iter())
+                .filter(|(frequency, gain)| **gain != 0. && **frequency < spec.rate as f32 / 2.)
+                .map(|(frequency, gain)| {
+                    PeakingFilter::new(*frequency, *gain, spec.rate, spec.channels.count())
+                })
+                .collect();
+        }
+        for filter in self.filters.iter_mut() {
+            filter.process(samples);
+        }
+    }
+}
+
+impl Default for Equalizer {
+    fn default() -> Self {
+        Equalizer::new()
+    }
+}
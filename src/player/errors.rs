@@ -0,0 +1,27 @@
+use thiserror::Error;
+
+use crate::ipc::errors::IpcError;
+use crate::mpd::errors::MpdError;
+use crate::utils::constants::PlayerStates;
+
+#[derive(Debug, Error)]
+pub enum PlayerError {
+    #[error("Could not open {path}: {reason}")]
+    FileNotOpened { path: String, reason: String },
+    #[error("{0} is not in a supported audio format")]
+    UnsupportedFormat(String),
+    #[error("{0} has no audio track that can be played")]
+    NoTrack(String),
+    #[error("The audio codec used by {0} is not supported")]
+    UnsupportedCodec(String),
+    #[error("Could not decode {path}: {reason}")]
+    DecodeFailed { path: String, reason: String },
+    #[error("Could not play through the audio output: {0}")]
+    Output(String),
+    #[error("The player can't go from {from:?} to {to:?}")]
+    Transition { from: PlayerStates, to: PlayerStates },
+    #[error(transparent)]
+    Mpd(#[from] MpdError),
+    #[error(transparent)]
+    Ipc(#[from] IpcError),
+}
@@ -0,0 +1,66 @@
+use std::time::Duration;
+use symphonia::core::audio::SignalSpec;
+
+// a gain envelope that ramps playback in when it begins or resumes and out before it pauses or
+// stops
+pub struct Fade {
+    gain: f32,
+    target: f32,
+    length: Duration,
+}
+
+impl Fade {
+    // starts silent so that the first samples played are faded in
+    pub fn new() -> Fade {
+        Fade {
+            gain: 0.,
+            target: 1.,
+            length: Duration::ZERO,
+        }
+    }
+
+    pub fn fade_in(&mut self, length: Duration) {
+        self.fade_to(1., length);
+    }
+
+    pub fn fade_out(&mut self, length: Duration) {
+        self.fade_to(0., length);
+    }
+
+    fn fade_to(&mut self, target: f32, length: Duration) {
+        self.target = target;
+        self.length = length;
+        if length.is_zero() {
+            self.gain = target;
+        }
+    }
+
+    // whether it has finished fading out
+    pub fn is_silent(&self) -> bool {
+        self.target == 0. && self.gain == 0.
+    }
+
+    pub fn apply(&mut self, samples: &mut [f32], spec: SignalSpec) {
+        if self.gain == self.target {
+            if self.gain != 1. {
+                samples.iter_mut().for_each(|sample| *sample *= self.gain);
+            }
+            return;
+        }
+        let step = 1. / (self.length.as_secs_f32() * spec.rate as f32);
+        for frame in samples.chunks_mut(spec.channels.count()) {
+            self.gain = if self.gain < self.target {
+                (self.gain + step).min(self.target)
+            } else {
+                (self.gain - step).max(self.target)
+            };
+            frame.iter_mut().for_each(|sample| *sample *= self.gain);
+        }
+    }
+}
+
+impl Default for Fade {
+    fn default() -> Self {
+        Fade::new()
+    }
+}
@@ -1,9 +1,510 @@
+pub mod channels;
+pub mod equalizer;
+pub mod errors;
+pub mod fade;
+pub mod mpd_player;
 pub mod output;
-pub mod rodio_player;
+#[cfg(unix)]
+pub mod remote_player;
+pub mod speed;
+pub mod stream;
 pub mod symphonia_player;
-use crate::{state::AppState, utils::constants::requests::*};
-use std::sync::{mpsc::Receiver, Arc, Mutex};
+pub mod visualizer;
+pub mod waveform;
+use crate::{
+    config,
+    player::errors::PlayerError,
+    library::song::Song,
+    scrobbler,
+    state::AppState,
+    utils::constants::{requests::*, EqPresets, PlayerStates, RepeatModes, ReplayGainModes},
+};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::{Receiver, UnboundedSender};
+use tokio::task;
+use tokio::time;
 
+pub const MAX_CROSSFADE_SECS: u64 = 10;
+pub const MAX_FADE_MS: u64 = 2000;
+
+// a way of playing songs, handed the requests for it by `listen` in a task of its own. what
+// it's doing is kept in the state's curr_state, changed with `set_state`
 pub trait Player {
-    fn listen(&mut self, app_state: Arc<Mutex<AppState>>, rx: Receiver<PlayerRequests>);
+    // before the first request
+    fn attach(
+        &mut self,
+        _app_state: &Arc<Mutex<AppState>>,
+        _main_tx: &UnboundedSender<AppRequests>,
+    ) -> Result<(), PlayerError> {
+        Ok(())
+    }
+
+    // once what the request changes in the state, like the equalizer or where to seek to, was
+    // changed
+    fn handle(
+        &mut self,
+        app_state: &Arc<Mutex<AppState>>,
+        main_tx: &UnboundedSender<AppRequests>,
+        request: PlayerRequests,
+    ) -> Result<(), PlayerError>;
+
+    // how often `poll` is called while no request comes, never if none
+    fn poll_rate(&self) -> Option<Duration> {
+        None
+    }
+
+    fn poll(
+        &mut self,
+        _app_state: &Arc<Mutex<AppState>>,
+        _main_tx: &UnboundedSender<AppRequests>,
+    ) -> Result<(), PlayerError> {
+        Ok(())
+    }
+
+    // on quit, before the thread ends
+    fn detach(&mut self, _app_state: &Arc<Mutex<AppState>>) {}
+}
+
+// hand the player each request until it's told to quit, letting the app know what failed. runs
+// as a task, with the player's own calls, which block on the network or on a song stopping, run
+// in place. what's played goes out on a thread of the player's
+pub async fn listen(
+    mut player: Box<dyn Player + Send>,
+    app_state: Arc<Mutex<AppState>>,
+    mut rx: Receiver<PlayerRequests>,
+    main_tx: UnboundedSender<AppRequests>,
+) {
+    if let Err(err) = task::block_in_place(|| player.attach(&app_state, &main_tx)) {
+        report(&main_tx, err);
+    }
+    loop {
+        let request = match player.poll_rate() {
+            Some(rate) => match time::timeout(rate, rx.recv()).await {
+                Ok(Some(request)) => request,
+                Ok(None) => return,
+                Err(_) => {
+                    if let Err(err) = task::block_in_place(|| player.poll(&app_state, &main_tx)) {
+                        report(&main_tx, err);
+                    }
+                    continue;
+                }
+            },
+            None => match rx.recv().await {
+                Some(request) => request,
+                None => return,
+            },
+        };
+        if let PlayerRequests::Quit = request {
+            return task::block_in_place(|| player.detach(&app_state));
+        }
+        let request = match dedupe_start(&app_state, request) {
+            Some(request) => request,
+            None => continue,
+        };
+        change_settings(&app_state, &request);
+        if let Err(err) = task::block_in_place(|| player.handle(&app_state, &main_tx, request)) {
+            report(&main_tx, err);
+        }
+    }
+}
+
+pub fn report(main_tx: &UnboundedSender<AppRequests>, err: PlayerError) {
+    error!("{}", err);
+    let _ = main_tx.send(AppRequests::UIRequests(UIRequests::PlayerEvent(
+        PlayerEvents::Error(err.to_string()),
+    )));
+}
+
+// starting the song that's loaded again would only make it skip, so that resumes it if it's
+// paused and is left alone otherwise. Restart starts the song that's loaded over, so players
+// only ever see Start
+fn dedupe_start(
+    app_state: &Arc<Mutex<AppState>>,
+    request: PlayerRequests,
+) -> Option<PlayerRequests> {
+    let mut guard = app_state.lock().unwrap();
+    if let (PlayerRequests::Restart, Some(curr)) = (&request, &guard.player.curr_song) {
+        guard.ui.selected_song = Some(curr.clone());
+    }
+    let loaded = match (&guard.ui.selected_song, &guard.player.curr_song) {
+        (Some(selected), Some(curr)) if selected.is_same(curr) => Some(&curr.path),
+        _ => None,
+    };
+    match (request, guard.player.curr_state, loaded) {
+        (PlayerRequests::Restart, _, _) => Some(PlayerRequests::Start),
+        (PlayerRequests::Start, PlayerStates::STOPPED, _) => Some(PlayerRequests::Start),
+        (PlayerRequests::Start, PlayerStates::PAUSED, Some(_)) => Some(PlayerRequests::Resume),
+        (PlayerRequests::Start, _, Some(path)) => {
+            debug!("Not starting {} again, it's playing", path);
+            None
+        }
+        (request, _, _) => Some(request),
+    }
+}
+
+// what every player keeps in the state for itself to pick up
+fn change_settings(app_state: &Arc<Mutex<AppState>>, request: &PlayerRequests) {
+    match *request {
+        PlayerRequests::SeekRelative(secs) => request_seek(app_state, secs),
+        PlayerRequests::SeekTo(position) => request_seek_to(app_state, position),
+        PlayerRequests::SeekAbsolute(fraction) => request_seek_fraction(app_state, fraction),
+        PlayerRequests::ToggleShuffle => toggle_shuffle(app_state),
+        PlayerRequests::CycleRepeat => cycle_repeat(app_state),
+        PlayerRequests::MarkLoop => mark_loop(app_state),
+        PlayerRequests::ToggleStopAfterCurrent => toggle_stop_after_current(app_state),
+        PlayerRequests::SetCrossfade(secs) => set_crossfade(app_state, secs),
+        PlayerRequests::SetEqBand(band, gain) => set_eq_band(app_state, band, gain),
+        PlayerRequests::SetEqPreset(preset) => set_eq_preset(app_state, preset),
+        PlayerRequests::SetSpeed(speed) => set_speed(app_state, speed),
+        PlayerRequests::ToggleMono => toggle_mono(app_state),
+        PlayerRequests::SetBalance(balance) => set_balance(app_state, balance),
+        _ => (),
+    }
+}
+
+// the player going from one state to another, refused if it can't get there from where it is.
+// staying where it is is left alone
+pub fn set_state(app_state: &mut AppState, to: PlayerStates) -> Result<(), PlayerError> {
+    let from = app_state.player.curr_state;
+    if from == to {
+        return Ok(());
+    }
+    if !from.can_become(to) {
+        return Err(PlayerError::Transition { from, to });
+    }
+    app_state.player.curr_state = to;
+    app_state.player.changed(PlayerChanges::PlaybackStatus);
+    Ok(())
+}
+
+// anything can stop, so there's nothing to refuse
+pub fn set_stopped(app_state: &mut AppState) {
+    let _ = set_state(app_state, PlayerStates::STOPPED);
+}
+
+// queue up a seek relative to the current position
+pub fn request_seek(app_state: &Arc<Mutex<AppState>>, offset_secs: i64) {
+    let base = {
+        let guard = app_state.lock().unwrap();
+        guard.player.seek_to.unwrap_or(guard.player.progress)
+    };
+    let target = if offset_secs < 0 {
+        base.saturating_sub(Duration::from_secs(offset_secs.unsigned_abs()))
+    } else {
+        base + Duration::from_secs(offset_secs as u64)
+    };
+    request_seek_to(app_state, target);
+}
+
+// queue up a seek to a position in the current song, clamped to its length
+pub fn request_seek_to(app_state: &Arc<Mutex<AppState>>, mut target: Duration) {
+    let mut guard = app_state.lock().unwrap();
+    let total_secs = match &guard.player.curr_song {
+        Some(song) => song.duration_secs,
+        None => return,
+    };
+    // duration_secs is 0 when the tag didn't have a length
+    if total_secs != 0 && target > Duration::from_secs(total_secs) {
+        target = Duration::from_secs(total_secs);
+    }
+    guard.player.seek_to = Some(target);
+}
+
+// queue up a seek to a fraction of the way through the current song, eg. where the progress bar
+// was clicked
+pub fn request_seek_fraction(app_state: &Arc<Mutex<AppState>>, fraction: f64) {
+    let total_secs = match &app_state.lock().unwrap().player.curr_song {
+        Some(song) => song.duration_secs,
+        None => return,
+    };
+    let target = Duration::from_secs_f64(total_secs as f64 * fraction.clamp(0., 1.));
+    request_seek_to(app_state, target);
+}
+
+// called once the current song finishes on its own. moves it to the previously played songs and
+// makes the next queued song current, or stops the player if nothing is queued
+pub fn advance_queue(app_state: &Arc<Mutex<AppState>>) -> Option<Song> {
+    let mut guard = app_state.lock().unwrap();
+    if let Some(song) = guard.player.curr_song.take() {
+        guard.queue.add_to_previous(song);
+    }
+    let next = if guard.player.shuffle {
+        guard.queue.next_random()
+    } else {
+        guard.queue.next()
+    };
+    match &next {
+        Some(song) => guard.player.curr_song = Some(song.to_owned()),
+        None => set_stopped(&mut guard),
+    }
+    guard.player.changed(PlayerChanges::Metadata);
+    next
+}
+
+// the song to play once the current one finishes on its own, which is the same song when
+// repeating a single track
+pub fn song_after_track_end(app_state: &Arc<Mutex<AppState>>) -> Option<Song> {
+    {
+        let guard = app_state.lock().unwrap();
+        if guard.player.repeat == RepeatModes::ONE {
+            if let Some(song) = &guard.player.curr_song {
+                return Some(song.to_owned());
+            }
+        }
+    }
+    advance_queue(app_state)
+}
+
+// a song starts playing from the beginning, it hasn't been counted or scrobbled yet
+pub fn song_started(app_state: &mut AppState, main_tx: &UnboundedSender<AppRequests>) {
+    app_state.player.progress = Duration::ZERO;
+    app_state.player.play_counted = false;
+    app_state.player.scrobbled = false;
+    app_state.player.stream_title = None;
+    app_state.player.loop_start = None;
+    app_state.player.loop_end = None;
+    app_state.player.changed(PlayerChanges::Metadata);
+    if let Some(curr_song) = &app_state.player.curr_song {
+        let _ = main_tx.send(AppRequests::UIRequests(UIRequests::PlayerEvent(
+            PlayerEvents::TrackStarted(curr_song.to_owned()),
+        )));
+    }
+}
+
+// past the end of the part being repeated, back to its start
+pub fn repeat_loop(app_state: &mut AppState) {
+    let player = &mut app_state.player;
+    if let (Some(start), Some(end)) = (player.loop_start, player.loop_end) {
+        if player.progress >= end && player.seek_to.is_none() {
+            player.seek_to = Some(start);
+        }
+    }
+}
+
+// the song that's playing got to `position`, which counts it as played and scrobbles it once
+// enough of it was played
+pub fn update_progress(
+    app_state: &mut AppState,
+    main_tx: &UnboundedSender<AppRequests>,
+    position: Duration,
+    length: Duration,
+) {
+    let second = app_state.player.progress.as_secs();
+    app_state.player.progress = position;
+    if position.as_secs() != second {
+        let _ = main_tx.send(AppRequests::UIRequests(UIRequests::PlayerEvent(
+            PlayerEvents::Progress,
+        )));
+    }
+
+    let played = position.as_secs_f32() / length.as_secs_f32();
+    if !app_state.player.play_counted
+        && !length.is_zero()
+        && played >= app_state.player.play_count_threshold
+    {
+        app_state.player.play_counted = true;
+        if let Some(curr_song) = &app_state.player.curr_song {
+            let _ = main_tx.send(AppRequests::LibraryRequests(LibraryRequests::CountPlay(
+                curr_song.to_owned(),
+            )));
+        }
+    }
+
+    repeat_loop(app_state);
+
+    if !app_state.player.scrobbled && scrobbler::should_scrobble(position, length) {
+        app_state.player.scrobbled = true;
+        if let Some(curr_song) = &app_state.player.curr_song {
+            let _ = main_tx.send(AppRequests::ScrobblerRequests(ScrobblerRequests::Scrobble(
+                curr_song.to_owned(),
+            )));
+        }
+    }
+}
+
+pub fn set_play_count_threshold(app_state: &Arc<Mutex<AppState>>, percent: u8) {
+    app_state.lock().unwrap().player.play_count_threshold = percent.min(100) as f32 / 100.;
+}
+
+// a song that's playing is being replaced before it was played long enough to count
+pub fn record_skip(app_state: &Arc<Mutex<AppState>>, main_tx: &UnboundedSender<AppRequests>) {
+    let guard = app_state.lock().unwrap();
+    if guard.player.curr_state == PlayerStates::STOPPED || guard.player.play_counted {
+        return;
+    }
+    if let Some(song) = &guard.player.curr_song {
+        let _ = main_tx.send(AppRequests::LibraryRequests(LibraryRequests::CountSkip(
+            song.to_owned(),
+        )));
+    }
+}
+
+// remember where the podcast episode that's playing was left, so that it carries on from there
+// the next time it's played. it starts over once it was played to the end
+pub fn save_position(
+    app_state: &Arc<Mutex<AppState>>,
+    main_tx: &UnboundedSender<AppRequests>,
+    finished: bool,
+) {
+    let guard = app_state.lock().unwrap();
+    let song = match &guard.player.curr_song {
+        Some(song) if guard.podcasts.episode(song).is_some() => song.to_owned(),
+        _ => return,
+    };
+    let position = Some(guard.player.progress).filter(|_| !finished);
+    let _ = main_tx.send(AppRequests::LibraryRequests(LibraryRequests::SavePosition(
+        song, position,
+    )));
+}
+
+// where to start a song from, which is where it was left for a podcast episode or where the last
+// session stopped
+pub fn start_position(app_state: &mut AppState) -> Option<Duration> {
+    let resume_at = app_state.player.resume_at.take();
+    let song = app_state.player.curr_song.as_ref()?;
+    resume_at.or_else(|| app_state.podcasts.position(song))
+}
+
+// stops the player instead of moving on when the current song was set to be the last one
+pub fn stop_after_track_end(app_state: &Arc<Mutex<AppState>>) -> bool {
+    let mut guard = app_state.lock().unwrap();
+    if !guard.player.stop_after_current {
+        return false;
+    }
+    guard.player.stop_after_current = false;
+    set_stopped(&mut guard);
+    if let Some(song) = guard.player.curr_song.take() {
+        guard.queue.add_to_previous(song);
+    }
+    true
+}
+
+pub fn toggle_stop_after_current(app_state: &Arc<Mutex<AppState>>) {
+    let mut guard = app_state.lock().unwrap();
+    guard.player.stop_after_current = !guard.player.stop_after_current;
+}
+
+pub fn toggle_shuffle(app_state: &Arc<Mutex<AppState>>) {
+    let mut guard = app_state.lock().unwrap();
+    guard.player.shuffle = !guard.player.shuffle;
+    guard.player.changed(PlayerChanges::Shuffle);
+}
+
+pub fn cycle_repeat(app_state: &Arc<Mutex<AppState>>) {
+    let mut guard = app_state.lock().unwrap();
+    guard.player.repeat = guard.player.repeat.cycle();
+    guard.player.changed(PlayerChanges::LoopStatus);
+}
+
+// the first mark starts the part of the song to repeat and the second ends it, a third goes back
+// to playing the whole song. an end before the start moves the start there instead
+pub fn mark_loop(app_state: &Arc<Mutex<AppState>>) {
+    let mut guard = app_state.lock().unwrap();
+    let player = &mut guard.player;
+    if player.curr_song.is_none() {
+        return;
+    }
+    match (player.loop_start, player.loop_end) {
+        (Some(start), None) if player.progress > start => player.loop_end = Some(player.progress),
+        (_, None) => player.loop_start = Some(player.progress),
+        (_, Some(_)) => {
+            player.loop_start = None;
+            player.loop_end = None;
+        }
+    }
+}
+
+pub fn set_speed(app_state: &Arc<Mutex<AppState>>, speed: f32) {
+    let mut guard = app_state.lock().unwrap();
+    guard.player.speed = speed.clamp(speed::MIN_SPEED, speed::MAX_SPEED);
+    guard.player.changed(PlayerChanges::Rate);
+}
+
+pub fn toggle_mono(app_state: &Arc<Mutex<AppState>>) {
+    let mut guard = app_state.lock().unwrap();
+    guard.player.mono = !guard.player.mono;
+}
+
+pub fn set_balance(app_state: &Arc<Mutex<AppState>>, balance: f32) {
+    app_state.lock().unwrap().player.balance =
+        balance.clamp(-channels::MAX_BALANCE, channels::MAX_BALANCE);
+}
+
+pub fn set_crossfade(app_state: &Arc<Mutex<AppState>>, secs: u64) {
+    app_state.lock().unwrap().player.crossfade = Duration::from_secs(secs.min(MAX_CROSSFADE_SECS));
+}
+
+pub fn set_fade(app_state: &Arc<Mutex<AppState>>, millis: u64) {
+    app_state.lock().unwrap().player.fade = Duration::from_millis(millis.min(MAX_FADE_MS));
+}
+
+// linear volume factor for a song given the ReplayGain mode and pre-amp. album gain falls back to
+// the track gain for songs that only have the latter
+pub fn replay_gain_factor(app_state: &AppState, song: &Song) -> f32 {
+    let gain_db = match app_state.player.replay_gain {
+        ReplayGainModes::OFF => return 1.,
+        ReplayGainModes::TRACK => song.track_gain,
+        ReplayGainModes::ALBUM => song.album_gain.or(song.track_gain),
+    };
+    let gain_db = gain_db.unwrap_or(0.) + app_state.player.preamp_db;
+    10_f32.powf(gain_db / 20.)
+}
+
+pub fn set_eq_band(app_state: &Arc<Mutex<AppState>>, band: usize, gain_db: f32) {
+    let mut guard = app_state.lock().unwrap();
+    if band >= guard.player.eq_bands.len() {
+        return;
+    }
+    guard.player.eq_bands[band] = gain_db.clamp(-equalizer::MAX_GAIN_DB, equalizer::MAX_GAIN_DB);
+    guard.player.eq_preset = EqPresets::CUSTOM;
+    save_equalizer(&guard);
+}
+
+pub fn set_eq_preset(app_state: &Arc<Mutex<AppState>>, preset: EqPresets) {
+    let mut guard = app_state.lock().unwrap();
+    // custom keeps whatever the bands were last set to
+    if preset != EqPresets::CUSTOM {
+        guard.player.eq_bands = preset.gains();
+    }
+    guard.player.eq_preset = preset;
+    save_equalizer(&guard);
+}
+
+fn save_equalizer(app_state: &AppState) {
+    if let Err(err) = config::save_equalizer(app_state.player.eq_preset, &app_state.player.eq_bands) {
+        error!("Could not save equalizer settings. Reason: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_a_started_song_plays_or_pauses() {
+        let mut app_state = AppState::default();
+        assert!(set_state(&mut app_state, PlayerStates::PLAYING).is_err());
+        assert!(set_state(&mut app_state, PlayerStates::PAUSED).is_err());
+        assert_eq!(app_state.player.curr_state, PlayerStates::STOPPED);
+
+        set_state(&mut app_state, PlayerStates::BUFFERING).unwrap();
+        set_state(&mut app_state, PlayerStates::PLAYING).unwrap();
+        set_state(&mut app_state, PlayerStates::PAUSED).unwrap();
+        set_state(&mut app_state, PlayerStates::PLAYING).unwrap();
+        // the next song
+        set_state(&mut app_state, PlayerStates::BUFFERING).unwrap();
+        set_stopped(&mut app_state);
+        assert_eq!(app_state.player.curr_state, PlayerStates::STOPPED);
+    }
+
+    #[test]
+    fn staying_in_a_state_is_not_refused() {
+        let mut app_state = AppState::default();
+        set_state(&mut app_state, PlayerStates::STOPPED).unwrap();
+        set_state(&mut app_state, PlayerStates::BUFFERING).unwrap();
+        set_state(&mut app_state, PlayerStates::PAUSED).unwrap();
+        set_state(&mut app_state, PlayerStates::PAUSED).unwrap();
+        assert_eq!(app_state.player.curr_state, PlayerStates::PAUSED);
+    }
 }
@@ -0,0 +1,245 @@
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::UnboundedSender;
+use std::time::Duration;
+
+use crate::config::MpdConfig;
+use crate::library::song::Song;
+use crate::mpd::errors::MpdError;
+use crate::mpd::{MpdClient, MpdStatus};
+use crate::player::PlayerRequests;
+use crate::state::AppState;
+use crate::utils::constants::requests::{AppRequests, PlayerChanges, PlayerEvents, UIRequests};
+use crate::utils::constants::{PlayerStates, ReplayGainModes};
+
+use super::errors::PlayerError;
+use super::{
+    advance_queue, record_skip, save_position, set_state, set_stopped, song_after_track_end, song_started,
+    start_position, stop_after_track_end, update_progress, Player,
+};
+
+// how often the server is asked how far into the song it is
+const POLL_RATE: Duration = Duration::from_millis(500);
+
+// has an mpd server play the songs instead of decoding them here. the queue stays splay's, the
+// server is only ever given the song that's playing and is asked when it's done with it. the
+// equalizer, speed, mono and balance are the server's own business and aren't sent to it
+pub struct MpdPlayer {
+    config: MpdConfig,
+    // connected the first time a command is sent, and again after the connection is lost
+    client: Option<MpdClient>,
+    // the server was told to play the current song, so it stopping means the song ended
+    started: bool,
+}
+
+impl MpdPlayer {
+    pub fn new(config: MpdConfig) -> MpdPlayer {
+        MpdPlayer {
+            config,
+            client: None,
+            started: false,
+        }
+    }
+
+    // the connection is dropped when a command fails, since it may be why. the next command
+    // connects again
+    fn command(&mut self, command: &str, args: &[&str]) -> Result<(), MpdError> {
+        let result = self.client().and_then(|client| client.command(command, args));
+        if result.is_err() {
+            self.client = None;
+        }
+        result.map(|_| ())
+    }
+
+    fn status(&mut self) -> Result<MpdStatus, MpdError> {
+        let result = self.client().and_then(MpdClient::status);
+        if result.is_err() {
+            self.client = None;
+        }
+        result
+    }
+
+    fn client(&mut self) -> Result<&mut MpdClient, MpdError> {
+        if self.client.is_none() {
+            self.client = Some(MpdClient::connect(&self.config)?);
+        }
+        Ok(self.client.as_mut().unwrap())
+    }
+
+    fn play(
+        &mut self,
+        app_state: &Arc<Mutex<AppState>>,
+        main_tx: &UnboundedSender<AppRequests>,
+        song: Song,
+    ) -> Result<(), PlayerError> {
+        let (replay_gain, resume_at) = {
+            let mut guard = app_state.lock().unwrap();
+            set_state(&mut guard, PlayerStates::BUFFERING)?;
+            guard.player.curr_song = Some(song.to_owned());
+            let resume_at = start_position(&mut guard);
+            song_started(&mut guard, main_tx);
+            (guard.player.replay_gain, resume_at)
+        };
+        let replay_gain = match replay_gain {
+            ReplayGainModes::OFF => "off",
+            ReplayGainModes::TRACK => "track",
+            ReplayGainModes::ALBUM => "album",
+        };
+        let result = self
+            .command("clear", &[])
+            .and_then(|_| self.command("add", &[&song.path]))
+            .and_then(|_| self.command("replay_gain_mode", &[replay_gain]))
+            .and_then(|_| self.command("play", &[]));
+        if let Err(err) = result {
+            self.started = false;
+            set_stopped(&mut app_state.lock().unwrap());
+            return Err(err.into());
+        }
+        // playing once the server says so
+        self.started = true;
+        if let Some(position) = resume_at {
+            app_state.lock().unwrap().player.seek_to = Some(position);
+            self.seek(app_state)?;
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self, app_state: &Arc<Mutex<AppState>>) -> Result<(), PlayerError> {
+        self.started = false;
+        set_stopped(&mut app_state.lock().unwrap());
+        Ok(self.command("stop", &[])?)
+    }
+
+    fn set_paused(
+        &mut self,
+        app_state: &Arc<Mutex<AppState>>,
+        paused: bool,
+    ) -> Result<(), PlayerError> {
+        let curr_state = app_state.lock().unwrap().player.curr_state;
+        let new_state = match (curr_state, paused) {
+            (PlayerStates::BUFFERING | PlayerStates::PLAYING, true) => PlayerStates::PAUSED,
+            (PlayerStates::PAUSED, false) => PlayerStates::PLAYING,
+            _ => return Ok(()),
+        };
+        self.command("pause", &[if paused { "1" } else { "0" }])?;
+        set_state(&mut app_state.lock().unwrap(), new_state)
+    }
+
+    // send the seek the other requests queued up
+    fn seek(&mut self, app_state: &Arc<Mutex<AppState>>) -> Result<(), PlayerError> {
+        let target = match app_state.lock().unwrap().player.seek_to.take() {
+            Some(target) => target,
+            None => return Ok(()),
+        };
+        if !self.started {
+            return Ok(());
+        }
+        self.command("seekcur", &[&format!("{:.3}", target.as_secs_f64())])?;
+        let mut guard = app_state.lock().unwrap();
+        guard.player.progress = target;
+        guard.player.changed(PlayerChanges::Seeked(target));
+        Ok(())
+    }
+}
+
+impl Player for MpdPlayer {
+    fn handle(
+        &mut self,
+        app_state: &Arc<Mutex<AppState>>,
+        main_tx: &UnboundedSender<AppRequests>,
+        request: PlayerRequests,
+    ) -> Result<(), PlayerError> {
+        match request {
+            PlayerRequests::Stop => {
+                save_position(app_state, main_tx, false);
+                self.stop(app_state)?;
+                let mut guard = app_state.lock().unwrap();
+                guard.player.curr_song = None;
+                guard.player.changed(PlayerChanges::Metadata);
+            }
+            PlayerRequests::Pause => self.set_paused(app_state, true)?,
+            PlayerRequests::Resume => self.set_paused(app_state, false)?,
+            PlayerRequests::PlayPause => {
+                let paused = app_state.lock().unwrap().player.curr_state == PlayerStates::PAUSED;
+                self.set_paused(app_state, !paused)?;
+            }
+            PlayerRequests::SeekRelative(_)
+            | PlayerRequests::SeekTo(_)
+            | PlayerRequests::SeekAbsolute(_) => self.seek(app_state)?,
+            PlayerRequests::SetCrossfade(_) => {
+                let secs = app_state.lock().unwrap().player.crossfade.as_secs();
+                self.command("crossfade", &[&secs.to_string()])?;
+            }
+            PlayerRequests::Start => {
+                record_skip(app_state, main_tx);
+                save_position(app_state, main_tx, false);
+                let song = app_state.lock().unwrap().ui.selected_song.to_owned();
+                if let Some(song) = song {
+                    self.play(app_state, main_tx, song)?;
+                }
+            }
+            PlayerRequests::Next => {
+                record_skip(app_state, main_tx);
+                save_position(app_state, main_tx, false);
+                match advance_queue(app_state) {
+                    Some(song) => self.play(app_state, main_tx, song)?,
+                    None => self.stop(app_state)?,
+                }
+            }
+            // the equalizer and the rest are the server's own business
+            _ => (),
+        }
+        Ok(())
+    }
+
+    // the server is asked how it's doing in between
+    fn poll_rate(&self) -> Option<Duration> {
+        Some(POLL_RATE)
+    }
+
+    // keep up with the server, which also moves on its own once a song ends or when another
+    // client pauses it
+    fn poll(
+        &mut self,
+        app_state: &Arc<Mutex<AppState>>,
+        main_tx: &UnboundedSender<AppRequests>,
+    ) -> Result<(), PlayerError> {
+        if !self.started {
+            return Ok(());
+        }
+        let status = self.status()?;
+        if status.state != PlayerStates::STOPPED {
+            let mut guard = app_state.lock().unwrap();
+            if guard.player.curr_state != PlayerStates::STOPPED {
+                set_state(&mut guard, status.state)?;
+            }
+            let length = Duration::from_secs(
+                guard
+                    .player
+                    .curr_song
+                    .as_ref()
+                    .map_or(0, |song| song.duration_secs),
+            );
+            update_progress(&mut guard, main_tx, status.elapsed, length);
+            drop(guard);
+            // back to the start of the part being repeated once it's played
+            return self.seek(app_state);
+        }
+
+        // the song ended
+        self.started = false;
+        save_position(app_state, main_tx, true);
+        if stop_after_track_end(app_state) {
+            return Ok(());
+        }
+        match song_after_track_end(app_state) {
+            Some(next) => self.play(app_state, main_tx, next)?,
+            None => {
+                // let the app decide what should play next
+                let _ = main_tx.send(AppRequests::UIRequests(UIRequests::PlayerEvent(
+                    PlayerEvents::TrackEnded,
+                )));
+            }
+        }
+        Ok(())
+    }
+}
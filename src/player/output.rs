@@ -1,10 +1,18 @@
 use std::result;
-use symphonia::core::audio::{AudioBufferRef, SignalSpec};
-use symphonia::core::units::Duration;
+use std::time::Duration;
+use symphonia::core::audio::SignalSpec;
 
+use crate::utils::constants::AudioBackends;
+
+// a device that plays interleaved f32 samples. writes block once enough audio is buffered so
+// that the player is paced by the output
 pub trait AudioOutput {
-    fn write(&mut self, decoded: AudioBufferRef<'_>) -> Result<()>;
+    fn write(&mut self, samples: &[f32]) -> Result<()>;
     fn flush(&mut self);
+    // wait for the buffered samples to be played
+    fn drain(&mut self);
+    fn pause(&mut self);
+    fn resume(&mut self);
 }
 
 #[allow(dead_code)]
@@ -19,12 +27,16 @@ pub enum AudioOutputError {
 // redefine Result to have an implied AudioOutputError
 pub type Result<T> = result::Result<T, AudioOutputError>;
 
+// how long to wait for buffered samples to be played before giving up on them
+const MAX_DRAIN_TIME: Duration = Duration::from_millis(500);
+const DRAIN_POLL_RATE: Duration = Duration::from_millis(5);
+
 mod cpal {
-    use super::{AudioOutput, AudioOutputError, Result};
+    use super::{AudioOutput, AudioOutputError, Result, DRAIN_POLL_RATE, MAX_DRAIN_TIME};
 
-    use symphonia::core::audio::{AudioBufferRef, RawSample, SampleBuffer, SignalSpec};
-    use symphonia::core::conv::ConvertibleSample;
-    use symphonia::core::units::Duration;
+    use std::thread;
+    use std::time::Instant;
+    use symphonia::core::audio::SignalSpec;
 
     use cpal;
     use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
@@ -34,17 +46,14 @@ mod cpal {
 
     pub struct CpalAudioOutput;
 
-    trait AudioOutputSample:
-        cpal::Sample + ConvertibleSample + RawSample + std::marker::Send + 'static
-    {
-    }
+    trait AudioOutputSample: cpal::Sample + Default + std::marker::Send + 'static {}
 
     impl AudioOutputSample for f32 {}
     impl AudioOutputSample for i16 {}
     impl AudioOutputSample for u16 {}
 
     impl CpalAudioOutput {
-        pub fn try_open(spec: SignalSpec, duration: Duration) -> Result<Box<dyn AudioOutput>> {
+        pub fn try_open(spec: SignalSpec) -> Result<Box<dyn AudioOutput>> {
             // Get default host.
             let host = cpal::default_host();
 
@@ -67,15 +76,9 @@ mod cpal {
 
             // Select proper playback routine based on sample format.
             match config.sample_format() {
-                cpal::SampleFormat::F32 => {
-                    CpalAudioOutputImpl::<f32>::try_open(spec, duration, &device)
-                }
-                cpal::SampleFormat::I16 => {
-                    CpalAudioOutputImpl::<i16>::try_open(spec, duration, &device)
-                }
-                cpal::SampleFormat::U16 => {
-                    CpalAudioOutputImpl::<u16>::try_open(spec, duration, &device)
-                }
+                cpal::SampleFormat::F32 => CpalAudioOutputImpl::<f32>::try_open(spec, &device),
+                cpal::SampleFormat::I16 => CpalAudioOutputImpl::<i16>::try_open(spec, &device),
+                cpal::SampleFormat::U16 => CpalAudioOutputImpl::<u16>::try_open(spec, &device),
             }
         }
     }
@@ -84,17 +87,14 @@ mod cpal {
     where
         T: AudioOutputSample,
     {
+        ring_buf: SpscRb<T>,
         ring_buf_producer: rb::Producer<T>,
-        sample_buf: SampleBuffer<T>,
+        sample_buf: Vec<T>,
         stream: cpal::Stream,
     }
 
     impl<T: AudioOutputSample> CpalAudioOutputImpl<T> {
-        pub fn try_open(
-            spec: SignalSpec,
-            duration: Duration,
-            device: &cpal::Device,
-        ) -> Result<Box<dyn AudioOutput>> {
+        pub fn try_open(spec: SignalSpec, device: &cpal::Device) -> Result<Box<dyn AudioOutput>> {
             let num_channels = spec.channels.count();
 
             // Output audio stream config.
@@ -117,18 +117,19 @@ mod cpal {
                     // output.
                     let written = ring_buf_consumer.read(data).unwrap_or(0);
                     // Mute any remaining samples.
-                    data[written..].iter_mut().for_each(|s| *s = T::MID);
+                    data[written..].iter_mut().for_each(|s| *s = T::from(&0.0f32));
                 },
                 move |err| error!("audio output error: {}", err),
             );
 
-            if let Err(err) = stream_result {
-                error!("audio output stream open error: {}", err);
-
-                return Err(AudioOutputError::OpenStreamError);
-            }
+            let stream = match stream_result {
+                Ok(stream) => stream,
+                Err(err) => {
+                    error!("audio output stream open error: {}", err);
 
-            let stream = stream_result.unwrap();
+                    return Err(AudioOutputError::OpenStreamError);
+                }
+            };
 
             // Start the output stream.
             if let Err(err) = stream.play() {
@@ -137,29 +138,28 @@ mod cpal {
                 return Err(AudioOutputError::PlayStreamError);
             }
 
-            let sample_buf = SampleBuffer::<T>::new(duration, spec);
-
             Ok(Box::new(CpalAudioOutputImpl {
+                ring_buf,
                 ring_buf_producer,
-                sample_buf,
+                sample_buf: Vec::new(),
                 stream,
             }))
         }
     }
 
     impl<T: AudioOutputSample> AudioOutput for CpalAudioOutputImpl<T> {
-        fn write(&mut self, decoded: AudioBufferRef<'_>) -> Result<()> {
+        fn write(&mut self, samples: &[f32]) -> Result<()> {
             // Do nothing if there are no audio frames.
-            if decoded.frames() == 0 {
+            if samples.is_empty() {
                 return Ok(());
             }
 
-            // Audio samples must be interleaved for cpal. Interleave the samples in the audio
-            // buffer into the sample buffer.
-            self.sample_buf.copy_interleaved_ref(decoded);
+            // Convert the samples into the device's sample format.
+            self.sample_buf.clear();
+            self.sample_buf.extend(samples.iter().map(T::from));
 
-            // Write all the interleaved samples to the ring buffer.
-            let mut samples = self.sample_buf.samples();
+            // Write all the samples to the ring buffer.
+            let mut samples = self.sample_buf.as_slice();
 
             while let Some(written) = self.ring_buf_producer.write_blocking(samples) {
                 samples = &samples[written..];
@@ -172,9 +172,408 @@ mod cpal {
             // Flush is best-effort, ignore the returned result.
             let _ = self.stream.pause();
         }
+
+        fn drain(&mut self) {
+            let start = Instant::now();
+            while !self.ring_buf.is_empty() && start.elapsed() < MAX_DRAIN_TIME {
+                thread::sleep(DRAIN_POLL_RATE);
+            }
+        }
+
+        fn pause(&mut self) {
+            let _ = self.stream.pause();
+        }
+
+        fn resume(&mut self) {
+            let _ = self.stream.play();
+        }
+    }
+}
+
+mod rodio {
+    use super::{AudioOutput, AudioOutputError, Result, DRAIN_POLL_RATE, MAX_DRAIN_TIME};
+
+    use rodio::buffer::SamplesBuffer;
+    use rodio::{OutputStream, Sink};
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use symphonia::core::audio::SignalSpec;
+
+    use log::error;
+
+    // how many decoded buffers may wait in the sink before writes block
+    const MAX_QUEUED_BUFFERS: usize = 8;
+
+    pub struct RodioAudioOutput {
+        // the sink stops playing once the stream is dropped
+        _stream: OutputStream,
+        sink: Sink,
+        channels: u16,
+        sample_rate: u32,
+    }
+
+    impl RodioAudioOutput {
+        pub fn try_open(spec: SignalSpec) -> Result<Box<dyn AudioOutput>> {
+            let (stream, stream_handle) = match OutputStream::try_default() {
+                Ok(output) => output,
+                Err(err) => {
+                    error!("failed to open default audio output stream: {}", err);
+                    return Err(AudioOutputError::OpenStreamError);
+                }
+            };
+
+            let sink = match Sink::try_new(&stream_handle) {
+                Ok(sink) => sink,
+                Err(err) => {
+                    error!("audio output stream play error: {}", err);
+                    return Err(AudioOutputError::PlayStreamError);
+                }
+            };
+
+            Ok(Box::new(RodioAudioOutput {
+                _stream: stream,
+                sink,
+                channels: spec.channels.count() as u16,
+                sample_rate: spec.rate,
+            }))
+        }
+    }
+
+    impl AudioOutput for RodioAudioOutput {
+        fn write(&mut self, samples: &[f32]) -> Result<()> {
+            if samples.is_empty() {
+                return Ok(());
+            }
+
+            // rodio has no blocking write, so wait for the sink to work through its backlog
+            while self.sink.len() >= MAX_QUEUED_BUFFERS {
+                thread::sleep(Duration::from_millis(5));
+            }
+
+            self.sink.append(SamplesBuffer::new(
+                self.channels,
+                self.sample_rate,
+                samples.to_vec(),
+            ));
+            Ok(())
+        }
+
+        fn flush(&mut self) {
+            self.sink.stop();
+        }
+
+        fn drain(&mut self) {
+            let start = Instant::now();
+            while !self.sink.empty() && start.elapsed() < MAX_DRAIN_TIME {
+                thread::sleep(DRAIN_POLL_RATE);
+            }
+        }
+
+        fn pause(&mut self) {
+            self.sink.pause();
+        }
+
+        fn resume(&mut self) {
+            self.sink.play();
+        }
     }
 }
 
-pub fn try_open(spec: SignalSpec, duration: Duration) -> Result<Box<dyn AudioOutput>> {
-    cpal::CpalAudioOutput::try_open(spec, duration)
+#[cfg(all(target_os = "linux", feature = "pipewire"))]
+mod pipewire {
+    use super::{AudioOutput, AudioOutputError, Result, DRAIN_POLL_RATE, MAX_DRAIN_TIME};
+
+    use std::io::Cursor;
+    use std::mem;
+    use std::result;
+    use std::sync::mpsc;
+    use std::thread::{self, JoinHandle};
+    use std::time::Instant;
+    use symphonia::core::audio::{Channels, SignalSpec};
+
+    use pipewire as pw;
+    use pw::properties::properties;
+    use pw::spa;
+    use pw::stream::{StreamFlags, StreamListener, StreamRc};
+    use rb::*;
+    use spa::param::audio::{AudioFormat, AudioInfoRaw, MAX_CHANNELS};
+    use spa::pod::serialize::PodSerializer;
+    use spa::pod::{Object, Pod, Value};
+
+    use log::error;
+
+    // pipewire's positions for the channels symphonia knows of, in the order samples come in
+    const POSITIONS: [(Channels, u32); 9] = [
+        (Channels::FRONT_LEFT, spa::sys::SPA_AUDIO_CHANNEL_FL),
+        (Channels::FRONT_RIGHT, spa::sys::SPA_AUDIO_CHANNEL_FR),
+        (Channels::FRONT_CENTRE, spa::sys::SPA_AUDIO_CHANNEL_FC),
+        (Channels::LFE1, spa::sys::SPA_AUDIO_CHANNEL_LFE),
+        (Channels::REAR_LEFT, spa::sys::SPA_AUDIO_CHANNEL_RL),
+        (Channels::REAR_RIGHT, spa::sys::SPA_AUDIO_CHANNEL_RR),
+        (Channels::REAR_CENTRE, spa::sys::SPA_AUDIO_CHANNEL_RC),
+        (Channels::SIDE_LEFT, spa::sys::SPA_AUDIO_CHANNEL_SL),
+        (Channels::SIDE_RIGHT, spa::sys::SPA_AUDIO_CHANNEL_SR),
+    ];
+
+    // what the player asks of the thread running the stream, which everything of pipewire's
+    // has to stay on
+    enum Control {
+        Pause,
+        Resume,
+        Quit,
+    }
+
+    // what the stream plays from each time it wants more
+    struct Playback {
+        ring_buf_consumer: rb::Consumer<f32>,
+        samples: Vec<f32>,
+        channels: usize,
+    }
+
+    pub struct PipeWireAudioOutput {
+        ring_buf: SpscRb<f32>,
+        ring_buf_producer: rb::Producer<f32>,
+        control: pw::channel::Sender<Control>,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl PipeWireAudioOutput {
+        pub fn try_open(spec: SignalSpec) -> Result<Box<dyn AudioOutput>> {
+            let num_channels = spec.channels.count();
+
+            // Create a ring buffer with a capacity for up-to 200ms of audio.
+            let ring_len = ((200 * spec.rate as usize) / 1000) * num_channels;
+
+            let ring_buf = SpscRb::new(ring_len);
+            let (ring_buf_producer, ring_buf_consumer) = (ring_buf.producer(), ring_buf.consumer());
+            let playback = Playback {
+                ring_buf_consumer,
+                samples: Vec::new(),
+                channels: num_channels,
+            };
+
+            // only whether the stream could be connected comes back from its thread
+            let (control, control_rx) = pw::channel::channel();
+            let (opened_tx, opened_rx) = mpsc::sync_channel(1);
+            let thread = thread::Builder::new()
+                .name("pipewire".to_string())
+                .spawn(move || run(spec, playback, control_rx, opened_tx));
+            let thread = match thread {
+                Ok(thread) => thread,
+                Err(err) => {
+                    error!("failed to start the pipewire thread: {}", err);
+                    return Err(AudioOutputError::OpenStreamError);
+                }
+            };
+            match opened_rx.recv() {
+                Ok(Ok(())) => (),
+                Ok(Err(err)) => {
+                    error!("pipewire stream open error: {}", err);
+                    let _ = thread.join();
+                    return Err(AudioOutputError::OpenStreamError);
+                }
+                Err(_) => {
+                    error!("the pipewire thread stopped before the stream was open");
+                    return Err(AudioOutputError::OpenStreamError);
+                }
+            }
+
+            Ok(Box::new(PipeWireAudioOutput {
+                ring_buf,
+                ring_buf_producer,
+                control,
+                thread: Some(thread),
+            }))
+        }
+    }
+
+    // connects the stream and plays it until asked to quit
+    fn run(
+        spec: SignalSpec,
+        playback: Playback,
+        control: pw::channel::Receiver<Control>,
+        opened: mpsc::SyncSender<result::Result<(), String>>,
+    ) {
+        let (main_loop, stream, _listener) = match connect(spec, playback) {
+            Ok(connected) => connected,
+            Err(err) => {
+                let _ = opened.send(Err(err));
+                return;
+            }
+        };
+        let quit = main_loop.clone();
+        let _control = control.attach(main_loop.loop_(), move |control| {
+            let result = match control {
+                Control::Pause => stream.set_active(false),
+                Control::Resume => stream.set_active(true),
+                Control::Quit => return quit.quit(),
+            };
+            if let Err(err) = result {
+                error!("pipewire stream error: {}", err);
+            }
+        });
+        let _ = opened.send(Ok(()));
+        main_loop.run();
+    }
+
+    fn connect(
+        spec: SignalSpec,
+        playback: Playback,
+    ) -> result::Result<(pw::main_loop::MainLoopRc, StreamRc, StreamListener<Playback>), String>
+    {
+        pw::init();
+        let main_loop = pw::main_loop::MainLoopRc::new(None).map_err(|err| err.to_string())?;
+        let context = pw::context::ContextRc::new(&main_loop, None).map_err(|err| err.to_string())?;
+        let core = context.connect_rc(None).map_err(|err| err.to_string())?;
+        let properties = properties! {
+            *pw::keys::MEDIA_TYPE => "Audio",
+            *pw::keys::MEDIA_CATEGORY => "Playback",
+            *pw::keys::MEDIA_ROLE => "Music",
+            *pw::keys::APP_NAME => "splay",
+        };
+        let stream = StreamRc::new(core, "splay", properties).map_err(|err| err.to_string())?;
+
+        let listener = stream
+            .add_local_listener_with_user_data(playback)
+            .process(|stream, playback| {
+                let mut buffer = match stream.dequeue_buffer() {
+                    Some(buffer) => buffer,
+                    None => return,
+                };
+                let data = match buffer.datas_mut().first_mut() {
+                    Some(data) => data,
+                    None => return,
+                };
+                let stride = playback.channels * mem::size_of::<f32>();
+                let frames = match data.data() {
+                    Some(bytes) => {
+                        let frames = bytes.len() / stride;
+                        playback.samples.resize(frames * playback.channels, 0.);
+                        // Write out as many samples as possible from the ring buffer to the audio
+                        // output.
+                        let written =
+                            playback.ring_buf_consumer.read(&mut playback.samples).unwrap_or(0);
+                        // Mute any remaining samples.
+                        playback.samples[written..].iter_mut().for_each(|s| *s = 0.);
+                        for (bytes, sample) in bytes.chunks_exact_mut(4).zip(&playback.samples) {
+                            bytes.copy_from_slice(&sample.to_le_bytes());
+                        }
+                        frames
+                    }
+                    None => 0,
+                };
+                let chunk = data.chunk_mut();
+                *chunk.offset_mut() = 0;
+                *chunk.stride_mut() = stride as i32;
+                *chunk.size_mut() = (frames * stride) as u32;
+            })
+            .register()
+            .map_err(|err| err.to_string())?;
+
+        let mut audio_info = AudioInfoRaw::new();
+        audio_info.set_format(AudioFormat::F32LE);
+        audio_info.set_rate(spec.rate);
+        audio_info.set_channels(spec.channels.count() as u32);
+        audio_info.set_position(positions(spec.channels));
+        let format = PodSerializer::serialize(
+            Cursor::new(Vec::new()),
+            &Value::Object(Object {
+                type_: spa::sys::SPA_TYPE_OBJECT_Format,
+                id: spa::sys::SPA_PARAM_EnumFormat,
+                properties: audio_info.into(),
+            }),
+        )
+        .map_err(|err| format!("{:?}", err))?
+        .0
+        .into_inner();
+        let format = Pod::from_bytes(&format).ok_or("the stream's format isn't valid")?;
+
+        stream
+            .connect(
+                spa::utils::Direction::Output,
+                None,
+                StreamFlags::AUTOCONNECT | StreamFlags::MAP_BUFFERS | StreamFlags::RT_PROCESS,
+                &mut [format],
+            )
+            .map_err(|err| err.to_string())?;
+        Ok((main_loop, stream, listener))
+    }
+
+    // mono has a position of its own. a layout with channels pipewire has no position for is
+    // left for it to place
+    fn positions(channels: Channels) -> [u32; MAX_CHANNELS] {
+        let mut positions = [0; MAX_CHANNELS];
+        if channels.count() == 1 {
+            positions[0] = spa::sys::SPA_AUDIO_CHANNEL_MONO;
+            return positions;
+        }
+        for (idx, channel) in channels.iter().enumerate() {
+            match POSITIONS.iter().find(|(known, _)| *known == channel) {
+                Some((_, position)) => positions[idx] = *position,
+                None => return [0; MAX_CHANNELS],
+            }
+        }
+        positions
+    }
+
+    impl AudioOutput for PipeWireAudioOutput {
+        fn write(&mut self, samples: &[f32]) -> Result<()> {
+            // Do nothing if there are no audio frames.
+            if samples.is_empty() {
+                return Ok(());
+            }
+
+            // Write all the samples to the ring buffer.
+            let mut samples = samples;
+
+            while let Some(written) = self.ring_buf_producer.write_blocking(samples) {
+                samples = &samples[written..];
+            }
+
+            Ok(())
+        }
+
+        fn flush(&mut self) {
+            let _ = self.control.send(Control::Pause);
+        }
+
+        fn drain(&mut self) {
+            let start = Instant::now();
+            while !self.ring_buf.is_empty() && start.elapsed() < MAX_DRAIN_TIME {
+                thread::sleep(DRAIN_POLL_RATE);
+            }
+        }
+
+        fn pause(&mut self) {
+            let _ = self.control.send(Control::Pause);
+        }
+
+        fn resume(&mut self) {
+            let _ = self.control.send(Control::Resume);
+        }
+    }
+
+    // the stream goes with its thread
+    impl Drop for PipeWireAudioOutput {
+        fn drop(&mut self) {
+            let _ = self.control.send(Control::Quit);
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+pub fn try_open(backend: AudioBackends, spec: SignalSpec) -> Result<Box<dyn AudioOutput>> {
+    match backend {
+        AudioBackends::CPAL => cpal::CpalAudioOutput::try_open(spec),
+        AudioBackends::RODIO => rodio::RodioAudioOutput::try_open(spec),
+        #[cfg(all(target_os = "linux", feature = "pipewire"))]
+        AudioBackends::PIPEWIRE => pipewire::PipeWireAudioOutput::try_open(spec),
+        #[cfg(not(all(target_os = "linux", feature = "pipewire")))]
+        AudioBackends::PIPEWIRE => {
+            log::error!("splay was built without the pipewire feature");
+            Err(AudioOutputError::OpenStreamError)
+        }
+    }
 }
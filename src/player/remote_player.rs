@@ -0,0 +1,325 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::UnboundedSender;
+use std::time::Duration;
+
+use crate::ipc::client::IpcClient;
+use crate::ipc::errors::IpcError;
+use crate::ipc::find_song;
+use crate::library::song::Song;
+use crate::player::PlayerRequests;
+use crate::state::AppState;
+use crate::utils::constants::requests::{AppRequests, PlayerChanges, PlayerEvents, UIRequests};
+use crate::utils::constants::PlayerStates;
+use serde_json::Value;
+
+use super::errors::PlayerError;
+use super::{
+    advance_queue, repeat_loop, set_state, set_stopped, song_after_track_end, stop_after_track_end, Player,
+};
+
+// how often the daemon is asked how far into the song it is
+const POLL_RATE: Duration = Duration::from_millis(500);
+
+// how many polls the daemon gets to start a song before what it says it's playing is believed
+const START_POLLS: u8 = 4;
+
+// the interface attached to a splay started with --daemon, which does the playing. the queue is
+// taken from the daemon while attached and given back on quit, so it keeps playing once the
+// terminal is closed. the daemon counts plays and scrobbles, the equalizer, speed, crossfade and
+// the rest only change here
+pub struct RemotePlayer {
+    socket: PathBuf,
+    // connected again by the next command once the connection is lost
+    client: Option<IpcClient>,
+    // the daemon was told to play the current song, so it stopping means the song ended
+    started: bool,
+    // polls left before the daemon has to be playing the song it was told to
+    starting: u8,
+}
+
+impl RemotePlayer {
+    pub fn new(socket: PathBuf, client: IpcClient) -> RemotePlayer {
+        RemotePlayer {
+            socket,
+            client: Some(client),
+            started: false,
+            starting: 0,
+        }
+    }
+
+    // the connection is dropped when a request fails, since it may be why. the next request
+    // connects again
+    fn request(&mut self, command: &str, args: Vec<(&str, Value)>) -> Result<Value, IpcError> {
+        if self.client.is_none() {
+            self.client = Some(IpcClient::connect(&self.socket)?);
+        }
+        let result = self.client.as_mut().unwrap().request(command, args);
+        if result.is_err() {
+            self.client = None;
+        }
+        result
+    }
+
+    // move what the daemon has queued up here, where it can be changed
+    fn take_over(&mut self, app_state: &Arc<Mutex<AppState>>) -> Result<(), IpcError> {
+        let reply = self.request("queue", vec![])?;
+        if let Some(Value::Array(entries)) = reply.get("queue") {
+            let mut guard = app_state.lock().unwrap();
+            for entry in entries {
+                if let Some(song) = song_of(&guard, entry) {
+                    guard.queue.add_upcoming(song);
+                }
+            }
+        }
+        self.request("clear", vec![])?;
+        Ok(())
+    }
+
+    // give the queue back so the daemon plays it once the interface is gone
+    fn hand_back(&mut self, app_state: &Arc<Mutex<AppState>>) {
+        let songs: Vec<Song> = app_state.lock().unwrap().queue.iter().cloned().collect();
+        for song in songs {
+            let args = vec![
+                ("path", Value::from(song.path.as_str())),
+                ("start_ms", song.start_ms.into()),
+            ];
+            if let Err(err) = self.request("enqueue", args) {
+                error!(
+                    "Could not give {} back to the daemon. Reason: {}",
+                    song.path, err
+                );
+            }
+        }
+    }
+
+    fn play(
+        &mut self,
+        app_state: &Arc<Mutex<AppState>>,
+        main_tx: &UnboundedSender<AppRequests>,
+        song: Song,
+    ) -> Result<(), PlayerError> {
+        // where to start is left to the daemon, which knows where episodes were left
+        let position = {
+            let mut guard = app_state.lock().unwrap();
+            set_state(&mut guard, PlayerStates::BUFFERING)?;
+            guard.player.curr_song = Some(song.to_owned());
+            guard.player.progress = Duration::ZERO;
+            guard.player.stream_title = None;
+            guard.player.changed(PlayerChanges::Metadata);
+            guard.player.resume_at.take()
+        };
+        let args = vec![
+            ("path", Value::from(song.path.as_str())),
+            ("start_ms", song.start_ms.into()),
+            (
+                "position",
+                position.map(|position| position.as_secs_f64()).into(),
+            ),
+        ];
+        if let Err(err) = self.request("play", args) {
+            self.started = false;
+            set_stopped(&mut app_state.lock().unwrap());
+            return Err(err.into());
+        }
+        // the daemon tells the services, this only lets the rest of the app know
+        let _ = main_tx.send(AppRequests::UIRequests(UIRequests::PlayerEvent(
+            PlayerEvents::TrackStarted(song),
+        )));
+        self.started = true;
+        self.starting = START_POLLS;
+        Ok(())
+    }
+
+    fn stop(&mut self, app_state: &Arc<Mutex<AppState>>) -> Result<(), PlayerError> {
+        self.started = false;
+        set_stopped(&mut app_state.lock().unwrap());
+        self.request("stop", vec![])?;
+        Ok(())
+    }
+
+    // send the seek the other requests queued up
+    fn seek(&mut self, app_state: &Arc<Mutex<AppState>>) -> Result<(), PlayerError> {
+        let target = match app_state.lock().unwrap().player.seek_to.take() {
+            Some(target) => target,
+            None => return Ok(()),
+        };
+        if !self.started {
+            return Ok(());
+        }
+        self.request("seek", vec![("position", target.as_secs_f64().into())])?;
+        let mut guard = app_state.lock().unwrap();
+        guard.player.progress = target;
+        guard.player.changed(PlayerChanges::Seeked(target));
+        Ok(())
+    }
+}
+
+// the song the daemon sent by its path and start, if it can be found here too
+fn song_of(state: &AppState, song: &Value) -> Option<Song> {
+    let path = song.get("path").and_then(Value::as_str)?;
+    let start_ms = song.get("start_ms").and_then(Value::as_f64).unwrap_or(0.);
+    find_song(state, path, start_ms as u64)
+}
+
+impl Player for RemotePlayer {
+    fn attach(
+        &mut self,
+        app_state: &Arc<Mutex<AppState>>,
+        main_tx: &UnboundedSender<AppRequests>,
+    ) -> Result<(), PlayerError> {
+        let taken = self.take_over(app_state);
+        self.poll(app_state, main_tx)?;
+        Ok(taken?)
+    }
+
+    fn handle(
+        &mut self,
+        app_state: &Arc<Mutex<AppState>>,
+        main_tx: &UnboundedSender<AppRequests>,
+        request: PlayerRequests,
+    ) -> Result<(), PlayerError> {
+        match request {
+            PlayerRequests::Stop => {
+                self.stop(app_state)?;
+                let mut guard = app_state.lock().unwrap();
+                guard.player.curr_song = None;
+                guard.player.changed(PlayerChanges::Metadata);
+            }
+            PlayerRequests::Pause => {
+                self.request("pause", vec![])?;
+                let mut guard = app_state.lock().unwrap();
+                if guard.player.curr_state.is_playing() {
+                    set_state(&mut guard, PlayerStates::PAUSED)?;
+                }
+            }
+            // a stopped daemon would start whatever it last had selected instead
+            PlayerRequests::Resume | PlayerRequests::PlayPause => {
+                let curr_state = app_state.lock().unwrap().player.curr_state;
+                let (command, new_state) = match (curr_state, request) {
+                    (PlayerStates::STOPPED, _) => return Ok(()),
+                    (state, PlayerRequests::PlayPause) if state.is_playing() => {
+                        ("pause", PlayerStates::PAUSED)
+                    }
+                    _ => ("play", PlayerStates::PLAYING),
+                };
+                self.request(command, vec![])?;
+                set_state(&mut app_state.lock().unwrap(), new_state)?;
+            }
+            PlayerRequests::SeekRelative(_)
+            | PlayerRequests::SeekTo(_)
+            | PlayerRequests::SeekAbsolute(_) => self.seek(app_state)?,
+            // the daemon records the skip and where an episode was left as it's told to
+            // play something else
+            PlayerRequests::Start => {
+                let song = app_state.lock().unwrap().ui.selected_song.to_owned();
+                if let Some(song) = song {
+                    self.play(app_state, main_tx, song)?;
+                }
+            }
+            PlayerRequests::Next => match advance_queue(app_state) {
+                Some(song) => self.play(app_state, main_tx, song)?,
+                None => self.stop(app_state)?,
+            },
+            // the equalizer and the rest only change here
+            _ => (),
+        }
+        Ok(())
+    }
+
+    // the daemon is asked how it's doing in between
+    fn poll_rate(&self) -> Option<Duration> {
+        Some(POLL_RATE)
+    }
+
+    // keep up with the daemon, which scripts can also pause or give another song to
+    fn poll(
+        &mut self,
+        app_state: &Arc<Mutex<AppState>>,
+        main_tx: &UnboundedSender<AppRequests>,
+    ) -> Result<(), PlayerError> {
+        if self.client.is_none() {
+            return Ok(());
+        }
+        let status = self.request("status", vec![])?;
+        let state = match status.get("state").and_then(Value::as_str) {
+            Some("playing") => PlayerStates::PLAYING,
+            Some("paused") => PlayerStates::PAUSED,
+            _ => PlayerStates::STOPPED,
+        };
+        let song = {
+            let guard = app_state.lock().unwrap();
+            status.get("song").and_then(|song| song_of(&guard, song))
+        };
+        if state != PlayerStates::STOPPED {
+            let mut guard = app_state.lock().unwrap();
+            let same_song = match (&song, &guard.player.curr_song) {
+                (Some(song), Some(curr)) => song.is_same(curr),
+                _ => false,
+            };
+            if !same_song {
+                // still on the song before the one it was told to play
+                if self.starting > 0 {
+                    self.starting -= 1;
+                    return Ok(());
+                }
+                guard.player.curr_song = song;
+                guard.player.changed(PlayerChanges::Metadata);
+            }
+            self.started = true;
+            self.starting = 0;
+            // the daemon may have been started by something else while this was stopped
+            if guard.player.curr_state == PlayerStates::STOPPED {
+                set_state(&mut guard, PlayerStates::BUFFERING)?;
+            }
+            set_state(&mut guard, state)?;
+            guard.player.progress = Duration::from_secs_f64(
+                status
+                    .get("position")
+                    .and_then(Value::as_f64)
+                    .unwrap_or(0.)
+                    .max(0.),
+            );
+            let stream_title = status
+                .get("stream_title")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            if stream_title != guard.player.stream_title {
+                guard.player.stream_title = stream_title;
+                guard.player.changed(PlayerChanges::Metadata);
+            }
+            repeat_loop(&mut guard);
+            drop(guard);
+            // back to the start of the part being repeated once it's played
+            return self.seek(app_state);
+        }
+        if !self.started {
+            return Ok(());
+        }
+        if self.starting > 0 {
+            self.starting -= 1;
+            return Ok(());
+        }
+
+        // the song ended
+        self.started = false;
+        set_stopped(&mut app_state.lock().unwrap());
+        if stop_after_track_end(app_state) {
+            return Ok(());
+        }
+        match song_after_track_end(app_state) {
+            Some(next) => self.play(app_state, main_tx, next)?,
+            None => {
+                // let the app decide what should play next
+                let _ = main_tx.send(AppRequests::UIRequests(UIRequests::PlayerEvent(
+                    PlayerEvents::TrackEnded,
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn detach(&mut self, app_state: &Arc<Mutex<AppState>>) {
+        self.hand_back(app_state);
+    }
+}
@@ -0,0 +1,71 @@
+use symphonia::core::audio::SignalSpec;
+
+pub const MIN_SPEED: f32 = 0.5;
+pub const MAX_SPEED: f32 = 2.;
+
+// changes playback speed by resampling, so the pitch moves with it like a tape being sped up
+pub struct Resampler {
+    spec: Option<SignalSpec>,
+    // the last frame of the previous buffer, interpolated towards the first frame of the next
+    prev_frame: Vec<f32>,
+    // how far into the next buffer the following output frame is, in input frames
+    position: f64,
+}
+
+impl Resampler {
+    pub fn new() -> Resampler {
+        Resampler {
+            spec: None,
+            prev_frame: Vec::new(),
+            position: 0.,
+        }
+    }
+
+    pub fn process(&mut self, speed: f32, samples: &mut Vec<f32>, spec: SignalSpec) {
+        let channels = spec.channels.count();
+        if self.spec != Some(spec) {
+            self.spec = Some(spec);
+            self.prev_frame = vec![0.; channels];
+            self.position = 0.;
+        }
+        if samples.len() < channels {
+            return;
+        }
+
+        if speed == 1. {
+            self.position = 0.;
+            self.prev_frame.copy_from_slice(&samples[samples.len() - channels..]);
+            return;
+        }
+
+        // frame 0 is the previous buffer's last frame, followed by the frames of this one
+        let n_frames = samples.len() / channels;
+        let frame = |idx: usize, channel: usize| match idx {
+            0 => self.prev_frame[channel],
+            idx => samples[(idx - 1) * channels + channel],
+        };
+
+        let mut resampled = Vec::with_capacity((samples.len() as f32 / speed) as usize + channels);
+        let mut position = self.position;
+        while position < n_frames as f64 {
+            let idx = position as usize;
+            let frac = (position - idx as f64) as f32;
+            for channel in 0..channels {
+                let from = frame(idx, channel);
+                let to = frame(idx + 1, channel);
+                resampled.push(from + (to - from) * frac);
+            }
+            position += speed as f64;
+        }
+
+        self.position = position - n_frames as f64;
+        self.prev_frame.copy_from_slice(&samples[samples.len() - channels..]);
+        *samples = resampled;
+    }
+}
+
+impl Default for Resampler {
+    fn default() -> Self {
+        Resampler::new()
+    }
+}
@@ -0,0 +1,202 @@
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::{Arc, Mutex};
+
+use symphonia::core::io::MediaSource;
+
+use crate::state::AppState;
+use crate::utils::constants::requests::PlayerChanges;
+
+use super::errors::PlayerError;
+
+// an http stream, eg. an internet radio station. shoutcast and icecast servers put the title of
+// what's on between the audio when asked to, which is taken out before it reaches the decoder.
+// files on servers that take ranges, eg. podcast episodes, can be seeked by asking for the rest
+// of the file from somewhere else
+pub struct StreamSource {
+    url: String,
+    response: Response,
+    // bytes of audio between two blocks of metadata, if the server sends any
+    metaint: Option<usize>,
+    until_meta: usize,
+    position: u64,
+    length: Option<u64>,             // only known for files that can be seeked
+    app_state: Arc<Mutex<AppState>>, // where the stream title goes
+}
+
+impl StreamSource {
+    // starts reading the stream, also returning the type of audio the server said it is
+    pub fn open(
+        url: &str,
+        app_state: &Arc<Mutex<AppState>>,
+    ) -> Result<(StreamSource, Option<String>), PlayerError> {
+        let response = Response::get(url, None).map_err(|err| PlayerError::FileNotOpened {
+            path: url.to_string(),
+            reason: err.to_string(),
+        })?;
+        let metaint = response
+            .header("icy-metaint")
+            .and_then(|metaint| metaint.parse().ok());
+        let ranges = response
+            .header("accept-ranges")
+            .is_some_and(|ranges| ranges.eq_ignore_ascii_case("bytes"));
+        let length = response
+            .header("content-length")
+            .and_then(|length| length.parse().ok())
+            .filter(|_| ranges && metaint.is_none());
+        let content_type = response.header("content-type");
+        let stream = StreamSource {
+            url: url.to_string(),
+            response,
+            metaint,
+            until_meta: metaint.unwrap_or(0),
+            position: 0,
+            length,
+            app_state: app_state.clone(),
+        };
+        Ok((stream, content_type))
+    }
+
+    // the metadata is a length in 16 byte blocks and then eg. StreamTitle='Artist - Song';
+    fn read_metadata(&mut self) -> io::Result<()> {
+        let mut length = [0; 1];
+        self.response.reader.read_exact(&mut length)?;
+        if length[0] == 0 {
+            return Ok(());
+        }
+        let mut metadata = vec![0; length[0] as usize * 16];
+        self.response.reader.read_exact(&mut metadata)?;
+        let metadata = String::from_utf8_lossy(&metadata);
+        if let Some(title) = stream_title(&metadata) {
+            let mut guard = self.app_state.lock().unwrap();
+            guard.player.stream_title = Some(title).filter(|title| !title.is_empty());
+            guard.player.changed(PlayerChanges::Metadata);
+        }
+        Ok(())
+    }
+}
+
+impl Read for StreamSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let max = match self.metaint {
+            Some(metaint) => {
+                if self.until_meta == 0 {
+                    self.read_metadata()?;
+                    self.until_meta = metaint;
+                }
+                buf.len().min(self.until_meta)
+            }
+            None => buf.len(),
+        };
+        let read = self.response.reader.read(&mut buf[..max])?;
+        if self.metaint.is_some() {
+            self.until_meta -= read;
+        }
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for StreamSource {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let unsupported = || io::Error::new(io::ErrorKind::Unsupported, "the stream can't seek");
+        let target = match pos {
+            SeekFrom::Start(target) => Some(target),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+            SeekFrom::End(offset) => self
+                .length
+                .ok_or_else(unsupported)?
+                .checked_add_signed(offset),
+        }
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seeked before the start"))?;
+        if target == self.position {
+            return Ok(target);
+        }
+        let length = self.length.ok_or_else(unsupported)?;
+        // the old response is dropped, which closes its connection
+        self.response = Response::get(&self.url, Some(target.min(length)))?;
+        self.position = target.min(length);
+        Ok(self.position)
+    }
+}
+
+impl MediaSource for StreamSource {
+    fn is_seekable(&self) -> bool {
+        self.length.is_some()
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        self.length
+    }
+}
+
+// the body of a response as it comes in, and its headers
+struct Response {
+    reader: Box<dyn Read + Send + Sync>,
+    headers: Vec<(String, String)>,
+}
+
+impl Response {
+    // the whole body, or from a byte on
+    fn get(url: &str, from: Option<u64>) -> io::Result<Response> {
+        let mut request = ureq::get(url).set("Icy-MetaData", "1");
+        if let Some(from) = from {
+            request = request.set("Range", &format!("bytes={}-", from));
+        }
+        let response = request.call().map_err(|err| match err {
+            ureq::Error::Status(status, _) => {
+                io::Error::other(format!("the server answered with {}", status))
+            }
+            err => io::Error::other(err.to_string()),
+        })?;
+        // a server that ignored the range would start from the beginning again
+        if from.is_some() && response.status() != 206 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the server doesn't send parts of the file",
+            ));
+        }
+        let headers = response
+            .headers_names()
+            .into_iter()
+            .filter_map(|name| {
+                let value = response.header(&name)?.to_string();
+                Some((name, value))
+            })
+            .collect();
+        Ok(Response {
+            reader: response.into_reader(),
+            headers,
+        })
+    }
+
+    fn header(&self, name: &str) -> Option<String> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.to_owned())
+    }
+}
+
+fn stream_title(metadata: &str) -> Option<String> {
+    let start = metadata.find("StreamTitle='")? + "StreamTitle='".len();
+    let rest = &metadata[start..];
+    // titles can have quotes in them, it's the quote before the semicolon that ends it
+    let end = rest
+        .find("';")
+        .unwrap_or_else(|| rest.trim_end_matches('\0').len());
+    Some(rest[..end].trim().to_string())
+}
+
+// the extension symphonia should expect for the type of audio a server says it sends
+pub fn extension_for(content_type: &str) -> Option<&'static str> {
+    let content_type = content_type.split(';').next()?.trim().to_lowercase();
+    match content_type.as_str() {
+        "audio/mpeg" | "audio/mp3" => Some("mp3"),
+        "audio/aac" | "audio/aacp" | "audio/x-aac" => Some("aac"),
+        "audio/mp4" | "audio/x-m4a" => Some("m4a"),
+        "audio/flac" | "audio/x-flac" => Some("flac"),
+        "audio/ogg" | "application/ogg" => Some("ogg"),
+        "audio/wav" | "audio/x-wav" => Some("wav"),
+        _ => None,
+    }
+}
@@ -1,220 +1,746 @@
+use symphonia::core::audio::{SampleBuffer, SignalSpec};
 use symphonia::core::codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL};
-use symphonia::core::formats::{FormatOptions, FormatReader};
-use symphonia::core::io::MediaSourceStream;
+use symphonia::core::errors::Error;
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::{MediaSource, MediaSourceStream};
 use symphonia::core::meta::MetadataOptions;
 use symphonia::core::probe::Hint;
+use symphonia::core::units::{Time, TimeBase};
 
+use crate::library::song::{self, Song};
 use crate::player::PlayerRequests;
 use crate::state::AppState;
-use crate::utils::constants::PlayerStates;
+use crate::utils::constants::requests::{AppRequests, PlayerChanges, PlayerEvents, UIRequests};
+use crate::utils::constants::{AudioBackends, PlayerStates, RepeatModes};
 use std::fs::File;
+use std::io::ErrorKind;
 use std::path::Path;
-use std::sync::mpsc::Receiver;
-use std::sync::mpsc::RecvError;
 use std::sync::{Arc, Mutex};
-use std::thread::JoinHandle;
-use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedSender;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::channels;
+use super::equalizer::Equalizer;
+use super::errors::PlayerError;
+use super::fade::Fade;
+use super::speed::Resampler;
+use super::stream::{self, StreamSource};
+use super::visualizer::SampleTap;
+use super::waveform;
+use super::{
+    advance_queue, output, record_skip, replay_gain_factor, report, save_position, set_state,
+    set_stopped, song_after_track_end, song_started, start_position, stop_after_track_end,
+    update_progress, Player,
+};
+
+// how often a paused player checks whether it has been resumed or stopped
+const PAUSE_POLL_RATE: Duration = Duration::from_millis(50);
+
+// decodes songs with symphonia and plays them through one of the audio backends
+pub struct SymphoniaPlayer {
+    backend: AudioBackends,
+    tap: Arc<Mutex<SampleTap>>, // what's played, for the visualizer
+    join_handle: Option<JoinHandle<()>>, // of the thread playing, none while stopped
+}
 
-use super::{output, Player};
+impl SymphoniaPlayer {
+    pub fn new(backend: AudioBackends, tap: Arc<Mutex<SampleTap>>) -> SymphoniaPlayer {
+        SymphoniaPlayer {
+            backend,
+            tap,
+            join_handle: None,
+        }
+    }
 
-pub struct SymphoniaPlayer {}
+    // stops what's playing, waiting for it to fade out
+    fn stop(&mut self, app_state: &Arc<Mutex<AppState>>) {
+        set_stopped(&mut app_state.lock().unwrap());
+        self.join_handle.take().map(JoinHandle::join);
+    }
 
-impl SymphoniaPlayer {
-    pub fn new() -> SymphoniaPlayer {
-        SymphoniaPlayer {}
+    // on a thread of its own, which keeps playing from the queue after it
+    fn play(
+        &mut self,
+        app_state: &Arc<Mutex<AppState>>,
+        main_tx: &UnboundedSender<AppRequests>,
+        song: Song,
+    ) -> Result<(), PlayerError> {
+        {
+            let mut guard = app_state.lock().unwrap();
+            set_state(&mut guard, PlayerStates::BUFFERING)?;
+            guard.player.curr_song = Some(song.to_owned());
+        }
+        let cloned_state = app_state.clone();
+        let cloned_main_tx = main_tx.clone();
+        let backend = self.backend;
+        let tap = self.tap.clone();
+        self.join_handle = Some(thread::spawn(move || {
+            play_queue(cloned_state, song, cloned_main_tx, backend, tap)
+        }));
+        Ok(())
     }
 }
 
 impl Player for SymphoniaPlayer {
-    // listen for actions the player should take
-    fn listen(&mut self, app_state: Arc<Mutex<AppState>>, rx: Receiver<PlayerRequests>) {
-        let mut join_handle: Option<JoinHandle<()>> = None;
-
-        let _result = loop {
-            match rx.recv() {
-                Ok(request) => match request {
-                    PlayerRequests::Quit => return,
-                    PlayerRequests::Stop => {
-                        app_state.lock().unwrap().player.curr_state = PlayerStates::STOPPED;
-                        join_handle.take().map(JoinHandle::join);
-                        app_state.lock().unwrap().player.curr_song = None;
-                    }
-                    PlayerRequests::Pause => {
-                        app_state.lock().unwrap().player.curr_state = PlayerStates::PAUSED;
-                    }
-                    PlayerRequests::Resume => {
-                        app_state.lock().unwrap().player.curr_state = PlayerStates::PLAYING;
-                    }
-                    PlayerRequests::PlayPause => {
-                        match app_state.lock().unwrap().player.curr_state {
-                            PlayerStates::PLAYING => {
-                                app_state.lock().unwrap().player.curr_state = PlayerStates::PAUSED
-                            }
-                            PlayerStates::PAUSED => {
-                                app_state.lock().unwrap().player.curr_state = PlayerStates::PLAYING
-                            }
-                            _ => (),
-                        }
-                    }
-                    PlayerRequests::Start => {
-                        // stop player if previously playing
-                        app_state.lock().unwrap().player.curr_state = PlayerStates::STOPPED;
-                        join_handle.take().map(JoinHandle::join);
-                        app_state.lock().unwrap().player.curr_state = PlayerStates::PLAYING;
-
-                        // init setup for playing a song
-
-                        // fetch which song is selected in the UI
-                        // TODO: maybe just have other threads modify player.curr_song instead
-                        let song = match app_state.lock().unwrap().ui.selected_song.to_owned() {
-                            Some(song) => song,
-                            None => continue,
-                        };
-
-                        app_state.lock().unwrap().player.curr_song = Some(song.to_owned());
-
-                        let song_path = Path::new(&song.path);
-                        let mut hint = Hint::new();
-                        if let Some(extension) = song_path.extension() {
-                            if let Some(extension_str) = extension.to_str() {
-                                hint.with_extension(extension_str);
-                            }
-                        }
-
-                        let source = match File::open(song_path) {
-                            Ok(f) => Box::new(f),
-                            Err(err) => {
-                                panic!("Could not open song at path {}. Reason: {}", song.path, err)
-                                //TODO: return Result instead of panic here
-                            }
-                        };
-
-                        let media_source_stream =
-                            MediaSourceStream::new(source, Default::default());
-
-                        let format_opts = FormatOptions {
-                            enable_gapless: true, // TODO: have this be a config option
-                            ..Default::default()
-                        };
-
-                        let metadata_opts: MetadataOptions = Default::default();
-
-                        let probed = symphonia::default::get_probe()
-                            .format(&hint, media_source_stream, &format_opts, &metadata_opts)
-                            .expect("unsupported media format");
-
-                        let mut format = probed.format;
-
-                        // Finds the first decodable track
-                        let track = format
-                            .tracks()
-                            .iter()
-                            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
-                            .expect("No supported audio track");
-
-                        let track_id = track.id;
-
-                        let dec_opts: DecoderOptions = Default::default();
-                        let track = match format.tracks().iter().find(|track| track.id == track_id)
-                        {
-                            Some(track) => track,
-                            _ => return,
-                        };
-
-                        // TODO: have these .expects be errors that are returned and displayed
-                        // TODO: user configurable option for how errors are displayed (popup or printed at the bottom)
-                        let mut decoder = symphonia::default::get_codecs()
-                            .make(&track.codec_params, &dec_opts)
-                            .expect("unsupported codec");
-
-                        let cloned_state = app_state.clone();
-
-                        // spin up another thread that will start playing audio
-                        join_handle = Some(std::thread::spawn(move || {
-                            player(cloned_state, &mut format, track_id, &mut decoder)
-                        }));
-                    }
-                },
-                Err(err) => match err {
-                    RecvError => {
-                        error!(
-                            "Could not receive request to player app state. Reason: {}",
-                            err.to_string()
-                        );
-                    }
-                },
+    fn handle(
+        &mut self,
+        app_state: &Arc<Mutex<AppState>>,
+        main_tx: &UnboundedSender<AppRequests>,
+        request: PlayerRequests,
+    ) -> Result<(), PlayerError> {
+        match request {
+            PlayerRequests::Stop => {
+                save_position(app_state, main_tx, false);
+                self.stop(app_state);
+                let mut guard = app_state.lock().unwrap();
+                guard.player.curr_song = None;
+                guard.player.changed(PlayerChanges::Metadata);
             }
-        };
+            // the thread playing picks the state up, fading out or in
+            PlayerRequests::Pause | PlayerRequests::Resume | PlayerRequests::PlayPause => {
+                let mut guard = app_state.lock().unwrap();
+                let to = match (guard.player.curr_state, request) {
+                    (PlayerStates::STOPPED, _) => return Ok(()),
+                    (PlayerStates::PAUSED, PlayerRequests::Pause) => return Ok(()),
+                    (PlayerStates::PAUSED, _) => PlayerStates::PLAYING,
+                    (_, PlayerRequests::Resume) => return Ok(()),
+                    _ => PlayerStates::PAUSED,
+                };
+                set_state(&mut guard, to)?;
+            }
+            PlayerRequests::Start => {
+                record_skip(app_state, main_tx);
+                save_position(app_state, main_tx, false);
+                self.stop(app_state);
+                let song = app_state.lock().unwrap().ui.selected_song.to_owned();
+                if let Some(song) = song {
+                    self.play(app_state, main_tx, song)?;
+                }
+            }
+            PlayerRequests::Next => {
+                record_skip(app_state, main_tx);
+                save_position(app_state, main_tx, false);
+                self.stop(app_state);
+                if let Some(song) = advance_queue(app_state) {
+                    self.play(app_state, main_tx, song)?;
+                }
+            }
+            // seeks and the rest are picked up from the state as the song plays
+            _ => (),
+        }
+        Ok(())
     }
 }
 
-fn player(
+// keep playing from the queue until it runs dry or playback is stopped
+fn play_queue(
     app_state: Arc<Mutex<AppState>>,
-    format: &mut Box<dyn FormatReader>,
-    track_id: u32,
-    decoder: &mut Box<dyn Decoder>,
+    song: Song,
+    main_tx: UnboundedSender<AppRequests>,
+    backend: AudioBackends,
+    tap: Arc<Mutex<SampleTap>>,
 ) {
-    let mut audio_output = None;
-    app_state.lock().unwrap().player.progress = Duration::ZERO;
+    let mut output = Output::new(backend);
+    let mut pipeline = Pipeline::new(tap);
+    let mut song = song;
+    let mut fading = None;
+    loop {
+        let gain = replay_gain_factor(&app_state.lock().unwrap(), &song);
+        let decoding = match DecodingSong::open(&app_state, &song, gain) {
+            Ok(song) => song,
+            Err(err) => match skip_failed_song(&app_state, &main_tx, err) {
+                Some(next) => {
+                    song = next;
+                    continue;
+                }
+                None => return,
+            },
+        };
+        match play_song(
+            &app_state,
+            &main_tx,
+            &mut output,
+            &mut pipeline,
+            decoding,
+            fading.take(),
+        ) {
+            SongEnd::Stopped => {
+                output.flush();
+                pipeline.tap.lock().unwrap().clear();
+                return;
+            }
+            SongEnd::Finished => save_position(&app_state, &main_tx, true),
+            SongEnd::Crossfading(prev) => {
+                save_position(&app_state, &main_tx, true);
+                fading = Some(*prev);
+            }
+            SongEnd::Failed(err) => match skip_failed_song(&app_state, &main_tx, err) {
+                Some(next) => {
+                    song = next;
+                    continue;
+                }
+                None => return,
+            },
+        }
+        if stop_after_track_end(&app_state) {
+            return;
+        }
+        match song_after_track_end(&app_state) {
+            Some(next) => song = next,
+            None => {
+                // let the app decide what should play next
+                let _ = main_tx.send(AppRequests::UIRequests(UIRequests::PlayerEvent(PlayerEvents::TrackEnded)));
+                return;
+            }
+        }
+    }
+}
+
+// report a song that couldn't be played and move on to the next queued song, if there is one
+fn skip_failed_song(
+    app_state: &Arc<Mutex<AppState>>,
+    main_tx: &UnboundedSender<AppRequests>,
+    err: PlayerError,
+) -> Option<Song> {
+    report(main_tx, err);
+    if stop_after_track_end(app_state) {
+        return None;
+    }
+    // skips straight to the queue so that repeating a broken song doesn't retry it forever
+    let next = advance_queue(app_state);
+    if next.is_none() {
+        let _ = main_tx.send(AppRequests::UIRequests(UIRequests::PlayerEvent(
+            PlayerEvents::TrackEnded,
+        )));
+    }
+    next
+}
+
+enum SongEnd {
+    Stopped,
+    Finished,
+    Failed(PlayerError),
+    Crossfading(Box<FadingSong>),
+}
+
+// plays a song until it ends, is stopped, or reaches the point where it should crossfade into the
+// next song. `fading` is the previous song if this one is starting in the middle of a crossfade
+fn play_song(
+    app_state: &Arc<Mutex<AppState>>,
+    main_tx: &UnboundedSender<AppRequests>,
+    output: &mut Output,
+    pipeline: &mut Pipeline,
+    song: DecodingSong,
+    fading: Option<FadingSong>,
+) -> SongEnd {
+    let mut song = song;
+    let mut fading = fading;
+    {
+        let mut guard = app_state.lock().unwrap();
+        guard.player.seek_to = start_position(&mut guard);
+        song_started(&mut guard, main_tx);
+    }
+    waveform::load(app_state, main_tx);
+    // fall back to the tagged length when the container doesn't say how long the track is
+    let length = song.length().unwrap_or_else(|| {
+        let guard = app_state.lock().unwrap();
+        Duration::from_secs(guard.player.curr_song.as_ref().map_or(0, |song| song.duration_secs))
+    });
+    // only ask the app for the next song once per song
+    let mut requested_next = false;
+    let mut paused = false;
+    let mut samples = Vec::new();
 
     loop {
-        match app_state.lock().unwrap().player.curr_state {
-            PlayerStates::STOPPED => break,
+        // copy the state out so the lock isn't held while idling
+        let (curr_state, fade_length) = {
+            let guard = app_state.lock().unwrap();
+            (guard.player.curr_state, guard.player.fade)
+        };
+        // keep playing until the fade out is over before stopping or pausing
+        match curr_state {
+            PlayerStates::STOPPED => {
+                pipeline.fade.fade_out(fade_length);
+                if pipeline.fade.is_silent() {
+                    output.drain();
+                    return SongEnd::Stopped;
+                }
+            }
             PlayerStates::PAUSED => {
-                continue;
+                pipeline.fade.fade_out(fade_length);
+                if pipeline.fade.is_silent() {
+                    if !paused {
+                        output.drain();
+                        output.pause();
+                        paused = true;
+                    }
+                    // keep the decoder and output around so playback picks up where it left off
+                    thread::sleep(PAUSE_POLL_RATE);
+                    continue;
+                }
+            }
+            PlayerStates::BUFFERING | PlayerStates::PLAYING => {
+                if paused {
+                    output.resume();
+                    paused = false;
+                }
+                pipeline.fade.fade_in(fade_length);
             }
-            _ => (),
         }
 
-        let packet = match format.next_packet() {
-            Ok(packet) => packet,
-            Err(..) => return,
+        let seek_to = app_state.lock().unwrap().player.seek_to.take();
+        if let Some(target) = seek_to {
+            if song.seek(target) {
+                let mut guard = app_state.lock().unwrap();
+                guard.player.progress = target;
+                guard.player.changed(PlayerChanges::Seeked(target));
+            }
+        }
+
+        samples.clear();
+        if !song.decode_next(&mut samples) {
+            return match song.error.take() {
+                Some(err) => SongEnd::Failed(err),
+                None => SongEnd::Finished,
+            };
+        }
+        // only set once a packet has been decoded
+        let spec = match song.spec {
+            Some(spec) => spec,
+            None => return SongEnd::Failed(PlayerError::DecodeFailed {
+                path: song.path.clone(),
+                reason: "no audio was decoded".to_string(),
+            }),
         };
 
-        if packet.track_id() != track_id {
-            continue;
+        if let Some(prev) = &mut fading {
+            if !prev.mix_into(&mut samples, spec) {
+                fading = None;
+            }
         }
 
-        while !format.metadata().is_latest() {
-            format.metadata().pop();
+        pipeline.process(app_state, &mut samples, spec);
 
-            if let Some(rev) = format.metadata().current() {
-                info!("{:?}", rev);
-            }
+        if let Err(err) = output.write(spec, &samples) {
+            set_stopped(&mut app_state.lock().unwrap());
+            report(main_tx, err);
+            return SongEnd::Stopped;
         }
 
-        let start_packet_time = Instant::now(); // record the time before a packet is played
-        let _ = play_packet(&mut audio_output, decoder, packet);
         let mut guard = app_state.lock().unwrap(); //idk I just did this not to call lock() a bunch
                                                    //of times
-                                                   // update player time with how long the last packet took to play
-        guard.player.progress = guard.player.progress + start_packet_time.elapsed();
-        drop(guard);
+        // the first of the song was played
+        if guard.player.curr_state == PlayerStates::BUFFERING {
+            let _ = set_state(&mut guard, PlayerStates::PLAYING);
+        }
+        update_progress(&mut guard, main_tx, song.position, length);
+
+        // start fading into the next song once the rest of this one fits in the crossfade
+        // a part of it being repeated doesn't get there
+        if guard.player.crossfade.is_zero()
+            || length.is_zero()
+            || guard.player.stop_after_current
+            || guard.player.loop_end.is_some()
+        {
+            continue;
+        }
+        let remaining = length.saturating_sub(song.position);
+        if remaining > guard.player.crossfade {
+            continue;
+        }
+        if !guard.queue.is_empty() || guard.player.repeat == RepeatModes::ONE {
+            let length_frames = (remaining.as_secs_f64() * spec.rate as f64) as u64;
+            return SongEnd::Crossfading(Box::new(FadingSong {
+                song,
+                pending: Vec::new(),
+                faded_frames: 0,
+                length_frames,
+            }));
+        } else if !requested_next {
+            // nothing queued yet, give the app a chance to pick a song before this one ends
+            requested_next = true;
+            let _ = main_tx.send(AppRequests::UIRequests(UIRequests::PlayerEvent(PlayerEvents::TrackEnded)));
+        }
+    }
+}
+
+// the effects applied to decoded samples before they are played
+struct Pipeline {
+    equalizer: Equalizer,
+    resampler: Resampler,
+    fade: Fade,
+    tap: Arc<Mutex<SampleTap>>,
+}
+
+impl Pipeline {
+    fn new(tap: Arc<Mutex<SampleTap>>) -> Pipeline {
+        Pipeline {
+            equalizer: Equalizer::new(),
+            resampler: Resampler::new(),
+            fade: Fade::new(),
+            tap,
+        }
+    }
+
+    fn process(
+        &mut self,
+        app_state: &Arc<Mutex<AppState>>,
+        samples: &mut Vec<f32>,
+        spec: SignalSpec,
+    ) {
+        let (eq_bands, speed, mono, balance) = {
+            let guard = app_state.lock().unwrap();
+            let player = &guard.player;
+            (player.eq_bands, player.speed, player.mono, player.balance)
+        };
+        self.equalizer.process(eq_bands, samples, spec);
+        if mono {
+            channels::downmix_to_mono(samples, spec.channels.count());
+        }
+        channels::apply_balance(samples, spec.channels.count(), balance);
+        self.resampler.process(speed, samples, spec);
+        self.fade.apply(samples, spec);
+        self.tap
+            .lock()
+            .unwrap()
+            .push(samples, spec.channels.count(), spec.rate);
+    }
+}
+
+// the backend output, reopened whenever a song has a different sample rate or channel layout
+struct Output {
+    backend: AudioBackends,
+    spec: Option<SignalSpec>,
+    output: Option<Box<dyn output::AudioOutput>>,
+}
+
+impl Output {
+    fn new(backend: AudioBackends) -> Output {
+        Output {
+            backend,
+            spec: None,
+            output: None,
+        }
+    }
+
+    // fails if the output couldn't be opened or stopped taking samples
+    fn write(&mut self, spec: SignalSpec, samples: &[f32]) -> Result<(), PlayerError> {
+        if self.spec != Some(spec) {
+            self.output = None;
+            self.spec = Some(spec);
+            let output = output::try_open(self.backend, spec)
+                .map_err(|err| PlayerError::Output(format!("{:?}", err)))?;
+            self.output = Some(output);
+        }
+        match &mut self.output {
+            Some(output) => output
+                .write(samples)
+                .map_err(|err| PlayerError::Output(format!("{:?}", err))),
+            None => Err(PlayerError::Output("it isn't open".to_string())),
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some(output) = &mut self.output {
+            output.flush();
+        }
+    }
+
+    fn drain(&mut self) {
+        if let Some(output) = &mut self.output {
+            output.drain();
+        }
+    }
+
+    fn pause(&mut self) {
+        if let Some(output) = &mut self.output {
+            output.pause();
+        }
+    }
+
+    fn resume(&mut self) {
+        if let Some(output) = &mut self.output {
+            output.resume();
+        }
     }
 }
 
-fn play_packet(
-    audio_output: &mut Option<Box<dyn output::AudioOutput>>,
-    decoder: &mut Box<dyn Decoder>,
-    packet: symphonia::core::formats::Packet,
-) -> Result<(), symphonia::core::errors::Error> {
-    match decoder.decode(&packet) {
-        Ok(decoded) => {
-            if audio_output.is_none() {
-                let spec = *decoded.spec();
+// a song being decoded into interleaved samples, also used to measure its loudness
+pub(crate) struct DecodingSong {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    // used to turn packet timestamps into the position within the song
+    time_base: Option<TimeBase>,
+    // packets before this timestamp are decoded but not played, used to land exactly on a seek
+    required_ts: u64,
+    sample_buf: Option<SampleBuffer<f32>>,
+    pub(crate) spec: Option<SignalSpec>,
+    gain: f32,
+    // where the song starts and ends within the file, positions are relative to the start
+    start: Duration,
+    end: Option<Duration>,
+    position: Duration,
+    path: String,
+    // why decoding stopped before the end of the song
+    pub(crate) error: Option<PlayerError>,
+}
 
-                let duration = decoded.capacity() as u64;
-                audio_output.replace(output::try_open(spec, duration).unwrap());
+impl DecodingSong {
+    pub(crate) fn open(
+        app_state: &Arc<Mutex<AppState>>,
+        song: &Song,
+        gain: f32,
+    ) -> Result<DecodingSong, PlayerError> {
+        let (format, track_id, decoder) = open_song(app_state, &song.path)?;
+        let time_base = format
+            .tracks()
+            .iter()
+            .find(|track| track.id == track_id)
+            .and_then(|track| track.codec_params.time_base);
+        let mut decoding = DecodingSong {
+            format,
+            decoder,
+            track_id,
+            time_base,
+            required_ts: 0,
+            sample_buf: None,
+            spec: None,
+            gain,
+            start: Duration::from_millis(song.start_ms),
+            end: song.end_ms.map(Duration::from_millis),
+            position: Duration::ZERO,
+            path: song.path.to_owned(),
+            error: None,
+        };
+        // tracks of a cue sheet start somewhere in the middle of the file
+        if !decoding.start.is_zero() {
+            decoding.seek(Duration::ZERO);
+        }
+        Ok(decoding)
+    }
+
+    fn length(&self) -> Option<Duration> {
+        let track = self
+            .format
+            .tracks()
+            .iter()
+            .find(|track| track.id == self.track_id)?;
+        let end = match self.end {
+            Some(end) => end,
+            None => {
+                let n_frames = track.codec_params.n_frames?;
+                to_duration(self.time_base?.calc_time(n_frames))
             }
+        };
+        Some(end.saturating_sub(self.start))
+    }
 
-            if let Some(audio_output) = audio_output {
-                audio_output.write(decoded).unwrap()
+    fn seek(&mut self, target: Duration) -> bool {
+        let seek = SeekTo::Time {
+            time: Time::from((self.start + target).as_secs_f64()),
+            track_id: Some(self.track_id),
+        };
+        match self.format.seek(SeekMode::Accurate, seek) {
+            Ok(seeked_to) => {
+                self.decoder.reset();
+                self.required_ts = seeked_to.required_ts;
+                self.position = target;
+                true
+            }
+            Err(err) => {
+                warn!("Could not seek to {:?}. Reason: {}", target, err);
+                false
             }
         }
-        Err(symphonia::core::errors::Error::DecodeError(err)) => {
-            warn!("Decode error: {}", err);
+    }
+
+    // decode the next packet of the track and append its samples to `out`. returns false once
+    // the end of the stream, or of the song within it, is reached
+    pub(crate) fn decode_next(&mut self, out: &mut Vec<f32>) -> bool {
+        if let Some(end) = self.end {
+            if self.start + self.position >= end {
+                return false;
+            }
         }
-        Err(err) => return Err(err),
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                // the stream ending is how the format reader reports the end of the song
+                Err(Error::IoError(err)) if err.kind() == ErrorKind::UnexpectedEof => return false,
+                Err(err) => return self.fail(err),
+            };
+
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            if packet.ts() < self.required_ts {
+                let _ = self.decoder.decode(&packet);
+                continue;
+            }
+
+            while !self.format.metadata().is_latest() {
+                self.format.metadata().pop();
+
+                if let Some(rev) = self.format.metadata().current() {
+                    info!("{:?}", rev);
+                }
+            }
+
+            match self.decoder.decode(&packet) {
+                Ok(decoded) => {
+                    let spec = *decoded.spec();
+                    if self.spec != Some(spec) {
+                        self.sample_buf = None;
+                        self.spec = Some(spec);
+                    }
+                    let duration = decoded.capacity() as u64;
+                    let sample_buf = self
+                        .sample_buf
+                        .get_or_insert_with(|| SampleBuffer::new(duration, spec));
+                    sample_buf.copy_interleaved_ref(decoded);
+                    let gain = self.gain;
+                    out.extend(sample_buf.samples().iter().map(|s| s * gain));
+                }
+                Err(Error::DecodeError(err)) => {
+                    warn!("Decode error: {}", err);
+                    continue;
+                }
+                Err(err) => return self.fail(err),
+            }
+
+            if let Some(time_base) = self.time_base {
+                let time = to_duration(time_base.calc_time(packet.ts()));
+                self.position = time.saturating_sub(self.start);
+            }
+            return true;
+        }
+    }
+
+    // remember why decoding failed, returning false like the end of the stream does
+    fn fail(&mut self, err: Error) -> bool {
+        self.error = Some(PlayerError::DecodeFailed {
+            path: self.path.to_owned(),
+            reason: err.to_string(),
+        });
+        false
+    }
+}
+
+// a song that is being faded out while the next one fades in
+struct FadingSong {
+    song: DecodingSong,
+    // decoded samples that haven't been mixed in yet
+    pending: Vec<f32>,
+    faded_frames: u64,
+    length_frames: u64,
+}
+
+impl FadingSong {
+    // ramp `samples` up while mixing in this song's samples ramped down. returns false once the
+    // crossfade is over
+    fn mix_into(&mut self, samples: &mut [f32], spec: SignalSpec) -> bool {
+        // songs with different sample rates or channels can't be mixed without resampling
+        if self.song.spec != Some(spec) || self.length_frames == 0 {
+            return false;
+        }
+        let channels = spec.channels.count();
+
+        while self.pending.len() < samples.len() && self.song.decode_next(&mut self.pending) {}
+
+        for (i, sample) in samples.iter_mut().enumerate() {
+            let frame = self.faded_frames + (i / channels) as u64;
+            let ratio = (frame as f32 / self.length_frames as f32).min(1.);
+            let prev_sample = self.pending.get(i).copied().unwrap_or(0.);
+            *sample = *sample * ratio + prev_sample * (1. - ratio);
+        }
+
+        let mixed = self.pending.len().min(samples.len());
+        self.pending.drain(..mixed);
+        self.faded_frames += (samples.len() / channels) as u64;
+        self.faded_frames < self.length_frames
     }
-    Ok(())
+}
+
+fn to_duration(time: Time) -> Duration {
+    Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac)
+}
+
+// format reader, id of the track being played, and its decoder
+type OpenedSong = (Box<dyn FormatReader>, u32, Box<dyn Decoder>);
+
+// set up the format reader and decoder for the first decodable track of a song. streams tell the
+// state what's on as they're played
+fn open_song(app_state: &Arc<Mutex<AppState>>, path: &str) -> Result<OpenedSong, PlayerError> {
+    let song_path = Path::new(path);
+    let mut hint = Hint::new();
+    if let Some(extension) = song_path.extension() {
+        if let Some(extension_str) = extension.to_str() {
+            hint.with_extension(extension_str);
+        }
+    }
+
+    let source: Box<dyn MediaSource> = if song::is_stream_url(path) {
+        let (stream, content_type) = StreamSource::open(path, app_state)?;
+        // urls of streams rarely end with an extension, the server says what it sends instead
+        if let Some(extension) = content_type.as_deref().and_then(stream::extension_for) {
+            hint.with_extension(extension);
+        }
+        Box::new(stream)
+    } else {
+        match File::open(song_path) {
+            Ok(f) => Box::new(f),
+            Err(err) => {
+                return Err(PlayerError::FileNotOpened {
+                    path: path.to_string(),
+                    reason: err.to_string(),
+                })
+            }
+        }
+    };
+
+    let media_source_stream = MediaSourceStream::new(source, Default::default());
+
+    let format_opts = FormatOptions {
+        enable_gapless: true, // TODO: have this be a config option
+        ..Default::default()
+    };
+
+    let metadata_opts: MetadataOptions = Default::default();
+
+    let probed = match symphonia::default::get_probe().format(
+        &hint,
+        media_source_stream,
+        &format_opts,
+        &metadata_opts,
+    ) {
+        Ok(probed) => probed,
+        Err(_) => return Err(PlayerError::UnsupportedFormat(path.to_string())),
+    };
+
+    let format = probed.format;
+
+    // Finds the first decodable track
+    let track = match format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+    {
+        Some(track) => track,
+        None => return Err(PlayerError::NoTrack(path.to_string())),
+    };
+
+    let track_id = track.id;
+
+    let dec_opts: DecoderOptions = Default::default();
+
+    // TODO: user configurable option for how errors are displayed (popup or printed at the bottom)
+    let decoder = match symphonia::default::get_codecs().make(&track.codec_params, &dec_opts) {
+        Ok(decoder) => decoder,
+        Err(_) => return Err(PlayerError::UnsupportedCodec(path.to_string())),
+    };
+
+    Ok((format, track_id, decoder))
 }
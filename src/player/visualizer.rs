@@ -0,0 +1,124 @@
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+// samples the spectrum is worked out from, about 45ms at 44.1kHz
+pub const FFT_SIZE: usize = 2048;
+
+// the range of frequencies shown, spaced out the way they're heard
+const MIN_FREQUENCY: f32 = 40.;
+const MAX_FREQUENCY: f32 = 16000.;
+// anything quieter shows as an empty bar
+const FLOOR_DB: f32 = -70.;
+
+// the last samples the player sent to the output, mixed down to mono. the player writes to it
+// and the visualizer reads from it, each behind the same mutex
+pub struct SampleTap {
+    samples: VecDeque<f32>,
+    rate: u32,
+}
+
+impl SampleTap {
+    pub fn new() -> SampleTap {
+        SampleTap {
+            samples: VecDeque::with_capacity(FFT_SIZE),
+            rate: 0,
+        }
+    }
+
+    pub fn push(&mut self, samples: &[f32], channels: usize, rate: u32) {
+        self.rate = rate;
+        for frame in samples.chunks(channels.max(1)) {
+            if self.samples.len() == FFT_SIZE {
+                self.samples.pop_front();
+            }
+            self.samples
+                .push_back(frame.iter().sum::<f32>() / frame.len() as f32);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    // how loud each of `bands` bands is from 0 to 1, lowest first. all 0 until enough has been
+    // played to fill the buffer
+    pub fn spectrum(&self, bands: usize) -> Vec<f32> {
+        if self.samples.len() < FFT_SIZE || self.rate == 0 {
+            return vec![0.; bands];
+        }
+        // a hann window keeps the ends of the buffer from smearing into every band
+        let mut re: Vec<f32> = self
+            .samples
+            .iter()
+            .enumerate()
+            .map(|(i, sample)| {
+                sample * 0.5 * (1. - (2. * PI * i as f32 / (FFT_SIZE - 1) as f32).cos())
+            })
+            .collect();
+        let mut im = vec![0.; FFT_SIZE];
+        fft(&mut re, &mut im);
+
+        let bin_width = self.rate as f32 / FFT_SIZE as f32;
+        let max_frequency = MAX_FREQUENCY.min(self.rate as f32 / 2.);
+        let ratio = max_frequency / MIN_FREQUENCY;
+        (0..bands)
+            .map(|band| {
+                let low = MIN_FREQUENCY * ratio.powf(band as f32 / bands as f32);
+                let high = MIN_FREQUENCY * ratio.powf((band + 1) as f32 / bands as f32);
+                let first = ((low / bin_width) as usize).clamp(1, FFT_SIZE / 2 - 1);
+                let last = ((high / bin_width) as usize).clamp(first + 1, FFT_SIZE / 2);
+                let peak = (first..last)
+                    .map(|bin| (re[bin] * re[bin] + im[bin] * im[bin]).sqrt())
+                    .fold(0., f32::max);
+                // a full scale sine peaks at a quarter of the buffer's length once windowed
+                let db = 20.
+                    * (peak / (FFT_SIZE as f32 / 4.))
+                        .max(f32::MIN_POSITIVE)
+                        .log10();
+                ((db - FLOOR_DB) / -FLOOR_DB).clamp(0., 1.)
+            })
+            .collect()
+    }
+}
+
+impl Default for SampleTap {
+    fn default() -> Self {
+        SampleTap::new()
+    }
+}
+
+// radix-2 fast fourier transform in place. the length has to be a power of two
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    // put the samples in bit reversed order so each pass combines neighbouring halves
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let angle = -2. * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let odd_re = re[b] * cos - im[b] * sin;
+                let odd_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - odd_re;
+                im[b] = im[a] - odd_im;
+                re[a] += odd_re;
+                im[a] += odd_im;
+            }
+        }
+        len <<= 1;
+    }
+}
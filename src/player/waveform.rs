@@ -0,0 +1,140 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::UnboundedSender;
+use std::thread;
+
+use super::symphonia_player::DecodingSong;
+use crate::library::song::Song;
+use crate::state::AppState;
+use crate::utils::constants::requests::{AppRequests, UIRequests};
+
+// the loudest sample of every tenth of a second
+const PEAKS_PER_SEC: usize = 10;
+// waveforms of the songs played last are kept, so going back and forth doesn't decode them again
+const CACHED_WAVEFORMS: usize = 32;
+// how many packets are decoded between checks that the song is still playing
+const PACKETS_PER_CHECK: usize = 256;
+
+// peaks of the songs played lately, by file and where in it the song starts
+pub struct Waveforms {
+    cached: VecDeque<(String, u64, Arc<Vec<f32>>)>,
+    // the song whose waveform is being worked out
+    pending: Option<(String, u64)>,
+}
+
+impl Default for Waveforms {
+    fn default() -> Self {
+        Waveforms {
+            cached: VecDeque::new(),
+            pending: None,
+        }
+    }
+}
+
+impl Waveforms {
+    pub fn get(&self, song: &Song) -> Option<Arc<Vec<f32>>> {
+        self.cached
+            .iter()
+            .find(|(path, start_ms, _)| *path == song.path && *start_ms == song.start_ms)
+            .map(|(_, _, peaks)| peaks.clone())
+    }
+
+    fn insert(&mut self, song: &Song, peaks: Vec<f32>) {
+        if self.cached.len() == CACHED_WAVEFORMS {
+            self.cached.pop_front();
+        }
+        self.cached
+            .push_back((song.path.to_owned(), song.start_ms, Arc::new(peaks)));
+    }
+
+    fn is_pending(&self, song: &Song) -> bool {
+        self.pending
+            .as_ref()
+            .is_some_and(|(path, start_ms)| *path == song.path && *start_ms == song.start_ms)
+    }
+}
+
+// work out the waveform of the song that started playing in the background, unless it's known
+// already. streams have no end to decode up to
+pub fn load(app_state: &Arc<Mutex<AppState>>, main_tx: &UnboundedSender<AppRequests>) {
+    let mut guard = app_state.lock().unwrap();
+    let song = match &guard.player.curr_song {
+        Some(song) if !song.is_stream() => song.clone(),
+        _ => return,
+    };
+    let waveforms = &mut guard.player.waveforms;
+    if waveforms.get(&song).is_some() || waveforms.is_pending(&song) {
+        return;
+    }
+    waveforms.pending = Some((song.path.to_owned(), song.start_ms));
+    let cloned_state = app_state.clone();
+    let cloned_main_tx = main_tx.clone();
+    thread::spawn(move || {
+        let peaks = peaks(&cloned_state, &song);
+        let mut guard = cloned_state.lock().unwrap();
+        if guard.player.waveforms.is_pending(&song) {
+            guard.player.waveforms.pending = None;
+        }
+        if let Some(peaks) = peaks {
+            guard.player.waveforms.insert(&song, peaks);
+            let _ = cloned_main_tx.send(AppRequests::UIRequests(UIRequests::Redraw));
+        }
+    });
+}
+
+// decode the song, giving up if it fails or another song is played before it's done
+fn peaks(app_state: &Arc<Mutex<AppState>>, song: &Song) -> Option<Vec<f32>> {
+    let mut decoding = match DecodingSong::open(app_state, song, 1.) {
+        Ok(decoding) => decoding,
+        Err(err) => {
+            warn!(
+                "Could not work out the waveform of {}. Reason: {}",
+                song.path, err
+            );
+            return None;
+        }
+    };
+    let mut peaks = Vec::new();
+    let (mut peak, mut frames) = (0f32, 0);
+    let mut samples = Vec::new();
+    let mut packets = 0;
+    while decoding.decode_next(&mut samples) {
+        packets += 1;
+        if packets % PACKETS_PER_CHECK == 0 {
+            let guard = app_state.lock().unwrap();
+            if !guard
+                .player
+                .curr_song
+                .as_ref()
+                .is_some_and(|curr| curr.is_same(song))
+            {
+                return None;
+            }
+        }
+        if let Some(spec) = decoding.spec {
+            let frames_per_peak = (spec.rate as usize / PEAKS_PER_SEC).max(1);
+            for frame in samples.chunks_exact(spec.channels.count()) {
+                peak = frame
+                    .iter()
+                    .fold(peak, |peak, sample| peak.max(sample.abs()));
+                frames += 1;
+                if frames == frames_per_peak {
+                    peaks.push(peak);
+                    (peak, frames) = (0., 0);
+                }
+            }
+        }
+        samples.clear();
+    }
+    if let Some(err) = decoding.error.take() {
+        warn!(
+            "Could not work out the waveform of {}. Reason: {}",
+            song.path, err
+        );
+        return None;
+    }
+    if frames > 0 {
+        peaks.push(peak);
+    }
+    Some(peaks)
+}
@@ -0,0 +1,12 @@
+use std::io;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PodcastError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("Could not fetch {url}: {reason}")]
+    Request { url: String, reason: String },
+    #[error("{url} is not a podcast feed: {reason}")]
+    Feed { url: String, reason: String },
+}
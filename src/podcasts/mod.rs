@@ -0,0 +1,241 @@
+pub mod errors;
+mod rss;
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::library::song::Song;
+use errors::PodcastError;
+
+// episodes played up to this close to their end are done with, they start over next time
+const FINISHED_WITHIN: Duration = Duration::from_secs(30);
+
+// a feed subscribed to in the config
+#[derive(Debug, Clone)]
+pub struct Podcast {
+    pub title: String,
+    pub episodes: Vec<Episode>, // newest first
+}
+
+#[derive(Debug, Clone)]
+pub struct Episode {
+    pub url: String, // of the audio on the server, which is what its position is saved by
+    pub song: Song,  // streamed from the url until it's downloaded, then played from the file
+}
+
+// the podcasts from the config and where each episode was left
+#[derive(Default)]
+pub struct Podcasts {
+    pub podcasts: Vec<Podcast>,
+    pub positions: HashMap<String, Duration>,
+    pub download_dir: Option<String>,
+}
+
+impl Podcasts {
+    // songs queued before their episode was downloaded still have its url
+    pub fn episode(&self, song: &Song) -> Option<&Episode> {
+        self.podcasts
+            .iter()
+            .flat_map(|podcast| podcast.episodes.iter())
+            .find(|episode| episode.url == song.path || episode.song.path == song.path)
+    }
+
+    // where to carry on playing a song that's an episode
+    pub fn position(&self, song: &Song) -> Option<Duration> {
+        let episode = self.episode(song)?;
+        self.positions.get(&episode.url).copied()
+    }
+
+    // remember where an episode was left, none if it was played to the end. returns the url
+    // it's saved by and the position kept, which is none once the episode is done with
+    pub fn leave(
+        &mut self,
+        song: &Song,
+        position: Option<Duration>,
+    ) -> Option<(String, Option<Duration>)> {
+        let url = self.episode(song)?.url.to_owned();
+        let length = Duration::from_secs(song.duration_secs);
+        let position = position.filter(|position| {
+            !position.is_zero() && (length.is_zero() || *position + FINISHED_WITHIN < length)
+        });
+        match position {
+            Some(position) => self.positions.insert(url.to_owned(), position),
+            None => self.positions.remove(&url),
+        };
+        Some((url, position))
+    }
+
+    // play the downloaded file from now on
+    pub fn set_downloaded(&mut self, url: &str, path: String) {
+        for podcast in self.podcasts.iter_mut() {
+            for episode in podcast
+                .episodes
+                .iter_mut()
+                .filter(|episode| episode.url == url)
+            {
+                episode.song.path = path.to_owned();
+            }
+        }
+    }
+}
+
+// read a feed, with its episodes pointing at the files already downloaded to `download_dir`
+pub fn fetch(feed_url: &str, download_dir: Option<&str>) -> Result<Podcast, PodcastError> {
+    let mut xml = Vec::new();
+    get(feed_url)?.read_to_end(&mut xml)?;
+    let xml = String::from_utf8_lossy(&xml).into_owned();
+    let channel = rss::parse(&xml).map_err(|reason| PodcastError::Feed {
+        url: feed_url.to_string(),
+        reason,
+    })?;
+    let mut episodes: Vec<Episode> = channel
+        .items
+        .into_iter()
+        .map(|item| {
+            let mut song = Song::stream(item.url.to_owned());
+            song.title = item.title;
+            song.album_title = channel.title.to_owned();
+            song.album_artist = channel
+                .author
+                .to_owned()
+                .unwrap_or(channel.title.to_owned());
+            song.track_artist = item.author.unwrap_or(song.album_artist.to_owned());
+            song.duration_secs = item
+                .duration
+                .as_deref()
+                .and_then(parse_duration)
+                .unwrap_or(0);
+            song.added_secs = item.published.as_deref().and_then(parse_date).unwrap_or(0);
+            if let Some(dir) = download_dir {
+                let path = download_path(dir, &song, &item.url);
+                if path.is_file() {
+                    song.path = path.to_string_lossy().into_owned();
+                }
+            }
+            Episode {
+                url: item.url,
+                song,
+            }
+        })
+        .collect();
+    episodes.sort_by_key(|episode| Reverse(episode.song.added_secs));
+    Ok(Podcast {
+        title: channel.title,
+        episodes,
+    })
+}
+
+// save an episode to the download directory, returning where it went
+pub fn download(episode: &Episode, download_dir: &str) -> Result<String, PodcastError> {
+    let path = download_path(download_dir, &episode.song, &episode.url);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // a download that's cut off isn't mistaken for the episode
+    let partial = path.with_extension("part");
+    io::copy(&mut get(&episode.url)?, &mut File::create(&partial)?)?;
+    fs::rename(&partial, &path)?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+// <dir>/<podcast>/<episode>.<extension of the url>
+fn download_path(dir: &str, song: &Song, url: &str) -> PathBuf {
+    let url_path = url.split(['?', '#']).next().unwrap_or(url);
+    let extension = Path::new(url_path)
+        .extension()
+        .map_or("mp3".to_string(), |extension| {
+            extension.to_string_lossy().into_owned()
+        });
+    Path::new(dir)
+        .join(file_name(&song.album_title))
+        .join(format!("{}.{}", file_name(&song.title), extension))
+}
+
+// titles can have characters that aren't allowed in file names
+fn file_name(title: &str) -> String {
+    title
+        .chars()
+        .map(|ch| match ch {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            ch => ch,
+        })
+        .collect::<String>()
+        .trim_matches(['.', ' '])
+        .to_string()
+}
+
+// the body of what's at a url, read as it comes in
+fn get(url: &str) -> Result<impl Read, PodcastError> {
+    match ureq::get(url).call() {
+        Ok(response) => Ok(response.into_reader()),
+        Err(err) => Err(PodcastError::Request {
+            url: url.to_string(),
+            reason: err.to_string(),
+        }),
+    }
+}
+
+// itunes:duration is seconds, minutes:seconds or hours:minutes:seconds
+fn parse_duration(duration: &str) -> Option<u64> {
+    let mut secs = 0;
+    for part in duration.split(':') {
+        // some feeds give fractions of a second
+        let part = part.split('.').next()?;
+        secs = secs * 60 + part.trim().parse::<u64>().ok()?;
+    }
+    Some(secs)
+}
+
+// rfc 2822 dates like "Wed, 02 Oct 2002 13:00:00 GMT" or "2 Oct 2002 13:00 +0200" as unix time
+fn parse_date(date: &str) -> Option<u64> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let mut parts = date.split_whitespace().peekable();
+    // the day of the week is optional
+    if parts.peek()?.ends_with(',') {
+        parts.next();
+    }
+    let day: i64 = parts.next()?.parse().ok()?;
+    let month_name = parts.next()?.to_lowercase();
+    let month = MONTHS
+        .iter()
+        .position(|month| month_name.starts_with(month))? as i64
+        + 1;
+    let year: i64 = parts.next()?.parse().ok()?;
+    let mut time = parts.next().unwrap_or("0:0").split(':');
+    let hours: i64 = time.next()?.parse().ok()?;
+    let minutes: i64 = time.next()?.parse().ok()?;
+    let secs: i64 = time.next().map_or(Some(0), |secs| secs.parse().ok())?;
+    // named zones other than utc are rare enough to be taken as utc too
+    let offset_secs = match parts.next() {
+        Some(zone) if zone.len() == 5 && (zone.starts_with('+') || zone.starts_with('-')) => {
+            let hhmm: i64 = zone[1..].parse().ok()?;
+            let offset = (hhmm / 100) * 3600 + (hhmm % 100) * 60;
+            if zone.starts_with('-') {
+                -offset
+            } else {
+                offset
+            }
+        }
+        _ => 0,
+    };
+    let unix = days_from_civil(year, month, day) * 86400 + hours * 3600 + minutes * 60 + secs
+        - offset_secs;
+    u64::try_from(unix).ok()
+}
+
+// days since 1970-01-01 of a date in the gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = (month + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
@@ -0,0 +1,236 @@
+// reads the channel and its episodes out of a podcast feed. namespaced fields are matched by
+// their prefix, eg. itunes:duration
+
+use quick_xml::escape::{resolve_html5_entity, resolve_predefined_entity};
+use quick_xml::events::{BytesRef, BytesStart, Event};
+use quick_xml::{Reader, XmlVersion};
+
+// an episode as the feed lists it
+pub struct Item {
+    pub title: String,
+    pub author: Option<String>,
+    pub url: String, // of the audio, from the enclosure
+    pub published: Option<String>,
+    pub duration: Option<String>,
+}
+
+pub struct Channel {
+    pub title: String,
+    pub author: Option<String>,
+    pub items: Vec<Item>,
+}
+
+// what's read of an item until it ends
+#[derive(Default)]
+struct ItemFields {
+    title: Option<String>,
+    author: Option<String>,
+    url: Option<String>,
+    published: Option<String>,
+    duration: Option<String>,
+}
+
+pub fn parse(xml: &str) -> Result<Channel, String> {
+    let mut reader = Reader::from_str(xml);
+    // the elements open around where the reader is, innermost last
+    let mut open: Vec<String> = Vec::new();
+    // the text of the innermost element, cdata and entities included
+    let mut text = String::new();
+    let mut in_channel = false;
+    let mut title = None;
+    let mut author = None;
+    let mut item: Option<ItemFields> = None;
+    let mut items = Vec::new();
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|err| format!("{} at byte {}", err, reader.error_position()))?;
+        match event {
+            Event::Start(tag) => {
+                let name = tag.name().as_ref().to_string();
+                match name.as_str() {
+                    "channel" if !in_channel => in_channel = true,
+                    "item" if in_channel => item = Some(ItemFields::default()),
+                    "enclosure" => enclosure(&tag, item.as_mut())?,
+                    _ => (),
+                }
+                open.push(name);
+                text.clear();
+            }
+            Event::Empty(tag) if tag.name().as_ref() == "enclosure" => {
+                enclosure(&tag, item.as_mut())?
+            }
+            Event::Text(content) => text.push_str(&content.xml10_content()),
+            Event::CData(content) => text.push_str(&content.xml10_content()),
+            Event::GeneralRef(entity) => push_entity(&mut text, &entity),
+            Event::End(_) => {
+                let name = open.pop().unwrap_or_default();
+                let parent = open.last().map(String::as_str);
+                let value = Some(text.trim().to_string()).filter(|value| !value.is_empty());
+                text.clear();
+                match (parent, item.as_mut()) {
+                    (_, Some(_)) if name == "item" => {
+                        let fields = item.take().unwrap();
+                        // episodes without audio, eg. announcements, aren't playable
+                        if let Some(url) = fields.url {
+                            items.push(Item {
+                                title: fields.title.unwrap_or_else(|| url.to_owned()),
+                                author: fields.author,
+                                url,
+                                published: fields.published,
+                                duration: fields.duration,
+                            });
+                        }
+                    }
+                    (Some("item"), Some(fields)) => match name.as_str() {
+                        "title" => fields.title = value,
+                        "itunes:author" => fields.author = value,
+                        "pubDate" => fields.published = value,
+                        "itunes:duration" => fields.duration = value,
+                        _ => (),
+                    },
+                    (Some("channel"), None) => match name.as_str() {
+                        "title" if title.is_none() => title = value,
+                        "itunes:author" if author.is_none() => author = value,
+                        _ => (),
+                    },
+                    // only the first channel is read
+                    _ if name == "channel" && in_channel => break,
+                    _ => (),
+                }
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+    if !in_channel {
+        return Err("there's no channel in the feed".to_string());
+    }
+    Ok(Channel {
+        title: title.ok_or("the feed has no title")?,
+        author,
+        items,
+    })
+}
+
+// the url of the audio, taken from the first enclosure of an item
+fn enclosure(
+    tag: &BytesStart,
+    item: Option<&mut ItemFields>,
+) -> Result<(), String> {
+    let item = match item {
+        Some(item) if item.url.is_none() => item,
+        _ => return Ok(()),
+    };
+    let url = match tag.try_get_attribute("url").map_err(|err| err.to_string())? {
+        Some(url) => url,
+        None => return Ok(()),
+    };
+    let url = url
+        .normalized_value_with(XmlVersion::Implicit1_0, 128, resolve_entity)
+        .map_err(|err| err.to_string())?;
+    item.url = Some(url.trim().to_string()).filter(|url| !url.is_empty());
+    Ok(())
+}
+
+// feeds written by hand or from html often use html's entities, eg. &nbsp; or &eacute;. ones
+// that aren't known are kept as they were written
+fn push_entity(text: &mut String, entity: &BytesRef) {
+    let name: &str = entity;
+    match entity.resolve_char_ref() {
+        Ok(Some(ch)) => text.push(ch),
+        _ => match resolve_entity(name) {
+            Some(resolved) => text.push_str(resolved),
+            None => {
+                text.push('&');
+                text.push_str(name);
+                text.push(';');
+            }
+        },
+    }
+}
+
+fn resolve_entity(entity: &str) -> Option<&'static str> {
+    resolve_predefined_entity(entity).or_else(|| resolve_html5_entity(entity))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEED: &str = r#"<?xml version="1.0"?>
+<rss xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd">
+  <channel>
+    <title>Tom &amp; Jerry's Show</title>
+    <itunes:author>Tom</itunes:author>
+    <item>
+      <title><![CDATA[Episode <1>]]></title>
+      <enclosure length="1" type="audio/mpeg" url="https://example.com/1.mp3?a=1&amp;b=2"/>
+      <itunes:duration>1:02:03</itunes:duration>
+      <pubDate>Mon, 01 Jan 2024 00:00:00 GMT</pubDate>
+    </item>
+    <item>
+      <title>Announcement</title>
+    </item>
+    <item>
+      <titleExtra>not the title</titleExtra>
+      <enclosure url='https://example.com/3.mp3' />
+    </item>
+  </channel>
+</rss>"#;
+
+    #[test]
+    fn channel_and_playable_items() {
+        let channel = parse(FEED).unwrap();
+        assert_eq!(channel.title, "Tom & Jerry's Show");
+        assert_eq!(channel.author.as_deref(), Some("Tom"));
+        assert_eq!(channel.items.len(), 2);
+        let first = &channel.items[0];
+        assert_eq!(first.title, "Episode <1>");
+        assert_eq!(first.url, "https://example.com/1.mp3?a=1&b=2");
+        assert_eq!(first.duration.as_deref(), Some("1:02:03"));
+        assert_eq!(first.author, None);
+        // without a title of its own, an episode goes by its url
+        assert_eq!(channel.items[1].title, "https://example.com/3.mp3");
+    }
+
+    #[test]
+    fn entities() {
+        let feed = "<rss><channel><title>&lt;a&gt; &#233;&#xE9;&nbsp;&eacute; &bogus;</title>\
+                    </channel></rss>";
+        assert_eq!(parse(feed).unwrap().title, "<a> éé\u{a0}é &bogus;");
+    }
+
+    #[test]
+    fn enclosures_with_their_attributes_on_other_lines() {
+        let feed = "<rss><channel><title>Show</title><item><enclosure
+\turl=\"https://example.com/1.mp3\"
+\ttype=\"audio/mpeg\"/></item></channel></rss>";
+        let channel = parse(feed).unwrap();
+        assert_eq!(channel.items[0].url, "https://example.com/1.mp3");
+    }
+
+    #[test]
+    fn markup_in_cdata_is_only_text() {
+        let feed = r#"<rss><channel><title>Show</title>
+            <item>
+              <title>One</title>
+              <description><![CDATA[<item><title>Not one</title></item></channel>]]></description>
+              <enclosure url="https://example.com/1.mp3"/>
+            </item>
+            <item>
+              <title>Two</title>
+              <enclosure url="https://example.com/2.mp3"/>
+            </item>
+          </channel></rss>"#;
+        let channel = parse(feed).unwrap();
+        let titles: Vec<&str> = channel.items.iter().map(|item| item.title.as_str()).collect();
+        assert_eq!(titles, ["One", "Two"]);
+    }
+
+    #[test]
+    fn feeds_without_a_channel_or_title() {
+        assert!(parse("<rss></rss>").is_err());
+        assert!(parse("<channel><item></item></channel>").is_err());
+    }
+}
@@ -1,6 +1,20 @@
+pub mod undo;
+
+use crate::library::playlist::{self, PlaylistEntry, PlaylistFormats};
 use crate::library::song::Song;
+use crate::library::Library;
+use crate::state::AppState;
+use crate::utils::constants::requests::QueueRequests;
+
+use undo::Inverses;
 
+use rand::Rng;
 use std::collections::VecDeque;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub struct SongQueue {
     previous_queue: VecDeque<Song>,
@@ -40,4 +54,240 @@ impl SongQueue {
             return self.immediate_queue.pop_front();
         }
     }
+
+    pub fn next_random(&mut self) -> Option<Song> {
+        if self.is_empty() {
+            return None;
+        }
+        let idx = rand::thread_rng().gen_range(0..self.len());
+        self.remove(idx)
+    }
+
+    pub fn last_played(&self) -> Option<&Song> {
+        self.previous_queue.back()
+    }
+
+    // to play it again, going back through what was played
+    pub fn take_last_played(&mut self) -> Option<Song> {
+        self.previous_queue.pop_back()
+    }
+
+    pub fn clear(&mut self) {
+        self.immediate_queue.clear();
+        self.upcoming_queue.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.immediate_queue.len() + self.upcoming_queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // songs in the order they will be played, immediate songs first
+    pub fn iter(&self) -> impl Iterator<Item = &Song> {
+        self.immediate_queue.iter().chain(self.upcoming_queue.iter())
+    }
+
+    pub fn remove(&mut self, idx: usize) -> Option<Song> {
+        let immediate_len = self.immediate_queue.len();
+        if idx < immediate_len {
+            self.immediate_queue.remove(idx)
+        } else {
+            self.upcoming_queue.remove(idx - immediate_len)
+        }
+    }
+
+    // move the song at `from` so that it ends up at `to`, both indices being in play order
+    pub fn move_song(&mut self, from: usize, to: usize) {
+        if from >= self.len() || to >= self.len() || from == to {
+            return;
+        }
+        let song = self.remove(from).unwrap();
+        let immediate_len = self.immediate_queue.len();
+        if to <= immediate_len {
+            self.immediate_queue.insert(to, song);
+        } else {
+            self.upcoming_queue.insert(to - immediate_len, song);
+        }
+    }
+}
+
+impl Default for SongQueue {
+    fn default() -> Self {
+        SongQueue::new()
+    }
+}
+
+// queue requests act on whichever songs are selected in the UI
+pub fn handle(app_state: &Arc<Mutex<AppState>>, request: QueueRequests) {
+    let mut guard = app_state.lock().unwrap();
+    match request {
+        QueueRequests::Enqueue => {
+            let songs = match songs_to_add(&mut guard) {
+                Some(songs) => songs,
+                None => return,
+            };
+            let count = songs.len();
+            for song in songs {
+                guard.queue.add_last_immediate(song);
+            }
+            notify_added(&mut guard, count, "the queue");
+        }
+        QueueRequests::InsertNext => {
+            let songs = match songs_to_add(&mut guard) {
+                Some(songs) => songs,
+                None => return,
+            };
+            let count = songs.len();
+            // backwards so that they're played in the order they were selected in
+            for song in songs.into_iter().rev() {
+                guard.queue.add_first_immediate(song);
+            }
+            notify_added(&mut guard, count, "the front of the queue");
+        }
+        QueueRequests::Clear => {
+            if !guard.queue.is_empty() {
+                let inverse = Inverses::cleared(&guard.queue);
+                guard.undo.record("clearing the queue", inverse);
+            }
+            guard.queue.clear();
+            guard.ui.notify("Cleared the queue");
+        }
+        QueueRequests::Undo => undo(&mut guard, false),
+        QueueRequests::Redo => undo(&mut guard, true),
+        QueueRequests::SaveAsPlaylist(name) => {
+            let songs: Vec<Song> = guard.queue.iter().cloned().collect();
+            let result = match &guard.library.playlist_dir {
+                _ if songs.is_empty() => Err("The queue is empty".into()),
+                Some(dir) => save_playlist(Path::new(dir), &songs, name),
+                None => Err("Set playlist_directory in the config to save playlists".into()),
+            };
+            match result {
+                Ok((path, inverse)) => {
+                    guard.undo.record(format!("saving {}", path), inverse);
+                    info!("Saved the queue to {}", path);
+                    guard.ui.notify(format!("Saved the queue to {}", path));
+                }
+                Err(e) => guard.ui.error = Some(format!("Could not save the queue: {}", e)),
+            }
+        }
+        QueueRequests::AddToPlaylist(name) => {
+            let songs = guard.ui.take_marked_songs();
+            let result = match &guard.library.playlist_dir {
+                _ if songs.is_empty() => Err("No songs are selected".into()),
+                Some(dir) => add_to_playlist(&guard.library, Path::new(dir), &songs, &name),
+                None => Err("Set playlist_directory in the config to save playlists".into()),
+            };
+            match result {
+                Ok((path, inverse)) => {
+                    guard.undo.record(format!("adding to {}", path), inverse);
+                    info!("Added {} songs to {}", songs.len(), path);
+                    notify_added(&mut guard, songs.len(), &path);
+                }
+                Err(e) => guard.ui.error = Some(format!("Could not add to {}: {}", name, e)),
+            }
+        }
+        QueueRequests::AddPath(path) => match guard.library.songs_at(&path) {
+            Ok(songs) if songs.is_empty() => {
+                guard.ui.error = Some(format!("There's nothing to play in {}", path.display()))
+            }
+            Ok(songs) => {
+                let count = songs.len();
+                for song in songs {
+                    guard.queue.add_last_immediate(song);
+                }
+                notify_added(&mut guard, count, "the queue");
+            }
+            Err(e) => guard.ui.error = Some(format!("Could not add {}: {}", path.display(), e)),
+        },
+    }
+}
+
+// what's under the file browser's cursor, or else the songs picked out
+fn songs_to_add(state: &mut AppState) -> Option<Vec<Song>> {
+    let path = match state.ui.selected_path.clone() {
+        Some(path) => path,
+        None => return Some(state.ui.take_marked_songs()),
+    };
+    match state.library.songs_at(&path) {
+        Ok(songs) => Some(songs),
+        Err(e) => {
+            state.ui.error = Some(format!("Could not add {}: {}", path.display(), e));
+            None
+        }
+    }
+}
+
+// puts back what the last change replaced, or what undoing it did
+fn undo(state: &mut AppState, redo: bool) {
+    let (done, undone) = match redo {
+        true => ("Redid", state.undo.redo(&mut state.queue)),
+        false => ("Undid", state.undo.undo(&mut state.queue)),
+    };
+    match undone {
+        Ok(Some(change)) => state.ui.notify(format!("{} {}", done, change)),
+        Ok(None) if redo => state.ui.notify_error("There's nothing to redo"),
+        Ok(None) => state.ui.notify_error("There's nothing to undo"),
+        Err(e) => state.ui.error = Some(format!("Could not {}: {}", done.to_lowercase(), e)),
+    }
+}
+
+fn notify_added(state: &mut AppState, count: usize, to: &str) {
+    match count {
+        0 => state.ui.notify_error("No song is selected"),
+        1 => state.ui.notify(format!("Added 1 song to {}", to)),
+        count => state.ui.notify(format!("Added {} songs to {}", count, to)),
+    }
+}
+
+// saved under the time it was made unless it was given a name. the file it replaced is returned
+// along with where it is
+fn save_playlist(
+    dir: &Path,
+    songs: &[Song],
+    name: Option<String>,
+) -> Result<(String, Inverses), Box<dyn Error>> {
+    fs::create_dir_all(dir)?;
+    let file_name = match name {
+        Some(name) => playlist_file_name(&name),
+        None => {
+            let secs = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+            format!("queue-{}.m3u8", secs)
+        }
+    };
+    let path = dir.join(file_name);
+    let inverse = Inverses::playlist(&path)?;
+    playlist::write(&path, songs)?;
+    Ok((path.to_string_lossy().into_owned(), inverse))
+}
+
+// append to the playlist with this name, which is made if there isn't one yet
+fn add_to_playlist(
+    library: &Library,
+    dir: &Path,
+    songs: &[Song],
+    name: &str,
+) -> Result<(String, Inverses), Box<dyn Error>> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(playlist_file_name(name));
+    let inverse = Inverses::playlist(&path)?;
+    let mut entries = if path.exists() {
+        library.playlist_songs(&PlaylistEntry::File(path.to_owned()))?
+    } else {
+        Vec::new()
+    };
+    entries.extend_from_slice(songs);
+    playlist::write(&path, &entries)?;
+    Ok((path.to_string_lossy().into_owned(), inverse))
+}
+
+// keeps the file in the playlist directory, an m3u8 unless the name says otherwise
+fn playlist_file_name(name: &str) -> String {
+    let name = name.replace(['/', '\\'], "-");
+    match PlaylistFormats::from_path(Path::new(&name)) {
+        Some(_) => name,
+        None => format!("{}.m3u8", name),
+    }
 }
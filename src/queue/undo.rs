@@ -0,0 +1,265 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crate::library::song::Song;
+use crate::queue::SongQueue;
+
+// how many changes can be undone, the oldest are forgotten after that
+const UNDO_LEN: usize = 20;
+
+// a song of the queue and where it is, in the songs added to play next or in the rest
+pub struct Placed {
+    immediate: bool,
+    idx: usize,
+    song: Song,
+}
+
+// what undoes a change. the queue is changed back a song at a time, so songs added, played or
+// moved since are kept. a playlist's file is put back as it was
+pub enum Inverses {
+    REINSERT(Vec<Placed>), // songs taken out of the queue, in the order they were in
+    REMOVE(Vec<Placed>),   // songs put back into it
+    PLAYLIST(PathBuf, Option<Vec<u8>>), // the file as it was, none if there wasn't one
+}
+
+impl Inverses {
+    // taken before the song at `idx` in play order is removed
+    pub fn removed(queue: &SongQueue, idx: usize) -> Option<Inverses> {
+        let immediate_len = queue.immediate_queue.len();
+        let placed = match idx < immediate_len {
+            true => Placed {
+                immediate: true,
+                idx,
+                song: queue.immediate_queue.get(idx)?.clone(),
+            },
+            false => Placed {
+                immediate: false,
+                idx: idx - immediate_len,
+                song: queue.upcoming_queue.get(idx - immediate_len)?.clone(),
+            },
+        };
+        Some(Inverses::REINSERT(vec![placed]))
+    }
+
+    // taken before the queue is cleared
+    pub fn cleared(queue: &SongQueue) -> Inverses {
+        let mut songs = Vec::new();
+        for (immediate, part) in [(true, &queue.immediate_queue), (false, &queue.upcoming_queue)] {
+            songs.extend(part.iter().enumerate().map(|(idx, song)| Placed {
+                immediate,
+                idx,
+                song: song.clone(),
+            }));
+        }
+        Inverses::REINSERT(songs)
+    }
+
+    pub fn playlist(path: &Path) -> io::Result<Inverses> {
+        let contents = match fs::read(path) {
+            Ok(contents) => Some(contents),
+            Err(err) if err.kind() == ErrorKind::NotFound => None,
+            Err(err) => return Err(err),
+        };
+        Ok(Inverses::PLAYLIST(path.to_owned(), contents))
+    }
+
+    // returns what undoes this in turn, to go back to that again
+    fn apply(&self, queue: &mut SongQueue) -> io::Result<Inverses> {
+        match self {
+            Inverses::REINSERT(songs) => Ok(Inverses::REMOVE(reinsert(queue, songs))),
+            Inverses::REMOVE(songs) => Ok(Inverses::REINSERT(remove(queue, songs))),
+            Inverses::PLAYLIST(path, contents) => {
+                let replaced = Inverses::playlist(path)?;
+                match contents {
+                    Some(contents) => fs::write(path, contents)?,
+                    None => fs::remove_file(path)?,
+                }
+                Ok(replaced)
+            }
+        }
+    }
+}
+
+fn part(queue: &mut SongQueue, immediate: bool) -> &mut VecDeque<Song> {
+    match immediate {
+        true => &mut queue.immediate_queue,
+        false => &mut queue.upcoming_queue,
+    }
+}
+
+fn same_song(a: &Song, b: &Song) -> bool {
+    a.path == b.path && a.start_ms == b.start_ms
+}
+
+// puts the songs back where they were, or at the end if the queue is shorter now. returns where
+// they went
+fn reinsert(queue: &mut SongQueue, songs: &[Placed]) -> Vec<Placed> {
+    let mut inserted = Vec::new();
+    for placed in songs {
+        let part = part(queue, placed.immediate);
+        let idx = placed.idx.min(part.len());
+        part.insert(idx, placed.song.clone());
+        inserted.push(Placed {
+            immediate: placed.immediate,
+            idx,
+            song: placed.song.clone(),
+        });
+    }
+    inserted
+}
+
+// takes the songs out again, looking for them elsewhere in the queue if it has changed around
+// them. returns where they were, for putting them back
+fn remove(queue: &mut SongQueue, songs: &[Placed]) -> Vec<Placed> {
+    let mut removed = Vec::new();
+    for placed in songs.iter().rev() {
+        let part = part(queue, placed.immediate);
+        let idx = match part.get(placed.idx) {
+            Some(song) if same_song(song, &placed.song) => Some(placed.idx),
+            _ => part.iter().position(|song| same_song(song, &placed.song)),
+        };
+        if let Some((idx, song)) = idx.and_then(|idx| Some((idx, part.remove(idx)?))) {
+            removed.push(Placed {
+                immediate: placed.immediate,
+                idx,
+                song,
+            });
+        }
+    }
+    removed.reverse();
+    removed
+}
+
+// the changes to the queue and the playlists, each with what it was called to tell what was
+// undone, eg. "clearing the queue"
+pub struct UndoHistory {
+    undo: Vec<(String, Inverses)>,
+    redo: Vec<(String, Inverses)>,
+}
+
+impl UndoHistory {
+    // what undoes a change that was just made. what was undone can't be redone after it
+    pub fn record(&mut self, change: impl Into<String>, inverse: Inverses) {
+        self.redo.clear();
+        self.undo.push((change.into(), inverse));
+        if self.undo.len() > UNDO_LEN {
+            self.undo.remove(0);
+        }
+    }
+
+    // the change undone, none if there wasn't one
+    pub fn undo(&mut self, queue: &mut SongQueue) -> io::Result<Option<String>> {
+        step(&mut self.undo, &mut self.redo, queue)
+    }
+
+    pub fn redo(&mut self, queue: &mut SongQueue) -> io::Result<Option<String>> {
+        step(&mut self.redo, &mut self.undo, queue)
+    }
+}
+
+impl Default for UndoHistory {
+    fn default() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+}
+
+// applies the last inverse of `from`, keeping what undoes it in `to`. one that couldn't be
+// applied is kept to try again
+fn step(
+    from: &mut Vec<(String, Inverses)>,
+    to: &mut Vec<(String, Inverses)>,
+    queue: &mut SongQueue,
+) -> io::Result<Option<String>> {
+    let (change, inverse) = match from.pop() {
+        Some(last) => last,
+        None => return Ok(None),
+    };
+    match inverse.apply(queue) {
+        Ok(undoing) => {
+            to.push((change.to_owned(), undoing));
+            Ok(Some(change))
+        }
+        Err(err) => {
+            from.push((change, inverse));
+            Err(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song(title: &str) -> Song {
+        Song::new(title.to_string(), format!("{}.flac", title))
+    }
+
+    fn titles(queue: &SongQueue) -> Vec<&str> {
+        queue.iter().map(|song| song.title.as_str()).collect()
+    }
+
+    fn queue_of(titles: &[&str]) -> SongQueue {
+        let mut queue = SongQueue::new();
+        for title in titles {
+            queue.add_upcoming(song(title));
+        }
+        queue
+    }
+
+    fn remove_at(history: &mut UndoHistory, queue: &mut SongQueue, idx: usize) {
+        let inverse = Inverses::removed(queue, idx).unwrap();
+        queue.remove(idx);
+        history.record("removing", inverse);
+    }
+
+    #[test]
+    fn undoing_a_removal_keeps_songs_added_since() {
+        let (mut history, mut queue) = (UndoHistory::default(), queue_of(&["a", "b", "c"]));
+        remove_at(&mut history, &mut queue, 1);
+        queue.add_upcoming(song("d"));
+        history.undo(&mut queue).unwrap();
+        assert_eq!(titles(&queue), ["a", "b", "c", "d"]);
+        history.redo(&mut queue).unwrap();
+        assert_eq!(titles(&queue), ["a", "c", "d"]);
+    }
+
+    #[test]
+    fn undoing_a_clear_keeps_songs_added_since() {
+        let (mut history, mut queue) = (UndoHistory::default(), queue_of(&["a", "b"]));
+        queue.add_last_immediate(song("next"));
+        history.record("clearing", Inverses::cleared(&queue));
+        queue.clear();
+        queue.add_last_immediate(song("c"));
+        history.undo(&mut queue).unwrap();
+        assert_eq!(titles(&queue), ["next", "c", "a", "b"]);
+        history.redo(&mut queue).unwrap();
+        assert_eq!(titles(&queue), ["c"]);
+        history.undo(&mut queue).unwrap();
+        assert_eq!(titles(&queue), ["next", "c", "a", "b"]);
+    }
+
+    #[test]
+    fn redo_finds_a_song_that_was_moved() {
+        let (mut history, mut queue) = (UndoHistory::default(), queue_of(&["a", "b", "c"]));
+        remove_at(&mut history, &mut queue, 0);
+        history.undo(&mut queue).unwrap();
+        queue.move_song(0, 2);
+        history.redo(&mut queue).unwrap();
+        assert_eq!(titles(&queue), ["b", "c"]);
+    }
+
+    #[test]
+    fn undoing_a_removal_after_songs_were_played() {
+        let (mut history, mut queue) = (UndoHistory::default(), queue_of(&["a", "b", "c"]));
+        remove_at(&mut history, &mut queue, 2);
+        queue.next();
+        queue.next();
+        history.undo(&mut queue).unwrap();
+        assert_eq!(titles(&queue), ["c"]);
+    }
+}
@@ -0,0 +1,81 @@
+use rusqlite::{params, Connection};
+use std::error::Error;
+use std::path::Path;
+
+use super::Scrobble;
+
+// plays that haven't been accepted by a service yet, eg. while offline. they're kept in the
+// library database and sent again, oldest first, the next time something is scrobbled
+pub struct ScrobbleCache {
+    conn: Connection,
+}
+
+impl ScrobbleCache {
+    pub fn open(path: &Path) -> Result<ScrobbleCache, Box<dyn Error>> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS scrobbles (
+                id INTEGER PRIMARY KEY,
+                service TEXT NOT NULL,
+                artist TEXT NOT NULL,
+                title TEXT NOT NULL,
+                album TEXT NOT NULL,
+                duration_secs INTEGER NOT NULL,
+                timestamp INTEGER NOT NULL
+            );",
+        )?;
+        Ok(ScrobbleCache { conn })
+    }
+
+    pub fn add(&mut self, service: &str, scrobble: &Scrobble) -> Result<(), Box<dyn Error>> {
+        self.conn.execute(
+            "INSERT INTO scrobbles (service, artist, title, album, duration_secs, timestamp) \
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                service,
+                scrobble.artist,
+                scrobble.title,
+                scrobble.album,
+                scrobble.duration_secs as i64,
+                scrobble.timestamp as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    // the oldest plays waiting to be sent to a service, with their ids to remove them by
+    pub fn pending(
+        &self,
+        service: &str,
+        limit: usize,
+    ) -> Result<Vec<(i64, Scrobble)>, Box<dyn Error>> {
+        let mut statement = self.conn.prepare(
+            "SELECT id, artist, title, album, duration_secs, timestamp FROM scrobbles \
+            WHERE service = ?1 ORDER BY timestamp LIMIT ?2",
+        )?;
+        let pending = statement
+            .query_map(params![service, limit as i64], |row| {
+                Ok((
+                    row.get(0)?,
+                    Scrobble {
+                        artist: row.get(1)?,
+                        title: row.get(2)?,
+                        album: row.get(3)?,
+                        duration_secs: row.get::<_, i64>(4)? as u64,
+                        timestamp: row.get::<_, i64>(5)? as u64,
+                    },
+                ))
+            })?
+            .collect::<Result<Vec<(i64, Scrobble)>, rusqlite::Error>>()?;
+        Ok(pending)
+    }
+
+    pub fn remove(&mut self, ids: &[i64]) -> Result<(), Box<dyn Error>> {
+        let tx = self.conn.transaction()?;
+        for id in ids {
+            tx.execute("DELETE FROM scrobbles WHERE id = ?1", [id])?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,17 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ScrobbleError {
+    #[error("The request failed: {0}")]
+    Request(String),
+    #[error("{service} answered with something that isn't json: {reason}")]
+    Response {
+        service: &'static str,
+        reason: String,
+    },
+    #[error("{service} refused it: {reason}")]
+    Refused {
+        service: &'static str,
+        reason: String,
+    },
+}
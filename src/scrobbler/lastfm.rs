@@ -0,0 +1,117 @@
+use serde_json::Value;
+
+use super::errors::ScrobbleError;
+use super::{check_refused, parse_response, post, url_encode, Scrobble, Service};
+use crate::config::LastfmConfig;
+
+const API_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+
+pub struct Lastfm {
+    config: LastfmConfig,
+    // asked for with the username and password the first time something is sent
+    session_key: Option<String>,
+}
+
+impl Lastfm {
+    pub fn new(config: LastfmConfig) -> Lastfm {
+        Lastfm {
+            config,
+            session_key: None,
+        }
+    }
+
+    // every call is signed with the md5 of its parameters, sorted by name, and the secret
+    fn call(
+        &self,
+        method: &str,
+        mut params: Vec<(String, String)>,
+    ) -> Result<Value, ScrobbleError> {
+        params.push(("method".to_string(), method.to_string()));
+        params.push(("api_key".to_string(), self.config.api_key.to_owned()));
+        params.sort();
+        let mut signed: String = params
+            .iter()
+            .map(|(name, value)| format!("{}{}", name, value))
+            .collect();
+        signed.push_str(&self.config.api_secret);
+        params.push(("api_sig".to_string(), format!("{:x}", md5::compute(signed.as_bytes()))));
+        // the format isn't part of the signature
+        params.push(("format".to_string(), "json".to_string()));
+
+        let body = params
+            .iter()
+            .map(|(name, value)| format!("{}={}", url_encode(name), url_encode(value)))
+            .collect::<Vec<String>>()
+            .join("&");
+        let headers = [("Content-Type", "application/x-www-form-urlencoded")];
+        let response = parse_response(self.name(), &post(API_URL, &headers, &body)?)?;
+        check_refused(self.name(), &response, "message")?;
+        Ok(response)
+    }
+
+    fn session_key(&mut self) -> Result<String, ScrobbleError> {
+        if let Some(key) = &self.session_key {
+            return Ok(key.to_owned());
+        }
+        let response = self.call(
+            "auth.getMobileSession",
+            vec![
+                ("username".to_string(), self.config.username.to_owned()),
+                ("password".to_string(), self.config.password.to_owned()),
+            ],
+        )?;
+        let key = response["session"]["key"].as_str().ok_or(ScrobbleError::Refused {
+            service: self.name(),
+            reason: "no session was given".to_string(),
+        })?;
+        self.session_key = Some(key.to_owned());
+        Ok(key.to_owned())
+    }
+}
+
+impl Service for Lastfm {
+    fn name(&self) -> &'static str {
+        "Last.fm"
+    }
+
+    fn now_playing(&mut self, scrobble: &Scrobble) -> Result<(), ScrobbleError> {
+        let mut params = track_params(scrobble, None);
+        params.push(("sk".to_string(), self.session_key()?));
+        self.call("track.updateNowPlaying", params)?;
+        Ok(())
+    }
+
+    // up to 50 at a time
+    fn scrobble(&mut self, scrobbles: &[Scrobble]) -> Result<(), ScrobbleError> {
+        let mut params: Vec<(String, String)> = scrobbles
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, scrobble)| track_params(scrobble, Some(idx)))
+            .collect();
+        params.push(("sk".to_string(), self.session_key()?));
+        self.call("track.scrobble", params)?;
+        Ok(())
+    }
+
+    fn batch_size(&self) -> usize {
+        50
+    }
+}
+
+// the parameters of several tracks in one call are told apart by an index after their names
+fn track_params(scrobble: &Scrobble, idx: Option<usize>) -> Vec<(String, String)> {
+    let name = |name: &str| match idx {
+        Some(idx) => format!("{}[{}]", name, idx),
+        None => name.to_string(),
+    };
+    let mut params = vec![
+        (name("artist"), scrobble.artist.to_owned()),
+        (name("track"), scrobble.title.to_owned()),
+        (name("album"), scrobble.album.to_owned()),
+        (name("duration"), scrobble.duration_secs.to_string()),
+    ];
+    if idx.is_some() {
+        params.push((name("timestamp"), scrobble.timestamp.to_string()));
+    }
+    params
+}
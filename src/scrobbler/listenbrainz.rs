@@ -0,0 +1,76 @@
+use serde_json::{json, Value};
+
+use super::errors::ScrobbleError;
+use super::{check_refused, parse_response, post, Scrobble, Service};
+use crate::config::ListenBrainzConfig;
+
+const API_URL: &str = "https://api.listenbrainz.org/1/submit-listens";
+
+pub struct ListenBrainz {
+    config: ListenBrainzConfig,
+}
+
+impl ListenBrainz {
+    pub fn new(config: ListenBrainzConfig) -> ListenBrainz {
+        ListenBrainz { config }
+    }
+
+    // `listen_type` is "playing_now", "single" for one listen or "import" for several
+    fn submit(&self, listen_type: &str, listens: Vec<Value>) -> Result<(), ScrobbleError> {
+        let body = json!({ "listen_type": listen_type, "payload": listens }).to_string();
+        let authorization = format!("Token {}", self.config.token);
+        let headers = [
+            ("Authorization", authorization.as_str()),
+            ("Content-Type", "application/json"),
+        ];
+        let response = parse_response(self.name(), &post(API_URL, &headers, &body)?)?;
+        check_refused(self.name(), &response, "error")
+    }
+}
+
+impl Service for ListenBrainz {
+    fn name(&self) -> &'static str {
+        "ListenBrainz"
+    }
+
+    fn now_playing(&mut self, scrobble: &Scrobble) -> Result<(), ScrobbleError> {
+        self.submit("playing_now", vec![listen(scrobble, false)])
+    }
+
+    fn scrobble(&mut self, scrobbles: &[Scrobble]) -> Result<(), ScrobbleError> {
+        let listen_type = if scrobbles.len() == 1 {
+            "single"
+        } else {
+            "import"
+        };
+        let listens = scrobbles
+            .iter()
+            .map(|scrobble| listen(scrobble, true))
+            .collect();
+        self.submit(listen_type, listens)
+    }
+
+    // the most listens a request is allowed to have
+    fn batch_size(&self) -> usize {
+        1000
+    }
+}
+
+// a song that's playing now has no time it was listened at
+fn listen(scrobble: &Scrobble, listened: bool) -> Value {
+    let mut listen = json!({
+        "track_metadata": {
+            "artist_name": scrobble.artist,
+            "track_name": scrobble.title,
+            "release_name": scrobble.album,
+            "additional_info": {
+                "duration_ms": scrobble.duration_secs * 1000,
+                "media_player": "splay",
+            },
+        },
+    });
+    if listened {
+        listen["listened_at"] = json!(scrobble.timestamp);
+    }
+    listen
+}
@@ -0,0 +1,270 @@
+pub mod cache;
+pub mod errors;
+mod lastfm;
+mod listenbrainz;
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::Value;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::config::ScrobblingConfig;
+use crate::library::db;
+use crate::library::song::Song;
+use crate::utils::constants::requests::ScrobblerRequests;
+use cache::ScrobbleCache;
+use errors::ScrobbleError;
+use lastfm::Lastfm;
+use listenbrainz::ListenBrainz;
+
+// songs shorter than this aren't scrobbled
+const MIN_LENGTH: Duration = Duration::from_secs(30);
+// long songs count once this much of them was played, even if it's less than half
+const MAX_WAIT: Duration = Duration::from_secs(240);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+// a song that's playing or was played, as the services are told about it
+#[derive(Debug, Clone)]
+pub struct Scrobble {
+    pub artist: String,
+    pub title: String,
+    pub album: String,
+    pub duration_secs: u64,
+    pub timestamp: u64, // unix time it started playing
+}
+
+impl Scrobble {
+    fn new(song: &Song, timestamp: u64) -> Scrobble {
+        Scrobble {
+            artist: song.track_artist.to_owned(),
+            title: song.title.to_owned(),
+            album: song.album_title.to_owned(),
+            duration_secs: song.duration_secs,
+            timestamp,
+        }
+    }
+}
+
+// somewhere plays are sent to
+trait Service {
+    fn name(&self) -> &'static str;
+    fn now_playing(&mut self, scrobble: &Scrobble) -> Result<(), ScrobbleError>;
+    fn scrobble(&mut self, scrobbles: &[Scrobble]) -> Result<(), ScrobbleError>;
+    fn batch_size(&self) -> usize;
+}
+
+// the song that's playing is scrobbled once half of it or 4 minutes were played, unless it's
+// too short to count
+pub fn should_scrobble(position: Duration, length: Duration) -> bool {
+    length >= MIN_LENGTH && (position >= length / 2 || position >= MAX_WAIT)
+}
+
+// sends what the player plays to the services enabled in the config. runs until every sender
+// is gone
+pub fn listen(config: ScrobblingConfig, mut rx: UnboundedReceiver<ScrobblerRequests>) {
+    let mut services = enabled_services(&config);
+    let mut cache = match ScrobbleCache::open(&db::path()) {
+        Ok(cache) => Some(cache),
+        Err(err) => {
+            error!(
+                "Could not open the scrobble cache, failed scrobbles are lost. Reason: {}",
+                err
+            );
+            None
+        }
+    };
+    // the song that's playing and when it started
+    let mut playing: Option<(Song, u64)> = None;
+    // whatever is left over from last time
+    if let Some(cache) = &mut cache {
+        send_pending(&mut services, cache);
+    }
+
+    while let Some(request) = rx.blocking_recv() {
+        match request {
+            ScrobblerRequests::NowPlaying(song) => {
+                let scrobble = Scrobble::new(&song, now_secs());
+                for service in services.iter_mut() {
+                    if let Err(err) = service.now_playing(&scrobble) {
+                        warn!(
+                            "Could not update {}'s now playing. Reason: {}",
+                            service.name(),
+                            err
+                        );
+                    }
+                }
+                playing = Some((song, scrobble.timestamp));
+            }
+            ScrobblerRequests::Scrobble(song) => {
+                let started = match &playing {
+                    Some((curr_song, started)) if curr_song.is_same(&song) => *started,
+                    _ => now_secs(),
+                };
+                let scrobble = Scrobble::new(&song, started);
+                match &mut cache {
+                    // it's sent from the cache so it's not lost if that fails
+                    Some(cache) => {
+                        for service in &services {
+                            if let Err(err) = cache.add(service.name(), &scrobble) {
+                                error!("Could not cache a scrobble. Reason: {}", err);
+                            }
+                        }
+                        send_pending(&mut services, cache);
+                    }
+                    None => {
+                        for service in services.iter_mut() {
+                            if let Err(err) = service.scrobble(std::slice::from_ref(&scrobble)) {
+                                warn!("Could not scrobble to {}. Reason: {}", service.name(), err);
+                            }
+                        }
+                    }
+                }
+            }
+            ScrobblerRequests::SetConfig(config) => services = enabled_services(&config),
+        }
+    }
+}
+
+fn enabled_services(config: &ScrobblingConfig) -> Vec<Box<dyn Service>> {
+    let mut services: Vec<Box<dyn Service>> = Vec::new();
+    if config.lastfm.enabled {
+        services.push(Box::new(Lastfm::new(config.lastfm.clone())));
+    }
+    if config.listenbrainz.enabled {
+        services.push(Box::new(ListenBrainz::new(config.listenbrainz.clone())));
+    }
+    services
+}
+
+// each service is sent what's waiting for it until it's all gone or a request fails, in which
+// case the rest is tried again after the next scrobble
+fn send_pending(services: &mut [Box<dyn Service>], cache: &mut ScrobbleCache) {
+    for service in services.iter_mut() {
+        loop {
+            let pending = match cache.pending(service.name(), service.batch_size()) {
+                Ok(pending) => pending,
+                Err(err) => {
+                    error!("Could not read the scrobble cache. Reason: {}", err);
+                    return;
+                }
+            };
+            if pending.is_empty() {
+                break;
+            }
+            let (ids, scrobbles): (Vec<i64>, Vec<Scrobble>) = pending.into_iter().unzip();
+            if let Err(err) = service.scrobble(&scrobbles) {
+                warn!(
+                    "Could not scrobble to {}, {} scrobbles are kept for later. Reason: {}",
+                    service.name(),
+                    scrobbles.len(),
+                    err
+                );
+                break;
+            }
+            info!("Scrobbled {} songs to {}", scrobbles.len(), service.name());
+            if let Err(err) = cache.remove(&ids) {
+                // they'd only be sent again forever
+                error!(
+                    "Could not remove sent scrobbles from the cache. Reason: {}",
+                    err
+                );
+                return;
+            }
+        }
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs())
+}
+
+// the body of the response, which is also where the services say what was wrong with a request
+fn post(url: &str, headers: &[(&str, &str)], body: &str) -> Result<String, ScrobbleError> {
+    let mut request = ureq::post(url).timeout(REQUEST_TIMEOUT);
+    for (name, value) in headers {
+        request = request.set(name, value);
+    }
+    let response = match request.send_string(body) {
+        Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+        Err(err) => return Err(ScrobbleError::Request(err.to_string())),
+    };
+    response
+        .into_string()
+        .map_err(|err| ScrobbleError::Request(err.to_string()))
+}
+
+pub fn url_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+// the json every service answers with
+fn parse_response(service: &'static str, response: &str) -> Result<Value, ScrobbleError> {
+    serde_json::from_str(response).map_err(|err| ScrobbleError::Response {
+        service,
+        reason: err.to_string(),
+    })
+}
+
+// a response with an error at its top level is refused, with the reason in `reason_field`, or
+// the whole response if there's none
+fn check_refused(
+    service: &'static str,
+    response: &Value,
+    reason_field: &str,
+) -> Result<(), ScrobbleError> {
+    if response.get("error").is_none() {
+        return Ok(());
+    }
+    let reason = match response.get(reason_field).and_then(Value::as_str) {
+        Some(reason) => reason.to_string(),
+        None => response.to_string(),
+    };
+    Err(ScrobbleError::Refused { service, reason })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_a_top_level_error_is_refused() {
+        let playing = r##"{"nowplaying":{"track":{"#text":"error"},"artist":{"#text":"error"}}}"##;
+        let response = parse_response("Last.fm", playing).unwrap();
+        assert!(check_refused("Last.fm", &response, "message").is_ok());
+        let nested = r#"{"scrobbles":{"scrobble":{"ignoredMessage":{"error":"0"}}}}"#;
+        let response = parse_response("Last.fm", nested).unwrap();
+        assert!(check_refused("Last.fm", &response, "message").is_ok());
+    }
+
+    #[test]
+    fn refused_with_the_reason() {
+        let response = r#"{"error":9,"message":"Invalid \"session\" key"}"#;
+        let response = parse_response("Last.fm", response).unwrap();
+        match check_refused("Last.fm", &response, "message") {
+            Err(ScrobbleError::Refused { reason, .. }) => {
+                assert_eq!(reason, "Invalid \"session\" key")
+            }
+            _ => panic!("not refused"),
+        }
+        let response = parse_response("ListenBrainz", r#"{"code":401,"error":"Bad token"}"#);
+        match check_refused("ListenBrainz", &response.unwrap(), "error") {
+            Err(ScrobbleError::Refused { reason, .. }) => assert_eq!(reason, "Bad token"),
+            _ => panic!("not refused"),
+        }
+    }
+
+    #[test]
+    fn responses_that_arent_json() {
+        assert!(parse_response("Last.fm", "<html>").is_err());
+    }
+}
@@ -1,8 +1,23 @@
-use std::time::Duration;
+pub mod session;
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedSender;
 
 use crate::{
+    config::{ColumnConfig, LayoutConfig, SongListConfig, UiConfig},
     library::{song::Song, Library},
-    utils::constants::PlayerStates,
+    player::waveform::Waveforms,
+    metadata::Metadata,
+    podcasts::Podcasts,
+    queue::{undo::UndoHistory, SongQueue},
+    ui::song_format::SongFormats,
+    utils::constants::{
+        requests::{LibraryRequests, PlayerChanges}, AppViews, EqPresets, LibraryViews, PlayerStates, RepeatModes,
+        ReplayGainModes, SortModes, EQ_NUM_BANDS,
+    },
 };
 
 pub struct AppState {
@@ -10,6 +25,10 @@ pub struct AppState {
     pub ui: UIState,
     pub player: PlayerState,
     pub search: SearchState,
+    pub queue: SongQueue,
+    pub undo: UndoHistory, // of the queue and the playlists
+    pub podcasts: Podcasts,
+    pub metadata: Metadata, // looked up online
 }
 
 impl Default for AppState {
@@ -19,24 +38,215 @@ impl Default for AppState {
             ui: UIState::default(),
             player: PlayerState::default(),
             search: SearchState::default(),
+            queue: SongQueue::default(),
+            undo: UndoHistory::default(),
+            podcasts: Podcasts::default(),
+            metadata: Metadata::default(),
         }
     }
 }
 
 pub struct UIState {
-    curr_tab: u8,
-    selected_pane: u8,
-    selected_row: u8,
     pub selected_song: Option<Song>,
+    // the file or directory under the cursor of the file browser, added instead of the selected
+    // song. everything under a directory is
+    pub selected_path: Option<PathBuf>,
+    pub error: Option<String>, // shown in a popup until dismissed
+    pub show_equalizer: bool,
+    pub eq_band: usize, // band selected in the equalizer popup
+    pub scan_progress: Option<(usize, usize)>, // files read and files to read while scanning
+    pub gain_progress: Option<(usize, usize)>, // songs measured and songs to measure
+    pub tag_editor: Option<TagEditor>,
+    pub show_lyrics: bool, // in place of the queue
+    pub show_stats: bool,
+    pub show_visualizer: bool, // beside the song that's playing
+    pub show_log: bool,        // under the main pane
+    pub command: Option<CommandLine>, // the ":" prompt, while it's open
+    pub columns: Vec<ColumnConfig>,   // of the song list
+    pub group_by_album: bool,         // the song list has a header over each album's songs
+    pub formats: SongFormats,         // how songs are written out
+    pub layout: LayoutConfig,         // of the panes on screen
+    pub selected_songs: Vec<Song>,    // picked out in visual mode, empty outside of it
+    // the prefixes of the actions bound while what's on screen is, the innermost first. their
+    // keys are looked up in that order before the rest
+    pub key_contexts: Vec<&'static str>,
+    pub notifications: VecDeque<Notification>, // shown one after the other on the status line
+    pub jumping: bool, // the next letter typed jumps to the first item starting with it
+    pub menu_action: Option<usize>, // the one under the cursor while the song menu is open
+    pub file_info: Option<Song>,     // shown in a popup until dismissed
+    // what's asked, and what's done if enter is pressed. esc gives up on it
+    pub confirm: Option<(String, LibraryRequests)>,
+    pub bookmark: Option<usize>, // the one under the cursor while the song's bookmarks are open
+    pub tick_rate: u64, // redraws a second while something on screen moves
+    pub party: bool,    // the library can't be changed and the queue only added to
+    pub marquee_speed: u16, // cells a second that a title too long to fit scrolls by
+    pub marquee_pause: Duration, // at either end of it
+    pub browsing: Browsing,
 }
 
 impl Default for UIState {
     fn default() -> Self {
         Self {
-            curr_tab: 0,
-            selected_pane: 0,
-            selected_row: 0,
             selected_song: None,
+            selected_path: None,
+            error: None,
+            show_equalizer: false,
+            eq_band: 0,
+            scan_progress: None,
+            gain_progress: None,
+            tag_editor: None,
+            show_lyrics: false,
+            show_stats: false,
+            show_visualizer: false,
+            show_log: false,
+            command: None,
+            columns: SongListConfig::default().columns,
+            group_by_album: false,
+            formats: SongFormats::default(),
+            layout: LayoutConfig::default(),
+            selected_songs: Vec::new(),
+            key_contexts: Vec::new(),
+            notifications: VecDeque::new(),
+            jumping: false,
+            menu_action: None,
+            confirm: None,
+            file_info: None,
+            bookmark: None,
+            tick_rate: UiConfig::default().tick_rate,
+            party: false,
+            marquee_speed: UiConfig::default().marquee_speed,
+            marquee_pause: Duration::from_millis(UiConfig::default().marquee_pause_ms),
+            browsing: Browsing::default(),
+        }
+    }
+}
+
+impl UIState {
+    // what batch actions act on, the songs picked out in visual mode or else the selected song.
+    // taking them ends visual mode
+    pub fn take_marked_songs(&mut self) -> Vec<Song> {
+        if self.selected_songs.is_empty() {
+            self.selected_song.iter().cloned().collect()
+        } else {
+            std::mem::take(&mut self.selected_songs)
+        }
+    }
+
+    pub fn notify(&mut self, text: impl Into<String>) {
+        self.push_notification(text.into(), false);
+    }
+
+    // for things that went wrong but don't need to be dismissed like the error popup
+    pub fn notify_error(&mut self, text: impl Into<String>) {
+        self.push_notification(text.into(), true);
+    }
+
+    fn push_notification(&mut self, text: String, error: bool) {
+        // the oldest make way if they pile up faster than they're shown
+        if self.notifications.len() == MAX_NOTIFICATIONS {
+            self.notifications.pop_front();
+        }
+        self.notifications.push_back(Notification {
+            text,
+            error,
+            shown_since: None,
+        });
+    }
+
+    // only the notification being shown counts down, the next one is shown once it's gone
+    pub fn tick_notifications(&mut self) {
+        if let Some(notification) = self.notifications.front_mut() {
+            let shown_since = *notification.shown_since.get_or_insert_with(Instant::now);
+            if shown_since.elapsed() >= NOTIFICATION_TIME {
+                self.notifications.pop_front();
+            }
+        }
+    }
+}
+
+// the tab, view and sort order the UI was left in, kept up to date by the UI so it's saved with
+// the session and read by the UI when it starts
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Browsing {
+    pub tab: AppViews,
+    pub view: LibraryViews,
+    pub sort: SortModes,
+}
+
+impl Default for Browsing {
+    fn default() -> Self {
+        Browsing {
+            tab: AppViews::LIBRARY,
+            view: LibraryViews::SONGS,
+            sort: SortModes::TITLE,
+        }
+    }
+}
+
+const NOTIFICATION_TIME: Duration = Duration::from_secs(3);
+const MAX_NOTIFICATIONS: usize = 5;
+
+// a message on the status line, eg. that songs were added to the queue
+pub struct Notification {
+    pub text: String,
+    pub error: bool,
+    shown_since: Option<Instant>, // none until it's first shown
+}
+
+pub const TAG_FIELDS: [&str; 5] = ["Title", "Artist", "Album", "Track #", "Genre"];
+
+// the tags of a song being edited in the tag editor popup, one value per field in TAG_FIELDS
+pub struct TagEditor {
+    pub song: Song,
+    pub values: [String; TAG_FIELDS.len()],
+    pub field: usize, // the field being typed into
+}
+
+impl TagEditor {
+    pub fn new(song: Song) -> Self {
+        let values = [
+            song.title.to_owned(),
+            song.track_artist.to_owned(),
+            song.album_title.to_owned(),
+            song.track_number.to_owned().unwrap_or_default(),
+            song.genre.to_owned().unwrap_or_default(),
+        ];
+        Self {
+            song,
+            values,
+            field: 0,
+        }
+    }
+
+    // the song with the edited tags, empty optional fields are removed
+    pub fn edited_song(&self) -> Song {
+        let optional = |value: &String| {
+            let value = value.trim();
+            (!value.is_empty()).then(|| value.to_string())
+        };
+        let mut song = self.song.clone();
+        song.title = self.values[0].trim().to_string();
+        song.track_artist = self.values[1].trim().to_string();
+        song.album_title = self.values[2].trim().to_string();
+        song.track_number = optional(&self.values[3]);
+        song.genre = optional(&self.values[4]);
+        song
+    }
+}
+
+// a command being typed after ":"
+pub struct CommandLine {
+    pub input: String,
+    pub history_idx: Option<usize>, // the earlier command being shown, if any
+    pub completions: Vec<String>,   // shown when tab matched more than one
+}
+
+impl Default for CommandLine {
+    fn default() -> Self {
+        Self {
+            input: String::new(),
+            history_idx: None,
+            completions: Vec::new(),
         }
     }
 }
@@ -45,6 +255,29 @@ pub struct PlayerState {
     pub curr_state: PlayerStates,
     pub progress: Duration,
     pub curr_song: Option<Song>,
+    pub seek_to: Option<Duration>, // picked up by the playing thread
+    pub resume_at: Option<Duration>, // where the next song starts, to carry on the last session
+    pub shuffle: bool,
+    pub repeat: RepeatModes,
+    pub stop_after_current: bool,
+    pub play_counted: bool, // whether the current song has been played long enough to count
+    pub scrobbled: bool,    // and long enough to scrobble
+    pub stream_title: Option<String>, // what a stream that's playing says is on
+    pub play_count_threshold: f32, // fraction of a song that has to be played
+    pub crossfade: Duration,
+    pub fade: Duration, // when starting, pausing, resuming and stopping
+    pub replay_gain: ReplayGainModes,
+    pub preamp_db: f32,
+    pub eq_preset: EqPresets,
+    pub eq_bands: [f32; EQ_NUM_BANDS], // gain of each band in dB
+    pub speed: f32,
+    pub mono: bool,
+    pub balance: f32,
+    // the part of the song played over and over, from the first mark to the second
+    pub loop_start: Option<Duration>,
+    pub loop_end: Option<Duration>,
+    pub waveforms: Waveforms, // of the songs played lately, drawn as the progress bar
+    pub changes_tx: Option<UnboundedSender<PlayerChanges>>, // set while mpris is serving
 }
 
 impl Default for PlayerState {
@@ -53,13 +286,53 @@ impl Default for PlayerState {
             curr_state: PlayerStates::STOPPED,
             progress: Duration::ZERO,
             curr_song: None,
+            seek_to: None,
+            resume_at: None,
+            shuffle: false,
+            repeat: RepeatModes::OFF,
+            stop_after_current: false,
+            play_counted: false,
+            scrobbled: false,
+            stream_title: None,
+            play_count_threshold: 0.5,
+            crossfade: Duration::ZERO,
+            fade: Duration::ZERO,
+            replay_gain: ReplayGainModes::OFF,
+            preamp_db: 0.,
+            eq_preset: EqPresets::FLAT,
+            eq_bands: [0.; EQ_NUM_BANDS],
+            speed: 1.,
+            mono: false,
+            balance: 0.,
+            loop_start: None,
+            loop_end: None,
+            waveforms: Waveforms::default(),
+            changes_tx: None,
         }
     }
 }
 
+impl PlayerState {
+    // said by whatever changed it, so the desktop hears of it right away
+    pub fn changed(&self, change: PlayerChanges) {
+        if let Some(changes_tx) = &self.changes_tx {
+            let _ = changes_tx.send(change);
+        }
+    }
+}
+
+// how many searches up and down go back through
+pub const SEARCH_HISTORY_LEN: usize = 100;
+
 pub struct SearchState {
     pub searching: bool,
     pub term: String,
+    // the last search, highlighted in the lists until esc is pressed
+    pub highlight: Option<String>,
+    pub history: Vec<String>,       // searches made before, oldest first
+    pub history_idx: Option<usize>, // the earlier search in the prompt, if any
+    typed: String,                  // what was typed before going back through the history
+    cursor: usize,                  // in characters from the start of the term
 }
 
 impl Default for SearchState {
@@ -67,6 +340,103 @@ impl Default for SearchState {
         Self {
             searching: false,
             term: String::default(),
+            highlight: None,
+            history: Vec::new(),
+            history_idx: None,
+            typed: String::new(),
+            cursor: 0,
+        }
+    }
+}
+
+impl SearchState {
+    // the search being closed becomes the newest in the history, returned to be saved unless
+    // nothing was typed
+    pub fn remember(&mut self) -> Option<String> {
+        self.history_idx = None;
+        let term = self.term.trim();
+        if term.is_empty() {
+            return None;
+        }
+        let term = term.to_string();
+        self.history.retain(|search| *search != term);
+        self.history.push(term.to_owned());
+        if self.history.len() > SEARCH_HISTORY_LEN {
+            self.history.remove(0);
         }
+        Some(term)
+    }
+
+    // put an older or newer search in the prompt, going back to what was typed after the newest.
+    // false if there's nothing to go to
+    pub fn step_history(&mut self, older: bool) -> bool {
+        let idx = match (self.history_idx, older) {
+            (None, true) if self.history.is_empty() => return false,
+            (None, true) => {
+                self.typed = self.term.to_owned();
+                self.history.len() - 1
+            }
+            (None, false) => return false,
+            (Some(idx), true) => idx.saturating_sub(1),
+            (Some(idx), false) if idx + 1 < self.history.len() => idx + 1,
+            (Some(_), false) => {
+                self.history_idx = None;
+                self.term = std::mem::take(&mut self.typed);
+                return true;
+            }
+        };
+        self.history_idx = Some(idx);
+        self.term = self.history[idx].to_owned();
+        self.cursor = self.term.chars().count();
+        true
+    }
+
+    // where the cursor is in the term as a byte index, kept within it when the term was cleared
+    // behind its back
+    pub fn cursor(&self) -> usize {
+        self.term.char_indices().nth(self.cursor).map_or(self.term.len(), |(idx, _)| idx)
+    }
+
+    // typed or pasted text goes in at the cursor, and an earlier search brought back is changed
+    // like one that was typed
+    pub fn insert(&mut self, text: &str) {
+        let idx = self.cursor();
+        self.term.insert_str(idx, text);
+        self.cursor = self.term[..idx + text.len()].chars().count();
+        self.history_idx = None;
+    }
+
+    pub fn backspace(&mut self) {
+        let idx = self.cursor();
+        if let Some((start, _)) = self.term[..idx].char_indices().last() {
+            self.term.replace_range(start..idx, "");
+            self.cursor = self.term[..start].chars().count();
+        }
+        self.history_idx = None;
+    }
+
+    // the word before the cursor and the spaces after it, like ctrl+w in a shell
+    pub fn delete_word(&mut self) {
+        let idx = self.cursor();
+        let before = self.term[..idx].trim_end();
+        let start = before.trim_end_matches(|ch: char| !ch.is_whitespace()).len();
+        self.term.replace_range(start..idx, "");
+        self.cursor = self.term[..start].chars().count();
+        self.history_idx = None;
+    }
+
+    pub fn clear(&mut self) {
+        self.term.clear();
+        self.cursor = 0;
+        self.history_idx = None;
+    }
+
+    pub fn move_cursor(&mut self, left: bool) {
+        let len = self.term.chars().count();
+        let cursor = self.cursor.min(len);
+        self.cursor = match left {
+            true => cursor.saturating_sub(1),
+            false => (cursor + 1).min(len),
+        };
     }
 }
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::{AppState, Browsing};
+use crate::library::song::Song;
+use crate::utils::constants::RepeatModes;
+use crate::utils::data;
+
+const SESSION_FILE: &str = "session.toml";
+
+// what's put back when splay starts again, saved when it quits
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    curr_song: Option<SongKey>,
+    position_ms: u64, // into the current song
+    shuffle: bool,
+    repeat: RepeatModes,
+    browsing: Browsing,
+    queue: Vec<SongKey>, // in play order
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session {
+            curr_song: None,
+            position_ms: 0,
+            shuffle: false,
+            repeat: RepeatModes::OFF,
+            browsing: Browsing::default(),
+            queue: Vec::new(),
+        }
+    }
+}
+
+// songs are looked up in the library again, tracks of a cue sheet share their file's path
+#[derive(Serialize, Deserialize)]
+struct SongKey {
+    path: String,
+    #[serde(default)]
+    start_ms: u64,
+}
+
+impl SongKey {
+    fn new(song: &Song) -> SongKey {
+        SongKey {
+            path: song.path.to_owned(),
+            start_ms: song.start_ms,
+        }
+    }
+}
+
+impl Session {
+    pub fn capture(state: &AppState) -> Session {
+        Session {
+            curr_song: state.player.curr_song.as_ref().map(SongKey::new),
+            position_ms: state.player.progress.as_millis() as u64,
+            shuffle: state.player.shuffle,
+            repeat: state.player.repeat,
+            browsing: state.ui.browsing,
+            queue: state.queue.iter().map(SongKey::new).collect(),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        fs::write(data::path(SESSION_FILE), toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    // none the first time splay is run
+    pub fn load() -> Result<Option<Session>, Box<dyn Error>> {
+        match fs::read_to_string(data::path(SESSION_FILE)) {
+            Ok(contents) => Ok(Some(toml::from_str(&contents)?)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    // songs that are no longer in the library are left out. true if the song that was playing
+    // is set up to start again from where it was, otherwise it's first in the queue
+    pub fn restore(self, state: &Arc<Mutex<AppState>>, resume: bool) -> bool {
+        let mut guard = state.lock().unwrap();
+        let songs: HashMap<(&str, u64), &Song> = guard
+            .library
+            .songs
+            .iter()
+            .map(|song| ((song.path.as_str(), song.start_ms), song))
+            .collect();
+        let find = |key: &SongKey| {
+            songs
+                .get(&(key.path.as_str(), key.start_ms))
+                .cloned()
+                .cloned()
+        };
+        let curr_song = self.curr_song.as_ref().and_then(find);
+        let queue: Vec<Song> = self.queue.iter().filter_map(find).collect();
+        drop(songs);
+
+        guard.player.shuffle = self.shuffle;
+        guard.player.repeat = self.repeat;
+        guard.ui.browsing = self.browsing;
+        for song in queue {
+            guard.queue.add_upcoming(song);
+        }
+        match curr_song {
+            Some(song) if resume => {
+                guard.ui.selected_song = Some(song);
+                guard.player.resume_at = Some(Duration::from_millis(self.position_ms));
+                true
+            }
+            Some(song) => {
+                guard.queue.add_first_immediate(song);
+                false
+            }
+            None => false,
+        }
+    }
+}
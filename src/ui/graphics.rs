@@ -0,0 +1,176 @@
+use image::{codecs::png::PngEncoder, ColorType, ImageEncoder, RgbImage};
+use std::collections::BTreeSet;
+use std::env;
+
+// there's no portable way to ask the terminal how big a cell is, so sixel images are sized for
+// the most common cell size
+pub const SIXEL_CELL_WIDTH: u32 = 8;
+pub const SIXEL_CELL_HEIGHT: u32 = 16;
+
+// kitty only takes this much base64 per escape sequence
+const KITTY_CHUNK_LEN: usize = 4096;
+
+// sixel colors come from a 6x6x6 color cube
+const SIXEL_LEVELS: u32 = 6;
+
+// ways of drawing images, from best to worst
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphicsProtocols {
+    KITTY,
+    ITERM2,
+    SIXEL,
+    HALF_BLOCKS, // two pixels per cell with colored ▀ characters, works anywhere with true color
+}
+
+// guess what the terminal supports from the environment, since querying it would race with the
+// input thread for the reply
+pub fn detect() -> GraphicsProtocols {
+    let term = env::var("TERM").unwrap_or_default();
+    let term_program = env::var("TERM_PROGRAM").unwrap_or_default();
+    if env::var("KITTY_WINDOW_ID").is_ok() || term.contains("kitty") {
+        GraphicsProtocols::KITTY
+    } else if term_program == "iTerm.app" || term_program == "WezTerm" {
+        GraphicsProtocols::ITERM2
+    } else if term.contains("sixel")
+        || term.starts_with("foot")
+        || term.starts_with("mlterm")
+        || term_program == "mintty"
+    {
+        GraphicsProtocols::SIXEL
+    } else {
+        GraphicsProtocols::HALF_BLOCKS
+    }
+}
+
+// removes images drawn earlier. only kitty keeps images apart from the text, the other
+// protocols draw into the cells which get overwritten by redrawing them
+pub fn clear_images(protocol: GraphicsProtocols) -> String {
+    match protocol {
+        GraphicsProtocols::KITTY => "\x1b_Ga=d,q=2\x1b\\".to_string(),
+        _ => String::new(),
+    }
+}
+
+// the escape sequence that draws an image into `cols` x `rows` cells from the cursor
+pub fn encode(protocol: GraphicsProtocols, image: &RgbImage, cols: u16, rows: u16) -> String {
+    match protocol {
+        GraphicsProtocols::KITTY => kitty(image, cols, rows),
+        GraphicsProtocols::ITERM2 => iterm2(image, cols, rows),
+        GraphicsProtocols::SIXEL => sixel(image),
+        GraphicsProtocols::HALF_BLOCKS => String::new(),
+    }
+}
+
+fn png_base64(image: &RgbImage) -> String {
+    let mut png = Vec::new();
+    let encoded = PngEncoder::new(&mut png).write_image(
+        image.as_raw(),
+        image.width(),
+        image.height(),
+        ColorType::Rgb8,
+    );
+    match encoded {
+        Ok(_) => base64::encode(png),
+        Err(_) => String::new(),
+    }
+}
+
+fn kitty(image: &RgbImage, cols: u16, rows: u16) -> String {
+    let data = png_base64(image);
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK_LEN).collect();
+    let mut out = String::new();
+    for (idx, chunk) in chunks.iter().enumerate() {
+        // m=1 means more chunks follow
+        let more = (idx + 1 < chunks.len()) as u8;
+        let chunk = String::from_utf8_lossy(chunk);
+        if idx == 0 {
+            out.push_str(&format!(
+                "\x1b_Gf=100,a=T,q=2,c={},r={},m={};{}\x1b\\",
+                cols, rows, more, chunk
+            ));
+        } else {
+            out.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+        }
+    }
+    out
+}
+
+fn iterm2(image: &RgbImage, cols: u16, rows: u16) -> String {
+    format!(
+        "\x1b]1337;File=inline=1;width={};height={};preserveAspectRatio=1:{}\x07",
+        cols,
+        rows,
+        png_base64(image)
+    )
+}
+
+fn sixel(image: &RgbImage) -> String {
+    let (width, height) = image.dimensions();
+    let mut out = format!("\x1bPq\"1;1;{};{}", width, height);
+    let levels = SIXEL_LEVELS;
+    for color in 0..levels.pow(3) {
+        let (r, g, b) = (
+            color / (levels * levels),
+            color / levels % levels,
+            color % levels,
+        );
+        // sixel colors are given in percent
+        let percent = |level: u32| level * 100 / (levels - 1);
+        out.push_str(&format!(
+            "#{};2;{};{};{}",
+            color,
+            percent(r),
+            percent(g),
+            percent(b)
+        ));
+    }
+    let quantize = |value: u8| (value as u32 * (levels - 1) + 127) / 255;
+    let color_at = |x: u32, y: u32| {
+        let pixel = image.get_pixel(x, y);
+        quantize(pixel[0]) * levels * levels + quantize(pixel[1]) * levels + quantize(pixel[2])
+    };
+
+    // each line of sixels is six pixels tall, drawn once for every color in it
+    for band in (0..height).step_by(6) {
+        let rows = band..(band + 6).min(height);
+        let colors: BTreeSet<u32> = rows
+            .clone()
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| color_at(x, y))
+            .collect();
+        for color in colors {
+            out.push_str(&format!("#{}", color));
+            let sixels: Vec<char> = (0..width)
+                .map(|x| {
+                    let bits = rows
+                        .clone()
+                        .filter(|y| color_at(x, *y) == color)
+                        .fold(0, |bits, y| bits | 1 << (y - band));
+                    char::from(63 + bits as u8)
+                })
+                .collect();
+            push_run_length(&mut out, &sixels);
+            // back to the start of the line for the next color
+            out.push('$');
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+// repeated sixels are written as !<count><sixel>
+fn push_run_length(out: &mut String, sixels: &[char]) {
+    let mut idx = 0;
+    while idx < sixels.len() {
+        let sixel = sixels[idx];
+        let run = sixels[idx..].iter().take_while(|s| **s == sixel).count();
+        if run > 3 {
+            out.push_str(&format!("!{}{}", run, sixel));
+        } else {
+            (0..run).for_each(|_| out.push(sixel));
+        }
+        idx += run;
+    }
+}
@@ -1,27 +1,147 @@
-use tui::layout::{Constraint, Direction, Layout, Rect};
-
-pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
-    let popup_layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints(
-            [
-                Constraint::Percentage((100 - percent_y) / 2),
-                Constraint::Percentage(percent_y),
-                Constraint::Percentage((100 - percent_y) / 2),
-            ]
-            .as_ref(),
-        )
-        .split(r);
-
-    Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints(
-            [
-                Constraint::Percentage((100 - percent_x) / 2),
-                Constraint::Percentage(percent_x),
-                Constraint::Percentage((100 - percent_x) / 2),
-            ]
-            .as_ref(),
-        )
-        .split(popup_layout[1])[1]
+use std::borrow::Cow;
+use std::time::Duration;
+
+use tui::layout::Rect;
+use tui::text::Span;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+// a popup in the middle of `r` taking the given percentages of it, but at least `min_width` by
+// `min_height` so what's in it fits. it never goes past `r`, however small that is
+pub fn centered_rect(
+    percent_x: u16,
+    percent_y: u16,
+    min_width: u16,
+    min_height: u16,
+    r: Rect,
+) -> Rect {
+    let width = ((r.width as u32 * percent_x as u32 / 100) as u16)
+        .max(min_width)
+        .min(r.width);
+    let height = ((r.height as u32 * percent_y as u32 / 100) as u16)
+        .max(min_height)
+        .min(r.height);
+    Rect::new(
+        r.x + (r.width - width) / 2,
+        r.y + (r.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+// text wider than this many cells is cut short with an ellipsis in its last cell. wide characters
+// like CJK and emoji take two cells, and are left out whole rather than cut in half
+pub fn truncate(text: &str, width: usize) -> Cow<'_, str> {
+    if text.width() <= width {
+        return Cow::Borrowed(text);
+    }
+    let mut truncated = String::new();
+    let mut used = 0;
+    for ch in text.chars() {
+        let ch_width = ch.width().unwrap_or(0);
+        if used + ch_width + 1 > width {
+            break;
+        }
+        used += ch_width;
+        truncated.push(ch);
+    }
+    if width > 0 {
+        truncated.push('…');
+    }
+    Cow::Owned(truncated)
+}
+
+// the same for text in several styles, which keep theirs up to where it's cut
+pub fn truncate_spans(spans: Vec<Span<'static>>, width: usize) -> Vec<Span<'static>> {
+    if spans.iter().map(|span| span.content.width()).sum::<usize>() <= width {
+        return spans;
+    }
+    let mut truncated = Vec::new();
+    let mut used = 0;
+    for span in spans {
+        let mut content = String::new();
+        for ch in span.content.chars() {
+            let ch_width = ch.width().unwrap_or(0);
+            if used + ch_width + 1 > width {
+                break;
+            }
+            used += ch_width;
+            content.push(ch);
+        }
+        let full = content.len() == span.content.len();
+        truncated.push(Span::styled(content, span.style));
+        if !full {
+            break;
+        }
+    }
+    if width > 0 {
+        truncated.push(Span::raw("…"));
+    }
+    truncated
+}
+
+// text wider than this many cells goes back and forth across them at `speed` cells a second,
+// stopping for `pause` at either end, as it is `elapsed` into doing so. it's cut short instead
+// while the speed is 0
+pub fn marquee(
+    text: &str,
+    width: usize,
+    elapsed: Duration,
+    speed: u16,
+    pause: Duration,
+) -> Cow<'_, str> {
+    let overflow = text.width().saturating_sub(width);
+    if overflow == 0 || speed == 0 {
+        return truncate(text, width);
+    }
+    let pause = pause.as_millis() as u64;
+    let scroll = overflow as u64 * 1000 / speed as u64;
+    // a title a cell too wide scrolls in no time at all at high speeds
+    let at = elapsed.as_millis() as u64 % (2 * (pause + scroll)).max(1);
+    let scrolled = |ms: u64| (ms * speed as u64 / 1000) as usize;
+    let offset = if at < pause {
+        0
+    } else if at < pause + scroll {
+        scrolled(at - pause)
+    } else if at < 2 * pause + scroll {
+        overflow
+    } else {
+        overflow.saturating_sub(scrolled(at - 2 * pause - scroll))
+    };
+    Cow::Owned(window(text, offset.min(overflow), width))
+}
+
+// the cells from `skip` on that fit in `width`. a wide character cut in half at either edge is
+// a space instead
+fn window(text: &str, skip: usize, width: usize) -> String {
+    let mut shown = String::new();
+    let mut at = 0;
+    for ch in text.chars() {
+        let ch_width = ch.width().unwrap_or(0);
+        let (start, end) = (at, at + ch_width);
+        at = end;
+        if end <= skip {
+            continue;
+        }
+        if start >= skip + width {
+            break;
+        }
+        if start < skip || end > skip + width {
+            let cells = end.min(skip + width) - start.max(skip);
+            shown.push_str(&" ".repeat(cells));
+        } else {
+            shown.push(ch);
+        }
+    }
+    shown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marquee_without_a_pause_at_high_speeds() {
+        let shown = marquee("abcde", 4, Duration::from_millis(1500), 5000, Duration::ZERO);
+        assert_eq!(shown.width(), 4);
+    }
 }
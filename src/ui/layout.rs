@@ -0,0 +1,58 @@
+use tui::layout::{Constraint, Direction, Layout, Rect};
+
+use crate::config::{LayoutConfig, PaneSize};
+use crate::utils::constants::{LayoutDirections, LayoutWidgets};
+
+// the song that's playing has three lines above its progress bar
+const MIN_NOW_PLAYING_HEIGHT: u16 = 5;
+
+// where each widget of the layout goes within the area
+pub fn areas(layout: &LayoutConfig, area: Rect) -> Vec<(LayoutWidgets, Rect)> {
+    let (direction, panes) = match layout {
+        LayoutConfig::Widget { widget, .. } => return vec![(*widget, area)],
+        LayoutConfig::Split {
+            direction, panes, ..
+        } => (*direction, panes),
+    };
+    let constraints: Vec<Constraint> = panes
+        .iter()
+        .map(|pane| constraint(pane, direction, area))
+        .collect();
+    let direction = match direction {
+        LayoutDirections::VERTICAL => Direction::Vertical,
+        LayoutDirections::HORIZONTAL => Direction::Horizontal,
+    };
+    let chunks = Layout::default()
+        .direction(direction)
+        .constraints(constraints)
+        .split(area);
+    panes
+        .iter()
+        .zip(chunks)
+        .flat_map(|(pane, chunk)| areas(pane, chunk))
+        .collect()
+}
+
+// shares are worked out here rather than left to tui, so that the song that's playing can be
+// kept tall enough to read
+fn constraint(pane: &LayoutConfig, direction: LayoutDirections, area: Rect) -> Constraint {
+    let extent = match direction {
+        LayoutDirections::VERTICAL => area.height,
+        LayoutDirections::HORIZONTAL => area.width,
+    };
+    let min = match pane {
+        LayoutConfig::Widget {
+            widget: LayoutWidgets::NOW_PLAYING,
+            ..
+        } if direction == LayoutDirections::VERTICAL => MIN_NOW_PLAYING_HEIGHT,
+        _ => 0,
+    };
+    match pane.size() {
+        Some(PaneSize::Cells(cells)) => Constraint::Length(*cells),
+        Some(size) => {
+            let percent = size.percent().unwrap_or(100);
+            Constraint::Length(((extent as u32 * percent as u32 / 100) as u16).max(min))
+        }
+        None => Constraint::Min(min),
+    }
+}
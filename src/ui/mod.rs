@@ -1,101 +1,275 @@
+pub mod graphics;
 pub mod helper;
+pub mod layout;
+pub mod song_format;
+pub mod theme;
+pub mod views;
 pub mod widgets;
 
 use crate::library::song::Song;
 use crate::library::tag;
-use crate::player::symphonia_player::SymphoniaPlayer;
-use crate::player::Player;
-use crate::state::AppState;
-use crate::utils::constants::requests::{AppRequests, PlayerRequests, UIRequests::*};
-use crate::utils::constants::PlayerStates;
-use crate::{library::Library, utils::constants::requests::UIRequests};
-use std::sync::{mpsc, Arc, Mutex};
+use crate::player::visualizer::SampleTap;
+use crate::input::commands;
+use crate::state::{AppState, Browsing, CommandLine, TagEditor};
+use crate::utils::errors::SplayError;
+use crate::utils::logs;
+use crate::utils::constants::requests::{
+    AppRequests, FileActions, LibraryRequests, MetadataRequests, PlayerEvents, PlayerRequests,
+    QueueRequests, UIRequests::*,
+};
+use crate::library::duplicates;
+use crate::library::files::{self, FileEntry};
+use crate::library::lyrics::Lyrics;
+use crate::library::stats::LibraryStats;
+use crate::library::playlist::PlaylistEntry;
+use crate::podcasts::{self, Podcast};
+use crate::library::AlbumKey;
+use crate::metadata::Metadata;
+use crate::config::ColumnConfig;
+use crate::utils::constants::{
+    AppViews, BrowserLevels, FocusedPanes, LayoutWidgets, LibraryViews, MenuActions, PlayerStates,
+    RepeatModes, SongColumns, SortModes,
+};
+use rand::Rng;
+use crate::utils::constants::requests::UIRequests;
+use std::collections::BTreeSet;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::{
-    fmt::format,
-    io::{self, Stdout},
-    sync::mpsc::{Receiver, Sender},
+    io::{self, Stdout, Write},
+    panic,
     time::{Duration, Instant},
 };
-use std::{thread, time};
+use std::thread;
+use tokio::runtime::Handle;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 use tui::layout::Alignment;
+use unicode_width::UnicodeWidthStr;
 use tui::widgets::Wrap;
+use graphics::GraphicsProtocols;
+use theme::Theme;
+use widgets::album_art::{CoverArt, HalfBlocks};
+use widgets::album_groups::{self, TableRows};
+use widgets::scrollbar::Scrollbar;
+use widgets::song_table::RowCache;
 use widgets::stateful_list::StatefulList;
 
 use crossterm::{
     cursor, event,
-    event::Event,
-    event::KeyCode,
-    execute, style,
+    execute,
     terminal::{self, disable_raw_mode, enable_raw_mode},
 };
 use tui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
-    text::{Span, Spans, Text},
-    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::Style,
+    text::Spans,
+    widgets::{Block, Borders, List, ListItem, Paragraph, TableState},
     Frame, Terminal,
 };
 
 pub fn start<'a>(
     app_state: Arc<Mutex<AppState>>,
-    rx: Receiver<UIRequests>,
-    main_tx: Sender<AppRequests>,
-) {
+    rx: UnboundedReceiver<UIRequests>,
+    main_tx: UnboundedSender<AppRequests>,
+    theme: Theme,
+    tap: Arc<Mutex<SampleTap>>,
+) -> Result<(), SplayError> {
     info!("Starting up UI...");
 
     // initialize terminal state
-    enable_raw_mode().unwrap();
+    enable_raw_mode()?;
+    // from here on the terminal is put back however the UI stops, a panic included
+    let _guard = TerminalGuard;
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| match thread::current().name() {
+        // with nothing left to draw or read keys, splay would be half working with no way to quit
+        Some(name) if UI_THREADS.contains(&name) => {
+            restore_terminal();
+            default_hook(info);
+            std::process::exit(101);
+        }
+        // the dispatcher's, the process goes down with it
+        Some("main") => {
+            restore_terminal();
+            default_hook(info);
+        }
+        // the player is started again by the dispatcher and the rest go on without, so it's only
+        // logged instead of written over the interface
+        _ => error!("{}", info),
+    }));
     let mut stdout = io::stdout();
     execute!(
         stdout,
         cursor::Hide,
         terminal::EnterAlternateScreen,
         event::EnableMouseCapture
-    )
-    .unwrap();
+    )?;
     let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend).unwrap();
+    let mut terminal = Terminal::new(backend)?;
 
     debug!("Terminal started successfully");
 
     let mut songs = app_state.lock().unwrap().library.songs.to_owned();
-    songs.sort_by(|a, b| a.title.cmp(&b.title));
-    let app = App::with_songs(app_state, songs);
-    app.run(&mut terminal, rx, main_tx);
+    sort_songs(&mut songs, SortModes::TITLE);
+    let mut app = App::with_songs(app_state, songs);
+    app.theme = theme;
+    app.tap = tap;
+    app.run(&mut terminal, rx, main_tx)?;
 
     info!("stopping now");
+    Ok(())
+}
 
-    // restore terminal
-    info!("Starting to cleanup terminal ...");
-    disable_raw_mode().unwrap();
-    execute!(
-        terminal.backend_mut(),
+// the threads the interface runs on, named so the panic hook can tell them apart
+pub const UI_THREADS: [&str; 2] = ["ui", "input"];
+
+// restores the terminal when dropped, once the UI stops or while a panic unwinds through it
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        info!("Starting to cleanup terminal ...");
+        restore_terminal();
+        info!("Terminal cleaned successfully");
+    }
+}
+
+// errors are ignored, this is as much as can be done on the way out
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(
+        io::stdout(),
         terminal::LeaveAlternateScreen,
-        event::DisableMouseCapture
-    )
-    .unwrap();
-    terminal.show_cursor().unwrap();
-    info!("Terminal cleaned successfully");
+        event::DisableMouseCapture,
+        cursor::Show
+    );
 }
 
+// smaller terminals only get a note asking for more room
+// redraws a second while something on screen moves, as the config allows it
+pub const MIN_TICK_RATE: u64 = 4;
+pub const MAX_TICK_RATE: u64 = 30;
+// how often the screen is redrawn while nothing moves, to catch changes made outside of a
+// request to the UI, eg. the queue filled from the command line
+const IDLE_REDRAW: Duration = Duration::from_secs(1);
+
+const MIN_WIDTH: u16 = 40;
+const MIN_HEIGHT: u16 = 15;
+// narrower than this the library has no room for the queue or lyrics beside it
+const SIDE_PANE_MIN_WIDTH: u16 = 100;
+// the song that's playing and the visualizer share this much beside the cover art
+const VISUALIZER_MIN_WIDTH: u16 = 80;
+// the most the log pane takes of the main pane, which keeps at least half
+const LOG_PANE_HEIGHT: u16 = 12;
+
 pub struct App {
     state: Arc<Mutex<AppState>>,
+    // the tab shown, unless a search is being typed which has a tab of its own
+    tab: AppViews,
+    view: LibraryViews,
+    library_view: LibraryViews, // the last view of the library tab
+    level: BrowserLevels,
+    sort: SortModes, // of the songs view
+    all_songs: Vec<Song>,
+    playlist_list: StatefulList<PlaylistEntry>,
+    podcast_list: StatefulList<Podcast>,
+    duplicate_list: StatefulList<Vec<Song>>,
+    // found in the background the first time the duplicates view is opened
+    duplicates: Option<Vec<Vec<Song>>>,
+    finding_duplicates: bool,
+    file_list: StatefulList<FileEntry>,
+    // the directory the file browser is in, none while it lists the library directories
+    file_dir: Option<PathBuf>,
+    artist_list: StatefulList<String>,
+    album_list: StatefulList<AlbumKey>,
+    // the tracks being browsed, every song in the songs view
     song_list: StatefulList<Song>,
+    // the rows of the song list while it's grouped by album, and the version of the list they're
+    // from. worked out again once either changes
+    groups: Vec<TableRows>,
+    groups_version: Option<u64>,
+    collapsed: BTreeSet<AlbumKey>,
+    graphics: GraphicsProtocols,
+    cover_art: Option<CoverArt>,
+    // where the art goes when it's drawn with escape sequences after the rest of the ui
+    art_area: Option<Rect>,
+    // the art and area that are on screen, so the image is only sent again when they change
+    drawn_art: Option<(String, Rect)>,
+    // the lyrics of the song at this path, loaded when the lyrics pane is shown
+    lyrics: Option<(String, Option<Lyrics>)>,
+    // computed when the stats dashboard is opened and whenever the library changes
+    stats: Option<LibraryStats>,
+    // the songs matching the search, best first
+    search_results: StatefulList<Song>,
+    // scrolled along with whichever of the song list and the search results is shown
+    song_rows: RowCache,
+    table_offset: usize, // the first row of the table on screen
+    // where the view tabs and the lists being browsed were drawn, to tell what's clicked on
+    tabs_area: Option<Rect>,
+    list_areas: Vec<(BrowserLevels, Rect)>,
+    tab_bar_area: Option<Rect>,
+    progress_bar: Option<Rect>, // clicking on it seeks to that point of the song
+    // the cursor of the queue tab, also used by the queue beside the open tab while it has the
+    // focus
+    queue_table: TableState,
+    queue_offset: usize,
+    focus: FocusedPanes,
+    queue_pane: bool, // whether the queue is beside the open tab, so it can have the focus
+    // rows of the list with the focus as it was last drawn, to move a page at a time
+    page_rows: usize,
+    // commands run from the ":" prompt, oldest first
+    command_history: Vec<String>,
+    // what the player is sending to the output, for the visualizer
+    tap: Arc<Mutex<SampleTap>>,
+    theme: Theme,
 }
 
 impl App {
-    pub fn new(state: Arc<Mutex<AppState>>) -> App {
-        App {
-            state,
-            song_list: StatefulList::with_items(vec![]),
-        }
-    }
-
     pub fn with_songs(state: Arc<Mutex<AppState>>, songs: Vec<Song>) -> App {
         App {
             state,
+            tab: AppViews::LIBRARY,
+            view: LibraryViews::SONGS,
+            library_view: LibraryViews::SONGS,
+            level: BrowserLevels::TRACKS,
+            sort: SortModes::TITLE,
+            all_songs: songs.clone(),
+            playlist_list: StatefulList::with_items(vec![]),
+            podcast_list: StatefulList::with_items(vec![]),
+            duplicate_list: StatefulList::with_items(vec![]),
+            duplicates: None,
+            finding_duplicates: false,
+            file_list: StatefulList::with_items(vec![]),
+            file_dir: None,
+            artist_list: StatefulList::with_items(vec![]),
+            album_list: StatefulList::with_items(vec![]),
             song_list: StatefulList::with_items(songs),
+            groups: Vec::new(),
+            groups_version: None,
+            collapsed: BTreeSet::new(),
+            graphics: graphics::detect(),
+            cover_art: None,
+            art_area: None,
+            drawn_art: None,
+            lyrics: None,
+            stats: None,
+            search_results: StatefulList::with_items(vec![]),
+            song_rows: RowCache::new(),
+            table_offset: 0,
+            tabs_area: None,
+            list_areas: Vec::new(),
+            tab_bar_area: None,
+            progress_bar: None,
+            queue_table: TableState::default(),
+            queue_offset: 0,
+            focus: FocusedPanes::MAIN,
+            queue_pane: false,
+            page_rows: 0,
+            command_history: Vec::new(),
+            tap: Arc::new(Mutex::new(SampleTap::new())),
+            theme: Theme::default(),
         }
     }
 
@@ -103,169 +277,1618 @@ impl App {
     pub fn run(
         mut self,
         terminal: &mut Terminal<CrosstermBackend<Stdout>>,
-        rx: Receiver<UIRequests>,
-        main_tx: Sender<AppRequests>,
-    ) -> () {
+        mut rx: UnboundedReceiver<UIRequests>,
+        main_tx: UnboundedSender<AppRequests>,
+    ) -> Result<(), SplayError> {
         if self.song_list.len() != 0 {
-            self.on_down(); //select first element
+            views::browser::down(&mut self); //select first element
         }
+        self.restore_browsing(&main_tx);
+        self.sync_browsing(terminal.size()?);
 
-        let tick_rate = Duration::from_millis(250);
-        let mut last_tick = Instant::now();
+        // the screen is only redrawn after a request changed something, or on every tick while
+        // something on it moves. it stays still and the thread asleep otherwise
+        let mut dirty = true;
+        let mut was_moving = false;
+        let mut last_draw = Instant::now();
+        // the requests come through tokio's channel, waited on from this thread
+        let runtime = Handle::current();
 
         loop {
-            let timeout = tick_rate
-                .checked_sub(last_tick.elapsed())
-                .unwrap_or_else(|| Duration::from_secs(0));
-            terminal.draw(|f| self.get_ui(f, &main_tx)).unwrap();
-            match rx.recv_timeout(timeout) {
-                Ok(request) => match request {
-                    Up => self.on_up(),
-                    Down => self.on_down(),
-                    Enter => self.on_enter(),
-                    ShowSearch => self.state.lock().unwrap().search.searching = true,
-                    SearchInput(ch) => self.state.lock().unwrap().search.term.push(ch),
-                    GoBack => self.go_back(),
-                    Quit => return,
-                    _ => {
-                        error!("This UI event is not implemented yet")
+            let (moving, tick_rate) = {
+                let state = self.state.lock().unwrap();
+                let moving = state.player.curr_state.is_playing()
+                    || !state.ui.notifications.is_empty()
+                    || state.ui.scan_progress.is_some();
+                (moving, Duration::from_millis(1000 / state.ui.tick_rate.max(1)))
+            };
+            // one more frame once it stops moving, so what's shown isn't stuck mid way
+            let interval = if moving || was_moving { tick_rate } else { IDLE_REDRAW };
+            if dirty || last_draw.elapsed() >= interval {
+                // drawing only reads the state, everything that plays or changes something
+                // happens in response to a request below
+                terminal.draw(|f| self.get_ui(f))?;
+                self.draw_cover_art(terminal)?;
+                last_draw = Instant::now();
+                was_moving = moving;
+            }
+            let timeout = interval.saturating_sub(last_draw.elapsed());
+            let request = runtime.block_on(tokio::time::timeout(timeout, rx.recv()));
+            dirty = matches!(request, Ok(Some(_)));
+            let request = match request {
+                Ok(Some(request)) => self.route(request, &main_tx),
+                Ok(None) => {
+                    error!("Could not receive UI event. \n \t Reason: every sender is gone");
+                    None
+                }
+                // nothing came before the next frame
+                Err(_) => None,
+            };
+            // what's left does the same whichever view is open
+            if let Some(request) = request {
+                match request {
+                    // only the views have something to move through or act on
+                    Up | Down | Top | Bottom | PageUp | PageDown | HalfPageUp | HalfPageDown
+                    | Enter | Play | FocusLeft | FocusRight | MoveInQueue(_) | RemoveFromQueue
+                    | ToggleVisual | ToggleGroup | NextMatch | PreviousMatch | GoBack
+                    | SearchInput(_) | SearchBackspace | SearchDeleteWord | SearchClear
+                    | SearchCursor(_) => (),
+                    StartJump => self.state.lock().unwrap().ui.jumping = true,
+                    JumpToLetter(_) => self.state.lock().unwrap().ui.jumping = false,
+                    OpenStream(url) => {
+                        self.state.lock().unwrap().ui.selected_song = Some(Song::stream(url));
+                        let _ = main_tx.send(AppRequests::PlayerRequests(PlayerRequests::Start));
+                    }
+                    Redraw => (),
+                    ScanProgress(scanned, total) => {
+                        self.state.lock().unwrap().ui.scan_progress = Some((scanned, total))
+                    }
+                    GainProgress(measured, total) => {
+                        self.state.lock().unwrap().ui.gain_progress =
+                            (measured < total).then_some((measured, total))
+                    }
+                    LibraryUpdated => self.on_library_updated(&main_tx),
+                    LoadPlaylist => self.load_playlist(),
+                    EditTags => self.edit_tags(),
+                    TagInput(ch) => {
+                        if let Some(editor) = &mut self.state.lock().unwrap().ui.tag_editor {
+                            editor.values[editor.field].push(ch);
+                        }
                     }
-                },
-                Err(err) => match err {
-                    mpsc::RecvTimeoutError::Disconnected => error!(
-                        "Could not receive UI event. \n \t Reason: {}",
-                        err.to_string()
-                    ),
-                    _ => (),
-                },
+                    TagBackspace => {
+                        if let Some(editor) = &mut self.state.lock().unwrap().ui.tag_editor {
+                            editor.values[editor.field].pop();
+                        }
+                    }
+                    NextView => self.show_view(self.library_view.cycle(), &main_tx),
+                    ShowView(view) => self.show_view(view, &main_tx),
+                    NextTab => self.show_tab(self.current_tab().cycle(), &main_tx),
+                    ShowTab(tab) => self.show_tab(tab, &main_tx),
+                    CyclePane => self.cycle_pane(),
+                    ConfirmFiles(action) => self.confirm_files(action),
+                    ShowMenu => self.show_menu(),
+                    ShowBookmarks => self.show_bookmarks(),
+                    ToggleParty => {
+                        let ui = &mut self.state.lock().unwrap().ui;
+                        ui.party = !ui.party;
+                        match ui.party {
+                            true => ui.notify("It's a party, guests can only add to the queue"),
+                            false => ui.notify("The party's over"),
+                        }
+                    }
+                    ShowFileInfo => self.show_file_info(None, &main_tx),
+                    FetchMetadata => self.fetch_metadata(&main_tx),
+                    DuplicatesFound(groups) => self.on_duplicates_found(groups),
+                    PodcastsUpdated => self.on_podcasts_updated(),
+                    PlaylistsUpdated => self.on_playlists_updated(),
+                    DownloadEpisode => self.download_episode(&main_tx),
+                    JumpToSong => self.jump_to_song(&main_tx),
+                    JumpToPlaying => self.jump_to_playing(&main_tx),
+                    Rate(stars) => self.update_stats(&main_tx, |song| song.rating = stars.min(5)),
+                    ChangeRating(step) => self.update_stats(&main_tx, |song| {
+                        song.rating = (song.rating as i8 + step).clamp(0, 5) as u8
+                    }),
+                    ToggleFavorite => {
+                        // a selection becomes favorites unless all of it already is
+                        let favorite = !self.marked_songs().iter().all(|song| song.favorite);
+                        self.update_stats(&main_tx, |song| song.favorite = favorite)
+                    }
+                    CycleSort => self.cycle_sort(&main_tx),
+                    ToggleStats => self.toggle_stats(),
+                    SetTheme(theme) => self.theme = theme,
+                    ShowCommand => {
+                        self.state.lock().unwrap().ui.command = Some(CommandLine::default())
+                    }
+                    CommandInput(ch) => {
+                        if let Some(command) = &mut self.state.lock().unwrap().ui.command {
+                            command.input.push(ch);
+                            command.completions.clear();
+                        }
+                    }
+                    CommandBackspace => {
+                        let mut state = self.state.lock().unwrap();
+                        // like vim, backspacing past the start closes the prompt
+                        match &mut state.ui.command {
+                            Some(command) if command.input.is_empty() => state.ui.command = None,
+                            Some(command) => {
+                                command.input.pop();
+                                command.completions.clear();
+                            }
+                            None => (),
+                        }
+                    }
+                    CompleteCommand => self.complete_command(),
+                    Click(column, row) | DoubleClick(column, row) => {
+                        self.on_click(column, row, &main_tx)
+                    }
+                    ToggleVisualizer => {
+                        let mut state = self.state.lock().unwrap();
+                        state.ui.show_visualizer = !state.ui.show_visualizer;
+                    }
+                    ToggleLog => {
+                        let mut state = self.state.lock().unwrap();
+                        state.ui.show_log = !state.ui.show_log;
+                    }
+                    ToggleLyrics => {
+                        let mut state = self.state.lock().unwrap();
+                        state.ui.show_lyrics = !state.ui.show_lyrics;
+                    }
+                    ShowSearch => {
+                        self.state.lock().unwrap().search.searching = true;
+                        views::search::update(&mut self);
+                    }
+                    PlayerEvent(PlayerEvents::TrackEnded) => self.on_track_ended(&main_tx),
+                    // drawn again from the state
+                    PlayerEvent(PlayerEvents::TrackStarted(_) | PlayerEvents::Progress) => (),
+                    PlayerEvent(PlayerEvents::Error(message)) => {
+                        self.state.lock().unwrap().ui.error = Some(message)
+                    }
+                    ShowEqualizer => self.state.lock().unwrap().ui.show_equalizer = true,
+                    EqRaise => self.adjust_eq_band(1., &main_tx),
+                    EqLower => self.adjust_eq_band(-1., &main_tx),
+                    SpeedUp => self.change_speed(0.1, &main_tx),
+                    SlowDown => self.change_speed(-0.1, &main_tx),
+                    BalanceLeft => self.change_balance(-0.1, &main_tx),
+                    BalanceRight => self.change_balance(0.1, &main_tx),
+                    EqNextPreset => {
+                        let preset = self.state.lock().unwrap().player.eq_preset.cycle();
+                        let _ = main_tx.send(AppRequests::PlayerRequests(
+                            PlayerRequests::SetEqPreset(preset),
+                        ));
+                    }
+                    Handled(reply) => reply.send(),
+                    Quit => return Ok(()),
+                }
             }
-            if last_tick.elapsed() >= tick_rate {
-                last_tick = Instant::now();
+            self.reveal_selected();
+            self.sync_selection();
+            self.sync_browsing(terminal.size()?);
+            self.state.lock().unwrap().ui.tick_notifications();
+        }
+    }
+
+    // the popup that's open takes what moves through it first, then the view the request is
+    // for. what neither of them takes is passed back
+    fn route(
+        &mut self,
+        request: UIRequests,
+        main_tx: &UnboundedSender<AppRequests>,
+    ) -> Option<UIRequests> {
+        let request = views::popups::handle(self, request, main_tx)?;
+        // a click is for the tab it's on, wherever the focus is
+        let view = match request {
+            Click(..) | DoubleClick(..) => self.current_tab(),
+            _ => self.active_view(),
+        };
+        match view {
+            AppViews::LIBRARY | AppViews::PLAYLISTS => {
+                views::browser::handle(self, request, main_tx)
             }
+            AppViews::QUEUE => views::queue::handle(self, request, main_tx),
+            AppViews::SEARCH => views::search::handle(self, request, main_tx),
+            AppViews::SETTINGS => views::settings::handle(request),
         }
     }
 
-    fn on_up(&mut self) {
-        match self.song_list.state.selected() {
-            Some(idx) => if idx == 0 {return} else { () },
+    // the search being typed takes the keys over the pane with the focus
+    fn active_view(&self) -> AppViews {
+        match self.state.lock().unwrap().search.searching {
+            true => AppViews::SEARCH,
+            false => self.acting_tab(),
+        }
+    }
+
+    // the artists view shows the albums of the selected artist and the tracks of the selected
+    // album beside it, with the browser's level as the column that has the focus
+    fn in_columns(&self) -> bool {
+        self.columns_shown(self.current_tab())
+    }
+
+    fn columns_shown(&self, tab: AppViews) -> bool {
+        tab == AppViews::LIBRARY && self.view == LibraryViews::ARTISTS && self.stats.is_none()
+    }
+
+    // the columns right of the focused one follow what's selected in it
+    fn fill_columns(&mut self) {
+        if self.view != LibraryViews::ARTISTS {
+            return;
+        }
+        if self.level == BrowserLevels::ARTISTS {
+            let albums = match selected(&self.artist_list) {
+                Some(artist) => self.state.lock().unwrap().library.albums_by(artist),
+                None => vec![],
+            };
+            self.album_list = StatefulList::with_items(albums);
+            select_next(&mut self.album_list);
+        }
+        let tracks = match selected(&self.album_list) {
+            Some(album) => self.state.lock().unwrap().library.album_tracks(album),
+            None => vec![],
+        };
+        self.song_list = StatefulList::with_items(tracks);
+    }
+
+    // the queue beside the open tab acts like the queue tab while it has the focus
+    fn acting_tab(&self) -> AppViews {
+        match self.focus {
+            FocusedPanes::QUEUE => AppViews::QUEUE,
+            FocusedPanes::MAIN => self.tab,
+        }
+    }
+
+    // the contexts whose keys are bound right now, from the popup that's open to the pane with
+    // the focus and what's beside it
+    fn key_contexts(&self, tab: AppViews, state: &AppState) -> Vec<&'static str> {
+        let main = self.focus == FocusedPanes::MAIN;
+        let library = matches!(
+            self.acting_tab(),
+            AppViews::LIBRARY | AppViews::PLAYLISTS | AppViews::SEARCH
+        );
+        [
+            ("equalizer", state.ui.show_equalizer),
+            ("search", state.search.highlight.is_some()),
+            ("columns", self.columns_shown(tab) && main),
+            ("queue", self.acting_tab() == AppViews::QUEUE),
+            ("library", library),
+            ("panes", self.queue_pane),
+        ]
+        .into_iter()
+        .filter(|(_, active)| *active)
+        .map(|(context, _)| context)
+        .collect()
+    }
+
+    // there are only two panes to go between, the open tab and the queue beside it
+    fn cycle_pane(&mut self) {
+        self.focus = match self.focus {
+            FocusedPanes::MAIN if self.queue_pane => FocusedPanes::QUEUE,
+            _ => FocusedPanes::MAIN,
+        };
+    }
+
+    // put the cursor of the songs being browsed on this one, if they're shown and it's there
+    fn select_in_song_list(&mut self, song: &Song) {
+        if self.level != BrowserLevels::TRACKS {
+            return;
+        }
+        if let Some(idx) = self.song_list.items.iter().position(|s| s.is_same(song)) {
+            self.song_list.state.select(Some(idx));
+            self.state.lock().unwrap().ui.selected_song = Some(song.clone());
+        }
+    }
+
+    // commands go out as the same requests their keys send
+    fn run_command(&mut self, main_tx: &UnboundedSender<AppRequests>) {
+        let line = match self.state.lock().unwrap().ui.command.take() {
+            Some(command) => command.input.trim().to_string(),
             None => return,
+        };
+        if line.is_empty() {
+            return;
         }
-        self.song_list.previous();
-        match self.song_list.items.get(self.song_list.state.selected().unwrap()) {
-            Some(song) => {
-                self.state.lock().unwrap().ui.selected_song = Some(song.clone());
+        if self.command_history.last() != Some(&line) {
+            self.command_history.push(line.to_owned());
+        }
+        match commands::parse(&line) {
+            Ok(request) => {
+                let _ = main_tx.send(request);
+            }
+            Err(message) => self.state.lock().unwrap().ui.error = Some(message),
+        }
+    }
+
+    // fill in as much as every completion has in common, listing them if there's more than one
+    fn complete_command(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        let command = match &mut state.ui.command {
+            Some(command) => command,
+            None => return,
+        };
+        let completions = commands::complete(&command.input);
+        let common = commands::common_prefix(&completions);
+        if common.len() > command.input.len() {
+            command.input = common;
+        }
+        command.completions = if completions.len() > 1 {
+            completions
+        } else {
+            Vec::new()
+        };
+    }
+
+    // pick up the songs found by the scan
+    fn on_library_updated(&mut self, main_tx: &UnboundedSender<AppRequests>) {
+        let mut songs = {
+            let mut guard = self.state.lock().unwrap();
+            guard.ui.scan_progress = None;
+            guard.library.songs.to_owned()
+        };
+        sort_songs(&mut songs, self.sort);
+        self.all_songs = songs;
+        if self.state.lock().unwrap().search.searching {
+            views::search::update(self);
+        }
+        // look for duplicates again the next time they're shown
+        self.duplicates = None;
+        if self.stats.is_some() {
+            self.stats = Some(LibraryStats::compute(&self.all_songs));
+        }
+        self.show_view(self.view, main_tx);
+    }
+
+    fn toggle_stats(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.ui.show_stats = !state.ui.show_stats;
+        if state.ui.show_stats {
+            self.stats = Some(LibraryStats::compute(&state.library.songs));
+        } else {
+            self.stats = None;
+        }
+    }
+
+    fn current_tab(&self) -> AppViews {
+        self.tab_shown(self.state.lock().unwrap().search.searching)
+    }
+
+    fn tab_shown(&self, searching: bool) -> AppViews {
+        if searching {
+            AppViews::SEARCH
+        } else {
+            self.tab
+        }
+    }
+
+    // the library and playlists tabs share the browser, so it's switched over between them
+    fn show_tab(&mut self, tab: AppViews, main_tx: &UnboundedSender<AppRequests>) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if tab == AppViews::SEARCH {
+                if !state.search.searching {
+                    state.search.searching = true;
+                    drop(state);
+                    views::search::update(self);
+                }
+                return;
+            }
+            if state.search.searching {
+                remember_search(&mut state, main_tx);
+            }
+            state.search.searching = false;
+            state.search.term.clear();
+        }
+        match tab {
+            AppViews::LIBRARY if self.view == LibraryViews::PLAYLISTS => {
+                self.show_view(self.library_view, main_tx)
+            }
+            AppViews::PLAYLISTS if self.view != LibraryViews::PLAYLISTS => {
+                self.show_view(LibraryViews::PLAYLISTS, main_tx)
+            }
+            _ => self.tab = tab,
+        }
+    }
+
+    fn show_view(&mut self, view: LibraryViews, main_tx: &UnboundedSender<AppRequests>) {
+        self.view = view;
+        if view == LibraryViews::PLAYLISTS {
+            self.tab = AppViews::PLAYLISTS;
+        } else {
+            self.tab = AppViews::LIBRARY;
+            self.library_view = view;
+        }
+        match view {
+            LibraryViews::SONGS => self.show_tracks(self.all_songs.clone()),
+            LibraryViews::ARTISTS => {
+                let artists = self.state.lock().unwrap().library.artists();
+                self.artist_list = StatefulList::with_items(artists);
+                select_next(&mut self.artist_list);
+                self.level = BrowserLevels::ARTISTS;
+                self.fill_columns();
+            }
+            LibraryViews::ALBUMS => {
+                let albums = self.state.lock().unwrap().library.albums();
+                self.album_list = StatefulList::with_items(albums);
+                select_next(&mut self.album_list);
+                self.level = BrowserLevels::ALBUMS;
+            }
+            LibraryViews::PLAYLISTS => {
+                let playlists = self.state.lock().unwrap().library.playlists();
+                self.playlist_list = StatefulList::with_items(playlists);
+                select_next(&mut self.playlist_list);
+                self.level = BrowserLevels::PLAYLISTS;
+            }
+            LibraryViews::MOST_PLAYED => {
+                let songs = self.state.lock().unwrap().library.most_played();
+                self.show_tracks(songs);
+            }
+            LibraryViews::RECENTLY_PLAYED => {
+                let songs = self.state.lock().unwrap().library.recently_played();
+                self.show_tracks(songs);
+            }
+            LibraryViews::RECENTLY_ADDED => {
+                let songs = self.state.lock().unwrap().library.recently_added();
+                self.show_tracks(songs);
+            }
+            LibraryViews::PODCASTS => {
+                let podcasts = self.state.lock().unwrap().podcasts.podcasts.to_owned();
+                self.podcast_list = StatefulList::with_items(podcasts);
+                select_next(&mut self.podcast_list);
+                self.level = BrowserLevels::PODCASTS;
+            }
+            LibraryViews::FILES => {
+                self.open_dir(self.file_dir.clone(), None);
+                self.level = BrowserLevels::FILES;
+            }
+            LibraryViews::DUPLICATES => {
+                let groups = match &self.duplicates {
+                    Some(groups) => groups.to_owned(),
+                    None => {
+                        self.find_duplicates(main_tx);
+                        vec![]
+                    }
+                };
+                self.duplicate_list = StatefulList::with_items(groups);
+                select_next(&mut self.duplicate_list);
+                self.level = BrowserLevels::DUPLICATES;
+            }
+        }
+    }
+
+    // show what's in the directory, or the library directories if none, with the cursor on the
+    // entry for that path if it's there. one that can't be read leaves the browser where it was
+    fn open_dir(&mut self, dir: Option<PathBuf>, cursor: Option<&Path>) {
+        let entries = match &dir {
+            Some(dir) => match files::list(dir) {
+                Ok(entries) => entries,
+                Err(err) => {
+                    let message = format!("Could not open {}: {}", dir.display(), err);
+                    return self.state.lock().unwrap().ui.notify_error(message);
+                }
             },
-            None => ()
+            None => {
+                let roots = self.state.lock().unwrap().library.roots.to_owned();
+                roots.iter().map(|root| FileEntry::root(root)).collect()
+            }
+        };
+        self.file_dir = dir;
+        self.file_list = StatefulList::with_items(entries);
+        match cursor.and_then(|path| self.file_list.items.iter().position(|e| e.path == path)) {
+            Some(idx) => self.file_list.state.select(Some(idx)),
+            None => select_next(&mut self.file_list),
+        }
+    }
+
+    // the directory up from the one the file browser is in, the library directories above them
+    fn leave_dir(&mut self) {
+        let dir = match self.file_dir.take() {
+            Some(dir) => dir,
+            None => return,
+        };
+        let roots = self.state.lock().unwrap().library.roots.to_owned();
+        let parent = match roots.iter().any(|root| Path::new(root) == dir) {
+            true => None,
+            false => dir.parent().map(Path::to_path_buf),
+        };
+        self.open_dir(parent, Some(&dir));
+    }
+
+    // play the file, or everything under the directory with the rest of it queued up first
+    fn play_path(&mut self, path: &Path, main_tx: &UnboundedSender<AppRequests>) {
+        let mut guard = self.state.lock().unwrap();
+        let mut songs = match guard.library.songs_at(path) {
+            Ok(songs) => songs.into_iter(),
+            Err(err) => {
+                guard.ui.error = Some(format!("Could not play {}: {}", path.display(), err));
+                return;
+            }
+        };
+        let first = match songs.next() {
+            Some(song) => song,
+            None => {
+                let message = format!("There's nothing to play in {}", path.display());
+                return guard.ui.notify_error(message);
+            }
+        };
+        for song in songs.rev() {
+            guard.queue.add_first_immediate(song);
+        }
+        guard.ui.selected_song = Some(first);
+        let _ = main_tx.send(AppRequests::PlayerRequests(PlayerRequests::Start));
+    }
+
+    // reading the audio of every song that might be a copy takes a while, so it's done on another
+    // thread which sends the groups back once it's done
+    fn find_duplicates(&mut self, main_tx: &UnboundedSender<AppRequests>) {
+        if self.finding_duplicates {
+            return;
+        }
+        self.finding_duplicates = true;
+        let songs = self.state.lock().unwrap().library.songs.to_owned();
+        let main_tx = main_tx.clone();
+        thread::spawn(move || {
+            let groups = duplicates::find_duplicates(&songs);
+            let _ = main_tx.send(AppRequests::UIRequests(DuplicatesFound(groups)));
+        });
+    }
+
+    fn on_duplicates_found(&mut self, groups: Vec<Vec<Song>>) {
+        self.finding_duplicates = false;
+        self.duplicates = Some(groups.clone());
+        if self.view == LibraryViews::DUPLICATES && self.level == BrowserLevels::DUPLICATES {
+            self.duplicate_list = StatefulList::with_items(groups);
+            select_next(&mut self.duplicate_list);
+        }
+    }
+
+    // the feeds were read again or an episode was downloaded, which only changes what's shown
+    // while looking at them
+    fn on_podcasts_updated(&mut self) {
+        if self.view != LibraryViews::PODCASTS {
+            return;
+        }
+        let podcasts = self.state.lock().unwrap().podcasts.podcasts.to_owned();
+        let selected_podcast = self.podcast_list.state.selected();
+        self.podcast_list = StatefulList::with_items(podcasts);
+        match selected_podcast.filter(|idx| *idx < self.podcast_list.len()) {
+            Some(idx) => self.podcast_list.state.select(Some(idx)),
+            None => select_next(&mut self.podcast_list),
+        }
+        if self.level != BrowserLevels::TRACKS {
+            return;
+        }
+        let episodes: Vec<Song> = match selected(&self.podcast_list) {
+            Some(podcast) => podcast.episodes.iter().map(|e| e.song.to_owned()).collect(),
+            None => vec![],
+        };
+        let selected_episode = self.song_list.state.selected();
+        self.song_list = StatefulList::with_items(episodes);
+        if let Some(idx) = selected_episode.filter(|idx| *idx < self.song_list.len()) {
+            self.song_list.state.select(Some(idx));
+        }
+    }
+
+    // the one that was selected stays selected if it's still there
+    fn on_playlists_updated(&mut self) {
+        if self.view != LibraryViews::PLAYLISTS {
+            return;
+        }
+        let playlists = self.state.lock().unwrap().library.playlists();
+        let selected_playlist = selected(&self.playlist_list).cloned();
+        self.playlist_list = StatefulList::with_items(playlists);
+        match selected_playlist
+            .and_then(|playlist| self.playlist_list.items.iter().position(|p| *p == playlist))
+        {
+            Some(idx) => self.playlist_list.state.select(Some(idx)),
+            None => select_next(&mut self.playlist_list),
+        }
+    }
+
+    // episodes are saved to play from the file from then on, on another thread since it takes a
+    // while
+    fn download_episode(&mut self, main_tx: &UnboundedSender<AppRequests>) {
+        let song = match selected(&self.song_list).filter(|_| self.level == BrowserLevels::TRACKS) {
+            Some(song) => song.clone(),
+            None => return self.state.lock().unwrap().ui.notify_error("No episode is selected"),
+        };
+        let mut guard = self.state.lock().unwrap();
+        let episode = match guard.podcasts.episode(&song) {
+            Some(episode) => episode.to_owned(),
+            None => {
+                let message = format!("{} is not a podcast episode", song.title);
+                return guard.ui.notify_error(message);
+            }
+        };
+        let dir = match guard.podcasts.download_dir.to_owned() {
+            Some(dir) => dir,
+            None => {
+                return guard.ui.notify_error(
+                    "Set podcasts.download_directory in the config to download episodes",
+                )
+            }
+        };
+        if !episode.song.is_stream() {
+            return guard.ui.notify(format!("{} is already downloaded", song.title));
+        }
+        guard.ui.notify(format!("Downloading {}", song.title));
+        drop(guard);
+        let state = self.state.clone();
+        let main_tx = main_tx.clone();
+        thread::spawn(move || match podcasts::download(&episode, &dir) {
+            Ok(path) => {
+                let mut guard = state.lock().unwrap();
+                guard.podcasts.set_downloaded(&episode.url, path);
+                guard.ui.notify(format!("Downloaded {}", episode.song.title));
+                let _ = main_tx.send(AppRequests::UIRequests(PodcastsUpdated));
+            }
+            Err(err) => {
+                error!("Could not download {}. Reason: {}", episode.url, err);
+                let message = format!("Could not download {}. {}", episode.song.title, err);
+                state.lock().unwrap().ui.notify_error(message);
+            }
+        });
+    }
+
+    fn jump_to_song(&mut self, main_tx: &UnboundedSender<AppRequests>) {
+        if self.level != BrowserLevels::TRACKS {
+            return;
+        }
+        let song = match selected(&self.song_list) {
+            Some(song) => song.clone(),
+            None => return,
+        };
+        self.show_view(LibraryViews::SONGS, main_tx);
+        if let Some(idx) = self.song_list.items.iter().position(|s| s.is_same(&song)) {
+            self.song_list.state.select(Some(idx));
+            self.state.lock().unwrap().ui.selected_song = Some(song);
+        }
+    }
+
+    // back to the browser from wherever it was left, on the song that's playing among the songs
+    // being browsed or else in the songs view
+    fn jump_to_playing(&mut self, main_tx: &UnboundedSender<AppRequests>) {
+        let song = match self.state.lock().unwrap().player.curr_song.to_owned() {
+            Some(song) => song,
+            None => return,
+        };
+        let tab = match self.view {
+            LibraryViews::PLAYLISTS => AppViews::PLAYLISTS,
+            _ => AppViews::LIBRARY,
+        };
+        self.show_tab(tab, main_tx);
+        let browsing = self.level == BrowserLevels::TRACKS
+            && self.song_list.items.iter().any(|s| s.is_same(&song));
+        if !browsing {
+            self.show_view(LibraryViews::SONGS, main_tx);
+        }
+        self.select_in_song_list(&song);
+    }
+
+    // files are read as the playlist is opened, so problems with them only show up here
+    fn selected_playlist_songs(&mut self) -> Option<Vec<Song>> {
+        let playlist = selected(&self.playlist_list)?.to_owned();
+        let mut guard = self.state.lock().unwrap();
+        match guard.library.playlist_songs(&playlist) {
+            Ok(songs) => Some(songs),
+            Err(e) => {
+                guard.ui.error = Some(format!("Could not open {}: {}", playlist.name(), e));
+                None
+            }
+        }
+    }
+
+    fn load_playlist(&mut self) {
+        if self.view != LibraryViews::PLAYLISTS {
+            return;
+        }
+        if let Some(songs) = self.selected_playlist_songs() {
+            let mut guard = self.state.lock().unwrap();
+            for song in songs {
+                guard.queue.add_last_immediate(song);
+            }
+        }
+    }
+
+    fn show_tracks(&mut self, tracks: Vec<Song>) {
+        self.song_list = StatefulList::with_items(tracks);
+        self.level = BrowserLevels::TRACKS;
+        if self.song_list.len() != 0 {
+            views::browser::down(self);
+        }
+    }
+
+    fn edit_tags(&mut self) {
+        if self.level != BrowserLevels::TRACKS {
+            return;
+        }
+        let song = match selected(&self.song_list) {
+            Some(song) => song.clone(),
+            None => return,
+        };
+        let mut guard = self.state.lock().unwrap();
+        // the tags of a cue sheet's tracks live in the cue sheet, not the audio file
+        if song.end_ms.is_some() || song.start_ms != 0 {
+            guard.ui.error = Some("Tracks of a cue sheet can't be edited".to_string());
+            return;
+        }
+        guard.ui.tag_editor = Some(TagEditor::new(song));
+    }
+
+    // the editor stays open when saving fails so that nothing typed is lost
+    fn save_tags(&mut self) {
+        let mut guard = self.state.lock().unwrap();
+        let song = match &guard.ui.tag_editor {
+            Some(editor) => editor.edited_song(),
+            None => return,
+        };
+        if song.title.is_empty() {
+            guard.ui.error = Some("A song needs a title".to_string());
+            return;
+        }
+        if let Err(e) = tag::write_tags(&song) {
+            guard.ui.error = Some(format!("Could not save the tags of {}: {}", song.path, e));
+            return;
         }
+        guard.ui.tag_editor = None;
+        guard.library.update_song(song.clone());
+        drop(guard);
+        self.replace_song(&song);
     }
 
-    fn on_down(&mut self) {
-        let length = self.song_list.len();
-        match self.song_list.state.selected() {
-            Some(idx) => if idx == length {return} else { () },
-            None => (),
+    // change the rating or favorite of the selected songs
+    fn update_stats(&mut self, main_tx: &UnboundedSender<AppRequests>, change: impl Fn(&mut Song)) {
+        if self.level != BrowserLevels::TRACKS {
+            return;
         }
-        // panic!("yo");
-        self.song_list.next();
-        match self.song_list.items.get(self.song_list.state.selected().unwrap()) {
+        for selected in self.marked_songs() {
+            // changed in the library's copy, which has the latest play counts
+            let song = match self.state.lock().unwrap().library.change_stats(&selected, &change) {
+                Some(song) => song,
+                None => continue,
+            };
+            self.replace_song(&song);
+            let _ = main_tx.send(AppRequests::LibraryRequests(LibraryRequests::SaveStats(song)));
+        }
+        self.song_list.clear_selection();
+    }
+
+    // the songs picked out in visual mode, or else the one under the cursor
+    // the menu acts on the same songs as the keys for each of its actions would
+    fn show_menu(&mut self) {
+        let browsing_tracks = self.level == BrowserLevels::TRACKS
+            && matches!(self.tab, AppViews::LIBRARY | AppViews::PLAYLISTS)
+            && !self.state.lock().unwrap().search.searching;
+        let mut guard = self.state.lock().unwrap();
+        if !browsing_tracks || self.marked_songs().is_empty() {
+            guard.ui.notify_error("No song is selected");
+            return;
+        }
+        guard.ui.menu_action = Some(0);
+    }
+
+    // the bookmarks of the song playing, to seek to one of them
+    fn show_bookmarks(&mut self) {
+        let mut guard = self.state.lock().unwrap();
+        let song = match guard.player.curr_song.to_owned() {
+            Some(song) => song,
+            None => return guard.ui.notify_error("Nothing is playing"),
+        };
+        if guard.library.bookmarks(&song).is_empty() {
+            let message = format!("{} has no bookmarks, add one with :bookmark", song.title);
+            return guard.ui.notify_error(message);
+        }
+        guard.ui.bookmark = Some(0);
+    }
+
+    fn seek_to_bookmark(&mut self, bookmark: usize, main_tx: &UnboundedSender<AppRequests>) {
+        let guard = self.state.lock().unwrap();
+        let position = guard
+            .player
+            .curr_song
+            .as_ref()
+            .and_then(|song| guard.library.bookmarks(song).get(bookmark))
+            .map(|bookmark| bookmark.position);
+        if let Some(position) = position {
+            let _ = main_tx.send(AppRequests::PlayerRequests(PlayerRequests::SeekTo(position)));
+        }
+    }
+
+    // and look up what's missing from it online, if that's on
+    fn show_file_info(&mut self, song: Option<Song>, main_tx: &UnboundedSender<AppRequests>) {
+        let mut guard = self.state.lock().unwrap();
+        match song {
             Some(song) => {
-                self.state.lock().unwrap().ui.selected_song = Some(song.clone());
+                let request = MetadataRequests::Lookup(song.to_owned());
+                let _ = main_tx.send(AppRequests::MetadataRequests(request));
+                guard.ui.file_info = Some(song);
+            }
+            None => guard.ui.notify_error("No song is selected"),
+        }
+    }
+
+    // the songs picked out, or every track of the selected album or artist
+    fn fetch_metadata(&mut self, main_tx: &UnboundedSender<AppRequests>) {
+        let songs = {
+            let state = self.state.lock().unwrap();
+            let library = &state.library;
+            match self.level {
+                BrowserLevels::TRACKS => self.marked_songs(),
+                BrowserLevels::ALBUMS => selected(&self.album_list)
+                    .map(|album| library.album_tracks(album))
+                    .unwrap_or_default(),
+                BrowserLevels::ARTISTS => selected(&self.artist_list)
+                    .map(|artist| library.albums_by(artist))
+                    .unwrap_or_default()
+                    .iter()
+                    .flat_map(|album| library.album_tracks(album))
+                    .collect(),
+                _ => vec![],
+            }
+        };
+        if songs.is_empty() {
+            return self.state.lock().unwrap().ui.notify_error("No song is selected");
+        }
+        self.song_list.clear_selection();
+        let _ = main_tx.send(AppRequests::MetadataRequests(MetadataRequests::Fetch(songs)));
+    }
+
+    fn run_menu_action(&mut self, action: MenuActions, main_tx: &UnboundedSender<AppRequests>) {
+        let request = match action {
+            MenuActions::PLAY_NOW => return views::browser::play_selected(self, main_tx),
+            MenuActions::PLAY_NEXT => AppRequests::QueueRequests(QueueRequests::InsertNext),
+            MenuActions::ADD_TO_QUEUE => AppRequests::QueueRequests(QueueRequests::Enqueue),
+            MenuActions::ADD_TO_PLAYLIST => {
+                // the name is typed on the command line
+                let command = CommandLine {
+                    input: "add-to-playlist ".to_string(),
+                    ..CommandLine::default()
+                };
+                self.state.lock().unwrap().ui.command = Some(command);
+                return;
+            }
+            MenuActions::FILE_INFO => {
+                let song = selected(&self.song_list).cloned();
+                return self.show_file_info(song, main_tx);
+            }
+            MenuActions::EDIT_TAGS => AppRequests::UIRequests(UIRequests::EditTags),
+            MenuActions::REMOVE => {
+                let songs = self.marked_songs();
+                self.song_list.clear_selection();
+                AppRequests::LibraryRequests(LibraryRequests::Remove(songs))
+            }
+            MenuActions::MOVE_FILES => {
+                let command = CommandLine {
+                    input: "move-to ".to_string(),
+                    ..CommandLine::default()
+                };
+                self.state.lock().unwrap().ui.command = Some(command);
+                return;
+            }
+            MenuActions::TRASH_FILES => return self.confirm_files(FileActions::Trash),
+            MenuActions::DELETE_FILES => return self.confirm_files(FileActions::Delete),
+        };
+        let _ = main_tx.send(request);
+    }
+
+    // the songs picked out are kept with the question, so moving the cursor doesn't change them
+    fn confirm_files(&mut self, action: FileActions) {
+        let browsing_tracks = self.level == BrowserLevels::TRACKS
+            && matches!(self.tab, AppViews::LIBRARY | AppViews::PLAYLISTS)
+            && !self.state.lock().unwrap().search.searching;
+        let songs = match browsing_tracks {
+            true => self.marked_songs(),
+            false => vec![],
+        };
+        let files = match songs.as_slice() {
+            [] => return self.state.lock().unwrap().ui.notify_error("No song is selected"),
+            [song] => format!("the file of {}", song.title),
+            songs => format!("the files of {} songs", songs.len()),
+        };
+        let question = match &action {
+            FileActions::MoveTo(dir) => format!("Move {} to {}?", files, dir.display()),
+            FileActions::Trash => format!("Move {} to the trash?", files),
+            FileActions::Delete => format!("Delete {} for good?", files),
+        };
+        self.song_list.clear_selection();
+        let request = LibraryRequests::ChangeFiles(songs, action);
+        self.state.lock().unwrap().ui.confirm = Some((question, request));
+    }
+
+    fn marked_songs(&self) -> Vec<Song> {
+        match (self.song_list.selection(), self.collapsed_group()) {
+            (Some(_), _) => self.song_list.selected_items().to_vec(),
+            (None, Some(group)) => self.song_list.items[group].to_vec(),
+            (None, None) => selected(&self.song_list).cloned().into_iter().collect(),
+        }
+    }
+
+    // the progress bar and the tab bar are the same on every tab, what's in the tab is clicked
+    // on through its view
+    fn on_click(&mut self, column: u16, row: u16, main_tx: &UnboundedSender<AppRequests>) {
+        // a click on the progress bar seeks to that point of the song
+        if let Some(bar) = self.progress_bar.filter(|bar| {
+            (bar.left()..bar.right()).contains(&column) && (bar.top()..bar.bottom()).contains(&row)
+        }) {
+            let fraction = (column - bar.x) as f64 / bar.width as f64;
+            let seek = PlayerRequests::SeekAbsolute(fraction);
+            let _ = main_tx.send(AppRequests::PlayerRequests(seek));
+            return;
+        }
+        if let Some(tab) = self
+            .tab_bar_area
+            .and_then(|area| widgets::tab_bar::tab_at(area, column, row))
+        {
+            self.show_tab(tab, main_tx);
+        }
+    }
+
+    // the row of the song list grouped by album the cursor is on
+    fn selected_row(&self) -> Option<usize> {
+        let selected = self.song_list.state.selected()?;
+        album_groups::row_of(&self.groups, selected)
+    }
+
+    // the queue acts on the selection by taking it from the shared state, which ends visual mode
+    fn sync_selection(&mut self) {
+        let mut guard = self.state.lock().unwrap();
+        let browsing_tracks = self.level == BrowserLevels::TRACKS && !guard.search.searching;
+        if !browsing_tracks || guard.ui.selected_songs.is_empty() {
+            self.song_list.clear_selection();
+        }
+        guard.ui.selected_songs = self.song_list.selected_items().to_vec();
+        // the queue takes a collapsed album whole
+        if browsing_tracks && guard.ui.selected_songs.is_empty() {
+            if let Some(group) = self.collapsed_group() {
+                guard.ui.selected_songs = self.song_list.items[group].to_vec();
+            }
+        }
+    }
+
+    // what the input thread and the queue go by is shared as soon as a request changed it,
+    // rather than once it's next drawn
+    fn sync_browsing(&mut self, size: Rect) {
+        let mut guard = self.state.lock().unwrap();
+        let tab = self.tab_shown(guard.search.searching);
+        self.queue_pane = self.queue_beside(tab, &guard, size);
+        // the focus can't stay on a queue that's gone
+        if !self.queue_pane {
+            self.focus = FocusedPanes::MAIN;
+        }
+        guard.ui.key_contexts = self.key_contexts(tab, &guard);
+        let browsing_files = tab == AppViews::LIBRARY && self.level == BrowserLevels::FILES;
+        guard.ui.selected_path = selected(&self.file_list)
+            .filter(|_| browsing_files && self.focus == FocusedPanes::MAIN)
+            .map(|entry| entry.path.to_owned());
+        guard.ui.browsing = Browsing {
+            tab: self.tab,
+            view: self.view,
+            sort: self.sort,
+        };
+    }
+
+    // whether the queue is drawn beside the open tab, worked out from the layout the way
+    // drawing it does. the queue tab already shows the queue
+    fn queue_beside(&self, tab: AppViews, state: &AppState, size: Rect) -> bool {
+        if self.tab == AppViews::QUEUE || size.width < MIN_WIDTH || size.height < MIN_HEIGHT {
+            return false;
+        }
+        let has_pane = state.ui.layout.contains(LayoutWidgets::QUEUE);
+        let inner = Block::default().borders(Borders::ALL).inner(size);
+        layout::areas(&state.ui.layout, inner)
+            .into_iter()
+            .any(|(widget, area)| match widget {
+                LayoutWidgets::QUEUE => true,
+                LayoutWidgets::MAIN => {
+                    let browsing = matches!(
+                        tab,
+                        AppViews::LIBRARY | AppViews::PLAYLISTS | AppViews::SEARCH
+                    );
+                    browsing
+                        && !has_pane
+                        && !state.ui.show_lyrics
+                        && area.width >= SIDE_PANE_MIN_WIDTH
+                }
+                _ => false,
+            })
+    }
+
+    // whether the song list is grouped by album, working out its rows again if it changed
+    fn grouped(&mut self) -> bool {
+        let grouped = self.state.lock().unwrap().ui.group_by_album;
+        self.refresh_groups(grouped)
+    }
+
+    fn refresh_groups(&mut self, grouped: bool) -> bool {
+        if !grouped {
+            self.groups.clear();
+            self.groups_version = None;
+            return false;
+        }
+        let version = self.song_list.version();
+        if self.groups_version != Some(version) {
+            self.groups = album_groups::table_rows(&self.song_list.items, &self.collapsed);
+            self.groups_version = Some(version);
+        }
+        true
+    }
+
+    fn select_song(&mut self, idx: Option<usize>) {
+        self.song_list.state.select(idx);
+        if let Some(song) = selected(&self.song_list) {
+            self.state.lock().unwrap().ui.selected_song = Some(song.clone());
+        }
+    }
+
+    // the cursor skips the headers of open albums and the songs of collapsed ones
+    fn step_in_groups(&mut self, steps: usize, down: bool) {
+        let selected = self.song_list.state.selected();
+        let idx = album_groups::step(&self.groups, selected, steps, down);
+        self.select_song(idx);
+    }
+
+    // the songs of the collapsed album the cursor is on, which are acted on together
+    fn collapsed_group(&self) -> Option<Range<usize>> {
+        let version = Some(self.song_list.version());
+        if self.level != BrowserLevels::TRACKS || self.groups_version != version {
+            return None;
+        }
+        let row = album_groups::row_of(&self.groups, self.song_list.state.selected()?)?;
+        match self.groups[row] {
+            header @ TableRows::HEADER { .. } => Some(header.songs()),
+            TableRows::SONG(_) => None,
+        }
+    }
+
+    // a song selected some other way than by moving the cursor, eg. by a jump, opens its album
+    // if that's collapsed
+    fn reveal_selected(&mut self) {
+        if self.level != BrowserLevels::TRACKS || !self.grouped() {
+            return;
+        }
+        let (idx, song) = match self.song_list.state.selected() {
+            Some(idx) => match self.song_list.items.get(idx) {
+                Some(song) => (idx, song),
+                None => return,
             },
-            None => ()
+            None => return,
+        };
+        let hidden = matches!(
+            album_groups::row_of(&self.groups, idx).map(|row| self.groups[row]),
+            Some(TableRows::HEADER { first, .. }) if first != idx
+        );
+        if hidden {
+            self.collapsed.remove(&album_groups::album_of(song));
+            self.groups_version = None;
         }
     }
 
-    fn on_enter(&mut self) {}
+    // swap in a changed song wherever it is listed
+    fn replace_song(&mut self, song: &Song) {
+        for list in [&mut self.all_songs, &mut self.song_list.items] {
+            if let Some(old) = list.iter_mut().find(|old| old.is_same(song)) {
+                *old = song.clone();
+            }
+        }
+        self.song_list.changed();
+    }
 
-    fn go_back(&mut self) {
-        if self.state.lock().unwrap().search.searching {
-            self.state.lock().unwrap().search.searching = false;
-            self.state.lock().unwrap().search.term.clear();
+    // back to the tab, view and sort order the last session was left in
+    fn restore_browsing(&mut self, main_tx: &UnboundedSender<AppRequests>) {
+        let browsing = self.state.lock().unwrap().ui.browsing;
+        if browsing.sort != self.sort {
+            self.sort = browsing.sort;
+            sort_songs(&mut self.all_songs, self.sort);
+            self.show_view(LibraryViews::SONGS, main_tx);
+        }
+        if browsing.view != self.view {
+            self.show_view(browsing.view, main_tx);
+        }
+        if let AppViews::QUEUE | AppViews::SETTINGS = browsing.tab {
+            self.tab = browsing.tab;
+        }
+    }
+
+    fn cycle_sort(&mut self, main_tx: &UnboundedSender<AppRequests>) {
+        self.sort = self.sort.cycle();
+        sort_songs(&mut self.all_songs, self.sort);
+        if self.view == LibraryViews::SONGS {
+            self.show_view(LibraryViews::SONGS, main_tx);
+        }
+    }
+
+    // nothing is queued after the current song, so continue with the song after it in the list.
+    // the player may ask before the song is over so that it can crossfade into the next one
+    fn on_track_ended(&mut self, main_tx: &UnboundedSender<AppRequests>) {
+        let mut guard = self.state.lock().unwrap();
+        let last_song = match guard.player.curr_song.as_ref().or(guard.queue.last_played()) {
+            Some(song) => song.clone(),
+            None => return,
+        };
+        let len = self.song_list.len();
+        let next_idx = if guard.player.shuffle && len != 0 {
+            Some(rand::thread_rng().gen_range(0..len))
+        } else {
+            match self.song_list.items.iter().position(|song| song.is_same(&last_song)) {
+                Some(idx) if idx + 1 < len => Some(idx + 1),
+                Some(_) if guard.player.repeat == RepeatModes::ALL => Some(0),
+                _ => None,
+            }
+        };
+        if let Some(song) = next_idx.and_then(|idx| self.song_list.items.get(idx)) {
+            guard.queue.add_upcoming(song.clone());
+            // a player that is still playing will pick the song up from the queue by itself
+            if guard.player.curr_state == PlayerStates::STOPPED {
+                let _ = main_tx.send(AppRequests::PlayerRequests(PlayerRequests::Next));
+            }
+        }
+    }
+
+    fn adjust_eq_band(&mut self, step_db: f32, main_tx: &UnboundedSender<AppRequests>) {
+        let guard = self.state.lock().unwrap();
+        let band = guard.ui.eq_band;
+        let gain = guard.player.eq_bands[band] + step_db;
+        let _ = main_tx.send(AppRequests::PlayerRequests(PlayerRequests::SetEqBand(band, gain)));
+    }
+
+    fn change_speed(&mut self, step: f32, main_tx: &UnboundedSender<AppRequests>) {
+        let speed = self.state.lock().unwrap().player.speed + step;
+        // round so that repeated steps don't drift away from 1.0
+        let speed = (speed * 10.).round() / 10.;
+        let _ = main_tx.send(AppRequests::PlayerRequests(PlayerRequests::SetSpeed(speed)));
+    }
+
+    fn change_balance(&mut self, step: f32, main_tx: &UnboundedSender<AppRequests>) {
+        let balance = self.state.lock().unwrap().player.balance + step;
+        let balance = (balance * 10.).round() / 10.;
+        let _ = main_tx.send(AppRequests::PlayerRequests(PlayerRequests::SetBalance(balance)));
+    }
+
+    // only reads the lyrics again once another song starts
+    fn update_lyrics(&mut self, curr_song: Option<&Song>) {
+        match curr_song {
+            None => self.lyrics = None,
+            Some(song) => {
+                if self.lyrics.as_ref().map(|(path, _)| path) != Some(&song.path) {
+                    self.lyrics = Some((song.path.to_owned(), Lyrics::load(song)));
+                }
+            }
+        }
+    }
+
+    // only loads the art again once another song starts, or once art was fetched for a song that
+    // had none
+    fn update_cover_art(&mut self, curr_song: Option<&Song>, metadata: &Metadata) {
+        match curr_song {
+            None => self.cover_art = None,
+            Some(song) => {
+                let outdated = match &self.cover_art {
+                    Some(art) => {
+                        art.path != song.path || (art.is_empty() && metadata.has_cover(song))
+                    }
+                    None => true,
+                };
+                if outdated {
+                    self.cover_art = Some(CoverArt::load(song));
+                }
+            }
+        }
+    }
+
+    // sends the art to terminals with a graphics protocol. it's drawn on top of the cells tui
+    // left blank for it, so it's hidden while a popup is open and sent again after anything that
+    // could have drawn over it
+    fn draw_cover_art(
+        &mut self,
+        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    ) -> Result<(), SplayError> {
+        if self.graphics == GraphicsProtocols::HALF_BLOCKS {
+            return Ok(());
+        }
+        let popup_open = {
+            let state = self.state.lock().unwrap();
+            state.ui.show_equalizer
+                || state.ui.tag_editor.is_some()
+                || state.ui.menu_action.is_some()
+                || state.ui.bookmark.is_some()
+                || state.ui.file_info.is_some()
+                || state.ui.error.is_some()
+                || state.search.searching
+        };
+        let art = match (&self.cover_art, self.art_area) {
+            (Some(art), Some(area)) if !art.is_empty() && !popup_open => {
+                Some((art.path.to_owned(), area))
+            }
+            _ => None,
+        };
+        if art == self.drawn_art {
+            return Ok(());
+        }
+        if self.drawn_art.is_some() {
+            let _ = write!(
+                terminal.backend_mut(),
+                "{}",
+                graphics::clear_images(self.graphics)
+            );
+            // sixel and iterm2 images are part of the cells, which tui only redraws if it
+            // thinks they changed
+            if self.graphics != GraphicsProtocols::KITTY {
+                let _ = terminal.clear();
+                terminal.draw(|f| self.get_ui(f))?;
+            }
         }
+        self.drawn_art = art;
+        let area = match self.drawn_art {
+            Some((_, area)) => area,
+            None => {
+                let _ = Write::flush(terminal.backend_mut());
+                return Ok(());
+            }
+        };
+
+        let protocol = self.graphics;
+        let image = match self.cover_art.as_mut().and_then(|art| {
+            art.scaled(
+                area.width as u32 * graphics::SIXEL_CELL_WIDTH,
+                area.height as u32 * graphics::SIXEL_CELL_HEIGHT,
+            )
+        }) {
+            Some(image) => image,
+            None => return Ok(()),
+        };
+        // the size in cells of the scaled image, which keeps the art's aspect ratio
+        let cols = image.width().div_ceil(graphics::SIXEL_CELL_WIDTH);
+        let rows = image.height().div_ceil(graphics::SIXEL_CELL_HEIGHT);
+        let escape = graphics::encode(protocol, image, cols as u16, rows as u16);
+        let backend = terminal.backend_mut();
+        let _ = execute!(backend, cursor::MoveTo(area.x, area.y));
+        let _ = write!(backend, "{}", escape);
+        let _ = Write::flush(backend);
+        Ok(())
     }
 
-    fn get_ui<B: Backend>(&mut self, frame: &mut Frame<B>, main_tx: &Sender<AppRequests>) {
+    fn get_ui<B: Backend>(&mut self, frame: &mut Frame<B>) {
+        // the state is locked once for the whole frame, so the player isn't kept waiting on it
+        // over and over and everything drawn is from the same moment
+        let app_state = self.state.clone();
+        let state = app_state.lock().unwrap();
         let size = frame.size();
+        // nothing fits below this, so there's only a note saying so
+        if size.width < MIN_WIDTH || size.height < MIN_HEIGHT {
+            self.tab_bar_area = None;
+            self.tabs_area = None;
+            self.list_areas.clear();
+            self.art_area = None;
+            self.progress_bar = None;
+            let note = Paragraph::new(format!(
+                "Make the terminal at least {}x{} to use splay",
+                MIN_WIDTH, MIN_HEIGHT
+            ))
+            .style(self.theme.text)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true });
+            frame.render_widget(note, size);
+            return;
+        }
         let block = Block::default().title("splay").borders(Borders::ALL);
+        let panes = layout::areas(&state.ui.layout, block.inner(size));
         frame.render_widget(block, size);
 
-        let vert_chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .margin(1)
-            .constraints([Constraint::Percentage(20), Constraint::Percentage(80)].as_ref())
-            .split(frame.size());
+        let tab = self.tab_shown(state.search.searching);
+        // what isn't in the layout can't be clicked on
+        self.tab_bar_area = None;
+        self.art_area = None;
+        self.progress_bar = None;
+        // only the library has tabs of its own and a list to click on
+        self.tabs_area = None;
+        self.list_areas.clear();
+        for (widget, area) in panes {
+            match widget {
+                LayoutWidgets::TABS => {
+                    let crumbs = self.breadcrumb(&state);
+                    widgets::tab_bar::render(frame, area, tab, &crumbs, &self.theme);
+                    self.tab_bar_area = Some(area);
+                }
+                LayoutWidgets::NOW_PLAYING => self.draw_now_playing(frame, area, &state),
+                LayoutWidgets::MAIN => {
+                    let area = match state.ui.show_log {
+                        true => self.draw_log(frame, area),
+                        false => area,
+                    };
+                    match tab {
+                        AppViews::LIBRARY | AppViews::PLAYLISTS => {
+                            views::browser::draw(self, frame, area, tab, &state)
+                        }
+                        AppViews::QUEUE => views::queue::draw(self, frame, area, &state),
+                        AppViews::SEARCH => views::search::draw(self, frame, area, &state),
+                        AppViews::SETTINGS => views::settings::draw(self, frame, area, &state),
+                    }
+                }
+                LayoutWidgets::QUEUE => views::queue::draw_pane(self, frame, area, &state),
+                LayoutWidgets::STATUS => {
+                    widgets::status_line::render(frame, area, &state.ui, &self.theme)
+                }
+            }
+        }
 
-        let horiz_chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .margin(0)
-            .constraints([Constraint::Percentage(80), Constraint::Percentage(20)].as_ref())
-            .split(vert_chunks[1]);
+        views::popups::draw(self, frame, &state);
+    }
 
-        let song_list_vert_chunks = Layout::default()
+    // the last lines logged along the bottom of the area, returning what's left above them
+    fn draw_log<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) -> Rect {
+        let chunks = Layout::default()
             .direction(Direction::Vertical)
-            .margin(0)
-            .constraints([Constraint::Percentage(10), Constraint::Percentage(90)].as_ref())
-            .split(horiz_chunks[0]);
-
-        // match &self.state.lock().unwrap().player.curr_state {
-        //     PlayerStates::PLAYING => {
-        //         let block = Block::default().title("Popup").borders(Borders::ALL);
-        //         let area = helper::centered_rect(60, 60, size);
-        //         let selected_song = self
-        //             .song_list
-        //             .items
-        //             .get(self.song_list.state.selected().unwrap());
-        //         let paragraph = Paragraph::new(format!("{:#?}", selected_song.unwrap()))
-        //             .style(Style::default().fg(Color::White))
-        //             .alignment(Alignment::Left);
-        //         frame.render_widget(Clear, area);
-        //         frame.render_widget(paragraph, block.inner(area));
-        //         frame.render_widget(block, area);
-        //     }
-        //     x => info!("{:?}", x)
-        // }
-
-        let mut filtered_songs: Vec<Song> = Vec::new();
-        if self.state.lock().unwrap().search.searching {
-            let search = Paragraph::new(self.state.lock().unwrap().search.term.to_owned())
-                .style(Style::default().fg(Color::White))
-                .alignment(Alignment::Left)
-                .wrap(Wrap { trim: false });
-            frame.render_widget(Clear, song_list_vert_chunks[0]);
-            frame.render_widget(search, song_list_vert_chunks[0]);
-
-            let search_term = &self.state.lock().unwrap().search.term.to_lowercase();
-            for song in self.song_list.items.iter() {
-                if song.title.to_lowercase().contains(search_term) {
-                    filtered_songs.push(song.clone());
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(LOG_PANE_HEIGHT.min(area.height / 2)),
+            ])
+            .split(area);
+        let lines = logs::tail(chunks[1].height.saturating_sub(2) as usize);
+        widgets::log_pane::render(frame, chunks[1], &lines, &self.theme);
+        chunks[0]
+    }
+
+    // the cover art and the song that's playing
+    fn draw_now_playing<B: Backend>(
+        &mut self,
+        frame: &mut Frame<B>,
+        area: Rect,
+        state: &AppState,
+    ) {
+        self.update_cover_art(state.player.curr_song.as_ref(), &state.metadata);
+        let has_art = self.cover_art.as_ref().map_or(false, |art| !art.is_empty());
+        let now_playing_area = if has_art {
+            // cells are about twice as tall as they are wide, so this is close to square
+            let art_width = area.height * 2;
+            let now_playing_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Length(art_width), Constraint::Min(0)].as_ref())
+                .split(area);
+            let art_area = now_playing_chunks[0];
+            self.art_area = Some(art_area);
+            if self.graphics == GraphicsProtocols::HALF_BLOCKS {
+                if let Some(image) = self.cover_art.as_mut().and_then(|art| {
+                    art.scaled(art_area.width as u32, art_area.height as u32 * 2)
+                }) {
+                    frame.render_widget(HalfBlocks { image }, art_area);
                 }
             }
+            now_playing_chunks[1]
+        } else {
+            self.art_area = None;
+            area
+        };
+        // the visualizer takes the right of the song that's playing, if there's room for both
+        let show_visualizer =
+            state.ui.show_visualizer && now_playing_area.width >= VISUALIZER_MIN_WIDTH;
+        let now_playing_area = if show_visualizer {
+            let visualizer_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+                .split(now_playing_area);
+            let playing = state.player.curr_state == PlayerStates::PLAYING;
+            // a bar every other column
+            let bars = (visualizer_chunks[1].width.saturating_sub(2) / 2) as usize;
+            let levels = if playing {
+                Some(self.tap.lock().unwrap().spectrum(bars))
+            } else {
+                None
+            };
+            widgets::visualizer::render(frame, visualizer_chunks[1], levels, &self.theme);
+            visualizer_chunks[0]
         } else {
-            filtered_songs = self.song_list.items.clone();
+            now_playing_area
+        };
+        let progress_bar =
+            widgets::curr_playing_bar::render(frame, now_playing_area, state, &self.theme);
+        self.progress_bar = Some(progress_bar);
+    }
+
+    // where the browser is, from the open tab down to the level being browsed
+    fn breadcrumb(&self, state: &AppState) -> Vec<String> {
+        if state.search.searching {
+            let mut crumbs = vec![AppViews::SEARCH.title().to_string()];
+            if !state.search.term.is_empty() {
+                crumbs.push(format!("\"{}\"", state.search.term));
+            }
+            return crumbs;
+        }
+        let mut crumbs = vec![self.tab.title().to_string()];
+        if self.tab == AppViews::LIBRARY {
+            crumbs.push(self.view.title().to_string());
+        }
+        if !matches!(self.tab, AppViews::LIBRARY | AppViews::PLAYLISTS) {
+            return crumbs;
+        }
+        // the library directory the browser is under, then each directory down from it
+        if let (LibraryViews::FILES, Some(dir)) = (self.view, &self.file_dir) {
+            if let Some(root) = files::root_of(&state.library.roots, dir) {
+                crumbs.push(root.to_owned());
+                let below = dir.strip_prefix(root).unwrap_or(dir);
+                crumbs.extend(below.iter().map(|name| name.to_string_lossy().into_owned()));
+            }
+            return crumbs;
         }
+        let tracks = self.level == BrowserLevels::TRACKS;
+        let opened = match self.view {
+            LibraryViews::ARTISTS => [
+                selected(&self.artist_list)
+                    .filter(|_| self.level != BrowserLevels::ARTISTS)
+                    .cloned(),
+                selected(&self.album_list)
+                    .filter(|_| tracks)
+                    .map(|album| album.title.to_owned()),
+            ],
+            _ if !tracks => [None, None],
+            LibraryViews::ALBUMS => [
+                selected(&self.album_list)
+                    .map(|album| format!("{} - {}", album.artist, album.title)),
+                None,
+            ],
+            LibraryViews::PLAYLISTS => [
+                selected(&self.playlist_list).map(|playlist| playlist.name()),
+                None,
+            ],
+            LibraryViews::DUPLICATES => [
+                selected(&self.duplicate_list).map(|group| duplicate_name(group)),
+                None,
+            ],
+            LibraryViews::PODCASTS => [
+                selected(&self.podcast_list).map(|podcast| podcast.title.to_owned()),
+                None,
+            ],
+            _ => [None, None],
+        };
+        crumbs.extend(opened.into_iter().flatten());
+        crumbs
+    }
 
-        let filtered_stateful_list = StatefulList::with_items(filtered_songs);
+}
 
-        let list: Vec<ListItem> = filtered_stateful_list
-            .items
-            .iter()
-            .map(|i| {
-                let mut album = i.album_title.clone();
-                album.truncate(16);
-                ListItem::new(vec![Spans::from(format!("{: <16} {}", album, i.title.clone()))])
-            })
-            .collect();
-
-        let list = List::new(list)
-            .block(Block::default().borders(Borders::ALL).title("Songs"))
-            .highlight_style(
-                Style::default()
-                    .fg(Color::Cyan)
-                    .add_modifier(Modifier::BOLD),
-            )
-            .highlight_symbol(">> ");
+// the focused one of several lists side by side has its border stand out. what doesn't fit in
+// the area is cut short
+fn browser_list<'a>(
+    items: &[String],
+    title: String,
+    area: Rect,
+    focused: bool,
+    theme: &'a Theme,
+) -> List<'a> {
+    // less the borders and the room kept for the highlight symbol
+    let width = (area.width as usize).saturating_sub(2 + theme.highlight_symbol.width());
+    let items: Vec<ListItem> = items
+        .iter()
+        .map(|item| ListItem::new(Spans::from(helper::truncate(item, width).into_owned())))
+        .collect();
+    let title = helper::truncate(&title, (area.width as usize).saturating_sub(2)).into_owned();
+    let border = if focused { theme.accent } else { Style::default() };
+    List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title).border_style(border))
+        .style(theme.text)
+        .highlight_style(theme.highlight)
+        .highlight_symbol(&theme.highlight_symbol)
+}
+
+fn sort_songs(songs: &mut [Song], sort: SortModes) {
+    match sort {
+        SortModes::TITLE => songs.sort_by(|a, b| a.title.cmp(&b.title)),
+        SortModes::ARTIST => songs.sort_by(|a, b| {
+            (&a.track_artist, &a.title).cmp(&(&b.track_artist, &b.title))
+        }),
+        SortModes::RATING => songs.sort_by(|a, b| {
+            (b.favorite, b.rating)
+                .cmp(&(a.favorite, a.rating))
+                .then_with(|| a.title.cmp(&b.title))
+        }),
+    }
+}
+
+// stars and a heart after the title of rated and favorite songs
+// the rating after the title, unless it has a column of its own
+fn rating_suffix(columns: &[ColumnConfig]) -> fn(&Song) -> String {
+    match columns.iter().any(|column| column.name == SongColumns::RATING) {
+        true => |_| String::new(),
+        false => rating_marks,
+    }
+}
+
+fn rating_marks(song: &Song) -> String {
+    let mut marks = String::new();
+    if song.rating > 0 {
+        marks.push(' ');
+        marks.push_str(&"★".repeat(song.rating as usize));
+    }
+    if song.favorite {
+        marks.push_str(" ♥");
+    }
+    marks
+}
+
+fn duplicate_name(group: &[Song]) -> String {
+    match group.first() {
+        Some(song) => format!("{} - {}", song.track_artist, song.title),
+        None => String::new(),
+    }
+}
+
+fn selected<T>(list: &StatefulList<T>) -> Option<&T> {
+    list.state.selected().and_then(|idx| list.items.get(idx))
+}
+
+fn select_previous<T>(list: &mut StatefulList<T>) {
+    if list.len() != 0 {
+        list.previous();
+    }
+}
+
+fn select_edge<T>(list: &mut StatefulList<T>, last: bool) {
+    if list.len() != 0 {
+        let idx = if last { list.len() - 1 } else { 0 };
+        list.state.select(Some(idx));
+    }
+}
+
+// keep the search being closed, here and for the next time
+fn remember_search(state: &mut AppState, main_tx: &UnboundedSender<AppRequests>) {
+    if let Some(query) = state.search.remember() {
+        let request = LibraryRequests::RememberSearch(query);
+        let _ = main_tx.send(AppRequests::LibraryRequests(request));
+    }
+}
+
+// step through the commands run before, back to an empty line after the newest
+fn show_history(command: &mut CommandLine, history: &[String], older: bool) {
+    let idx = match (command.history_idx, older) {
+        (None, true) => history.len().checked_sub(1),
+        (None, false) => None,
+        (Some(idx), true) => Some(idx.saturating_sub(1)),
+        (Some(idx), false) => Some(idx + 1).filter(|idx| *idx < history.len()),
+    };
+    command.history_idx = idx;
+    command.input = idx.map_or(String::new(), |idx| history[idx].to_owned());
+    command.completions.clear();
+}
+
+fn list_scrollbar<'a, T>(list: &StatefulList<T>, theme: &'a Theme) -> Scrollbar<'a> {
+    Scrollbar {
+        offset: list.offset(),
+        len: list.len(),
+        selected: list.state.selected(),
+        header_rows: 0,
+        theme,
+    }
+}
+
+// the songs are in a table that scrolls on its own, under a header row
+fn table_scrollbar<'a>(
+    offset: usize,
+    songs: &StatefulList<Song>,
+    theme: &'a Theme,
+) -> Scrollbar<'a> {
+    Scrollbar {
+        offset,
+        len: songs.len(),
+        selected: songs.state.selected(),
+        header_rows: 1,
+        theme,
+    }
+}
+
+// false when there's nothing on that row
+fn select_clicked<T>(list: &mut StatefulList<T>, row: usize) -> bool {
+    match list.index_at(row) {
+        Some(idx) => select_row(list, idx),
+        None => false,
+    }
+}
+
+fn select_row<T>(list: &mut StatefulList<T>, idx: usize) -> bool {
+    if idx >= list.len() {
+        return false;
+    }
+    list.state.select(Some(idx));
+    true
+}
 
-        frame.render_stateful_widget(list, song_list_vert_chunks[1], &mut self.song_list.state);
-        widgets::curr_playing_bar::render(frame, vert_chunks[0], &(self.state.lock().unwrap()));
+fn select_next<T>(list: &mut StatefulList<T>) {
+    if list.len() != 0 {
+        list.next();
     }
 }
@@ -0,0 +1,311 @@
+use std::iter::Peekable;
+use std::path::Path;
+use std::str::Chars;
+
+use tui::text::Span;
+
+use crate::config::FormatsConfig;
+use crate::library::song::Song;
+use crate::ui::widgets::curr_playing_bar::readable_time;
+
+// how a song is written out, eg. "{%artist% - }%title%". a group in braces is left out when a
+// field in it is empty, and of groups written as {..}|{..} the first with all its fields is shown
+#[derive(Clone, PartialEq, Debug)]
+pub struct SongFormat {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Part {
+    TEXT(String),
+    FIELD(FormatFields),
+    GROUP(Vec<Vec<Part>>), // the alternatives, tried in order
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FormatFields {
+    TITLE,
+    ARTIST,
+    ALBUM,
+    ALBUM_ARTIST,
+    GENRE,
+    YEAR,
+    TRACK,
+    DISC,
+    DURATION,
+    RATING,
+    PLAYS,
+    CODEC,
+    FILENAME,
+    PATH,
+}
+
+const FIELDS: [(&str, FormatFields); 14] = [
+    ("title", FormatFields::TITLE),
+    ("artist", FormatFields::ARTIST),
+    ("album", FormatFields::ALBUM),
+    ("albumartist", FormatFields::ALBUM_ARTIST),
+    ("genre", FormatFields::GENRE),
+    ("year", FormatFields::YEAR),
+    ("track", FormatFields::TRACK),
+    ("disc", FormatFields::DISC),
+    ("duration", FormatFields::DURATION),
+    ("rating", FormatFields::RATING),
+    ("plays", FormatFields::PLAYS),
+    ("codec", FormatFields::CODEC),
+    ("filename", FormatFields::FILENAME),
+    ("path", FormatFields::PATH),
+];
+
+impl FormatFields {
+    // empty when the song doesn't have it
+    fn value(self, song: &Song) -> String {
+        let tag = |value: &Option<String>| value.to_owned().unwrap_or_default();
+        match self {
+            FormatFields::TITLE => song.title.to_owned(),
+            FormatFields::ARTIST => song.track_artist.to_owned(),
+            FormatFields::ALBUM => song.album_title.to_owned(),
+            FormatFields::ALBUM_ARTIST => song.album_artist.to_owned(),
+            FormatFields::GENRE => tag(&song.genre),
+            FormatFields::YEAR => tag(&song.year),
+            FormatFields::TRACK => tag(&song.track_number),
+            FormatFields::DISC => tag(&song.disc_number),
+            FormatFields::DURATION if song.duration_secs == 0 => String::new(),
+            FormatFields::DURATION => readable_time(song.duration_secs),
+            FormatFields::RATING => "★".repeat(song.rating.min(5) as usize),
+            FormatFields::PLAYS if song.play_count == 0 => String::new(),
+            FormatFields::PLAYS => song.play_count.to_string(),
+            FormatFields::CODEC => song.format(),
+            FormatFields::FILENAME => Path::new(&song.path)
+                .file_name()
+                .map_or(String::new(), |name| name.to_string_lossy().into_owned()),
+            FormatFields::PATH => song.path.to_owned(),
+        }
+    }
+}
+
+impl SongFormat {
+    pub fn parse(format: &str) -> Result<SongFormat, String> {
+        let parts = parse_parts(&mut format.chars().peekable(), false)?;
+        Ok(SongFormat { parts })
+    }
+
+    pub fn render(&self, song: &Song) -> String {
+        self.spans(song, |_, value| vec![Span::raw(value)])
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect()
+    }
+
+    // with each field's value styled by `field`, eg. to pick out what a search matched
+    pub fn spans(
+        &self,
+        song: &Song,
+        field: impl Fn(FormatFields, String) -> Vec<Span<'static>>,
+    ) -> Vec<Span<'static>> {
+        let mut spans = Vec::new();
+        write_parts(&self.parts, song, &field, &mut spans);
+        spans
+    }
+}
+
+// the rows of the song lists and the lines above the progress bar
+#[derive(Clone, PartialEq, Debug)]
+pub struct SongFormats {
+    pub song: SongFormat,
+    pub now_playing: SongFormat,
+}
+
+impl SongFormats {
+    pub fn from_config(config: &FormatsConfig) -> Result<SongFormats, String> {
+        Ok(SongFormats {
+            song: SongFormat::parse(&config.song)
+                .map_err(|err| format!("formats.song: {}", err))?,
+            now_playing: SongFormat::parse(&config.now_playing)
+                .map_err(|err| format!("formats.now_playing: {}", err))?,
+        })
+    }
+}
+
+// the title, with the artist on the line under it above the progress bar
+impl Default for SongFormats {
+    fn default() -> Self {
+        Self {
+            song: SongFormat {
+                parts: vec![Part::FIELD(FormatFields::TITLE)],
+            },
+            now_playing: SongFormat {
+                parts: vec![
+                    Part::FIELD(FormatFields::TITLE),
+                    Part::TEXT("\n".to_string()),
+                    Part::FIELD(FormatFields::ARTIST),
+                ],
+            },
+        }
+    }
+}
+
+// up to the end, or to the brace closing the group being read. %% is a % of its own
+fn parse_parts(chars: &mut Peekable<Chars>, in_group: bool) -> Result<Vec<Part>, String> {
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '%' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('%') => break,
+                        Some(ch) => name.push(ch),
+                        None => return Err(format!("%{} isn't closed with a %", name)),
+                    }
+                }
+                if name.is_empty() {
+                    text.push('%');
+                    continue;
+                }
+                let field = match FIELDS.iter().find(|(field, _)| *field == name) {
+                    Some((_, field)) => *field,
+                    None => {
+                        let names: Vec<&str> = FIELDS.iter().map(|(field, _)| *field).collect();
+                        return Err(format!(
+                            "has no field %{}%, the fields are {}",
+                            name,
+                            names.join(", ")
+                        ));
+                    }
+                };
+                push_text(&mut parts, &mut text);
+                parts.push(Part::FIELD(field));
+            }
+            '{' => {
+                push_text(&mut parts, &mut text);
+                let mut alternatives = vec![parse_parts(chars, true)?];
+                // a | only separates groups right between them, it's text anywhere else
+                while chars.peek() == Some(&'|') {
+                    let mut ahead = chars.clone();
+                    ahead.next();
+                    if ahead.peek() != Some(&'{') {
+                        break;
+                    }
+                    chars.next();
+                    chars.next();
+                    alternatives.push(parse_parts(chars, true)?);
+                }
+                parts.push(Part::GROUP(alternatives));
+            }
+            '}' if in_group => {
+                push_text(&mut parts, &mut text);
+                return Ok(parts);
+            }
+            '}' => return Err("has a } without a { before it".to_string()),
+            ch => text.push(ch),
+        }
+    }
+    if in_group {
+        return Err("has a { that isn't closed with a }".to_string());
+    }
+    push_text(&mut parts, &mut text);
+    Ok(parts)
+}
+
+fn push_text(parts: &mut Vec<Part>, text: &mut String) {
+    if !text.is_empty() {
+        parts.push(Part::TEXT(std::mem::take(text)));
+    }
+}
+
+// false when a field in them was empty, which leaves out the group they're in
+fn write_parts(
+    parts: &[Part],
+    song: &Song,
+    field: &dyn Fn(FormatFields, String) -> Vec<Span<'static>>,
+    spans: &mut Vec<Span<'static>>,
+) -> bool {
+    let mut complete = true;
+    for part in parts {
+        match part {
+            Part::TEXT(text) => spans.push(Span::raw(text.to_owned())),
+            Part::FIELD(name) => {
+                let value = name.value(song);
+                if value.is_empty() {
+                    complete = false;
+                } else {
+                    spans.extend(field(*name, value));
+                }
+            }
+            Part::GROUP(alternatives) => {
+                for alternative in alternatives {
+                    let mut group = Vec::new();
+                    if write_parts(alternative, song, field, &mut group) {
+                        spans.extend(group);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    complete
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song() -> Song {
+        let mut song = Song::new("Title".to_string(), "/music/a/title.flac".to_string());
+        song.track_artist = "Artist".to_string();
+        song
+    }
+
+    fn render(format: &str, song: &Song) -> String {
+        SongFormat::parse(format).unwrap().render(song)
+    }
+
+    #[test]
+    fn fields_and_text() {
+        assert_eq!(render("%artist% - %title%", &song()), "Artist - Title");
+        assert_eq!(render("%filename%", &song()), "title.flac");
+    }
+
+    #[test]
+    fn double_percent_is_a_percent() {
+        assert_eq!(render("100%% %title%", &song()), "100% Title");
+    }
+
+    #[test]
+    fn group_with_an_empty_field_is_left_out() {
+        let mut song = song();
+        assert_eq!(render("{%year% - }%title%", &song), "Title");
+        song.year = Some("1999".to_string());
+        assert_eq!(render("{%year% - }%title%", &song), "1999 - Title");
+    }
+
+    #[test]
+    fn first_complete_alternative_is_shown() {
+        let mut song = song();
+        let format = "{%track%. }|{%disc% }|{- }%title%";
+        assert_eq!(render(format, &song), "- Title");
+        song.disc_number = Some("2".to_string());
+        assert_eq!(render(format, &song), "2 Title");
+        song.track_number = Some("7".to_string());
+        assert_eq!(render(format, &song), "7. Title");
+    }
+
+    #[test]
+    fn bar_outside_alternatives_is_text() {
+        assert_eq!(render("%artist% | %title%", &song()), "Artist | Title");
+        assert_eq!(render("{%artist%}| %title%", &song()), "Artist| Title");
+        assert_eq!(render("{%year%}|x", &song()), "|x");
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(SongFormat::parse("%title").unwrap_err().contains("isn't closed"));
+        assert!(SongFormat::parse("%nope%").unwrap_err().contains("has no field %nope%"));
+        assert!(SongFormat::parse("{%title%").unwrap_err().contains("isn't closed with a }"));
+        assert!(SongFormat::parse("%title%}").unwrap_err().contains("without a {"));
+        assert!(SongFormat::parse("{a}|{b").is_err());
+    }
+}
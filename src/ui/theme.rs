@@ -0,0 +1,192 @@
+use tui::style::{Color, Modifier, Style};
+
+use crate::config::{StyleConfig, ThemeConfig};
+use crate::utils::constants::ThemePresets;
+
+// the colors of every widget
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub text: Style,
+    pub highlight: Style,
+    pub accent: Style, // modes that are on and progress bars
+    pub dimmed: Style, // modes that are off, missing songs and lyrics already sung
+    pub error: Style,
+    pub gauge: Style, // the song's progress
+    pub highlight_symbol: String,
+    pub chart_colors: Vec<Color>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::preset(ThemePresets::DEFAULT)
+    }
+}
+
+impl Theme {
+    pub fn preset(preset: ThemePresets) -> Theme {
+        match preset {
+            ThemePresets::DEFAULT => Theme {
+                text: Style::default().fg(Color::White),
+                highlight: Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+                accent: Style::default().fg(Color::Cyan),
+                dimmed: Style::default().fg(Color::DarkGray),
+                error: Style::default().fg(Color::Red),
+                gauge: Style::default()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::ITALIC),
+                highlight_symbol: ">> ".to_string(),
+                chart_colors: vec![Color::Cyan, Color::Yellow, Color::Green, Color::Magenta],
+            },
+            ThemePresets::GRUVBOX => Theme {
+                text: Style::default().fg(Color::Rgb(0xeb, 0xdb, 0xb2)),
+                highlight: Style::default()
+                    .fg(Color::Rgb(0xfa, 0xbd, 0x2f))
+                    .add_modifier(Modifier::BOLD),
+                accent: Style::default().fg(Color::Rgb(0x8e, 0xc0, 0x7c)),
+                dimmed: Style::default().fg(Color::Rgb(0x92, 0x83, 0x74)),
+                error: Style::default().fg(Color::Rgb(0xfb, 0x49, 0x34)),
+                gauge: Style::default()
+                    .fg(Color::Rgb(0xfe, 0x80, 0x19))
+                    .add_modifier(Modifier::ITALIC),
+                highlight_symbol: "> ".to_string(),
+                chart_colors: vec![
+                    Color::Rgb(0xfa, 0xbd, 0x2f),
+                    Color::Rgb(0x83, 0xa5, 0x98),
+                    Color::Rgb(0xb8, 0xbb, 0x26),
+                    Color::Rgb(0xd3, 0x86, 0x9b),
+                ],
+            },
+            ThemePresets::NORD => Theme {
+                text: Style::default().fg(Color::Rgb(0xd8, 0xde, 0xe9)),
+                highlight: Style::default()
+                    .fg(Color::Rgb(0x88, 0xc0, 0xd0))
+                    .add_modifier(Modifier::BOLD),
+                accent: Style::default().fg(Color::Rgb(0x81, 0xa1, 0xc1)),
+                dimmed: Style::default().fg(Color::Rgb(0x4c, 0x56, 0x6a)),
+                error: Style::default().fg(Color::Rgb(0xbf, 0x61, 0x6a)),
+                gauge: Style::default()
+                    .fg(Color::Rgb(0x8f, 0xbc, 0xbb))
+                    .add_modifier(Modifier::ITALIC),
+                highlight_symbol: "▶ ".to_string(),
+                chart_colors: vec![
+                    Color::Rgb(0x88, 0xc0, 0xd0),
+                    Color::Rgb(0xeb, 0xcb, 0x8b),
+                    Color::Rgb(0xa3, 0xbe, 0x8c),
+                    Color::Rgb(0xb4, 0x8e, 0xad),
+                ],
+            },
+        }
+    }
+
+    // the preset from the config with its overrides applied. an unknown color or modifier is an
+    // error instead of being ignored, so typos don't go unnoticed
+    pub fn from_config(config: &ThemeConfig) -> Result<Theme, String> {
+        let mut theme = Theme::preset(config.preset);
+        let styles = [
+            ("text", &config.text, &mut theme.text),
+            ("highlight", &config.highlight, &mut theme.highlight),
+            ("accent", &config.accent, &mut theme.accent),
+            ("dimmed", &config.dimmed, &mut theme.dimmed),
+            ("error", &config.error, &mut theme.error),
+            ("gauge", &config.gauge, &mut theme.gauge),
+        ];
+        for (name, style_config, style) in styles {
+            if let Some(style_config) = style_config {
+                *style = apply_style(*style, style_config)
+                    .map_err(|err| format!("theme.{}: {}", name, err))?;
+            }
+        }
+        if let Some(symbol) = &config.highlight_symbol {
+            theme.highlight_symbol = symbol.to_owned();
+        }
+        if let Some(colors) = &config.chart_colors {
+            if colors.is_empty() {
+                return Err("theme.chart_colors: needs at least one color".to_string());
+            }
+            theme.chart_colors = colors
+                .iter()
+                .map(|color| parse_color(color))
+                .collect::<Result<Vec<Color>, String>>()
+                .map_err(|err| format!("theme.chart_colors: {}", err))?;
+        }
+        Ok(theme)
+    }
+
+    // the color of the nth chart in the stats dashboard
+    pub fn chart_color(&self, idx: usize) -> Color {
+        self.chart_colors[idx % self.chart_colors.len()]
+    }
+}
+
+fn apply_style(mut style: Style, config: &StyleConfig) -> Result<Style, String> {
+    if let Some(fg) = &config.fg {
+        style.fg = Some(parse_color(fg)?);
+    }
+    if let Some(bg) = &config.bg {
+        style.bg = Some(parse_color(bg)?);
+    }
+    if let Some(modifiers) = &config.modifiers {
+        style.add_modifier = Modifier::empty();
+        for modifier in modifiers {
+            style.add_modifier |= parse_modifier(modifier)?;
+        }
+    }
+    Ok(style)
+}
+
+fn parse_color(color: &str) -> Result<Color, String> {
+    let color = color.trim().to_lowercase();
+    if let Some(hex) = color.strip_prefix('#') {
+        let channel = |idx: usize| {
+            hex.get(idx..idx + 2)
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+        };
+        return match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Color::Rgb(r, g, b)),
+            _ => Err(format!("\"{}\" is not a #rrggbb color", color)),
+        };
+    }
+    let color = match color.replace(['_', '-', ' '], "").as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        // a color from the terminal's 256 color palette
+        index => match index.parse::<u8>() {
+            Ok(index) => Color::Indexed(index),
+            Err(_) => return Err(format!("unknown color \"{}\"", color)),
+        },
+    };
+    Ok(color)
+}
+
+fn parse_modifier(modifier: &str) -> Result<Modifier, String> {
+    let modifier = match modifier.trim().to_lowercase().as_str() {
+        "bold" => Modifier::BOLD,
+        "dim" => Modifier::DIM,
+        "italic" => Modifier::ITALIC,
+        "underlined" | "underline" => Modifier::UNDERLINED,
+        "slow_blink" | "blink" => Modifier::SLOW_BLINK,
+        "rapid_blink" => Modifier::RAPID_BLINK,
+        "reversed" | "reverse" => Modifier::REVERSED,
+        "hidden" => Modifier::HIDDEN,
+        "crossed_out" | "strikethrough" => Modifier::CROSSED_OUT,
+        _ => return Err(format!("unknown modifier \"{}\"", modifier)),
+    };
+    Ok(modifier)
+}
@@ -0,0 +1,770 @@
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    Frame,
+};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::library::search;
+use crate::library::song::Song;
+use crate::state::AppState;
+use crate::ui::views::{self, ListAreas};
+use crate::ui::widgets::{self, album_groups::{self, TableRows}, scrollbar::Scrollbar};
+use crate::ui::widgets::stateful_list::scrolled_offset;
+use crate::ui::{
+    browser_list, duplicate_name, list_scrollbar, rating_suffix, select_clicked, select_edge,
+    select_next, select_previous, select_row, selected, table_scrollbar, App,
+};
+use crate::utils::constants::requests::{AppRequests, PlayerRequests, UIRequests::{self, *}};
+use crate::utils::constants::{AppViews, BrowserLevels, FocusedPanes, LibraryViews};
+
+// the library and playlists tabs, browsed a level at a time or in the artists view's columns
+pub fn handle(
+    app: &mut App,
+    request: UIRequests,
+    main_tx: &UnboundedSender<AppRequests>,
+) -> Option<UIRequests> {
+    if let Some((rows, down)) = views::page(app, &request) {
+        page(app, rows, down);
+        return None;
+    }
+    match request {
+        Up => up(app),
+        Down => down(app),
+        Top => select_edge_row(app, false),
+        Bottom => select_edge_row(app, true),
+        JumpToLetter(letter) => jump_to_letter(app, letter),
+        Enter => enter(app, main_tx),
+        Play => play_selected(app, main_tx),
+        FocusLeft => move_focus(app, false),
+        FocusRight => move_focus(app, true),
+        ToggleVisual => toggle_visual(app),
+        ToggleGroup => toggle_group(app),
+        NextMatch => select_match(app, true),
+        PreviousMatch => select_match(app, false),
+        Click(column, row) | DoubleClick(column, row) => {
+            let double = matches!(request, DoubleClick(..));
+            if !click(app, column, row, double, main_tx) {
+                return Some(request);
+            }
+        }
+        GoBack => go_back(app),
+        ShowFileInfo => {
+            let song = selected(&app.song_list).filter(|_| app.level == BrowserLevels::TRACKS);
+            app.show_file_info(song.cloned(), main_tx);
+        }
+        request => return Some(request),
+    }
+    None
+}
+
+pub fn up(app: &mut App) {
+    match app.level {
+        BrowserLevels::DUPLICATES => return select_previous(&mut app.duplicate_list),
+        BrowserLevels::PLAYLISTS => return select_previous(&mut app.playlist_list),
+        BrowserLevels::PODCASTS => return select_previous(&mut app.podcast_list),
+        BrowserLevels::FILES => return select_previous(&mut app.file_list),
+        BrowserLevels::ARTISTS => {
+            select_previous(&mut app.artist_list);
+            return app.fill_columns();
+        }
+        BrowserLevels::ALBUMS => {
+            select_previous(&mut app.album_list);
+            return app.fill_columns();
+        }
+        BrowserLevels::TRACKS => (),
+    }
+    if app.grouped() {
+        return app.step_in_groups(1, false);
+    }
+    match app.song_list.state.selected() {
+        Some(idx) => if idx == 0 {return} else { () },
+        None => return,
+    }
+    app.song_list.previous();
+    if let Some(song) = selected(&app.song_list) {
+        app.state.lock().unwrap().ui.selected_song = Some(song.clone());
+    }
+}
+
+pub fn down(app: &mut App) {
+    match app.level {
+        BrowserLevels::DUPLICATES => return select_next(&mut app.duplicate_list),
+        BrowserLevels::PLAYLISTS => return select_next(&mut app.playlist_list),
+        BrowserLevels::PODCASTS => return select_next(&mut app.podcast_list),
+        BrowserLevels::FILES => return select_next(&mut app.file_list),
+        BrowserLevels::ARTISTS => {
+            select_next(&mut app.artist_list);
+            return app.fill_columns();
+        }
+        BrowserLevels::ALBUMS => {
+            select_next(&mut app.album_list);
+            return app.fill_columns();
+        }
+        BrowserLevels::TRACKS => (),
+    }
+    if app.grouped() {
+        return app.step_in_groups(1, true);
+    }
+    let length = app.song_list.len();
+    match app.song_list.state.selected() {
+        Some(idx) => if idx == length {return} else { () },
+        None => (),
+    }
+    app.song_list.next();
+    if let Some(song) = selected(&app.song_list) {
+        app.state.lock().unwrap().ui.selected_song = Some(song.clone());
+    }
+}
+
+// select the first or last row of the list being browsed
+fn select_edge_row(app: &mut App, last: bool) {
+    let grouped = app.grouped();
+    match app.level {
+        BrowserLevels::DUPLICATES => select_edge(&mut app.duplicate_list, last),
+        BrowserLevels::PLAYLISTS => select_edge(&mut app.playlist_list, last),
+        BrowserLevels::PODCASTS => select_edge(&mut app.podcast_list, last),
+        BrowserLevels::FILES => select_edge(&mut app.file_list, last),
+        BrowserLevels::ARTISTS => {
+            select_edge(&mut app.artist_list, last);
+            app.fill_columns();
+        }
+        BrowserLevels::ALBUMS => {
+            select_edge(&mut app.album_list, last);
+            app.fill_columns();
+        }
+        BrowserLevels::TRACKS if grouped => {
+            let idx = album_groups::edge(&app.groups, last);
+            app.select_song(idx);
+        }
+        BrowserLevels::TRACKS => {
+            select_edge(&mut app.song_list, last);
+            if let Some(song) = selected(&app.song_list) {
+                app.state.lock().unwrap().ui.selected_song = Some(song.clone());
+            }
+        }
+    }
+}
+
+// move a page, as many rows as there were on screen, through the list being browsed
+fn page(app: &mut App, rows: usize, down: bool) {
+    let grouped = app.grouped();
+    match app.level {
+        BrowserLevels::DUPLICATES => app.duplicate_list.page(rows, down),
+        BrowserLevels::PLAYLISTS => app.playlist_list.page(rows, down),
+        BrowserLevels::PODCASTS => app.podcast_list.page(rows, down),
+        BrowserLevels::FILES => app.file_list.page(rows, down),
+        BrowserLevels::ARTISTS => {
+            app.artist_list.page(rows, down);
+            app.fill_columns();
+        }
+        BrowserLevels::ALBUMS => {
+            app.album_list.page(rows, down);
+            app.fill_columns();
+        }
+        BrowserLevels::TRACKS if grouped => app.step_in_groups(rows, down),
+        BrowserLevels::TRACKS => {
+            app.song_list.page(rows, down);
+            if let Some(song) = selected(&app.song_list) {
+                app.state.lock().unwrap().ui.selected_song = Some(song.clone());
+            }
+        }
+    }
+}
+
+fn jump_to_letter(app: &mut App, letter: Option<char>) {
+    views::jump_to_letter(app, letter, |app, starts_with_letter| {
+        let by_artist = views::by_artist(app);
+        match app.level {
+            BrowserLevels::DUPLICATES => app
+                .duplicate_list
+                .jump_to(|group| starts_with_letter(&duplicate_name(group))),
+            BrowserLevels::PLAYLISTS => app
+                .playlist_list
+                .jump_to(|playlist| starts_with_letter(&playlist.name())),
+            BrowserLevels::PODCASTS => app
+                .podcast_list
+                .jump_to(|podcast| starts_with_letter(&podcast.title)),
+            BrowserLevels::FILES => {
+                app.file_list.jump_to(|entry| starts_with_letter(&entry.name))
+            }
+            BrowserLevels::ARTISTS => {
+                let found = app.artist_list.jump_to(|artist| starts_with_letter(artist));
+                app.fill_columns();
+                found
+            }
+            BrowserLevels::ALBUMS => {
+                let found = app.album_list.jump_to(|album| starts_with_letter(&album.title));
+                app.fill_columns();
+                found
+            }
+            BrowserLevels::TRACKS => {
+                let found = app
+                    .song_list
+                    .jump_to(|song| starts_with_letter(views::jump_key(song, by_artist)));
+                if let Some(song) = selected(&app.song_list) {
+                    app.state.lock().unwrap().ui.selected_song = Some(song.clone());
+                }
+                found
+            }
+        }
+    })
+}
+
+// drill down into the selected artist or album, or play the selected track
+fn enter(app: &mut App, main_tx: &UnboundedSender<AppRequests>) {
+    match app.level {
+        BrowserLevels::DUPLICATES => {
+            if let Some(group) = selected(&app.duplicate_list) {
+                app.show_tracks(group.to_owned());
+            }
+        }
+        BrowserLevels::PLAYLISTS => {
+            if let Some(tracks) = app.selected_playlist_songs() {
+                app.show_tracks(tracks);
+            }
+        }
+        BrowserLevels::PODCASTS => {
+            if let Some(podcast) = selected(&app.podcast_list) {
+                let episodes = podcast.episodes.iter().map(|e| e.song.to_owned()).collect();
+                app.show_tracks(episodes);
+            }
+        }
+        BrowserLevels::FILES => {
+            let entry = match selected(&app.file_list) {
+                Some(entry) => entry.to_owned(),
+                None => return,
+            };
+            match entry.is_dir {
+                true => app.open_dir(Some(entry.path), None),
+                false => app.play_path(&entry.path, main_tx),
+            }
+        }
+        BrowserLevels::ARTISTS => move_focus(app, true),
+        BrowserLevels::ALBUMS => {
+            let album = match selected(&app.album_list) {
+                Some(album) => album.to_owned(),
+                None => return,
+            };
+            let tracks = app.state.lock().unwrap().library.album_tracks(&album);
+            app.show_tracks(tracks);
+        }
+        // a collapsed album is opened rather than played
+        BrowserLevels::TRACKS if app.collapsed_group().is_some() => toggle_group(app),
+        BrowserLevels::TRACKS => play_selected(app, main_tx),
+    }
+}
+
+// start the song under the cursor
+pub fn play_selected(app: &mut App, main_tx: &UnboundedSender<AppRequests>) {
+    // a directory is played whole
+    if app.level == BrowserLevels::FILES {
+        if let Some(entry) = selected(&app.file_list) {
+            let path = entry.path.to_owned();
+            return app.play_path(&path, main_tx);
+        }
+    }
+    match selected(&app.song_list).filter(|_| app.level == BrowserLevels::TRACKS) {
+        // the player starts the selected song
+        Some(song) => {
+            app.state.lock().unwrap().ui.selected_song = Some(song.clone());
+            let _ = main_tx.send(AppRequests::PlayerRequests(PlayerRequests::Start));
+        }
+        None => app.state.lock().unwrap().ui.notify_error("No song is selected"),
+    }
+}
+
+fn move_focus(app: &mut App, right: bool) {
+    if !app.in_columns() {
+        return;
+    }
+    match (app.level, right) {
+        (BrowserLevels::ARTISTS, true) if app.album_list.len() != 0 => {
+            app.level = BrowserLevels::ALBUMS
+        }
+        (BrowserLevels::ALBUMS, true) if app.song_list.len() != 0 => {
+            let tracks = app.song_list.items.to_owned();
+            app.show_tracks(tracks);
+        }
+        (BrowserLevels::ALBUMS, false) => app.level = BrowserLevels::ARTISTS,
+        (BrowserLevels::TRACKS, false) => {
+            app.song_list.clear_selection();
+            app.level = BrowserLevels::ALBUMS;
+        }
+        _ => (),
+    }
+}
+
+// in visual mode moving the cursor selects every song it passes over
+fn toggle_visual(app: &mut App) {
+    if app.level != BrowserLevels::TRACKS {
+        return;
+    }
+    if app.song_list.selection().is_some() {
+        app.song_list.clear_selection();
+    } else {
+        app.song_list.start_selection();
+        app.state.lock().unwrap().ui.selected_songs = app.song_list.selected_items().to_vec();
+    }
+}
+
+// collapse the album the cursor is in to its header, or open it again
+fn toggle_group(app: &mut App) {
+    if app.level != BrowserLevels::TRACKS || !app.grouped() {
+        return;
+    }
+    let row = match app.song_list.state.selected() {
+        Some(idx) => album_groups::row_of(&app.groups, idx),
+        None => None,
+    };
+    // the album's header is the last one before the row
+    let header = row.and_then(|row| {
+        app.groups[..=row]
+            .iter()
+            .rev()
+            .find(|row| matches!(row, TableRows::HEADER { .. }))
+            .copied()
+    });
+    if let Some(TableRows::HEADER {
+        first, collapsed, ..
+    }) = header
+    {
+        let album = album_groups::album_of(&app.song_list.items[first]);
+        if collapsed {
+            app.collapsed.remove(&album);
+        } else {
+            app.collapsed.insert(album);
+        }
+        app.groups_version = None;
+        app.grouped();
+        // the cursor stays on the album, on its header while it's collapsed
+        app.select_song(Some(first));
+    }
+}
+
+// select the next or previous song in the list being browsed that matches the last search,
+// going around at the ends like n and N in vim
+fn select_match(app: &mut App, forward: bool) {
+    let term = match app.state.lock().unwrap().search.highlight.to_owned() {
+        Some(term) => term,
+        None => return,
+    };
+    let len = app.song_list.len();
+    if app.level != BrowserLevels::TRACKS || len == 0 {
+        return;
+    }
+    let start = app.song_list.state.selected().unwrap_or(0);
+    for step in 1..=len {
+        let idx = if forward {
+            (start + step) % len
+        } else {
+            (start + len - step) % len
+        };
+        let song = &app.song_list.items[idx];
+        if search::match_song(&term, song).is_some() {
+            app.song_list.state.select(Some(idx));
+            app.state.lock().unwrap().ui.selected_song = Some(song.clone());
+            return;
+        }
+    }
+}
+
+// clicking a view shows it and clicking a row selects it. clicking it twice quickly also opens
+// or plays it like enter does. false if the click wasn't on either
+fn click(
+    app: &mut App,
+    column: u16,
+    row: u16,
+    double: bool,
+    main_tx: &UnboundedSender<AppRequests>,
+) -> bool {
+    if let Some(view) = app
+        .tabs_area
+        .and_then(|area| widgets::view_tabs::view_at(area, column, row))
+    {
+        app.show_view(view, main_tx);
+        return true;
+    }
+    if app.stats.is_some() {
+        return false;
+    }
+    let (level, row) = match views::clicked_row(app, column, row) {
+        Some(clicked) => clicked,
+        None => return false,
+    };
+    // clicking another of the artists view's columns moves the focus there
+    if app.in_columns() && level != app.level {
+        if app.level == BrowserLevels::TRACKS {
+            app.song_list.clear_selection();
+        }
+        app.level = level;
+    }
+    let grouped = app.grouped();
+    let clicked = match level {
+        BrowserLevels::DUPLICATES => select_clicked(&mut app.duplicate_list, row),
+        BrowserLevels::PLAYLISTS => select_clicked(&mut app.playlist_list, row),
+        BrowserLevels::PODCASTS => select_clicked(&mut app.podcast_list, row),
+        BrowserLevels::FILES => select_clicked(&mut app.file_list, row),
+        BrowserLevels::ARTISTS => select_clicked(&mut app.artist_list, row),
+        BrowserLevels::ALBUMS => select_clicked(&mut app.album_list, row),
+        BrowserLevels::TRACKS if grouped => {
+            match app.groups.get(app.table_offset + row).copied() {
+                // clicking the header of an open album collapses it
+                Some(TableRows::HEADER {
+                    first,
+                    collapsed: false,
+                    ..
+                }) => {
+                    app.select_song(Some(first));
+                    toggle_group(app);
+                    false
+                }
+                Some(row) => {
+                    app.select_song(row.song());
+                    true
+                }
+                None => false,
+            }
+        }
+        BrowserLevels::TRACKS => {
+            let clicked = select_row(&mut app.song_list, app.table_offset + row);
+            if let Some(song) = selected(&app.song_list).filter(|_| clicked) {
+                app.state.lock().unwrap().ui.selected_song = Some(song.clone());
+            }
+            clicked
+        }
+    };
+    if clicked && matches!(level, BrowserLevels::ARTISTS | BrowserLevels::ALBUMS) {
+        app.fill_columns();
+    }
+    if clicked && double {
+        enter(app, main_tx);
+    }
+    true
+}
+
+// the selection, then the last search's highlight, then a level up in the browser
+fn go_back(app: &mut App) {
+    if app.song_list.selection().is_some() {
+        return app.song_list.clear_selection();
+    }
+    if app.state.lock().unwrap().search.highlight.take().is_some() {
+        return;
+    }
+    match (app.view, app.level) {
+        (LibraryViews::ARTISTS, BrowserLevels::ALBUMS) => app.level = BrowserLevels::ARTISTS,
+        (LibraryViews::ARTISTS, BrowserLevels::TRACKS)
+        | (LibraryViews::ALBUMS, BrowserLevels::TRACKS) => app.level = BrowserLevels::ALBUMS,
+        (LibraryViews::PLAYLISTS, BrowserLevels::TRACKS) => app.level = BrowserLevels::PLAYLISTS,
+        (LibraryViews::DUPLICATES, BrowserLevels::TRACKS) => {
+            app.level = BrowserLevels::DUPLICATES
+        }
+        (LibraryViews::PODCASTS, BrowserLevels::TRACKS) => app.level = BrowserLevels::PODCASTS,
+        (LibraryViews::FILES, BrowserLevels::FILES) => app.leave_dir(),
+        _ => (),
+    }
+}
+
+// the songs being browsed with the queue or lyrics beside them
+pub fn draw<B: Backend>(
+    app: &mut App,
+    frame: &mut Frame<B>,
+    area: Rect,
+    tab: AppViews,
+    state: &AppState,
+) {
+    let ListAreas {
+        header,
+        list,
+        side,
+        queue_shown,
+    } = views::list_areas(area, state);
+    let header_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+        .split(header);
+    if app.view != LibraryViews::PLAYLISTS {
+        widgets::view_tabs::render(frame, header_chunks[0], app.view, &app.theme);
+        app.tabs_area = Some(header_chunks[0]);
+    }
+    if let Some((scanned, total)) = state.ui.scan_progress {
+        widgets::scan_progress::render(frame, header_chunks[1], scanned, total, &app.theme);
+    }
+
+    // the characters matching the last search stand out
+    let highlight = state
+        .search
+        .highlight
+        .to_owned()
+        .filter(|term| !term.trim().is_empty());
+    // copies are told apart by where they are
+    let suffix: fn(&Song) -> String = match app.view {
+        LibraryViews::DUPLICATES => |song| format!("  {}", song.path),
+        LibraryViews::MOST_PLAYED => |song| format!(" ({} plays)", song.play_count),
+        LibraryViews::RECENTLY_ADDED => {
+            |song| format!(" (added {})", widgets::file_info_popup::time_ago(song.added_secs))
+        }
+        _ => rating_suffix(&state.ui.columns),
+    };
+
+    let title = match app.view {
+        LibraryViews::SONGS => match app.all_songs.iter().filter(|song| song.missing).count() {
+            0 => format!("Songs (by {})", app.sort.name()),
+            missing => format!(
+                "Songs (by {}, {} missing, X: remove them)",
+                app.sort.name(),
+                missing
+            ),
+        },
+        LibraryViews::MOST_PLAYED
+        | LibraryViews::RECENTLY_PLAYED
+        | LibraryViews::RECENTLY_ADDED => app.view.title().to_string(),
+        LibraryViews::PLAYLISTS => selected(&app.playlist_list)
+            .map_or("Songs".to_string(), |playlist| playlist.name()),
+        LibraryViews::DUPLICATES => selected(&app.duplicate_list)
+            .map_or("Songs".to_string(), |group| duplicate_name(group)),
+        LibraryViews::PODCASTS => selected(&app.podcast_list).map_or(
+            "Episodes".to_string(),
+            |podcast| format!("{} (:download to keep an episode)", podcast.title),
+        ),
+        _ => match selected(&app.album_list) {
+            Some(album) => format!("{} - {}", album.artist, album.title),
+            None => "Songs".to_string(),
+        },
+    };
+    let title = match &highlight {
+        Some(term) => format!("{} - \"{}\" (n/N: next/previous match, Esc: clear)", title, term),
+        None => title,
+    };
+    let selection = app.song_list.selection();
+    let title = match &selection {
+        Some(range) => format!(
+            "{} - {} selected (a: enqueue, alt+1-5: rate, :add-to-playlist <name>)",
+            title,
+            range.clone().count()
+        ),
+        None => title,
+    };
+    // the artists view has a column for each level, the others show the level being browsed
+    let side_by_side = app.columns_shown(tab);
+    // with a queue beside the lists, the border shows which of them has the focus
+    let main_focused = app.focus == FocusedPanes::MAIN;
+    let bordered = (side_by_side || queue_shown) && main_focused;
+    let panes = if side_by_side {
+        let column_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(
+                [
+                    Constraint::Percentage(25),
+                    Constraint::Percentage(30),
+                    Constraint::Percentage(45),
+                ]
+                .as_ref(),
+            )
+            .split(list);
+        vec![
+            (BrowserLevels::ARTISTS, column_chunks[0]),
+            (BrowserLevels::ALBUMS, column_chunks[1]),
+            (BrowserLevels::TRACKS, column_chunks[2]),
+        ]
+    } else {
+        vec![(app.level, list)]
+    };
+    let grouped = app.refresh_groups(state.ui.group_by_album);
+    // only what's on screen is drawn, so where each list is scrolled to is needed first
+    for (level, area) in panes.iter().copied() {
+        if main_focused && (level == app.level || !side_by_side) {
+            // less the borders and the header row of a table
+            let header = u16::from(level == BrowserLevels::TRACKS);
+            app.page_rows = area.height.saturating_sub(2 + header) as usize;
+        }
+        track_offsets(app, level, area.height.saturating_sub(2) as usize);
+    }
+
+    let version = app.song_list.version();
+    let song_table = widgets::song_table::SongTable {
+        songs: &app.song_list.items,
+        groups: Some(app.groups.as_slice()).filter(|_| grouped),
+        offset: app.table_offset,
+        selected: if grouped {
+            app.selected_row()
+        } else {
+            app.song_list.state.selected()
+        },
+        title,
+        columns: &state.ui.columns,
+        format: &state.ui.formats.song,
+        highlight: highlight.as_deref(),
+        selection,
+        suffix,
+        focused: bordered && (!side_by_side || app.level == BrowserLevels::TRACKS),
+        cache: Some((&mut app.song_rows, version)),
+    };
+    let mut song_table = Some(song_table);
+    for (level, area) in panes.iter().copied() {
+        let focused = bordered && (!side_by_side || level == app.level);
+        let height = area.height.saturating_sub(2) as usize;
+        match level {
+            BrowserLevels::DUPLICATES => {
+                let names: Vec<String> = app
+                    .duplicate_list
+                    .visible(height)
+                    .iter()
+                    .map(|group| format!("{} ({} copies)", duplicate_name(group), group.len()))
+                    .collect();
+                let title = if app.finding_duplicates {
+                    "Duplicates (searching...)"
+                } else {
+                    "Duplicates (g: show in songs)"
+                };
+                let list = browser_list(&names, title.to_string(), area, focused, &app.theme);
+                let mut state = app.duplicate_list.visible_state();
+                frame.render_stateful_widget(list, area, &mut state);
+            }
+            BrowserLevels::PLAYLISTS => {
+                let names: Vec<String> = app
+                    .playlist_list
+                    .visible(height)
+                    .iter()
+                    .map(|playlist| playlist.name())
+                    .collect();
+                let title = "Playlists".to_string();
+                let list = browser_list(&names, title, area, focused, &app.theme);
+                let mut state = app.playlist_list.visible_state();
+                frame.render_stateful_widget(list, area, &mut state);
+            }
+            BrowserLevels::PODCASTS => {
+                let names: Vec<String> = app
+                    .podcast_list
+                    .visible(height)
+                    .iter()
+                    .map(|podcast| {
+                        format!("{} ({} episodes)", podcast.title, podcast.episodes.len())
+                    })
+                    .collect();
+                let title = if names.is_empty() {
+                    "Podcasts (subscribe to feeds in the config)"
+                } else {
+                    "Podcasts"
+                };
+                let list = browser_list(&names, title.to_string(), area, focused, &app.theme);
+                let mut state = app.podcast_list.visible_state();
+                frame.render_stateful_widget(list, area, &mut state);
+            }
+            BrowserLevels::FILES => {
+                let names: Vec<String> = app
+                    .file_list
+                    .visible(height)
+                    .iter()
+                    .map(|entry| match entry.is_dir {
+                        true => format!("{}/", entry.name.trim_end_matches('/')),
+                        false => entry.name.to_owned(),
+                    })
+                    .collect();
+                let title = match &app.file_dir {
+                    Some(dir) => format!(
+                        "{} (Enter: open or play, a: enqueue, Esc: up)",
+                        dir.display()
+                    ),
+                    None if names.is_empty() => {
+                        "Files (only the library directories can be browsed)".to_string()
+                    }
+                    None => "Library directories (Enter: open, a: enqueue)".to_string(),
+                };
+                let list = browser_list(&names, title, area, focused, &app.theme);
+                let mut state = app.file_list.visible_state();
+                frame.render_stateful_widget(list, area, &mut state);
+            }
+            BrowserLevels::ARTISTS => {
+                let title = "Artists".to_string();
+                let artists = app.artist_list.visible(height);
+                let list = browser_list(artists, title, area, focused, &app.theme);
+                let mut state = app.artist_list.visible_state();
+                frame.render_stateful_widget(list, area, &mut state);
+            }
+            BrowserLevels::ALBUMS => {
+                // the albums of a single artist don't need the artist repeated
+                let (albums, title): (Vec<String>, String) = match app.view {
+                    LibraryViews::ARTISTS => (
+                        app.album_list
+                            .visible(height)
+                            .iter()
+                            .map(|album| album.title.to_owned())
+                            .collect(),
+                        format!(
+                            "Albums by {}",
+                            selected(&app.artist_list).map_or("", |artist| artist.as_str())
+                        ),
+                    ),
+                    _ => (
+                        app.album_list
+                            .visible(height)
+                            .iter()
+                            .map(|album| format!("{} - {}", album.title, album.artist))
+                            .collect(),
+                        "Albums".to_string(),
+                    ),
+                };
+                let list = browser_list(&albums, title, area, focused, &app.theme);
+                let mut state = app.album_list.visible_state();
+                frame.render_stateful_widget(list, area, &mut state);
+            }
+            BrowserLevels::TRACKS => {
+                if let Some(song_table) = song_table.take() {
+                    widgets::song_table::render(frame, area, song_table, &app.theme);
+                }
+            }
+        }
+    }
+    for (level, area) in panes {
+        app.list_areas.push((level, area));
+        frame.render_widget(scrollbar(app, level), area);
+    }
+    if let Some(stats) = &app.stats {
+        widgets::stats_dashboard::render(frame, list, stats, &app.theme);
+    }
+    if let Some(side) = side {
+        views::draw_side(app, frame, side, state);
+    }
+}
+
+// where the list or table of a level of the browser is scrolled to
+fn scrollbar(app: &App, level: BrowserLevels) -> Scrollbar<'_> {
+    let theme = &app.theme;
+    match level {
+        BrowserLevels::TRACKS if app.groups_version.is_some() => Scrollbar {
+            offset: app.table_offset,
+            len: app.groups.len(),
+            selected: app.selected_row(),
+            header_rows: 1,
+            theme,
+        },
+        BrowserLevels::TRACKS => table_scrollbar(app.table_offset, &app.song_list, theme),
+        BrowserLevels::DUPLICATES => list_scrollbar(&app.duplicate_list, theme),
+        BrowserLevels::PLAYLISTS => list_scrollbar(&app.playlist_list, theme),
+        BrowserLevels::PODCASTS => list_scrollbar(&app.podcast_list, theme),
+        BrowserLevels::FILES => list_scrollbar(&app.file_list, theme),
+        BrowserLevels::ARTISTS => list_scrollbar(&app.artist_list, theme),
+        BrowserLevels::ALBUMS => list_scrollbar(&app.album_list, theme),
+    }
+}
+
+// tui doesn't tell how far it scrolled the list that was drawn, which clicks need to know
+fn track_offsets(app: &mut App, level: BrowserLevels, height: usize) {
+    let (selected, len) = match level {
+        // the headers of the albums take rows as well
+        BrowserLevels::TRACKS if app.groups_version.is_some() => {
+            (app.selected_row(), app.groups.len())
+        }
+        BrowserLevels::TRACKS => (app.song_list.state.selected(), app.song_list.len()),
+        BrowserLevels::DUPLICATES => return app.duplicate_list.track_offset(height),
+        BrowserLevels::PLAYLISTS => return app.playlist_list.track_offset(height),
+        BrowserLevels::PODCASTS => return app.podcast_list.track_offset(height),
+        BrowserLevels::FILES => return app.file_list.track_offset(height),
+        BrowserLevels::ARTISTS => return app.artist_list.track_offset(height),
+        BrowserLevels::ALBUMS => return app.album_list.track_offset(height),
+    };
+    // less the header row
+    let height = height.saturating_sub(1);
+    app.table_offset = scrolled_offset(app.table_offset, selected, len, height);
+}
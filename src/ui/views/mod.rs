@@ -0,0 +1,157 @@
+pub mod browser;
+pub mod popups;
+pub mod queue;
+pub mod search;
+pub mod settings;
+
+// each tab is drawn and takes the requests that move through it in a module of its own, with the
+// popups over them taking theirs first. the library and playlists tabs share the browser, and
+// the app is left with what does the same on every tab
+
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    Frame,
+};
+
+use crate::library::song::Song;
+use crate::state::AppState;
+use crate::ui::widgets;
+use crate::ui::{App, SIDE_PANE_MIN_WIDTH};
+use crate::utils::constants::requests::UIRequests::{self, *};
+use crate::utils::constants::{BrowserLevels, LayoutWidgets, LibraryViews, SortModes};
+
+// where the list being browsed or searched goes, under a row for the search being typed or the
+// library's views, with the queue or lyrics beside it
+pub struct ListAreas {
+    pub header: Rect,
+    pub list: Rect,
+    pub side: Option<Rect>,
+    // beside the list or in a pane of its own, so the list's border shows which has the focus
+    pub queue_shown: bool,
+}
+
+pub fn list_areas(area: Rect, state: &AppState) -> ListAreas {
+    // lyrics need more room than the queue. narrow terminals leave the queue to its own tab
+    // and put the lyrics under the list, and so does a layout with a pane for the queue
+    let show_lyrics = state.ui.show_lyrics;
+    let queue_beside = !state.ui.layout.contains(LayoutWidgets::QUEUE);
+    let (list_area, side) = if area.width >= SIDE_PANE_MIN_WIDTH && (show_lyrics || queue_beside)
+    {
+        let side_pane_width = if show_lyrics { 40 } else { 20 };
+        let horiz_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .margin(0)
+            .constraints(
+                [
+                    Constraint::Percentage(100 - side_pane_width),
+                    Constraint::Percentage(side_pane_width),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+        (horiz_chunks[0], Some(horiz_chunks[1]))
+    } else if show_lyrics {
+        let stacked_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+            .split(area);
+        (stacked_chunks[0], Some(stacked_chunks[1]))
+    } else {
+        (area, None)
+    };
+    let song_list_vert_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(0)
+        .constraints(
+            [
+                Constraint::Length((list_area.height / 10).max(1)),
+                Constraint::Min(0),
+            ]
+            .as_ref(),
+        )
+        .split(list_area);
+    ListAreas {
+        header: song_list_vert_chunks[0],
+        list: song_list_vert_chunks[1],
+        side,
+        queue_shown: !queue_beside || (side.is_some() && !show_lyrics),
+    }
+}
+
+// the lyrics of the song that's playing or the queue, beside the list
+pub fn draw_side<B: Backend>(app: &mut App, frame: &mut Frame<B>, side: Rect, state: &AppState) {
+    if !state.ui.show_lyrics {
+        return queue::draw_pane(app, frame, side, state);
+    }
+    app.update_lyrics(state.player.curr_song.as_ref());
+    let progress = state.player.progress;
+    let lyrics = app.lyrics.as_ref().and_then(|(_, lyrics)| lyrics.as_ref());
+    widgets::lyrics_pane::render(frame, side, lyrics, progress, &app.theme);
+}
+
+// the list clicked on and the row of it, counted from the first one on screen. nothing when the
+// click was on a border or past the last row
+pub fn clicked_row(app: &App, column: u16, row: u16) -> Option<(BrowserLevels, usize)> {
+    let (level, area) = *app
+        .list_areas
+        .iter()
+        .find(|(_, area)| column >= area.left() && column < area.right())?;
+    // the songs are shown in a table with a header row under its border
+    let top = if level == BrowserLevels::TRACKS {
+        area.top() + 2
+    } else {
+        area.top() + 1
+    };
+    let inside = column > area.left()
+        && column < area.right().saturating_sub(1)
+        && row >= top
+        && row < area.bottom().saturating_sub(1);
+    inside.then(|| (level, (row - top) as usize))
+}
+
+// how many rows a page moves, as many as there were on screen, and whether it's down
+pub fn page(app: &App, request: &UIRequests) -> Option<(usize, bool)> {
+    let rows = app.page_rows.max(1);
+    match request {
+        PageUp => Some((rows, false)),
+        PageDown => Some((rows, true)),
+        HalfPageUp => Some(((rows / 2).max(1), false)),
+        HalfPageDown => Some(((rows / 2).max(1), true)),
+        _ => None,
+    }
+}
+
+// the cursor goes to the next row starting with the letter typed after the jump key. a note says
+// so when there's none
+pub fn jump_to_letter(
+    app: &mut App,
+    letter: Option<char>,
+    jump: impl FnOnce(&mut App, &dyn Fn(&str) -> bool) -> bool,
+) {
+    app.state.lock().unwrap().ui.jumping = false;
+    let letter = match letter {
+        Some(letter) => letter,
+        None => return,
+    };
+    let starts_with_letter = |text: &str| {
+        matches!(text.chars().next(),
+            Some(first) if first.to_lowercase().eq(letter.to_lowercase()))
+    };
+    if !jump(app, &starts_with_letter) {
+        let message = format!("Nothing starts with {}", letter);
+        app.state.lock().unwrap().ui.notify_error(message);
+    }
+}
+
+// songs are found by what they're sorted by, everything else by its name
+pub fn jump_key(song: &Song, by_artist: bool) -> &str {
+    match by_artist {
+        true => &song.track_artist,
+        false => &song.title,
+    }
+}
+
+pub fn by_artist(app: &App) -> bool {
+    app.view == LibraryViews::SONGS && app.sort == SortModes::ARTIST
+}
@@ -0,0 +1,163 @@
+use tui::{backend::Backend, Frame};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::state::{AppState, TAG_FIELDS};
+use crate::ui::widgets;
+use crate::ui::{show_history, App};
+use crate::utils::constants::requests::{AppRequests, UIRequests::{self, *}};
+use crate::utils::constants::{MenuActions, EQ_NUM_BANDS};
+
+// the popups over the tabs take the requests that move through them before the tab under them
+pub fn handle(
+    app: &mut App,
+    request: UIRequests,
+    main_tx: &UnboundedSender<AppRequests>,
+) -> Option<UIRequests> {
+    // the ones with something to move through
+    let open = {
+        let ui = &app.state.lock().unwrap().ui;
+        ui.command.is_some()
+            || ui.tag_editor.is_some()
+            || ui.menu_action.is_some()
+            || ui.bookmark.is_some()
+            || ui.show_equalizer
+    };
+    match request {
+        Up if open => up(app),
+        Down if open => down(app),
+        // nothing under them moves a page either
+        PageUp | PageDown | HalfPageUp | HalfPageDown if open => (),
+        Enter if enter(app, main_tx) => (),
+        GoBack if close(app) => (),
+        request => return Some(request),
+    }
+    None
+}
+
+fn up(app: &mut App) {
+    let mut guard = app.state.lock().unwrap();
+    if let Some(command) = &mut guard.ui.command {
+        return show_history(command, &app.command_history, true);
+    }
+    if let Some(action) = &mut guard.ui.menu_action {
+        *action = action.saturating_sub(1);
+        return;
+    }
+    if let Some(bookmark) = &mut guard.ui.bookmark {
+        *bookmark = bookmark.saturating_sub(1);
+        return;
+    }
+    if let Some(editor) = &mut guard.ui.tag_editor {
+        editor.field = editor.field.saturating_sub(1);
+        return;
+    }
+    if guard.ui.show_equalizer {
+        guard.ui.eq_band = guard.ui.eq_band.saturating_sub(1);
+    }
+}
+
+fn down(app: &mut App) {
+    let mut guard = app.state.lock().unwrap();
+    if let Some(command) = &mut guard.ui.command {
+        return show_history(command, &app.command_history, false);
+    }
+    if let Some(action) = &mut guard.ui.menu_action {
+        *action = (*action + 1).min(MenuActions::ALL.len() - 1);
+        return;
+    }
+    if let Some(bookmark) = guard.ui.bookmark {
+        let len = guard.player.curr_song.as_ref().map_or(0, |song| {
+            guard.library.bookmarks(song).len()
+        });
+        guard.ui.bookmark = Some((bookmark + 1).min(len.saturating_sub(1)));
+        return;
+    }
+    if let Some(editor) = &mut guard.ui.tag_editor {
+        editor.field = (editor.field + 1) % TAG_FIELDS.len();
+        return;
+    }
+    if guard.ui.show_equalizer {
+        guard.ui.eq_band = (guard.ui.eq_band + 1).min(EQ_NUM_BANDS - 1);
+    }
+}
+
+// run, save or answer what the popup is for. false if there's none to take it
+fn enter(app: &mut App, main_tx: &UnboundedSender<AppRequests>) -> bool {
+    if app.state.lock().unwrap().ui.command.is_some() {
+        app.run_command(main_tx);
+        return true;
+    }
+    if app.state.lock().unwrap().ui.tag_editor.is_some() {
+        app.save_tags();
+        return true;
+    }
+    let menu_action = app.state.lock().unwrap().ui.menu_action.take();
+    if let Some(action) = menu_action {
+        app.run_menu_action(MenuActions::ALL[action], main_tx);
+        return true;
+    }
+    let bookmark = app.state.lock().unwrap().ui.bookmark.take();
+    if let Some(bookmark) = bookmark {
+        app.seek_to_bookmark(bookmark, main_tx);
+        return true;
+    }
+    let mut guard = app.state.lock().unwrap();
+    if guard.ui.file_info.take().is_some() {
+        return true;
+    }
+    match guard.ui.confirm.take() {
+        Some((_, request)) => {
+            let _ = main_tx.send(AppRequests::LibraryRequests(request));
+            true
+        }
+        None => false,
+    }
+}
+
+// only the first of them that's open is closed
+fn close(app: &mut App) -> bool {
+    let ui = &mut app.state.lock().unwrap().ui;
+    if ui.error.take().is_some()
+        || ui.tag_editor.take().is_some()
+        || ui.menu_action.take().is_some()
+        || ui.bookmark.take().is_some()
+        || ui.file_info.take().is_some()
+        || ui.confirm.take().is_some()
+        || ui.command.take().is_some()
+    {
+        return true;
+    }
+    std::mem::replace(&mut ui.show_equalizer, false)
+}
+
+pub fn draw<B: Backend>(app: &App, frame: &mut Frame<B>, state: &AppState) {
+    if state.ui.show_equalizer {
+        widgets::equalizer_popup::render(frame, state, &app.theme);
+    }
+    if let Some(editor) = &state.ui.tag_editor {
+        widgets::tag_editor_popup::render(frame, editor, &app.theme);
+    }
+    if let Some(song) = &state.ui.file_info {
+        widgets::file_info_popup::render(frame, song, &state.metadata, &app.theme);
+    }
+    if let Some(action) = state.ui.menu_action {
+        let title = match app.marked_songs().as_slice() {
+            [song] => song.title.to_owned(),
+            songs => format!("{} songs", songs.len()),
+        };
+        widgets::context_menu::render(frame, &title, action, &app.theme);
+    }
+    if let (Some(bookmark), Some(song)) = (state.ui.bookmark, &state.player.curr_song) {
+        let bookmarks = state.library.bookmarks(song);
+        widgets::bookmarks_popup::render(frame, &song.title, bookmarks, bookmark, &app.theme);
+    }
+    if let Some(command) = &state.ui.command {
+        widgets::command_line::render(frame, command, &app.theme);
+    }
+    if let Some((question, _)) = &state.ui.confirm {
+        widgets::confirm_popup::render(frame, question, &app.theme);
+    }
+    if let Some(message) = &state.ui.error {
+        widgets::error_popup::render(frame, message, &app.theme);
+    }
+}
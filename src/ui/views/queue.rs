@@ -0,0 +1,198 @@
+use tui::{backend::Backend, layout::Rect, Frame};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::library::song::Song;
+use crate::queue::undo::Inverses;
+use crate::state::AppState;
+use crate::ui::views;
+use crate::ui::widgets::{self, scrollbar::Scrollbar, stateful_list::scrolled_offset};
+use crate::ui::{rating_suffix, App};
+use crate::utils::constants::requests::{AppRequests, PlayerRequests, UIRequests::{self, *}};
+use crate::utils::constants::{AppViews, FocusedPanes};
+
+// the queue tab, and the queue beside the open tab while it has the focus
+pub fn handle(
+    app: &mut App,
+    request: UIRequests,
+    main_tx: &UnboundedSender<AppRequests>,
+) -> Option<UIRequests> {
+    if let Some((rows, down)) = views::page(app, &request) {
+        page(app, rows, down);
+        return None;
+    }
+    match request {
+        Up => move_cursor(app, false),
+        Down => move_cursor(app, true),
+        Top => select_edge(app, false),
+        Bottom => select_edge(app, true),
+        JumpToLetter(letter) => jump_to_letter(app, letter),
+        Enter | Play => play_from_queue(app, main_tx),
+        MoveInQueue(down) => move_in_queue(app, down),
+        RemoveFromQueue => remove_from_queue(app),
+        ShowFileInfo => {
+            let song = {
+                let state = app.state.lock().unwrap();
+                app.queue_table
+                    .selected()
+                    .and_then(|idx| state.queue.iter().nth(idx).cloned())
+            };
+            app.show_file_info(song, main_tx);
+        }
+        request => return Some(request),
+    }
+    None
+}
+
+fn move_cursor(app: &mut App, down: bool) {
+    let len = app.state.lock().unwrap().queue.len();
+    let idx = match app.queue_table.selected() {
+        Some(idx) if down => (idx + 1).min(len.saturating_sub(1)),
+        Some(idx) => idx.saturating_sub(1),
+        None => 0,
+    };
+    app.queue_table.select(Some(idx).filter(|_| len > 0));
+}
+
+fn select_edge(app: &mut App, last: bool) {
+    let len = app.state.lock().unwrap().queue.len();
+    let idx = if last { len.saturating_sub(1) } else { 0 };
+    app.queue_table.select(Some(idx).filter(|_| len > 0));
+}
+
+fn page(app: &mut App, rows: usize, down: bool) {
+    let len = app.state.lock().unwrap().queue.len();
+    let selected = app.queue_table.selected().unwrap_or(0);
+    let idx = if down {
+        (selected + rows).min(len.saturating_sub(1))
+    } else {
+        selected.saturating_sub(rows)
+    };
+    app.queue_table.select(Some(idx).filter(|_| len > 0));
+}
+
+// the queue is gone through by title, starting after the cursor and going around at the end
+fn jump_to_letter(app: &mut App, letter: Option<char>) {
+    views::jump_to_letter(app, letter, |app, starts_with_letter| {
+        let songs: Vec<Song> = app.state.lock().unwrap().queue.iter().cloned().collect();
+        let start = app.queue_table.selected().map_or(0, |idx| idx + 1);
+        let found = (0..songs.len())
+            .map(|i| (start + i) % songs.len())
+            .find(|idx| starts_with_letter(&songs[*idx].title));
+        if found.is_some() {
+            app.queue_table.select(found);
+        }
+        found.is_some()
+    })
+}
+
+// the player takes the next song from the shared queue, so it plays them in the new order
+fn move_in_queue(app: &mut App, down: bool) {
+    let idx = match app.queue_table.selected() {
+        Some(idx) => idx,
+        None => return,
+    };
+    let to = if down {
+        idx + 1
+    } else if idx > 0 {
+        idx - 1
+    } else {
+        return;
+    };
+    let mut state = app.state.lock().unwrap();
+    if to < state.queue.len() {
+        state.queue.move_song(idx, to);
+        app.queue_table.select(Some(to));
+    }
+}
+
+fn remove_from_queue(app: &mut App) {
+    if let Some(idx) = app.queue_table.selected() {
+        let mut state = app.state.lock().unwrap();
+        let inverse = Inverses::removed(&state.queue, idx);
+        if let (Some(song), Some(inverse)) = (state.queue.remove(idx), inverse) {
+            state.undo.record(format!("removing {}", song.title), inverse);
+        }
+    }
+}
+
+// play the song under the cursor right away, taking it out of the queue
+fn play_from_queue(app: &mut App, main_tx: &UnboundedSender<AppRequests>) {
+    let idx = match app.queue_table.selected() {
+        Some(idx) => idx,
+        None => return,
+    };
+    let mut state = app.state.lock().unwrap();
+    if let Some(song) = state.queue.remove(idx) {
+        state.ui.selected_song = Some(song);
+        let _ = main_tx.send(AppRequests::PlayerRequests(PlayerRequests::Start));
+    }
+}
+
+pub fn draw<B: Backend>(app: &mut App, frame: &mut Frame<B>, area: Rect, state: &AppState) {
+    // less the borders and the header row
+    let height = area.height.saturating_sub(3) as usize;
+    app.page_rows = height;
+    // only the songs on screen are copied out of the queue
+    let (songs, len): (Vec<Song>, _) = {
+        let len = state.queue.len();
+        // the queue changes as songs are played, so the cursor is kept on it here
+        let selected = match len {
+            0 => None,
+            len => Some(app.queue_table.selected().unwrap_or(0).min(len - 1)),
+        };
+        app.queue_table.select(selected);
+        app.queue_offset = scrolled_offset(app.queue_offset, selected, len, height);
+        let songs = state.queue.iter().skip(app.queue_offset).take(height).cloned();
+        (songs.collect(), len)
+    };
+    let selected = app.queue_table.selected();
+    let table = widgets::song_table::SongTable {
+        songs: &songs,
+        groups: None,
+        offset: 0,
+        selected: selected.map(|idx| idx.saturating_sub(app.queue_offset)),
+        title: format!(
+            "Queue ({} songs, Enter: play now, J/K: move, d: remove, c: clear)",
+            len
+        ),
+        columns: &state.ui.columns,
+        format: &state.ui.formats.song,
+        highlight: None,
+        selection: None,
+        suffix: rating_suffix(&state.ui.columns),
+        focused: false,
+        cache: None,
+    };
+    widgets::song_table::render(frame, area, table, &app.theme);
+    let scrollbar = Scrollbar {
+        offset: app.queue_offset,
+        len,
+        selected,
+        header_rows: 1,
+        theme: &app.theme,
+    };
+    frame.render_widget(scrollbar, area);
+}
+
+// the queue beside the open tab, which can take the focus from it
+pub fn draw_pane<B: Backend>(app: &mut App, frame: &mut Frame<B>, area: Rect, state: &AppState) {
+    // the queue tab already shows the queue
+    if app.tab == AppViews::QUEUE {
+        return widgets::queue_pane::render(frame, area, state, None, &app.theme);
+    }
+    let cursor = if app.focus == FocusedPanes::QUEUE {
+        let height = area.height.saturating_sub(2) as usize;
+        app.page_rows = height;
+        let len = state.queue.len();
+        let selected = match len {
+            0 => None,
+            len => Some(app.queue_table.selected().unwrap_or(0).min(len - 1)),
+        };
+        app.queue_table.select(selected);
+        app.queue_offset = scrolled_offset(app.queue_offset, selected, len, height);
+        Some((selected, app.queue_offset))
+    } else {
+        None
+    };
+    widgets::queue_pane::render(frame, area, state, cursor, &app.theme);
+}
@@ -0,0 +1,210 @@
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Rect},
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Clear, Paragraph, Wrap},
+    Frame,
+};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::library::search;
+use crate::state::AppState;
+use crate::ui::views::{self, ListAreas};
+use crate::ui::widgets::{self, stateful_list::{scrolled_offset, StatefulList}};
+use crate::ui::{
+    rating_suffix, remember_search, select_edge, select_next, select_previous, select_row,
+    selected, table_scrollbar, App,
+};
+use crate::utils::constants::requests::{AppRequests, PlayerRequests, UIRequests::{self, *}};
+use crate::utils::constants::{BrowserLevels, FocusedPanes, LibraryViews};
+
+// the search being typed, over the whole library whichever tab it was started from
+pub fn handle(
+    app: &mut App,
+    request: UIRequests,
+    main_tx: &UnboundedSender<AppRequests>,
+) -> Option<UIRequests> {
+    if let Some((rows, down)) = views::page(app, &request) {
+        app.search_results.page(rows, down);
+        return None;
+    }
+    match request {
+        Up => up(app),
+        Down => down(app),
+        Top => select_edge(&mut app.search_results, false),
+        Bottom => select_edge(&mut app.search_results, true),
+        JumpToLetter(letter) => {
+            views::jump_to_letter(app, letter, |app, starts_with_letter| {
+                let by_artist = views::by_artist(app);
+                app.search_results
+                    .jump_to(|song| starts_with_letter(views::jump_key(song, by_artist)))
+            })
+        }
+        Enter | Play => play_result(app, main_tx),
+        Click(column, row) | DoubleClick(column, row) => {
+            let clicked = match views::clicked_row(app, column, row) {
+                Some((_, row)) => select_row(&mut app.search_results, app.table_offset + row),
+                None => return Some(request),
+            };
+            if clicked && matches!(request, DoubleClick(..)) {
+                play_result(app, main_tx);
+            }
+        }
+        GoBack => close(app, main_tx),
+        SearchInput(_) | SearchBackspace | SearchDeleteWord | SearchClear => {
+            {
+                let search = &mut app.state.lock().unwrap().search;
+                match request {
+                    SearchInput(text) => search.insert(&text),
+                    SearchBackspace => search.backspace(),
+                    SearchDeleteWord => search.delete_word(),
+                    _ => search.clear(),
+                }
+            }
+            update(app);
+        }
+        SearchCursor(left) => app.state.lock().unwrap().search.move_cursor(left),
+        ShowFileInfo => {
+            let song = selected(&app.search_results).cloned();
+            app.show_file_info(song, main_tx);
+        }
+        request => return Some(request),
+    }
+    None
+}
+
+// past the first result is the search made before
+fn up(app: &mut App) {
+    let at_top = app.search_results.state.selected().is_none_or(|idx| idx == 0);
+    if at_top && app.state.lock().unwrap().search.step_history(true) {
+        return update(app);
+    }
+    select_previous(&mut app.search_results);
+}
+
+// until the search being typed is back
+fn down(app: &mut App) {
+    let stepped = {
+        let search = &mut app.state.lock().unwrap().search;
+        search.history_idx.is_some() && search.step_history(false)
+    };
+    match stepped {
+        true => update(app),
+        false => select_next(&mut app.search_results),
+    }
+}
+
+// search the whole library again with the search term as it is now
+pub fn update(app: &mut App) {
+    let term = app.state.lock().unwrap().search.term.to_owned();
+    let songs = search::search(&term, &app.all_songs)
+        .into_iter()
+        .map(|(song, _)| song)
+        .collect();
+    app.search_results = StatefulList::with_items(songs);
+    select_next(&mut app.search_results);
+}
+
+// play the selected match, which is the best one unless another was picked, from the songs
+// view so that playback goes on from there
+fn play_result(app: &mut App, main_tx: &UnboundedSender<AppRequests>) {
+    let song = match selected(&app.search_results) {
+        Some(song) => song.clone(),
+        None => return,
+    };
+    {
+        // the search stays highlighted so that n and N can go through its other matches
+        let mut state = app.state.lock().unwrap();
+        remember_search(&mut state, main_tx);
+        state.search.searching = false;
+        let term = std::mem::take(&mut state.search.term);
+        state.search.highlight = Some(term).filter(|term| !term.trim().is_empty());
+    }
+    app.show_view(LibraryViews::SONGS, main_tx);
+    app.select_in_song_list(&song);
+    app.state.lock().unwrap().ui.selected_song = Some(song);
+    let _ = main_tx.send(AppRequests::PlayerRequests(PlayerRequests::Start));
+}
+
+fn close(app: &mut App, main_tx: &UnboundedSender<AppRequests>) {
+    {
+        let mut guard = app.state.lock().unwrap();
+        remember_search(&mut guard, main_tx);
+        guard.search.searching = false;
+        guard.search.term.clear();
+    }
+    // the full list comes back with the cursor on the match that was picked, if it's one of the
+    // songs being browsed
+    if let Some(song) = selected(&app.search_results).cloned() {
+        app.select_in_song_list(&song);
+    }
+}
+
+// the search being typed above the songs matching it, best first
+pub fn draw<B: Backend>(app: &mut App, frame: &mut Frame<B>, area: Rect, state: &AppState) {
+    let ListAreas {
+        header,
+        list,
+        side,
+        queue_shown,
+    } = views::list_areas(area, state);
+    // until something is typed, how to search a single field instead
+    let status = match state.search.term.is_empty() {
+        true => search::SCOPES
+            .iter()
+            .map(|(prefix, field)| format!("{}{}", prefix, field))
+            .collect::<Vec<String>>()
+            .join(", "),
+        false => format!("{} songs", app.search_results.len()),
+    };
+    // the character under the cursor is reversed, past the end it's an underscore
+    let term = &state.search.term;
+    let (before, after) = term.split_at(state.search.cursor());
+    let mut chars = after.chars();
+    let under = chars.next().map_or("_".to_string(), |ch| ch.to_string());
+    let prompt = Paragraph::new(Spans::from(vec![
+        Span::raw(format!("Search: {}", before)),
+        Span::styled(under, Style::default().add_modifier(Modifier::REVERSED)),
+        Span::raw(format!("{} ({})", chars.as_str(), status)),
+    ]))
+    .style(app.theme.text)
+    .alignment(Alignment::Left)
+    .wrap(Wrap { trim: false });
+    frame.render_widget(Clear, header);
+    frame.render_widget(prompt, header);
+
+    // the characters matching what's typed stand out
+    let highlight = Some(term.to_owned()).filter(|term| !term.trim().is_empty());
+    let main_focused = app.focus == FocusedPanes::MAIN;
+    // less the borders and the header row
+    let height = list.height.saturating_sub(3) as usize;
+    if main_focused {
+        app.page_rows = height;
+    }
+    // only what's on screen is drawn, so where the table is scrolled to is needed first
+    let selected = app.search_results.state.selected();
+    let len = app.search_results.len();
+    app.table_offset = scrolled_offset(app.table_offset, selected, len, height);
+    let version = app.search_results.version();
+    let song_table = widgets::song_table::SongTable {
+        songs: &app.search_results.items,
+        groups: None,
+        offset: app.table_offset,
+        selected,
+        title: "Search results (Enter: play)".to_string(),
+        columns: &state.ui.columns,
+        format: &state.ui.formats.song,
+        highlight: highlight.as_deref(),
+        selection: None,
+        suffix: rating_suffix(&state.ui.columns),
+        focused: queue_shown && main_focused,
+        cache: Some((&mut app.song_rows, version)),
+    };
+    widgets::song_table::render(frame, list, song_table, &app.theme);
+    app.list_areas.push((BrowserLevels::TRACKS, list));
+    frame.render_widget(table_scrollbar(app.table_offset, &app.search_results, &app.theme), list);
+    if let Some(side) = side {
+        views::draw_side(app, frame, side, state);
+    }
+}
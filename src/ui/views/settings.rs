@@ -0,0 +1,14 @@
+use tui::{backend::Backend, layout::Rect, Frame};
+
+use crate::state::AppState;
+use crate::ui::{widgets, App};
+use crate::utils::constants::requests::UIRequests;
+
+// the settings are changed in the config file, so there's nothing on the tab to move through
+pub fn handle(request: UIRequests) -> Option<UIRequests> {
+    Some(request)
+}
+
+pub fn draw<B: Backend>(app: &App, frame: &mut Frame<B>, area: Rect, state: &AppState) {
+    widgets::settings_view::render(frame, area, state, &app.theme);
+}
@@ -0,0 +1,84 @@
+use image::{imageops::FilterType, DynamicImage, RgbImage};
+use tui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    widgets::Widget,
+};
+
+use crate::library::{song::Song, tag};
+use crate::metadata;
+
+// the cover of the song that's playing, decoded once and scaled whenever the area changes
+pub struct CoverArt {
+    pub path: String,
+    image: Option<DynamicImage>,
+    scaled: Option<(u32, u32, RgbImage)>,
+}
+
+impl CoverArt {
+    // the art in the file or next to it, or else what was fetched online for its album
+    pub fn load(song: &Song) -> CoverArt {
+        let image = tag::read_cover_art(&song.path)
+            .or_else(|| metadata::cached_cover(song))
+            .and_then(|data| image::load_from_memory(&data).ok());
+        CoverArt {
+            path: song.path.to_owned(),
+            image,
+            scaled: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.image.is_none()
+    }
+
+    // the art scaled to fit inside `width` x `height` pixels, keeping its aspect ratio
+    pub fn scaled(&mut self, width: u32, height: u32) -> Option<&RgbImage> {
+        let image = self.image.as_ref()?;
+        let outdated = match &self.scaled {
+            Some((w, h, _)) => (*w, *h) != (width, height),
+            None => true,
+        };
+        if outdated {
+            let scaled = image.resize(width, height, FilterType::Triangle).to_rgb8();
+            self.scaled = Some((width, height, scaled));
+        }
+        self.scaled.as_ref().map(|(_, _, scaled)| scaled)
+    }
+}
+
+// draws an image with ▀ characters, the foreground being the top pixel and the background the
+// bottom one
+pub struct HalfBlocks<'a> {
+    pub image: &'a RgbImage,
+}
+
+impl<'a> Widget for HalfBlocks<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let (width, height) = self.image.dimensions();
+        // center the image in the area
+        let x_offset = area.width.saturating_sub(width as u16) / 2;
+        let y_offset = area.height.saturating_sub((height as u16).div_ceil(2)) / 2;
+        for y in (0..height).step_by(2) {
+            for x in 0..width {
+                let top = self.image.get_pixel(x, y);
+                let bottom = if y + 1 < height {
+                    self.image.get_pixel(x, y + 1)
+                } else {
+                    top
+                };
+                let cell_x = area.x + x_offset + x as u16;
+                let cell_y = area.y + y_offset + (y / 2) as u16;
+                if cell_x >= area.right() || cell_y >= area.bottom() {
+                    continue;
+                }
+                buf.get_mut(cell_x, cell_y).set_symbol("▀").set_style(
+                    Style::default()
+                        .fg(Color::Rgb(top[0], top[1], top[2]))
+                        .bg(Color::Rgb(bottom[0], bottom[1], bottom[2])),
+                );
+            }
+        }
+    }
+}
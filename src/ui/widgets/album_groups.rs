@@ -0,0 +1,117 @@
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use crate::library::song::Song;
+use crate::library::AlbumKey;
+
+// a row of the song list while it's grouped by album. each group starts with a header, which is
+// all that's left of it while it's collapsed
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TableRows {
+    HEADER {
+        first: usize, // the group's songs, as positions in the list
+        len: usize,
+        collapsed: bool,
+    },
+    SONG(usize),
+}
+
+impl TableRows {
+    // the song the cursor is on while it's on this row. the header of an open group can't be
+    // selected, a collapsed one stands in for its first song
+    pub fn song(&self) -> Option<usize> {
+        match *self {
+            TableRows::SONG(idx) => Some(idx),
+            TableRows::HEADER {
+                first,
+                collapsed: true,
+                ..
+            } => Some(first),
+            TableRows::HEADER { .. } => None,
+        }
+    }
+
+    pub fn songs(&self) -> Range<usize> {
+        match *self {
+            TableRows::SONG(idx) => idx..idx + 1,
+            TableRows::HEADER { first, len, .. } => first..first + len,
+        }
+    }
+}
+
+pub fn album_of(song: &Song) -> AlbumKey {
+    AlbumKey {
+        artist: song.album_artist.to_owned(),
+        title: song.album_title.to_owned(),
+    }
+}
+
+// songs next to each other from the same album make a group, so an album sorted apart is in
+// more than one
+pub fn table_rows(songs: &[Song], collapsed: &BTreeSet<AlbumKey>) -> Vec<TableRows> {
+    let mut rows = Vec::new();
+    let mut first = 0;
+    while first < songs.len() {
+        let len = songs[first..]
+            .iter()
+            .take_while(|song| same_album(song, &songs[first]))
+            .count();
+        let collapsed = collapsed.contains(&album_of(&songs[first]));
+        rows.push(TableRows::HEADER {
+            first,
+            len,
+            collapsed,
+        });
+        if !collapsed {
+            rows.extend((first..first + len).map(TableRows::SONG));
+        }
+        first += len;
+    }
+    rows
+}
+
+// the row the song is on, or the header of its group while that's collapsed
+pub fn row_of(rows: &[TableRows], song: usize) -> Option<usize> {
+    rows.iter().position(|row| match row {
+        TableRows::SONG(idx) => *idx == song,
+        TableRows::HEADER {
+            collapsed: true, ..
+        } => row.songs().contains(&song),
+        TableRows::HEADER { .. } => false,
+    })
+}
+
+// the song `steps` rows down or up from the one selected, counting only rows that can be
+// selected and stopping at either end
+pub fn step(
+    rows: &[TableRows],
+    selected: Option<usize>,
+    steps: usize,
+    down: bool,
+) -> Option<usize> {
+    let selectable = rows.iter().filter(|row| row.song().is_some()).count();
+    let at = selected.and_then(|song| row_of(rows, song)).map(|row| {
+        rows[..row]
+            .iter()
+            .filter(|row| row.song().is_some())
+            .count()
+    });
+    let target = match (at, down) {
+        (None, _) => 0,
+        (Some(at), true) => (at + steps).min(selectable.saturating_sub(1)),
+        (Some(at), false) => at.saturating_sub(steps),
+    };
+    rows.iter().filter_map(TableRows::song).nth(target)
+}
+
+pub fn edge(rows: &[TableRows], last: bool) -> Option<usize> {
+    let mut songs = rows.iter().filter_map(TableRows::song);
+    match last {
+        true => songs.next_back(),
+        false => songs.next(),
+    }
+}
+
+fn same_album(song: &Song, other: &Song) -> bool {
+    song.album_title == other.album_title && song.album_artist == other.album_artist
+}
@@ -0,0 +1,55 @@
+use tui::{
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+use unicode_width::UnicodeWidthStr;
+
+use super::curr_playing_bar::readable_time;
+use crate::library::bookmark::Bookmark;
+use crate::ui::helper;
+use crate::ui::theme::Theme;
+
+// the bookmarks of the song playing, with the cursor on the one enter seeks to
+pub fn render(
+    frame: &mut Frame<impl tui::backend::Backend>,
+    title: &str,
+    bookmarks: &[Bookmark],
+    selected: usize,
+    theme: &Theme,
+) {
+    let size = frame.size();
+    let area = helper::centered_rect(50, 0, 40, bookmarks.len() as u16 + 2, size);
+    // the song's title gives way to the keys
+    let keys = " (Enter: seek, Esc: close)";
+    let title = helper::truncate(title, (area.width as usize).saturating_sub(2 + keys.width()));
+    let block = Block::default()
+        .title(format!("{}{}", title, keys))
+        .borders(Borders::ALL);
+    let width = (area.width as usize).saturating_sub(2 + theme.highlight_symbol.width());
+
+    let items: Vec<ListItem> = bookmarks
+        .iter()
+        .map(|bookmark| {
+            ListItem::new(Spans::from(helper::truncate_spans(
+                vec![
+                    Span::styled(readable_time(bookmark.position.as_secs()), theme.dimmed),
+                    Span::raw(format!(" {}", bookmark.name)),
+                ],
+                width,
+            )))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(block)
+        .style(theme.text)
+        .highlight_style(theme.highlight)
+        .highlight_symbol(&theme.highlight_symbol);
+
+    let mut list_state = ListState::default();
+    list_state.select(Some(selected));
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut list_state);
+}
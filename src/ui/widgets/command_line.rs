@@ -0,0 +1,34 @@
+use tui::{
+    layout::Rect,
+    text::{Span, Spans},
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+
+use crate::state::CommandLine;
+use crate::ui::theme::Theme;
+
+// the ":" prompt on the last line inside the border, with the tab completions above it
+pub fn render(frame: &mut Frame<impl tui::backend::Backend>, command: &CommandLine, theme: &Theme) {
+    let size = frame.size();
+    if size.height < 4 || size.width < 3 {
+        return;
+    }
+    let prompt_area = Rect::new(size.x + 1, size.bottom() - 2, size.width - 2, 1);
+    let prompt = Paragraph::new(Spans::from(vec![
+        Span::styled(":", theme.accent),
+        Span::styled(format!("{}_", command.input), theme.text),
+    ]));
+    frame.render_widget(Clear, prompt_area);
+    frame.render_widget(prompt, prompt_area);
+
+    if !command.completions.is_empty() {
+        let completions_area = Rect {
+            y: prompt_area.y - 1,
+            ..prompt_area
+        };
+        let completions = Paragraph::new(command.completions.join("  ")).style(theme.dimmed);
+        frame.render_widget(Clear, completions_area);
+        frame.render_widget(completions, completions_area);
+    }
+}
@@ -0,0 +1,25 @@
+use tui::{
+    layout::Alignment,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::ui::helper;
+use crate::ui::theme::Theme;
+
+// a question about something that can't be taken back
+pub fn render(frame: &mut Frame<impl tui::backend::Backend>, question: &str, theme: &Theme) {
+    let size = frame.size();
+    let block = Block::default()
+        .title("Are you sure? (Enter: yes, Esc: no)")
+        .borders(Borders::ALL)
+        .border_style(theme.accent);
+    let area = helper::centered_rect(50, 20, 30, 5, size);
+    let paragraph = Paragraph::new(question.to_string())
+        .style(theme.text)
+        .alignment(Alignment::Left)
+        .wrap(Wrap { trim: true });
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, block.inner(area));
+    frame.render_widget(block, area);
+}
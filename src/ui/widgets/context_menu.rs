@@ -0,0 +1,43 @@
+use tui::{
+    text::Spans,
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::ui::helper;
+use crate::ui::theme::Theme;
+use crate::utils::constants::MenuActions;
+
+// what can be done with the songs named in the title, with the cursor on one of them
+pub fn render(
+    frame: &mut Frame<impl tui::backend::Backend>,
+    title: &str,
+    action: usize,
+    theme: &Theme,
+) {
+    let size = frame.size();
+    let area = helper::centered_rect(40, 30, 30, MenuActions::ALL.len() as u16 + 2, size);
+    let keys = " (Enter: do it, Esc: close)";
+    let title = helper::truncate(title, (area.width as usize).saturating_sub(2 + keys.width()));
+    let block = Block::default()
+        .title(format!("{}{}", title, keys))
+        .borders(Borders::ALL);
+
+    let items: Vec<ListItem> = MenuActions::ALL
+        .iter()
+        .map(|action| ListItem::new(Spans::from(action.title())))
+        .collect();
+
+    let list = List::new(items)
+        .block(block)
+        .style(theme.text)
+        .highlight_style(theme.highlight)
+        .highlight_symbol(&theme.highlight_symbol);
+
+    let mut list_state = ListState::default();
+    list_state.select(Some(action));
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut list_state);
+}
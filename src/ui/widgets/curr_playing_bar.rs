@@ -1,27 +1,35 @@
-use std::{
-    fmt::format,
-    sync::{Arc, Mutex},
-};
-
 use tui::{
     layout::{Alignment::Left, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
     text::{Span, Spans},
     widgets::{Block, Borders, Clear, Gauge, Paragraph},
     Frame,
 };
 
-use crate::{queue::SongQueue, state::AppState, utils::constants::PlayerStates};
-
-pub fn render(frame: &mut Frame<impl tui::backend::Backend>, area: Rect, state: &AppState) {
-    let song_title = match &state.player.curr_song {
-        None => Span::raw(""),
-        Some(song) => Span::raw(song.title.to_owned()),
-    };
+use crate::{
+    state::AppState,
+    ui::{helper, theme::Theme},
+    utils::constants::{PlayerStates, RepeatModes},
+};
 
-    let song_artist = match &state.player.curr_song {
-        None => Span::raw(""),
-        Some(song) => Span::raw(song.track_artist.to_owned()),
+// returns where the progress bar is, so that clicks on it can seek
+pub fn render(
+    frame: &mut Frame<impl tui::backend::Backend>,
+    area: Rect,
+    state: &AppState,
+    theme: &Theme,
+) -> Rect {
+    // a stream shows what's on above the stream's url
+    let song_lines: Vec<String> = match (&state.player.curr_song, &state.player.stream_title) {
+        (None, _) => Vec::new(),
+        (Some(song), Some(stream_title)) => vec![stream_title.to_owned(), song.title.to_owned()],
+        (Some(song), None) => state
+            .ui
+            .formats
+            .now_playing
+            .render(song)
+            .lines()
+            .map(str::to_owned)
+            .collect(),
     };
 
     let curr_time_secs = state.player.progress.as_secs();
@@ -35,26 +43,38 @@ pub fn render(frame: &mut Frame<impl tui::backend::Backend>, area: Rect, state:
 
     let total_time_span = Span::raw(readable_time(total_time_secs));
 
+    let time_label = format!(
+        "{} / {}",
+        readable_time(curr_time_secs),
+        readable_time(total_time_secs)
+    );
+
+    // progress can run slightly past the tagged length, and the gauge panics on ratios above 1
     let percentage_played: f64 = match total_time_secs {
         0 => 0.,
-        _ => (curr_time_secs as f64) / (total_time_secs as f64),
+        _ => ((curr_time_secs as f64) / (total_time_secs as f64)).min(1.),
     };
 
     let time_gauge = Gauge::default()
         .block(Block::default().borders(Borders::NONE))
-        .gauge_style(
-            Style::default()
-                .fg(Color::White)
-                .add_modifier(Modifier::ITALIC),
-        )
-        .ratio(percentage_played);
+        .gauge_style(theme.gauge)
+        .ratio(percentage_played)
+        .label(time_label);
 
     let play_status = match &state.player.curr_state {
+        PlayerStates::BUFFERING => Span::raw("Buffering"),
         PlayerStates::PLAYING => Span::raw("Playing"),
         PlayerStates::STOPPED => Span::raw("Stopped"),
         PlayerStates::PAUSED => Span::raw("Paused"),
     };
 
+    let shuffle_status = indicator("shuffle", state.player.shuffle, theme);
+    let repeat_status = match &state.player.repeat {
+        RepeatModes::OFF => indicator("repeat", false, theme),
+        RepeatModes::ONE => indicator("repeat one", true, theme),
+        RepeatModes::ALL => indicator("repeat all", true, theme),
+    };
+
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
@@ -76,7 +96,51 @@ pub fn render(frame: &mut Frame<impl tui::backend::Backend>, area: Rect, state:
         Spans::from(vec![curr_time_span, Span::raw("/"), total_time_span]),
         Spans::from(play_status),
     ];
-    let song_status_text = vec![Spans::from(song_title), Spans::from(song_artist)];
+    // titles that don't fit scroll across rather than running into the modes beside them. they
+    // move along with the song, and stand still while it's paused
+    let width = player_info_chunks[1].width as usize;
+    let song_status_text: Vec<Spans> = song_lines
+        .iter()
+        .map(|line| {
+            let line = helper::marquee(
+                line,
+                width,
+                state.player.progress,
+                state.ui.marquee_speed,
+                state.ui.marquee_pause,
+            );
+            Spans::from(line.into_owned())
+        })
+        .collect();
+    let mut modes_text = vec![Spans::from(shuffle_status), Spans::from(repeat_status)];
+    // output adjustments are only shown while they change something
+    let mut adjustments = Vec::new();
+    if state.player.speed != 1. {
+        adjustments.push(format!("{:.1}x", state.player.speed));
+    }
+    if state.player.mono {
+        adjustments.push("mono".to_string());
+    }
+    if state.player.balance < 0. {
+        adjustments.push(format!("L{:.0}%", -state.player.balance * 100.));
+    } else if state.player.balance > 0. {
+        adjustments.push(format!("R{:.0}%", state.player.balance * 100.));
+    }
+    match (state.player.loop_start, state.player.loop_end) {
+        (Some(start), Some(end)) => adjustments.push(format!(
+            "A-B {}-{}",
+            readable_time(start.as_secs()),
+            readable_time(end.as_secs())
+        )),
+        (Some(start), None) => adjustments.push(format!("A {}-", readable_time(start.as_secs()))),
+        _ => (),
+    }
+    if state.player.stop_after_current {
+        adjustments.push("stop after this song".to_string());
+    }
+    if !adjustments.is_empty() {
+        modes_text.push(Spans::from(indicator(&adjustments.join(" "), true, theme)));
+    }
 
     frame.render_widget(Clear, area);
     frame.render_widget(
@@ -87,10 +151,32 @@ pub fn render(frame: &mut Frame<impl tui::backend::Backend>, area: Rect, state:
         Paragraph::new(song_status_text).alignment(Left),
         player_info_chunks[1],
     );
-    frame.render_widget(time_gauge, chunks[1]);
+    frame.render_widget(
+        Paragraph::new(modes_text).alignment(Left),
+        player_info_chunks[2],
+    );
+    // the song's waveform once it's been worked out, so that seeking can aim for a part of it
+    let waveform = state
+        .player
+        .curr_song
+        .as_ref()
+        .and_then(|song| state.player.waveforms.get(song));
+    match waveform {
+        Some(peaks) => {
+            super::waveform::render(frame, chunks[1], &peaks, percentage_played, theme)
+        }
+        None => frame.render_widget(time_gauge, chunks[1]),
+    }
+    chunks[1]
+}
+
+// dim a mode's label when it is off
+fn indicator(label: &str, enabled: bool, theme: &Theme) -> Span<'static> {
+    let style = if enabled { theme.accent } else { theme.dimmed };
+    Span::styled(label.to_string(), style)
 }
 
-fn readable_time(secs: u64) -> String {
+pub fn readable_time(secs: u64) -> String {
     let mins = secs / 60;
     let secs = secs % 60;
 
@@ -0,0 +1,69 @@
+use tui::{
+    text::Spans,
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+use crate::player::equalizer::{BAND_FREQUENCIES, MAX_GAIN_DB};
+use crate::state::AppState;
+use crate::ui::helper;
+use crate::ui::theme::Theme;
+use crate::utils::constants::EQ_NUM_BANDS;
+
+// width of each half of a band's bar, one cell per dB
+const HALF_BAR_WIDTH: usize = MAX_GAIN_DB as usize;
+
+pub fn render(frame: &mut Frame<impl tui::backend::Backend>, state: &AppState, theme: &Theme) {
+    let size = frame.size();
+    let title = format!(
+        "Equalizer: {} (Tab: preset, Esc: close)",
+        state.player.eq_preset.name()
+    );
+    let block = Block::default().title(title).borders(Borders::ALL);
+    let area = helper::centered_rect(60, 50, 50, EQ_NUM_BANDS as u16 + 2, size);
+
+    let items: Vec<ListItem> = BAND_FREQUENCIES
+        .iter()
+        .zip(state.player.eq_bands.iter())
+        .map(|(frequency, gain)| {
+            ListItem::new(Spans::from(format!(
+                "{: >6} {} {:+.0} dB",
+                band_label(*frequency),
+                gain_bar(*gain),
+                gain
+            )))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(block)
+        .style(theme.text)
+        .highlight_style(theme.highlight)
+        .highlight_symbol(&theme.highlight_symbol);
+
+    let mut list_state = ListState::default();
+    list_state.select(Some(state.ui.eq_band));
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut list_state);
+}
+
+fn band_label(frequency: f32) -> String {
+    if frequency >= 1000. {
+        format!("{}kHz", frequency / 1000.)
+    } else {
+        format!("{}Hz", frequency)
+    }
+}
+
+// a bar growing left of the center line for cuts and right of it for boosts
+fn gain_bar(gain: f32) -> String {
+    let cells = gain.abs().round() as usize;
+    let (cut, boost) = if gain < 0. { (cells, 0) } else { (0, cells) };
+    format!(
+        "[{}{}|{}{}]",
+        " ".repeat(HALF_BAR_WIDTH - cut),
+        "=".repeat(cut),
+        "=".repeat(boost),
+        " ".repeat(HALF_BAR_WIDTH - boost)
+    )
+}
@@ -0,0 +1,24 @@
+use tui::{
+    layout::Alignment,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::ui::helper;
+use crate::ui::theme::Theme;
+
+pub fn render(frame: &mut Frame<impl tui::backend::Backend>, message: &str, theme: &Theme) {
+    let size = frame.size();
+    let block = Block::default()
+        .title("Error (Esc to dismiss)")
+        .borders(Borders::ALL)
+        .style(theme.error);
+    let area = helper::centered_rect(50, 20, 30, 5, size);
+    let paragraph = Paragraph::new(message.to_string())
+        .style(theme.text)
+        .alignment(Alignment::Left)
+        .wrap(Wrap { trim: true });
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, block.inner(area));
+    frame.render_widget(block, area);
+}
@@ -0,0 +1,202 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use tui::{
+    style::Modifier,
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::library::song::Song;
+use crate::metadata::Metadata;
+use crate::ui::helper;
+use crate::ui::theme::Theme;
+
+use super::curr_playing_bar::readable_time;
+use super::stats_dashboard::readable_size;
+
+// wide enough for the longest field name
+const FIELD_WIDTH: usize = 13;
+
+// everything known about a song, its tags, what was found online, its audio, its file and how
+// often it was played, a field to a row with the values lined up
+pub fn render(
+    frame: &mut Frame<impl tui::backend::Backend>,
+    song: &Song,
+    metadata: &Metadata,
+    theme: &Theme,
+) {
+    let sections = [
+        ("Tags", tag_fields(song)),
+        ("Online", online_fields(song, metadata)),
+        ("Audio", audio_fields(song)),
+        ("File", file_fields(song)),
+        ("Stats", stats_fields(song)),
+    ];
+    let mut lines: Vec<Spans> = Vec::new();
+    for (heading, fields) in sections {
+        if fields.is_empty() {
+            continue;
+        }
+        if !lines.is_empty() {
+            lines.push(Spans::from(""));
+        }
+        lines.push(Spans::from(Span::styled(
+            heading,
+            theme.accent.add_modifier(Modifier::BOLD),
+        )));
+        for (field, value) in fields {
+            lines.push(Spans::from(vec![
+                Span::styled(format!("{: <1$}", field, FIELD_WIDTH), theme.dimmed),
+                Span::raw(value),
+            ]));
+        }
+    }
+
+    let size = frame.size();
+    // the bio goes last since it's the longest, and is wrapped to the popup's width
+    let width = (size.width as u32 * 70 / 100).max(40).min(size.width as u32) as usize;
+    let mut height = lines.len() + 2;
+    if let Some(bio) = metadata.bio(song) {
+        lines.push(Spans::from(""));
+        lines.push(Spans::from(Span::styled(
+            "Artist",
+            theme.accent.add_modifier(Modifier::BOLD),
+        )));
+        lines.push(Spans::from(bio.to_owned()));
+        height += 2 + bio.chars().count() / width.saturating_sub(2).max(1) + 1;
+    }
+    let block = Block::default()
+        .title(format!("{} (Esc: close)", song.title))
+        .borders(Borders::ALL);
+    let area = helper::centered_rect(70, 70, 40, height as u16, size);
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .style(theme.text)
+        .wrap(Wrap { trim: false });
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn tag_fields(song: &Song) -> Vec<(&'static str, String)> {
+    vec![
+        ("Title", song.title.to_owned()),
+        ("Artist", song.track_artist.to_owned()),
+        ("Album", song.album_title.to_owned()),
+        ("Album artist", song.album_artist.to_owned()),
+        ("Track #", optional(&song.track_number)),
+        ("Disc #", optional(&song.disc_number)),
+        ("Year", optional(&song.year)),
+        ("Genre", optional(&song.genre)),
+    ]
+}
+
+// the tags musicbrainz has and whether art was fetched, nothing if it was never looked up
+fn online_fields(song: &Song, metadata: &Metadata) -> Vec<(&'static str, String)> {
+    let mut fields = Vec::new();
+    if metadata.is_pending(song) {
+        fields.push(("Status", "looking it up…".to_string()));
+    }
+    if let Some(tags) = metadata.tags(song) {
+        fields.push(("Title", tags.title.to_owned()));
+        fields.push(("Artist", tags.artist.to_owned()));
+        fields.push(("Album", tags.album.to_owned()));
+        fields.push(("Year", optional(&tags.year)));
+    }
+    if metadata.has_cover(song) && !song.has_cover_art {
+        fields.push(("Cover art", "fetched".to_string()));
+    }
+    fields
+}
+
+fn audio_fields(song: &Song) -> Vec<(&'static str, String)> {
+    let gain = |gain: Option<f32>| gain.map_or("-".to_string(), |gain| format!("{:+.2} dB", gain));
+    let channels = match song.channels {
+        0 => "-".to_string(),
+        1 => "mono".to_string(),
+        2 => "stereo".to_string(),
+        channels => channels.to_string(),
+    };
+    let sample_rate = match song.sample_rate {
+        0 => "-".to_string(),
+        rate => format!("{:.1} kHz", rate as f32 / 1000.),
+    };
+    vec![
+        ("Codec", song.format()),
+        ("Duration", readable_time(song.duration_secs)),
+        ("Sample rate", sample_rate),
+        (
+            "Bitrate",
+            song.bitrate_kbps
+                .map_or("-".to_string(), |bitrate| format!("{} kbps", bitrate)),
+        ),
+        ("Channels", channels),
+        ("Track gain", gain(song.track_gain)),
+        ("Album gain", gain(song.album_gain)),
+    ]
+}
+
+fn file_fields(song: &Song) -> Vec<(&'static str, String)> {
+    let mut fields = vec![
+        ("Path", song.path.to_owned()),
+        ("Size", readable_size(song.size_bytes)),
+        (
+            "Cover art",
+            if song.has_cover_art { "embedded" } else { "-" }.to_string(),
+        ),
+    ];
+    // tracks of a cue sheet are a part of their file
+    if song.end_ms.is_some() || song.start_ms != 0 {
+        let end = song
+            .end_ms
+            .map_or("the end".to_string(), |end| readable_time(end / 1000));
+        let part = format!("{} to {}", readable_time(song.start_ms / 1000), end);
+        fields.push(("Cue sheet", part));
+    }
+    if song.missing {
+        fields.push(("Missing", "the file couldn't be found".to_string()));
+    }
+    fields
+}
+
+fn stats_fields(song: &Song) -> Vec<(&'static str, String)> {
+    let rating = match song.rating {
+        0 => "-".to_string(),
+        stars => "★".repeat(stars as usize),
+    };
+    vec![
+        ("Plays", song.play_count.to_string()),
+        ("Skips", song.skip_count.to_string()),
+        ("Last played", time_ago(song.last_played_secs)),
+        ("Added", time_ago(song.added_secs)),
+        ("Rating", rating),
+        (
+            "Favorite",
+            if song.favorite { "yes" } else { "no" }.to_string(),
+        ),
+    ]
+}
+
+fn optional(value: &Option<String>) -> String {
+    value.to_owned().unwrap_or_else(|| "-".to_string())
+}
+
+// eg. 3 days ago, from unix time
+pub fn time_ago(secs: u64) -> String {
+    if secs == 0 {
+        return "never".to_string();
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs());
+    let ago = now.saturating_sub(secs);
+    let (count, unit) = match ago {
+        0..=59 => return "just now".to_string(),
+        60..=3599 => (ago / 60, "minute"),
+        3600..=86399 => (ago / 3600, "hour"),
+        _ => (ago / 86400, "day"),
+    };
+    match count {
+        1 => format!("1 {} ago", unit),
+        count => format!("{} {}s ago", count, unit),
+    }
+}
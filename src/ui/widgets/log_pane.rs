@@ -0,0 +1,37 @@
+use tui::{
+    layout::Rect,
+    text::{Span, Spans},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+use crate::ui::theme::Theme;
+
+// lines too long for the pane are cut off, the whole of them is in the log file
+pub fn render(
+    frame: &mut Frame<impl tui::backend::Backend>,
+    area: Rect,
+    lines: &[String],
+    theme: &Theme,
+) {
+    let block = Block::default().borders(Borders::ALL).title("Log");
+    if lines.is_empty() {
+        let paragraph = Paragraph::new("Nothing was logged yet")
+            .block(block)
+            .style(theme.dimmed);
+        frame.render_widget(paragraph, area);
+        return;
+    }
+    let lines: Vec<Spans> = lines
+        .iter()
+        .map(|line| {
+            let style = if line.contains("[ERROR]") || line.contains("[WARN]") {
+                theme.error
+            } else {
+                theme.text
+            };
+            Spans::from(Span::styled(line.to_owned(), style))
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use tui::{
+    layout::{Alignment, Rect},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::library::lyrics::Lyrics;
+use crate::ui::theme::Theme;
+
+pub fn render(
+    frame: &mut Frame<impl tui::backend::Backend>,
+    area: Rect,
+    lyrics: Option<&Lyrics>,
+    progress: Duration,
+    theme: &Theme,
+) {
+    let block = Block::default().borders(Borders::ALL).title("Lyrics");
+    let lyrics = match lyrics {
+        Some(lyrics) => lyrics,
+        None => {
+            let paragraph = Paragraph::new("No lyrics found")
+                .block(block)
+                .alignment(Alignment::Center)
+                .style(theme.dimmed);
+            frame.render_widget(paragraph, area);
+            return;
+        }
+    };
+
+    let curr_line = lyrics.current_line(progress);
+    let lines: Vec<Spans> = lyrics
+        .lines
+        .iter()
+        .enumerate()
+        .map(|(idx, line)| {
+            let style = match curr_line {
+                Some(curr_line) if curr_line == idx => theme.highlight,
+                // lines that were already sung fade out
+                Some(curr_line) if idx < curr_line => theme.dimmed,
+                _ => theme.text,
+            };
+            Spans::from(Span::styled(line.text.to_owned(), style))
+        })
+        .collect();
+
+    // keep the current line in the middle of the pane. wrapped lines can push it down a little
+    let inner_height = area.height.saturating_sub(2);
+    let scroll = curr_line.map_or(0, |curr_line| {
+        (curr_line as u16).saturating_sub(inner_height / 2)
+    });
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true })
+        .scroll((scroll, 0));
+    frame.render_widget(paragraph, area);
+}
@@ -1,3 +1,25 @@
+pub mod album_art;
+pub mod album_groups;
+pub mod bookmarks_popup;
+pub mod command_line;
+pub mod confirm_popup;
+pub mod context_menu;
 pub mod curr_playing_bar;
-pub mod search_popup;
+pub mod equalizer_popup;
+pub mod error_popup;
+pub mod file_info_popup;
+pub mod log_pane;
+pub mod lyrics_pane;
+pub mod queue_pane;
+pub mod scan_progress;
+pub mod scrollbar;
+pub mod song_table;
+pub mod settings_view;
 pub mod stateful_list;
+pub mod stats_dashboard;
+pub mod status_line;
+pub mod tab_bar;
+pub mod tag_editor_popup;
+pub mod view_tabs;
+pub mod visualizer;
+pub mod waveform;
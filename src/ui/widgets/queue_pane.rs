@@ -0,0 +1,51 @@
+use tui::{
+    layout::Rect,
+    style::Style,
+    text::Spans,
+    widgets::{Block, Borders, List, ListItem, ListState},
+    Frame,
+};
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::state::AppState;
+use crate::ui::helper;
+use crate::ui::theme::Theme;
+
+// the cursor is what's selected and where the pane is scrolled to, while it has the focus
+pub fn render(
+    frame: &mut Frame<impl tui::backend::Backend>,
+    area: Rect,
+    state: &AppState,
+    cursor: Option<(Option<usize>, usize)>,
+    theme: &Theme,
+) {
+    let offset = cursor.map_or(0, |(_, offset)| offset);
+    // less the borders, and the highlight symbol while there's a cursor
+    let symbol_width = cursor.map_or(0, |_| theme.highlight_symbol.width());
+    let width = (area.width as usize).saturating_sub(2 + symbol_width);
+    let items: Vec<ListItem> = state
+        .queue
+        .iter()
+        .enumerate()
+        .skip(offset)
+        // less the borders, the rest wouldn't be seen
+        .take(area.height.saturating_sub(2) as usize)
+        .map(|(idx, song)| {
+            let row = format!("{}. {}", idx + 1, state.ui.formats.song.render(song));
+            ListItem::new(Spans::from(helper::truncate(&row, width).into_owned()))
+        })
+        .collect();
+
+    let title = format!("Queue ({})", state.queue.len());
+    let border = if cursor.is_some() { theme.accent } else { Style::default() };
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title).border_style(border))
+        .style(theme.text)
+        .highlight_style(theme.highlight)
+        .highlight_symbol(&theme.highlight_symbol);
+
+    let mut list_state = ListState::default();
+    list_state.select(cursor.and_then(|(selected, _)| selected).map(|idx| idx - offset));
+    frame.render_stateful_widget(list, area, &mut list_state);
+}
@@ -0,0 +1,27 @@
+use tui::{
+    layout::Rect,
+    style::Modifier,
+    widgets::{Block, Borders, Gauge},
+    Frame,
+};
+
+use crate::ui::theme::Theme;
+
+pub fn render(
+    frame: &mut Frame<impl tui::backend::Backend>,
+    area: Rect,
+    scanned: usize,
+    total: usize,
+    theme: &Theme,
+) {
+    let ratio = match total {
+        0 => 1.,
+        _ => (scanned as f64 / total as f64).min(1.),
+    };
+    let gauge = Gauge::default()
+        .block(Block::default().borders(Borders::NONE))
+        .gauge_style(theme.accent.add_modifier(Modifier::ITALIC))
+        .ratio(ratio)
+        .label(format!("Scanning library {}/{}", scanned, total));
+    frame.render_widget(gauge, area);
+}
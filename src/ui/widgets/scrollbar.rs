@@ -0,0 +1,42 @@
+use tui::{buffer::Buffer, layout::Rect, widgets::Widget};
+use unicode_width::UnicodeWidthStr;
+
+use crate::ui::theme::Theme;
+
+// drawn over the border of a list or table: a thumb on the right side for the rows on screen
+// once they don't all fit, and the position of the selected row in the bottom right corner
+pub struct Scrollbar<'a> {
+    pub offset: usize, // the first row on screen
+    pub len: usize,
+    pub selected: Option<usize>,
+    pub header_rows: u16, // between the top border and the first row, eg. a table's header
+    pub theme: &'a Theme,
+}
+
+impl<'a> Widget for Scrollbar<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.width < 3 || area.height < 3 + self.header_rows {
+            return;
+        }
+        let track_top = area.top() + 1 + self.header_rows;
+        let track = (area.bottom() - 1 - track_top) as usize;
+        if self.len > track {
+            let thumb = (track * track / self.len).max(1);
+            let start = (track * self.offset / self.len).min(track - thumb);
+            let x = area.right() - 1;
+            for row in start..start + thumb {
+                buf.get_mut(x, track_top + row as u16)
+                    .set_symbol("┃")
+                    .set_style(self.theme.accent);
+            }
+        }
+        if let Some(selected) = self.selected.filter(|_| self.len > 0) {
+            let position = format!(" {}/{} ", selected + 1, self.len);
+            // kept clear of the corners
+            if position.width() + 2 <= area.width as usize {
+                let x = area.right() - 1 - position.width() as u16;
+                buf.set_string(x, area.bottom() - 1, position, self.theme.dimmed);
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use tui::{
+    layout::Rect,
+    style::Modifier,
+    text::{Span, Spans},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+use crate::config;
+use crate::state::AppState;
+use crate::ui::theme::Theme;
+use crate::utils::constants::ReplayGainModes;
+
+// the settings in effect. they're changed in the config file, which is read again with R
+pub fn render(
+    frame: &mut Frame<impl tui::backend::Backend>,
+    area: Rect,
+    state: &AppState,
+    theme: &Theme,
+) {
+    let player = &state.player;
+    let replay_gain = match player.replay_gain {
+        ReplayGainModes::OFF => "off",
+        ReplayGainModes::TRACK => "track",
+        ReplayGainModes::ALBUM => "album",
+    };
+    let balance = if player.balance < 0. {
+        format!("{:.0}% left", -player.balance * 100.)
+    } else if player.balance > 0. {
+        format!("{:.0}% right", player.balance * 100.)
+    } else {
+        "centered".to_string()
+    };
+    let columns: Vec<String> = state
+        .ui
+        .columns
+        .iter()
+        .map(|column| match column.width {
+            Some(width) => format!("{} {}%", column.name.name(), width),
+            None => column.name.name().to_string(),
+        })
+        .collect();
+
+    let heading = |text: &str| Spans::from(Span::styled(text.to_string(), theme.accent));
+    let setting = |name: &str, value: String| {
+        Spans::from(vec![
+            Span::styled(
+                format!("  {:<22}", name),
+                theme.text.add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(value, theme.text),
+        ])
+    };
+    let lines = vec![
+        setting("Config file", config::path().display().to_string()),
+        Spans::default(),
+        heading("Playback"),
+        setting("Crossfade", format!("{} s", player.crossfade.as_secs())),
+        setting("Fade", format!("{} ms", player.fade.as_millis())),
+        setting("Speed", format!("{:.1}x", player.speed)),
+        setting("Mono", if player.mono { "on" } else { "off" }.to_string()),
+        setting("Balance", balance),
+        setting("Replay gain", replay_gain.to_string()),
+        setting("Preamp", format!("{:+.1} dB", player.preamp_db)),
+        setting("Equalizer", player.eq_preset.name().to_string()),
+        setting(
+            "Counts as played after",
+            format!("{:.0}% of a song", player.play_count_threshold * 100.),
+        ),
+        Spans::default(),
+        heading("Library"),
+        setting("Song list columns", columns.join(", ")),
+        setting(
+            "Grouped by album",
+            if state.ui.group_by_album { "on" } else { "off" }.to_string(),
+        ),
+    ];
+    let settings = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Settings (edit the config file, R: reload it)"),
+    );
+    frame.render_widget(settings, area);
+}
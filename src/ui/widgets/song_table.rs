@@ -0,0 +1,359 @@
+use std::ops::RangeInclusive;
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Rect},
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
+    Frame,
+};
+use unicode_width::UnicodeWidthStr;
+
+use crate::config::ColumnConfig;
+use crate::library::search::{self, SongMatch};
+use crate::library::song::Song;
+use crate::ui::helper;
+use crate::ui::song_format::{FormatFields, SongFormat};
+use crate::ui::widgets::album_groups::TableRows;
+use crate::ui::theme::Theme;
+use crate::utils::constants::SongColumns;
+
+use super::curr_playing_bar::readable_time;
+
+// wide enough for its header and a song of a few hours
+const DURATION_WIDTH: u16 = 8;
+// five stars and the heart
+const RATING_WIDTH: u16 = 7;
+const COLUMN_SPACING: u16 = 1;
+
+pub struct SongTable<'a> {
+    pub songs: &'a [Song],
+    // the songs grouped by album under their headers, which the offset and the selected row count
+    // in as well
+    pub groups: Option<&'a [TableRows]>,
+    // the first row on screen, only the rows from there that fit are drawn
+    pub offset: usize,
+    pub selected: Option<usize>,
+    pub title: String,
+    pub columns: &'a [ColumnConfig],
+    // of the title column
+    pub format: &'a SongFormat,
+    // the characters matching it stand out
+    pub highlight: Option<&'a str>,
+    // the rows picked out in visual mode
+    pub selection: Option<RangeInclusive<usize>>,
+    // added after the title, eg. the rating
+    pub suffix: fn(&Song) -> String,
+    // the border stands out while it has the focus beside other lists
+    pub focused: bool,
+    // where to keep the rows between frames, with the version of the list the songs are from
+    pub cache: Option<(&'a mut RowCache, u64)>,
+}
+
+// the cells of the rows last drawn, formatted again only once something in them changes
+pub struct RowCache {
+    key: Option<RowKey>,
+    cells: Vec<Vec<Spans<'static>>>,
+}
+
+#[derive(PartialEq)]
+struct RowKey {
+    version: u64,
+    offset: usize,
+    rows: Vec<TableRows>,
+    columns: Vec<SongColumns>,
+    format: SongFormat,
+    widths: Vec<u16>,
+    highlight: Option<String>,
+    match_style: Style,
+}
+
+impl RowCache {
+    pub fn new() -> RowCache {
+        RowCache {
+            key: None,
+            cells: Vec::new(),
+        }
+    }
+}
+
+impl Default for RowCache {
+    fn default() -> Self {
+        RowCache::new()
+    }
+}
+
+// the songs with a column for each of the configured fields
+pub fn render<B: Backend>(
+    frame: &mut Frame<B>,
+    area: Rect,
+    table: SongTable,
+    theme: &Theme,
+) {
+    let SongTable {
+        songs,
+        groups,
+        offset,
+        selected,
+        title,
+        columns,
+        format: title_format,
+        highlight,
+        selection,
+        suffix,
+        focused,
+        cache,
+    } = table;
+    let symbol_width = match selected {
+        Some(_) => theme.highlight_symbol.width() as u16,
+        None => 0,
+    };
+    let spacing = COLUMN_SPACING * (columns.len() as u16).saturating_sub(1);
+    let available = area
+        .width
+        .saturating_sub(2 + symbol_width)
+        .saturating_sub(spacing);
+    let widths = column_widths(columns, available);
+
+    // less the borders and the header row
+    let rows = area.height.saturating_sub(3) as usize;
+    let len = groups.map_or(songs.len(), <[TableRows]>::len);
+    let end = (offset + rows).min(len);
+    let offset = offset.min(end);
+    let visible: Vec<TableRows> = match groups {
+        Some(groups) => groups[offset..end].to_vec(),
+        None => (offset..end).map(TableRows::SONG).collect(),
+    };
+
+    let match_style = theme.accent.add_modifier(Modifier::UNDERLINED);
+    let format = || -> Vec<Vec<Spans<'static>>> {
+        visible
+            .iter()
+            .map(|row| {
+                // the header is written over its row once the table is drawn
+                let song = match row {
+                    TableRows::SONG(idx) => &songs[*idx],
+                    TableRows::HEADER { .. } => return Vec::new(),
+                };
+                let found = highlight
+                    .and_then(|term| search::match_song(term, song))
+                    .unwrap_or_default();
+                columns
+                    .iter()
+                    .zip(&widths)
+                    .map(|(column, width)| {
+                        let spans = column_spans(
+                            column.name,
+                            song,
+                            title_format,
+                            &found,
+                            suffix,
+                            match_style,
+                        );
+                        Spans::from(helper::truncate_spans(spans, *width as usize))
+                    })
+                    .collect()
+            })
+            .collect()
+    };
+    let cells = match cache {
+        Some((cache, version)) => {
+            let key = RowKey {
+                version,
+                offset,
+                rows: visible.clone(),
+                columns: columns.iter().map(|column| column.name).collect(),
+                format: title_format.clone(),
+                widths: widths.clone(),
+                highlight: highlight.map(str::to_owned),
+                match_style,
+            };
+            if cache.key.as_ref() != Some(&key) {
+                cache.cells = format();
+                cache.key = Some(key);
+            }
+            cache.cells.clone()
+        }
+        None => format(),
+    };
+    let rows: Vec<Row> = cells
+        .into_iter()
+        .zip(&visible)
+        .map(|(cells, row)| {
+            let cells: Vec<Cell> = cells.into_iter().map(Cell::from).collect();
+            // selected songs stand out and songs whose file is gone are greyed out
+            let selected = row.song().is_some_and(|idx| {
+                selection.as_ref().is_some_and(|range| range.contains(&idx))
+            });
+            let style = if selected {
+                theme.highlight
+            } else if matches!(row, TableRows::SONG(idx) if songs[*idx].missing) {
+                theme.dimmed
+            } else {
+                Style::default()
+            };
+            Row::new(cells).style(style)
+        })
+        .collect();
+    let header = Row::new(
+        columns
+            .iter()
+            .zip(&widths)
+            .map(|(column, width)| {
+                Cell::from(Spans::from(helper::truncate_spans(
+                    vec![Span::raw(column.name.name())],
+                    *width as usize,
+                )))
+            })
+            .collect::<Vec<Cell>>(),
+    )
+    .style(theme.accent.add_modifier(Modifier::BOLD));
+    let constraints: Vec<Constraint> = widths
+        .iter()
+        .map(|width| Constraint::Length(*width))
+        .collect();
+
+    let table = Table::new(rows)
+        .header(header)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(if focused { theme.accent } else { Style::default() }),
+        )
+        .widths(&constraints)
+        .column_spacing(COLUMN_SPACING)
+        .style(theme.text)
+        .highlight_style(theme.highlight)
+        .highlight_symbol(&theme.highlight_symbol);
+    // the rows start at the offset, so the selection is counted from there too
+    let mut state = TableState::default();
+    state.select(selected.map(|idx| idx.saturating_sub(offset)));
+    frame.render_stateful_widget(table, area, &mut state);
+
+    // right of where the cursor's symbol goes, under the border and the header row
+    let left = area.x + 1 + symbol_width;
+    let width = area.width.saturating_sub(2 + symbol_width);
+    for (row, header) in visible.iter().enumerate() {
+        if let TableRows::HEADER {
+            first,
+            len,
+            collapsed,
+        } = *header
+        {
+            let spans = group_header(&songs[first], len, collapsed, theme);
+            let spans = helper::truncate_spans(spans, width as usize);
+            let header_area = Rect::new(left, area.y + 2 + row as u16, width, 1);
+            frame.render_widget(Paragraph::new(Spans::from(spans)), header_area);
+        }
+    }
+}
+
+// the album and its year, and how many songs it has while they're hidden
+fn group_header(song: &Song, len: usize, collapsed: bool, theme: &Theme) -> Vec<Span<'static>> {
+    let album = match song.album_title.is_empty() {
+        true => "Unknown album",
+        false => &song.album_title,
+    };
+    let mut spans = vec![
+        Span::styled(if collapsed { "▸ " } else { "▾ " }, theme.dimmed),
+        Span::styled(album.to_owned(), theme.accent.add_modifier(Modifier::BOLD)),
+    ];
+    if let Some(year) = song.year.as_ref().filter(|year| !year.is_empty()) {
+        spans.push(Span::styled(format!(" ({})", year), theme.accent));
+    }
+    if !song.album_artist.is_empty() {
+        spans.push(Span::styled(format!(" · {}", song.album_artist), theme.dimmed));
+    }
+    if collapsed {
+        let songs = if len == 1 { "song" } else { "songs" };
+        spans.push(Span::styled(format!(" · {} {}", len, songs), theme.dimmed));
+    }
+    spans
+}
+
+// columns with a width get their share first, in order, until there's no room left. the others
+// split what's left between them
+fn column_widths(columns: &[ColumnConfig], available: u16) -> Vec<u16> {
+    let mut left = available;
+    let mut widths: Vec<Option<u16>> = columns
+        .iter()
+        .map(|column| {
+            let width = match (column.width, column.name) {
+                (Some(percent), _) => (available as u32 * percent as u32 / 100) as u16,
+                (None, SongColumns::DURATION) => DURATION_WIDTH,
+                (None, SongColumns::RATING) => RATING_WIDTH,
+                (None, _) => return None,
+            };
+            let width = width.min(left);
+            left -= width;
+            Some(width)
+        })
+        .collect();
+    let shared = widths.iter().filter(|width| width.is_none()).count() as u16;
+    let mut extra = if shared > 0 { left % shared } else { 0 };
+    for width in widths.iter_mut().filter(|width| width.is_none()) {
+        let share = left / shared + u16::from(extra > 0);
+        extra = extra.saturating_sub(1);
+        *width = Some(share);
+    }
+    widths.into_iter().flatten().collect()
+}
+
+fn column_spans(
+    column: SongColumns,
+    song: &Song,
+    format: &SongFormat,
+    found: &SongMatch,
+    suffix: fn(&Song) -> String,
+    match_style: Style,
+) -> Vec<Span<'static>> {
+    match column {
+        SongColumns::TITLE => {
+            // what the search matched is picked out wherever the format has the field
+            let mut spans = format.spans(song, |field, value| match field {
+                FormatFields::TITLE => highlight_matches(&value, &found.title, match_style),
+                FormatFields::ARTIST => highlight_matches(&value, &found.artist, match_style),
+                FormatFields::ALBUM => highlight_matches(&value, &found.album, match_style),
+                _ => vec![Span::raw(value)],
+            });
+            spans.push(Span::raw(suffix(song)));
+            spans
+        }
+        SongColumns::ARTIST => highlight_matches(&song.track_artist, &found.artist, match_style),
+        SongColumns::ALBUM => highlight_matches(&song.album_title, &found.album, match_style),
+        SongColumns::DURATION => vec![Span::raw(readable_time(song.duration_secs))],
+        SongColumns::RATING => {
+            let stars = song.rating.min(5) as usize;
+            let heart = if song.favorite { " ♥" } else { "" };
+            vec![Span::raw(format!("{}{}{}", "★".repeat(stars), "☆".repeat(5 - stars), heart))]
+        }
+    }
+}
+
+// the text split up so that the characters at the matched positions have their own style
+fn highlight_matches(text: &str, positions: &[usize], style: Style) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (idx, ch) in text.chars().enumerate() {
+        let matched = positions.contains(&idx);
+        if matched != run_matched && !run.is_empty() {
+            spans.push(styled_run(std::mem::take(&mut run), run_matched, style));
+        }
+        run_matched = matched;
+        run.push(ch);
+    }
+    if !run.is_empty() {
+        spans.push(styled_run(run, run_matched, style));
+    }
+    spans
+}
+
+fn styled_run(run: String, matched: bool, style: Style) -> Span<'static> {
+    if matched {
+        Span::styled(run, style)
+    } else {
+        Span::raw(run)
+    }
+}
@@ -1,8 +1,19 @@
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU64, Ordering};
 use tui::widgets::ListState;
 
+// every list and every change to one gets a new version, so what was drawn from one can be kept
+// until it changes
+static VERSIONS: AtomicU64 = AtomicU64::new(0);
+
 pub struct StatefulList<T> {
     pub state: ListState,
     pub items: Vec<T>,
+    // where a selection was started, it reaches from there to the cursor
+    anchor: Option<usize>,
+    // the first item on screen when the list was last drawn
+    offset: usize,
+    version: u64,
 }
 
 impl<T> StatefulList<T> {
@@ -10,10 +21,16 @@ impl<T> StatefulList<T> {
         StatefulList {
             state: ListState::default(),
             items,
+            anchor: None,
+            offset: 0,
+            version: VERSIONS.fetch_add(1, Ordering::Relaxed),
         }
     }
 
     pub fn next(&mut self) {
+        if self.items.is_empty() {
+            return self.unselect();
+        }
         let i = match self.state.selected() {
             Some(i) => {
                 if i >= self.items.len() - 1 {
@@ -28,6 +45,9 @@ impl<T> StatefulList<T> {
     }
 
     pub fn previous(&mut self) {
+        if self.items.is_empty() {
+            return self.unselect();
+        }
         let i = match self.state.selected() {
             Some(i) => {
                 if i == 0 {
@@ -41,11 +61,117 @@ impl<T> StatefulList<T> {
         self.state.select(Some(i));
     }
 
+    // move the cursor `rows` down or up, stopping at either end rather than wrapping around
+    pub fn page(&mut self, rows: usize, down: bool) {
+        if self.items.is_empty() {
+            return;
+        }
+        let selected = self.state.selected().unwrap_or(0);
+        let idx = if down {
+            (selected + rows).min(self.items.len() - 1)
+        } else {
+            selected.saturating_sub(rows)
+        };
+        self.state.select(Some(idx));
+    }
+
+    // select the first item after the cursor that matches, wrapping around past the end so the
+    // same jump again goes on to the next one. returns whether anything matched
+    pub fn jump_to(&mut self, matches: impl Fn(&T) -> bool) -> bool {
+        let len = self.items.len();
+        let start = self.state.selected().map_or(0, |idx| idx + 1);
+        let found = (0..len)
+            .map(|i| (start + i) % len)
+            .find(|idx| matches(&self.items[*idx]));
+        if found.is_some() {
+            self.state.select(found);
+        }
+        found.is_some()
+    }
+
     pub fn unselect(&mut self) {
         self.state.select(None);
     }
 
+    pub fn start_selection(&mut self) {
+        self.anchor = self.state.selected();
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.anchor = None;
+    }
+
+    pub fn selection(&self) -> Option<RangeInclusive<usize>> {
+        let (anchor, cursor) = (self.anchor?, self.state.selected()?);
+        Some(anchor.min(cursor)..=anchor.max(cursor).min(self.items.len().checked_sub(1)?))
+    }
+
+    pub fn selected_items(&self) -> &[T] {
+        match self.selection() {
+            Some(range) => &self.items[range],
+            None => &[],
+        }
+    }
+
     pub fn len(&self) -> usize {
         self.items.len()
     }
+
+    // the first item on screen, as of the last call to track_offset
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    // call before drawing the list in this many rows, only what's on screen is drawn and clicks
+    // are worked out from it later
+    pub fn track_offset(&mut self, height: usize) {
+        self.offset = scrolled_offset(self.offset, self.state.selected(), self.len(), height);
+    }
+
+    // the items that fit in this many rows from the offset
+    pub fn visible(&self, height: usize) -> &[T] {
+        let end = (self.offset + height).min(self.len());
+        &self.items[self.offset.min(end)..end]
+    }
+
+    // the state to draw the visible items with, the selection counted from the offset
+    pub fn visible_state(&self) -> ListState {
+        let mut state = ListState::default();
+        state.select(self.state.selected().map(|idx| idx.saturating_sub(self.offset)));
+        state
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    // call after changing the items in place
+    pub fn changed(&mut self) {
+        self.version = VERSIONS.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // the item shown on this row of the list, counting from the top of its inside
+    pub fn index_at(&self, row: usize) -> Option<usize> {
+        Some(self.offset + row).filter(|idx| *idx < self.len())
+    }
+}
+
+// tui keeps how far it scrolled a list or table to itself, so it's worked out the same way here:
+// as little scrolling from before as brings the selected row into view
+pub fn scrolled_offset(offset: usize, selected: Option<usize>, len: usize, height: usize) -> usize {
+    let selected = match selected {
+        Some(selected) => selected.min(len.saturating_sub(1)),
+        // unselecting scrolls back to the top
+        None => return 0,
+    };
+    // tui doesn't touch the offset of an empty list
+    if len == 0 || height == 0 {
+        return offset;
+    }
+    let offset = offset.min(len - 1);
+    if selected >= offset + height {
+        selected + 1 - height
+    } else {
+        offset.min(selected)
+    }
 }
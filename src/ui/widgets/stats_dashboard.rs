@@ -0,0 +1,125 @@
+use tui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{BarChart, Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::library::stats::LibraryStats;
+use crate::ui::theme::Theme;
+
+const MIN_BAR_WIDTH: u16 = 3;
+const MAX_BAR_WIDTH: u16 = 10;
+
+pub fn render(
+    frame: &mut Frame<impl tui::backend::Backend>,
+    area: Rect,
+    stats: &LibraryStats,
+    theme: &Theme,
+) {
+    frame.render_widget(Clear, area);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("Library stats (i: close)");
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(1),
+                Constraint::Percentage(50),
+                Constraint::Percentage(50),
+            ]
+            .as_ref(),
+        )
+        .split(inner);
+    let summary = Paragraph::new(format!(
+        "{} songs, {} of music, {}",
+        stats.song_count,
+        readable_duration(stats.total_duration_secs),
+        readable_size(stats.total_size_bytes)
+    ))
+    .alignment(Alignment::Center)
+    .style(theme.text.add_modifier(Modifier::BOLD));
+    frame.render_widget(summary, rows[0]);
+
+    let charts = [
+        ("Genres", &stats.genres),
+        ("Decades", &stats.decades),
+        ("Formats", &stats.formats),
+        ("Bitrates (kbps)", &stats.bitrates),
+    ];
+    let cells: Vec<Rect> = rows[1..]
+        .iter()
+        .flat_map(|row| {
+            Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+                .split(*row)
+        })
+        .collect();
+    for (idx, ((title, counts), cell)) in charts.iter().zip(cells).enumerate() {
+        render_chart(frame, cell, title, counts, theme.chart_color(idx), theme);
+    }
+}
+
+fn render_chart(
+    frame: &mut Frame<impl tui::backend::Backend>,
+    area: Rect,
+    title: &str,
+    counts: &[(String, u64)],
+    color: Color,
+    theme: &Theme,
+) {
+    let data: Vec<(&str, u64)> = counts
+        .iter()
+        .map(|(label, count)| (label.as_str(), *count))
+        .collect();
+    // as wide as the longest label, as long as every bar still fits
+    let longest_label = counts
+        .iter()
+        .map(|(label, _)| label.chars().count() as u16)
+        .max()
+        .unwrap_or(0);
+    let bars = (data.len() as u16).max(1);
+    let room = area.width.saturating_sub(2) / bars;
+    let bar_width = longest_label
+        .min(room.saturating_sub(1))
+        .clamp(MIN_BAR_WIDTH, MAX_BAR_WIDTH);
+    let chart = BarChart::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title.to_owned()),
+        )
+        .data(&data)
+        .bar_width(bar_width)
+        .bar_gap(1)
+        .bar_style(Style::default().fg(color))
+        .value_style(Style::default().fg(Color::Black).bg(color))
+        .label_style(theme.text);
+    frame.render_widget(chart, area);
+}
+
+// eg. 3 days 4 hours
+fn readable_duration(secs: u64) -> String {
+    let hours = secs / 3600;
+    match hours {
+        0 => format!("{} minutes", secs / 60),
+        1..=47 => format!("{} hours {} minutes", hours, secs / 60 % 60),
+        _ => format!("{} days {} hours", hours / 24, hours % 24),
+    }
+}
+
+pub fn readable_size(bytes: u64) -> String {
+    let units = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024. && unit + 1 < units.len() {
+        size /= 1024.;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, units[unit])
+}
@@ -0,0 +1,49 @@
+use tui::{layout::Rect, text::Span, widgets::Paragraph, Frame};
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::state::UIState;
+use crate::ui::helper;
+use crate::ui::theme::Theme;
+
+// the notification being shown and how many are waiting after it, or what a key is waiting for
+pub fn render(
+    frame: &mut Frame<impl tui::backend::Backend>,
+    area: Rect,
+    ui: &UIState,
+    theme: &Theme,
+) {
+    if ui.jumping {
+        let prompt = Span::styled("Jump to the first one starting with: _", theme.accent);
+        frame.render_widget(Paragraph::new(prompt), area);
+        return;
+    }
+    let notification = match ui.notifications.front() {
+        Some(notification) => notification,
+        // what's going on in the background when there's nothing to tell
+        None => {
+            let text = match ui.gain_progress {
+                Some((measured, total)) => {
+                    format!("Measuring loudness: {} of {} songs", measured, total)
+                }
+                None if ui.party => "Party mode: only adding to the queue works".to_string(),
+                None => return,
+            };
+            frame.render_widget(Paragraph::new(Span::styled(text, theme.dimmed)), area);
+            return;
+        }
+    };
+    // a long one is cut short before how many more there are
+    let waiting = match ui.notifications.len() - 1 {
+        0 => String::new(),
+        waiting => format!(" (+{} more)", waiting),
+    };
+    let width = (area.width as usize).saturating_sub(waiting.width());
+    let text = format!("{}{}", helper::truncate(&notification.text, width), waiting);
+    let style = if notification.error {
+        theme.error
+    } else {
+        theme.text
+    };
+    frame.render_widget(Paragraph::new(Span::styled(text, style)), area);
+}
@@ -0,0 +1,94 @@
+use tui::{
+    layout::{Alignment, Rect},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Paragraph, Tabs},
+    Frame,
+};
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::ui::helper;
+use crate::ui::theme::Theme;
+use crate::utils::constants::AppViews;
+
+use super::view_tabs;
+
+const SEPARATOR: &str = " ▸ ";
+
+const TABS: [AppViews; 5] = [
+    AppViews::LIBRARY,
+    AppViews::QUEUE,
+    AppViews::PLAYLISTS,
+    AppViews::SEARCH,
+    AppViews::SETTINGS,
+];
+
+// the tabs, and right of them where in the open tab the browser is, e.g. Library ▸ Artists ▸
+// Miles Davis ▸ Kind of Blue
+pub fn render(
+    frame: &mut Frame<impl tui::backend::Backend>,
+    area: Rect,
+    tab: AppViews,
+    crumbs: &[String],
+    theme: &Theme,
+) {
+    let titles = tab_titles();
+    // tui puts a space on both sides of each title and a line between them
+    let tabs_width = titles.iter().map(|title| title.width() as u16 + 3).sum::<u16>();
+    let tabs = Tabs::new(titles.into_iter().map(Spans::from).collect())
+        .block(Block::default().borders(Borders::NONE))
+        .select(TABS.iter().position(|t| *t == tab).unwrap_or(0))
+        .style(theme.text)
+        .highlight_style(theme.highlight);
+    frame.render_widget(tabs, area);
+
+    let rest = Rect {
+        x: area.x + tabs_width.min(area.width),
+        width: area.width.saturating_sub(tabs_width + 1),
+        ..area
+    };
+    let breadcrumb = Paragraph::new(breadcrumb(crumbs, rest.width as usize, theme))
+        .alignment(Alignment::Right);
+    frame.render_widget(breadcrumb, rest);
+}
+
+// the levels that don't fit are left out from the top, and the last one is cut short if even it
+// doesn't fit on its own
+fn breadcrumb<'a>(crumbs: &'a [String], width: usize, theme: &Theme) -> Spans<'a> {
+    let joined_width = |crumbs: &[String]| {
+        crumbs.iter().map(|crumb| crumb.width() + SEPARATOR.width()).sum::<usize>()
+    };
+    let mut skipped = 0;
+    // an ellipsis takes the place of the levels left out
+    while skipped + 1 < crumbs.len() && joined_width(&crumbs[skipped..]) > width + 3 {
+        skipped += 1;
+    }
+    let (last, shown) = match crumbs[skipped..].split_last() {
+        Some(split) => split,
+        None => return Spans::default(),
+    };
+    let mut spans = Vec::new();
+    if skipped > 0 {
+        spans.push(Span::styled("…", theme.dimmed));
+        spans.push(Span::styled(SEPARATOR, theme.dimmed));
+    }
+    for crumb in shown {
+        spans.push(Span::styled(crumb.as_str(), theme.text));
+        spans.push(Span::styled(SEPARATOR, theme.dimmed));
+    }
+    let room = width.saturating_sub(spans.iter().map(Span::width).sum());
+    spans.push(Span::styled(helper::truncate(last, room), theme.accent));
+    Spans::from(spans)
+}
+
+fn tab_titles() -> Vec<String> {
+    TABS.iter()
+        .enumerate()
+        .map(|(idx, tab)| format!("{} {}", idx + 1, tab.title()))
+        .collect()
+}
+
+// the tab at this spot of the tab bar
+pub fn tab_at(area: Rect, column: u16, row: u16) -> Option<AppViews> {
+    view_tabs::title_at(&tab_titles(), area, column, row).map(|idx| TABS[idx])
+}
@@ -0,0 +1,39 @@
+use tui::{
+    text::Spans,
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+use crate::state::{TagEditor, TAG_FIELDS};
+use crate::ui::helper;
+use crate::ui::theme::Theme;
+
+pub fn render(frame: &mut Frame<impl tui::backend::Backend>, editor: &TagEditor, theme: &Theme) {
+    let size = frame.size();
+    let block = Block::default()
+        .title("Edit tags (Enter: save, Esc: cancel)")
+        .borders(Borders::ALL);
+    let area = helper::centered_rect(60, 30, 40, TAG_FIELDS.len() as u16 + 2, size);
+
+    let items: Vec<ListItem> = TAG_FIELDS
+        .iter()
+        .zip(editor.values.iter())
+        .enumerate()
+        .map(|(idx, (field, value))| {
+            // a cursor after the text of the field being typed into
+            let cursor = if idx == editor.field { "_" } else { "" };
+            ListItem::new(Spans::from(format!("{: <8} {}{}", field, value, cursor)))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(block)
+        .style(theme.text)
+        .highlight_style(theme.highlight)
+        .highlight_symbol(&theme.highlight_symbol);
+
+    let mut list_state = ListState::default();
+    list_state.select(Some(editor.field));
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut list_state);
+}
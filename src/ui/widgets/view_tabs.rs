@@ -0,0 +1,67 @@
+use tui::{
+    layout::Rect,
+    text::Spans,
+    widgets::{Block, Borders, Tabs},
+    Frame,
+};
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::ui::theme::Theme;
+use crate::utils::constants::LibraryViews;
+
+const VIEWS: [LibraryViews; 9] = [
+    LibraryViews::SONGS,
+    LibraryViews::ARTISTS,
+    LibraryViews::ALBUMS,
+    LibraryViews::DUPLICATES,
+    LibraryViews::MOST_PLAYED,
+    LibraryViews::RECENTLY_PLAYED,
+    LibraryViews::RECENTLY_ADDED,
+    LibraryViews::PODCASTS,
+    LibraryViews::FILES,
+];
+
+pub fn render(
+    frame: &mut Frame<impl tui::backend::Backend>,
+    area: Rect,
+    view: LibraryViews,
+    theme: &Theme,
+) {
+    let titles: Vec<Spans> = tab_titles().into_iter().map(Spans::from).collect();
+    let tabs = Tabs::new(titles)
+        .block(Block::default().borders(Borders::NONE))
+        .select(VIEWS.iter().position(|v| *v == view).unwrap_or(0))
+        .style(theme.text)
+        .highlight_style(theme.highlight);
+    frame.render_widget(tabs, area);
+}
+
+fn tab_titles() -> Vec<String> {
+    VIEWS
+        .iter()
+        .enumerate()
+        .map(|(idx, view)| format!("F{} {}", idx + 1, view.title()))
+        .collect()
+}
+
+pub fn view_at(area: Rect, column: u16, row: u16) -> Option<LibraryViews> {
+    title_at(&tab_titles(), area, column, row).map(|idx| VIEWS[idx])
+}
+
+// which of the titles of tabs drawn in this area is at this spot. tui puts a space on both sides
+// of each title and a line between them
+pub fn title_at(titles: &[String], area: Rect, column: u16, row: u16) -> Option<usize> {
+    if row != area.top() {
+        return None;
+    }
+    let mut x = area.left();
+    for (idx, title) in titles.iter().enumerate() {
+        let width = title.width() as u16;
+        if (x..x + width + 2).contains(&column) {
+            return Some(idx);
+        }
+        x += width + 3;
+    }
+    None
+}
@@ -0,0 +1,52 @@
+use tui::{
+    layout::{Alignment, Rect},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+use crate::ui::theme::Theme;
+
+// eighths of a cell, from empty to full
+pub const BAR_PARTS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+// a bar for the level of each band, every other column. nothing to show unless a song is playing
+pub fn render(
+    frame: &mut Frame<impl tui::backend::Backend>,
+    area: Rect,
+    levels: Option<Vec<f32>>,
+    theme: &Theme,
+) {
+    let block = Block::default().borders(Borders::ALL).title("Visualizer");
+    let levels = match levels {
+        Some(levels) => levels,
+        None => {
+            let paragraph = Paragraph::new("Nothing is playing")
+                .block(block)
+                .alignment(Alignment::Center)
+                .style(theme.dimmed);
+            frame.render_widget(paragraph, area);
+            return;
+        }
+    };
+
+    let height = area.height.saturating_sub(2) as usize;
+    let filled: Vec<usize> = levels
+        .iter()
+        .map(|level| (level * (height * 8) as f32).round() as usize)
+        .collect();
+    let lines: Vec<Spans> = (0..height)
+        .rev()
+        .map(|row| {
+            let line: String = filled
+                .iter()
+                .flat_map(|filled| {
+                    let parts = filled.saturating_sub(row * 8).min(8);
+                    [BAR_PARTS[parts], ' ']
+                })
+                .collect();
+            Spans::from(Span::styled(line, theme.accent))
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
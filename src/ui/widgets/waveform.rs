@@ -0,0 +1,56 @@
+use tui::{
+    layout::Rect,
+    text::{Span, Spans},
+    widgets::Paragraph,
+    Frame,
+};
+
+use super::visualizer::BAR_PARTS;
+use crate::ui::theme::Theme;
+
+// the peaks of the song squeezed into the width of the area, in place of the progress bar. the
+// part that was played is drawn like the progress bar's
+pub fn render(
+    frame: &mut Frame<impl tui::backend::Backend>,
+    area: Rect,
+    peaks: &[f32],
+    played: f64,
+    theme: &Theme,
+) {
+    let width = area.width as usize;
+    if peaks.is_empty() || width == 0 {
+        return;
+    }
+    // quiet songs are scaled up so their shape can still be seen
+    let loudest = peaks.iter().copied().fold(f32::EPSILON, f32::max);
+    let height = area.height as usize;
+    let filled: Vec<usize> = (0..width)
+        .map(|column| {
+            let start = column * peaks.len() / width;
+            let end = ((column + 1) * peaks.len() / width).max(start + 1);
+            let peak = peaks[start..end.min(peaks.len())]
+                .iter()
+                .copied()
+                .fold(0., f32::max);
+            ((peak / loudest) * (height * 8) as f32).round() as usize
+        })
+        .collect();
+    let played_columns = (played * width as f64).round() as usize;
+    let lines: Vec<Spans> = (0..height)
+        .rev()
+        .map(|row| {
+            let bars = |columns: &[usize]| -> String {
+                columns
+                    .iter()
+                    .map(|filled| BAR_PARTS[filled.saturating_sub(row * 8).min(8)])
+                    .collect()
+            };
+            let (played, left) = filled.split_at(played_columns.min(width));
+            Spans::from(vec![
+                Span::styled(bars(played), theme.gauge),
+                Span::styled(bars(left), theme.dimmed),
+            ])
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines), area);
+}
@@ -1,41 +1,254 @@
+use serde::{Deserialize, Serialize};
+
+// 31Hz up to 16kHz, an octave apart
+pub const EQ_NUM_BANDS: usize = 10;
+
 pub mod requests {
 
-    #[derive(Debug, Copy, Clone)]
+    #[derive(Debug, Clone)]
     pub enum UIRequests {
         Up,
         Down,
+        Top,
+        Bottom,
+        PageUp,
+        PageDown,
+        HalfPageUp,
+        HalfPageDown,
+        StartJump,                 // to the first item starting with the next letter typed
+        JumpToLetter(Option<char>), // none if something else was pressed
+        Redraw,                     // the terminal was resized
         Quit,
         Enter,
+        Play, // the song under the cursor
+        OpenStream(String), // play the stream at a url right away
         //
         GoBack,
         //
         // ShowItemInfo,
         ShowSearch,
-        SearchInput(char),
+        SearchInput(String), // typed or pasted at the cursor
+        SearchBackspace,
+        SearchDeleteWord,
+        SearchClear,
+        SearchCursor(bool), // true to move it left
+        NextMatch, // of the last search, in the list being browsed
+        PreviousMatch,
+        PlayerEvent(PlayerEvents),
+        ScanProgress(usize, usize), // files read, files to read
+        GainProgress(usize, usize), // songs measured, songs to measure
+        LibraryUpdated,
+        PodcastsUpdated, // the feeds were read again
+        PlaylistsUpdated, // a search was saved or forgotten
+        DownloadEpisode, // the selected one, to the podcasts' download directory
+        LoadPlaylist, // add the selected playlist to the queue
+        EditTags,
+        TagInput(char),
+        TagBackspace,
+        DuplicatesFound(Vec<Vec<Song>>),
+        JumpToSong, // show the selected song in the songs view
+        JumpToPlaying, // select the song that's playing in the browser
+        Rate(u8),   // stars for the selected song, 0 clears the rating
+        ChangeRating(i8), // stars more or fewer than the selected song has
+        ToggleFavorite,
+        CycleSort,
+        ToggleLyrics,
+        ToggleVisualizer,
+        ToggleStats,
+        ToggleLog,
+        SetTheme(Theme), // after the config was reloaded
+        ShowCommand,     // open the ":" prompt
+        CommandInput(char),
+        CommandBackspace,
+        CompleteCommand,
+        ToggleVisual, // start or stop selecting songs from the one under the cursor
+        ToggleGroup,  // collapse the album group of the song under the cursor, or open it
+        Click(u16, u16), // column, row
+        DoubleClick(u16, u16),
+        NextView, // of the library
+        ShowView(LibraryViews),
+        NextTab,
+        FocusLeft, // the column left of the focused one in the artists view
+        FocusRight,
+        CyclePane, // from the open tab to the queue beside it and back
+        MoveInQueue(bool), // the song under the cursor of the queue tab, down if true
+        RemoveFromQueue,
+        ShowMenu, // of what can be done with the selected songs
+        ConfirmFiles(FileActions), // ask before doing it to the files of the selected songs
+        ToggleParty,
+        ShowBookmarks, // of the song playing
+        ShowFileInfo, // of the song under the cursor
+        FetchMetadata, // look up the selected songs or album online
+        ShowTab(AppViews),
+        ShowEqualizer,
+        EqRaise,
+        EqLower,
+        EqNextPreset,
+        SpeedUp,
+        SlowDown,
+        BalanceLeft,
+        BalanceRight,
+        Handled(Reply), // answered once the requests sent before it were handled
         //
         // UpdateBar,
         //
         // Query(UIStuff),
     }
 
+    use super::{AppViews, EqPresets, LibraryViews};
+    use crate::config::{MetadataConfig, NotificationsConfig, ScrobblingConfig};
+    use crate::library::song::Song;
+    use crate::ui::theme::Theme;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::sync::oneshot;
+
+    // answered once by whatever handles the request it's sent with. the requests have to be
+    // cloned for macros, and the clones share the one answer
+    #[derive(Debug, Clone)]
+    pub struct Reply(Arc<Mutex<Option<oneshot::Sender<()>>>>);
+
+    impl Reply {
+        pub fn new() -> (Reply, oneshot::Receiver<()>) {
+            let (reply_tx, reply_rx) = oneshot::channel();
+            (Reply(Arc::new(Mutex::new(Some(reply_tx)))), reply_rx)
+        }
+
+        pub fn send(&self) {
+            if let Some(reply_tx) = self.0.lock().unwrap().take() {
+                let _ = reply_tx.send(());
+            }
+        }
+    }
+
+    // sent by the player to let the rest of the app know what happened during playback. the song
+    // makes it big, but one is only sent as a song starts
+    #[allow(clippy::large_enum_variant)]
+    #[derive(Debug, Clone)]
+    pub enum PlayerEvents {
+        TrackStarted(Song),
+        Progress, // another second of the song was played
+        TrackEnded,
+        Error(String),
+    }
+
+    // what changed about the player, for the desktop to be told about through mpris
+    #[derive(Debug, Clone, Copy)]
+    pub enum PlayerChanges {
+        PlaybackStatus,
+        Metadata, // the song, or what a stream says is on
+        LoopStatus,
+        Shuffle,
+        Rate,
+        Seeked(Duration), // jumped somewhere else in the song
+    }
+
     #[derive(Debug, Clone)]
     pub enum PlayerRequests {
         Stop,
-        Start,
+        Start,   // the selected song, only resumed if it's the one loaded already
+        Restart, // the song that's loaded from its start, or the selected one if none is
         Resume,
         Pause,
         PlayPause,
-        // Next,
+        Next,
         // Previous,
-        // Seek(u64),
+        SeekRelative(i64), // seconds, negative seeks backwards
+        SeekTo(Duration),
+        SeekAbsolute(f64), // a fraction of the song, 0.0 is the start and 1.0 the end
+        ToggleShuffle,
+        CycleRepeat,
+        ToggleStopAfterCurrent,
+        SetCrossfade(u64), // seconds
+        SetEqBand(usize, f32), // band, gain in dB
+        SetEqPreset(EqPresets),
+        SetSpeed(f32), // 1.0 is normal speed
+        ToggleMono,
+        SetBalance(f32), // -1.0 is fully left, 1.0 fully right
+        MarkLoop, // the start or end of a part of the song to repeat, or back to all of it
         // ChangeVolume(f32),
         Quit,
     }
 
+    #[derive(Debug, Clone)]
+    pub enum QueueRequests {
+        Enqueue,
+        InsertNext,
+        Clear,
+        SaveAsPlaylist(Option<String>), // named after the time it was saved if not given
+        AddPath(PathBuf),               // a file, directory or playlist
+        AddToPlaylist(String),          // made if there's no playlist by that name
+        Undo,                           // the last clear or removal, or change to a playlist
+        Redo,
+    }
+
+    // changes to songs that have to be written to the library database
+    #[derive(Debug, Clone)]
+    pub enum LibraryRequests {
+        SaveStats(Song),
+        CountPlay(Song), // played past the threshold in the config
+        CountSkip(Song), // skipped before reaching it
+        PurgeMissing,    // forget the songs whose files are gone
+        Remove(Vec<Song>),
+        SavePosition(Song, Option<Duration>), // of a podcast episode, none if it was finished
+        RememberSearch(String),               // for up and down to bring back next time
+        SaveSearch(String),                   // the last search, under a name
+        ForgetSearch(String),                 // the saved search by that name
+        AddBookmark(Option<String>), // where the song playing is, named after the time if not given
+        DeleteBookmark(String),      // the song playing's bookmark by that name
+        MeasureGain(bool), // of the songs without ReplayGain, writing it to their files if true
+        SaveGains(Vec<Song>), // that were measured
+        ChangeFiles(Vec<Song>, FileActions), // once it was confirmed
+    }
+
+    // what can be done to the files of songs, which changes the library to match
+    #[derive(Debug, Clone)]
+    pub enum FileActions {
+        MoveTo(PathBuf),
+        Trash,
+        Delete, // for good
+    }
+
+    // what's played, to be sent to the scrobbling services
+    #[derive(Debug, Clone)]
+    pub enum ScrobblerRequests {
+        NowPlaying(Song),
+        Scrobble(Song), // played long enough to count
+        SetConfig(ScrobblingConfig),
+    }
+
+    // shown on the desktop, outside of the terminal. one per song, so the song isn't boxed
+    #[allow(clippy::large_enum_variant)]
+    #[derive(Debug, Clone)]
+    pub enum NotificationRequests {
+        SongChanged(Song),
+        Error(String),
+        SetConfig(NotificationsConfig),
+    }
+
+    // songs to look up online. the one song isn't boxed either, like the notifications'
+    #[allow(clippy::large_enum_variant)]
+    #[derive(Debug, Clone)]
+    pub enum MetadataRequests {
+        Fetch(Vec<Song>), // asked for, so it says when it's done or that it's off
+        Lookup(Song),     // whose info was opened, only if it's on
+        SetConfig(MetadataConfig),
+    }
+
+    // a song makes the library requests much bigger than the rest, but they're few enough that
+    // boxing it isn't worth it
+    #[allow(clippy::large_enum_variant)]
     #[derive(Debug, Clone)]
     pub enum AppRequests {
         UIRequests(UIRequests),
         PlayerRequests(PlayerRequests),
+        QueueRequests(QueueRequests),
+        LibraryRequests(LibraryRequests),
+        ScrobblerRequests(ScrobblerRequests),
+        MetadataRequests(MetadataRequests),
+        ReloadConfig, // from a key or SIGHUP
+        Macro(Vec<AppRequests>), // the actions of a key, run one after the other
         Quit,
     }
 }
@@ -50,9 +263,387 @@ pub mod requests {
 //     }
 // }
 //
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PlayerStates {
     STOPPED,
+    BUFFERING, // a song was started but nothing of it was played yet
     PLAYING,
     PAUSED,
 }
+
+impl PlayerStates {
+    // anything can stop and a song can be started from anything. it's only playing once the
+    // start of it was played, and only a song that was started can be paused or go on playing
+    pub fn can_become(self, to: PlayerStates) -> bool {
+        matches!(
+            (self, to),
+            (_, PlayerStates::STOPPED)
+                | (_, PlayerStates::BUFFERING)
+                | (PlayerStates::BUFFERING | PlayerStates::PAUSED, PlayerStates::PLAYING)
+                | (PlayerStates::BUFFERING | PlayerStates::PLAYING, PlayerStates::PAUSED)
+        )
+    }
+
+    // a song was started and isn't paused
+    pub fn is_playing(self) -> bool {
+        matches!(self, PlayerStates::BUFFERING | PlayerStates::PLAYING)
+    }
+}
+
+// the tabs along the top of the window
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AppViews {
+    LIBRARY,
+    QUEUE,
+    PLAYLISTS,
+    SEARCH,
+    SETTINGS,
+}
+
+impl AppViews {
+    pub fn cycle(self) -> AppViews {
+        match self {
+            AppViews::LIBRARY => AppViews::QUEUE,
+            AppViews::QUEUE => AppViews::PLAYLISTS,
+            AppViews::PLAYLISTS => AppViews::SEARCH,
+            AppViews::SEARCH => AppViews::SETTINGS,
+            AppViews::SETTINGS => AppViews::LIBRARY,
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            AppViews::LIBRARY => "Library",
+            AppViews::QUEUE => "Queue",
+            AppViews::PLAYLISTS => "Playlists",
+            AppViews::SEARCH => "Search",
+            AppViews::SETTINGS => "Settings",
+        }
+    }
+}
+
+// ways of browsing the library. playlists have a tab of their own
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LibraryViews {
+    SONGS,
+    ARTISTS,
+    ALBUMS,
+    PLAYLISTS,
+    DUPLICATES,
+    MOST_PLAYED,
+    RECENTLY_PLAYED,
+    RECENTLY_ADDED,
+    PODCASTS,
+    FILES,
+}
+
+impl LibraryViews {
+    pub fn cycle(self) -> LibraryViews {
+        match self {
+            LibraryViews::SONGS => LibraryViews::ARTISTS,
+            LibraryViews::ARTISTS => LibraryViews::ALBUMS,
+            LibraryViews::ALBUMS | LibraryViews::PLAYLISTS => LibraryViews::DUPLICATES,
+            LibraryViews::DUPLICATES => LibraryViews::MOST_PLAYED,
+            LibraryViews::MOST_PLAYED => LibraryViews::RECENTLY_PLAYED,
+            LibraryViews::RECENTLY_PLAYED => LibraryViews::RECENTLY_ADDED,
+            LibraryViews::RECENTLY_ADDED => LibraryViews::PODCASTS,
+            LibraryViews::PODCASTS => LibraryViews::FILES,
+            LibraryViews::FILES => LibraryViews::SONGS,
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            LibraryViews::SONGS => "Songs",
+            LibraryViews::ARTISTS => "Artists",
+            LibraryViews::ALBUMS => "Albums",
+            LibraryViews::PLAYLISTS => "Playlists",
+            LibraryViews::DUPLICATES => "Duplicates",
+            LibraryViews::MOST_PLAYED => "Most played",
+            LibraryViews::RECENTLY_PLAYED => "Recently played",
+            LibraryViews::RECENTLY_ADDED => "Recently added",
+            LibraryViews::PODCASTS => "Podcasts",
+            LibraryViews::FILES => "Files",
+        }
+    }
+}
+
+// orders the songs view can be sorted in
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortModes {
+    TITLE,
+    ARTIST,
+    RATING, // favorites first, then by stars
+}
+
+impl SortModes {
+    pub fn cycle(self) -> SortModes {
+        match self {
+            SortModes::TITLE => SortModes::ARTIST,
+            SortModes::ARTIST => SortModes::RATING,
+            SortModes::RATING => SortModes::TITLE,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SortModes::TITLE => "title",
+            SortModes::ARTIST => "artist",
+            SortModes::RATING => "rating",
+        }
+    }
+}
+
+// what can be done with the selected songs from their menu
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MenuActions {
+    PLAY_NOW,
+    PLAY_NEXT,
+    ADD_TO_QUEUE,
+    ADD_TO_PLAYLIST,
+    FILE_INFO,
+    EDIT_TAGS,
+    REMOVE,
+    MOVE_FILES,
+    TRASH_FILES,
+    DELETE_FILES,
+}
+
+impl MenuActions {
+    pub const ALL: [MenuActions; 10] = [
+        MenuActions::PLAY_NOW,
+        MenuActions::PLAY_NEXT,
+        MenuActions::ADD_TO_QUEUE,
+        MenuActions::ADD_TO_PLAYLIST,
+        MenuActions::FILE_INFO,
+        MenuActions::EDIT_TAGS,
+        MenuActions::REMOVE,
+        MenuActions::MOVE_FILES,
+        MenuActions::TRASH_FILES,
+        MenuActions::DELETE_FILES,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            MenuActions::PLAY_NOW => "Play now",
+            MenuActions::PLAY_NEXT => "Play next",
+            MenuActions::ADD_TO_QUEUE => "Add to queue",
+            MenuActions::ADD_TO_PLAYLIST => "Add to playlist…",
+            MenuActions::FILE_INFO => "Show file info",
+            MenuActions::EDIT_TAGS => "Edit tags",
+            MenuActions::REMOVE => "Delete from library",
+            MenuActions::MOVE_FILES => "Move file to…",
+            MenuActions::TRASH_FILES => "Move file to trash",
+            MenuActions::DELETE_FILES => "Delete file",
+        }
+    }
+}
+
+// how far into a view the library browser has drilled down
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BrowserLevels {
+    DUPLICATES,
+    PLAYLISTS,
+    PODCASTS,
+    FILES,
+    ARTISTS,
+    ALBUMS,
+    TRACKS,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RepeatModes {
+    OFF,
+    ONE,
+    ALL,
+}
+
+impl RepeatModes {
+    pub fn cycle(self) -> RepeatModes {
+        match self {
+            RepeatModes::OFF => RepeatModes::ALL,
+            RepeatModes::ALL => RepeatModes::ONE,
+            RepeatModes::ONE => RepeatModes::OFF,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplayGainModes {
+    OFF,
+    TRACK,
+    ALBUM,
+}
+
+impl Default for ReplayGainModes {
+    fn default() -> Self {
+        ReplayGainModes::OFF
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioBackends {
+    CPAL,
+    RODIO,
+    // straight to pipewire, or pulseaudio through pipewire-pulse. only with the pipewire feature
+    PIPEWIRE,
+}
+
+impl Default for AudioBackends {
+    fn default() -> Self {
+        AudioBackends::CPAL
+    }
+}
+
+// where songs are played. mpd has an mpd server play them and read the library from its database
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlayerModes {
+    LOCAL,
+    MPD,
+    // through a splay started with --daemon, only by `splay attach`
+    #[serde(skip_deserializing)]
+    REMOTE,
+}
+
+impl Default for PlayerModes {
+    fn default() -> Self {
+        PlayerModes::LOCAL
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EqPresets {
+    FLAT,
+    ROCK,
+    POP,
+    JAZZ,
+    CLASSICAL,
+    BASS,
+    CUSTOM, // bands adjusted by hand
+}
+
+impl EqPresets {
+    pub fn gains(self) -> [f32; EQ_NUM_BANDS] {
+        match self {
+            EqPresets::FLAT | EqPresets::CUSTOM => [0.; EQ_NUM_BANDS],
+            EqPresets::ROCK => [5., 4., 3., 1., -1., -1., 1., 3., 4., 5.],
+            EqPresets::POP => [-1., -1., 0., 2., 4., 4., 2., 0., -1., -1.],
+            EqPresets::JAZZ => [4., 3., 1., 2., -1., -1., 0., 1., 3., 4.],
+            EqPresets::CLASSICAL => [5., 4., 3., 2., -1., -1., 0., 2., 3., 4.],
+            EqPresets::BASS => [6., 5., 4., 2., 0., 0., 0., 0., 0., 0.],
+        }
+    }
+
+    pub fn cycle(self) -> EqPresets {
+        match self {
+            EqPresets::FLAT => EqPresets::ROCK,
+            EqPresets::ROCK => EqPresets::POP,
+            EqPresets::POP => EqPresets::JAZZ,
+            EqPresets::JAZZ => EqPresets::CLASSICAL,
+            EqPresets::CLASSICAL => EqPresets::BASS,
+            EqPresets::BASS | EqPresets::CUSTOM => EqPresets::FLAT,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EqPresets::FLAT => "flat",
+            EqPresets::ROCK => "rock",
+            EqPresets::POP => "pop",
+            EqPresets::JAZZ => "jazz",
+            EqPresets::CLASSICAL => "classical",
+            EqPresets::BASS => "bass",
+            EqPresets::CUSTOM => "custom",
+        }
+    }
+}
+
+impl Default for EqPresets {
+    fn default() -> Self {
+        EqPresets::FLAT
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemePresets {
+    DEFAULT,
+    GRUVBOX,
+    NORD,
+}
+
+impl Default for ThemePresets {
+    fn default() -> Self {
+        ThemePresets::DEFAULT
+    }
+}
+
+// the pane the navigation keys act on, when a queue is shown beside the open tab
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FocusedPanes {
+    MAIN,
+    QUEUE,
+}
+
+// what a pane of the layout shows
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LayoutWidgets {
+    TABS,
+    NOW_PLAYING,
+    MAIN,   // the library, queue or settings, whichever tab is open
+    QUEUE,  // in place of the one beside the library
+    STATUS, // notifications and prompts
+}
+
+impl LayoutWidgets {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LayoutWidgets::TABS => "tabs",
+            LayoutWidgets::NOW_PLAYING => "now_playing",
+            LayoutWidgets::MAIN => "main",
+            LayoutWidgets::QUEUE => "queue",
+            LayoutWidgets::STATUS => "status",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LayoutDirections {
+    VERTICAL, // panes from top to bottom
+    HORIZONTAL,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SongColumns {
+    TITLE,
+    ARTIST,
+    ALBUM,
+    DURATION,
+    RATING, // stars and the favorite heart
+}
+
+impl SongColumns {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SongColumns::TITLE => "Title",
+            SongColumns::ARTIST => "Artist",
+            SongColumns::ALBUM => "Album",
+            SongColumns::DURATION => "Duration",
+            SongColumns::RATING => "Rating",
+        }
+    }
+}
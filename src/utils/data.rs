@@ -0,0 +1,21 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+// $XDG_DATA_HOME/splay, which is ~/.local/share/splay unless it's set
+fn data_dir() -> Option<PathBuf> {
+    let data_home = match env::var("XDG_DATA_HOME").ok().filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var("HOME").ok()?).join(".local/share"),
+    };
+    Some(data_home.join("splay"))
+}
+
+// where splay keeps a file between runs: in the data directory, or in the one splay is run from
+// if that can't be made
+pub fn path(file_name: &str) -> PathBuf {
+    match data_dir().filter(|dir| fs::create_dir_all(dir).is_ok()) {
+        Some(dir) => dir.join(file_name),
+        None => PathBuf::from(file_name),
+    }
+}
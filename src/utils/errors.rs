@@ -0,0 +1,16 @@
+use std::io;
+use thiserror::Error;
+
+use crate::config::errors::ConfigError;
+use crate::player::errors::PlayerError;
+
+// any error that's passed up past the part of splay it happened in
+#[derive(Debug, Error)]
+pub enum SplayError {
+    #[error("The terminal could not be used: {0}")]
+    Terminal(#[from] io::Error),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error(transparent)]
+    Player(#[from] PlayerError),
+}
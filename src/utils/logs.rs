@@ -0,0 +1,71 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use simplelog::{ConfigBuilder, LevelFilter, WriteLogger};
+
+use super::data;
+
+// how many of the last lines are kept for the log pane
+const TAIL_LEN: usize = 200;
+
+// kept apart from the app's state, which is often locked while something is logged
+static TAIL: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+// log to the file in the data directory. every line has when, how bad, the thread and the
+// module it's from
+pub fn init(file_name: &str) -> io::Result<PathBuf> {
+    let path = data::path(file_name);
+    let writer = LogWriter {
+        file: File::create(&path)?,
+        line: Vec::new(),
+    };
+    let config = ConfigBuilder::new()
+        .set_time_format_str("%F %T")
+        .set_time_to_local(true)
+        .set_thread_level(LevelFilter::Error)
+        .set_target_level(LevelFilter::Error)
+        .build();
+    let _ = WriteLogger::init(LevelFilter::Info, config, writer);
+    Ok(path)
+}
+
+// the last lines logged, oldest first
+pub fn tail(lines: usize) -> Vec<String> {
+    let tail = TAIL.lock().unwrap();
+    tail.iter()
+        .skip(tail.len().saturating_sub(lines))
+        .cloned()
+        .collect()
+}
+
+// writes to the file, keeping each line once it's whole
+struct LogWriter {
+    file: File,
+    line: Vec<u8>,
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.file.write(buf)?;
+        for &byte in &buf[..written] {
+            if byte != b'\n' {
+                self.line.push(byte);
+                continue;
+            }
+            let mut tail = TAIL.lock().unwrap();
+            if tail.len() == TAIL_LEN {
+                tail.pop_front();
+            }
+            tail.push_back(String::from_utf8_lossy(&self.line).into_owned());
+            self.line.clear();
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
@@ -1,2 +1,5 @@
 pub mod constants;
+pub mod data;
+pub mod errors;
+pub mod logs;
 pub mod types;